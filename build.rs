@@ -0,0 +1,77 @@
+use shaderc::{CompileOptions, Compiler, ShaderKind};
+use std::fs;
+use std::path::Path;
+
+/* `vulkano_shaders::shader!` compiles each GLSL file while the crate is being compiled, and a
+ * syntax error in one comes out as a proc macro panic buried in a page of expansion noise. This
+ * compiles the same files first, with the same compiler, and reports each error as a plain
+ * warning naming the file and line. Finding them takes no list to keep in sync: every `shader!`
+ * in src names its stage and file, and those are what get checked. The build carries on either
+ * way, so the macro still fails it, but these warnings come first. */
+fn main() {
+    println!("cargo:rerun-if-changed=src");
+
+    let mut compiler = match Compiler::new() {
+        Some(compiler) => compiler,
+        None => return println!("cargo:warning=Could not create a GLSL compiler, not checking shaders"),
+    };
+    let options = CompileOptions::new().expect("Failed to initialize compile options");
+
+    for (kind, path) in shaders(Path::new("src")) {
+        println!("cargo:rerun-if-changed={}", path);
+
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                println!("cargo:warning={}: could not read shader: {}", path, e);
+                continue;
+            }
+        };
+
+        /* The file name is what errors are reported against, as <path>:<line>: error: ... */
+        if let Err(e) = compiler.compile_into_spirv(&source, kind, &path, "main", Some(&options)) {
+            for line in e.to_string().lines().filter(|line| !line.trim().is_empty()) {
+                println!("cargo:warning={}", line);
+            }
+        }
+    }
+}
+
+/* The stage and path of every `shader!` in the Rust files of `dir`, found by looking for the
+ * `ty: "..."` and `path: "..."` lines inside each invocation. */
+fn shaders(dir: &Path) -> Vec<(ShaderKind, String)> {
+    let mut found = Vec::new();
+    let entries = fs::read_dir(dir).expect("Could not list src");
+
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("rs") {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+
+        for invocation in text.split("shader!").skip(1) {
+            let body = invocation.split('}').next().unwrap_or("");
+            let kind = match quoted(body, "ty:").as_ref().map(String::as_str) {
+                Some("compute") => ShaderKind::Compute,
+                Some("vertex") => ShaderKind::Vertex,
+                Some("fragment") => ShaderKind::Fragment,
+                Some("geometry") => ShaderKind::Geometry,
+                _ => continue,
+            };
+            if let Some(shader) = quoted(body, "path:") {
+                found.push((kind, shader));
+            }
+        }
+    }
+
+    found
+}
+
+/* The string in quotes after `key` in `text`. */
+fn quoted(text: &str, key: &str) -> Option<String> {
+    let rest = &text[text.find(key)? + key.len()..];
+    let start = rest.find('"')? + 1;
+    let end = start + rest[start..].find('"')?;
+    Some(rest[start..end].to_string())
+}
@@ -0,0 +1,220 @@
+use image::{ImageBuffer, Rgba};
+use std::env;
+use std::path::Path;
+use std::process;
+
+use vulkano_tut::{instancing, mesh, triangle};
+use vulkano_tut::mesh::Matrix;
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/* Draws the images in tests/golden on the CPU, the way the spec says a GPU has to, so they can
+ * be made again without one and checked by reading this rather than trusting a device:
+ *
+ *     cargo run --example golden_reference -- tests/golden
+ *
+ * The scenes take their data from the crate itself, the vertices, instances, cube and matrices,
+ * and only the shaders are written out again here. Changing one of those means changing the
+ * matching function below, running this, and committing the images along with it. */
+fn main() {
+    let dir = match env::args().nth(1) {
+        Some(dir) => dir,
+        None => {
+            eprintln!("Usage: golden_reference <directory to write the images to>");
+            process::exit(2);
+        }
+    };
+    let images = [
+        ("triangle", triangle_scene()),
+        ("instancing", instancing_scene(100)),
+        ("uv", uv(64, 64)),
+        ("disc", disc(64, 48)),
+        ("fullscreen", fullscreen(triangle::SIZE, triangle::SIZE)),
+        ("mesh", cube()),
+    ];
+    for (name, image) in &images {
+        let path = Path::new(&dir).join(format!("{}.png", name));
+        if let Err(e) = image.save(&path) {
+            eprintln!("Could not write {}: {}", path.display(), e);
+            process::exit(1);
+        }
+        println!("Wrote {}", path.display());
+    }
+}
+
+/* A float color to what an unorm8 attachment stores for it: clamped, then rounded to the
+ * nearest of the 256 steps. */
+fn unorm(c: f32) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0 + 0.5).floor() as u8
+}
+
+fn rgba(color: [f32; 4]) -> Rgba<u8> {
+    Rgba([unorm(color[0]), unorm(color[1]), unorm(color[2]), unorm(color[3])])
+}
+
+fn cleared(width: u32, height: u32) -> Image {
+    ImageBuffer::from_pixel(width, height, rgba(triangle::CLEAR_COLOR))
+}
+
+/* A vertex once it is on screen: x and y in pixels, and its depth. */
+#[derive(Debug, Clone, Copy)]
+struct Screen {
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+/* Where normalized device coordinates land in a width x height viewport. */
+fn to_screen(x: f32, y: f32, z: f32, width: u32, height: u32) -> Screen {
+    Screen { x: (x + 1.0) * 0.5 * width as f32, y: (y + 1.0) * 0.5 * height as f32, z }
+}
+
+/* Rasterizers snap positions to a grid of sub-pixels before anything else; 1/256 of a pixel is
+ * finer than any device's. Working in f64 from there makes the edge values exact, so a pixel
+ * center right on an edge is on it here too rather than a rounding error to either side. */
+fn snap(v: f32) -> f64 {
+    (f64::from(v) * 256.0).round() / 256.0
+}
+
+/* Twice the signed area of `a`, `b` and the point `x`, `y`: positive on one side of the edge
+ * from `a` to `b`, negative on the other and zero on it. */
+fn edge(a: Screen, b: Screen, x: f32, y: f32) -> f64 {
+    let (ax, ay, bx, by) = (snap(a.x), snap(a.y), snap(b.x), snap(b.y));
+    (bx - ax) * (f64::from(y) - ay) - (by - ay) * (f64::from(x) - ax)
+}
+
+/* The top-left rule: a pixel center exactly on an edge belongs to the triangle only if the edge
+ * is a top or a left one, so two triangles sharing the edge never both draw it, nor both miss
+ * it. */
+fn top_left(a: Screen, b: Screen) -> bool {
+    let (dx, dy) = (snap(b.x) - snap(a.x), snap(b.y) - snap(a.y));
+    (dy == 0.0 && dx > 0.0) || dy < 0.0
+}
+
+/* Calls `shade` with the position and interpolated depth of every pixel whose center `corners`
+ * covers, whichever way round they wind. */
+fn rasterize(width: u32, height: u32, mut corners: [Screen; 3], mut shade: impl FnMut(u32, u32, f32)) {
+    let area = edge(corners[0], corners[1], corners[2].x, corners[2].y);
+    if area == 0.0 {
+        return;
+    }
+    if area < 0.0 {
+        corners.swap(1, 2);
+    }
+    let area = area.abs();
+    for y in 0..height {
+        for x in 0..width {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let mut weights = [0.0; 3];
+            let inside = (0..3).all(|i| {
+                let (a, b) = (corners[(i + 1) % 3], corners[(i + 2) % 3]);
+                let e = edge(a, b, px, py);
+                weights[i] = (e / area) as f32;
+                e > 0.0 || (e == 0.0 && top_left(a, b))
+            });
+            if inside {
+                shade(x, y, weights.iter().zip(&corners).map(|(w, c)| w * c.z).sum());
+            }
+        }
+    }
+}
+
+/* The triangle scene: triangle.rs's three vertices, in frag.glsl's flat color. */
+fn triangle_scene() -> Image {
+    let size = triangle::SIZE;
+    let positions = [[-0.5, -0.5], [0.0, 0.5], [0.5, -0.25]];
+    let corners = [0, 1, 2].map(|i: usize| to_screen(positions[i][0], positions[i][1], 0.0, size, size));
+    let color = rgba([0.5, 0.3, 0.6, 1.0]);
+    let mut image = cleared(size, size);
+    rasterize(size, size, corners, |x, y, _| image.put_pixel(x, y, color));
+    image
+}
+
+/* The instancing scene: each of `count` instances scales and moves the quad from -1 to 1 the
+ * way instancing.vert does, in the instance's own color. */
+fn instancing_scene(count: u32) -> Image {
+    let size = triangle::SIZE;
+    let quad = [[-1.0f32, -1.0], [1.0, -1.0], [1.0, 1.0], [1.0, 1.0], [-1.0, 1.0], [-1.0, -1.0]];
+    let mut image = cleared(size, size);
+    for instance in instancing::instances(count) {
+        let color = rgba(instance.color);
+        for triangle in quad.chunks(3) {
+            let corners = [0, 1, 2].map(|k: usize| to_screen(instance.offset[0] + triangle[k][0] * instance.scale,
+                instance.offset[1] + triangle[k][1] * instance.scale, 0.0, size, size));
+            rasterize(size, size, corners, |x, y, _| image.put_pixel(x, y, color));
+        }
+    }
+    image
+}
+
+/* uv.glsl: red across and green down, a step of 1/255 a pixel. */
+fn uv(width: u32, height: u32) -> Image {
+    ImageBuffer::from_fn(width, height, |x, y| Rgba([(x % 256) as u8, (y % 256) as u8, 0, 255]))
+}
+
+/* disc.glsl, with its radius. */
+fn disc(width: u32, height: u32) -> Image {
+    let (w, h) = (width as f32, height as f32);
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let p = [(x as f32 + 0.5 - 0.5 * w) / h, (y as f32 + 0.5 - 0.5 * h) / h];
+        let v = if (p[0] * p[0] + p[1] * p[1]).sqrt() < 0.4 { 255 } else { 0 };
+        Rgba([v, v, v, 255])
+    })
+}
+
+/* gradient.glsl, which shades each pixel from where its center is. */
+fn fullscreen(width: u32, height: u32) -> Image {
+    ImageBuffer::from_fn(width, height, |x, y| {
+        rgba([(x as f32 + 0.5) / width as f32, (y as f32 + 0.5) / height as f32, 0.5, 1.0])
+    })
+}
+
+fn apply(m: Matrix, v: [f32; 4]) -> [f32; 4] {
+    let mut out = [0.0; 4];
+    for (row, value) in out.iter_mut().enumerate() {
+        *value = (0..4).map(|column| m[column][row] * v[column]).sum();
+    }
+    out
+}
+
+/* The mesh demo's single frame: the cube at `spinning(0.6)`, lit the way mesh.frag lights it,
+ * each pixel keeping the nearest face the way the depth test does. */
+fn cube() -> Image {
+    let size = triangle::SIZE;
+    let transform = mesh::spinning(0.6);
+    let to_clip = mesh::multiply(transform.projection, mesh::multiply(transform.view, transform.model));
+    let light = {
+        let l = [-0.4f32, 0.8, 0.6];
+        let length = (l[0] * l[0] + l[1] * l[1] + l[2] * l[2]).sqrt();
+        [l[0] / length, l[1] / length, l[2] / length]
+    };
+    const AMBIENT: f32 = 0.2;
+
+    let (vertices, indices) = mesh::cube();
+    let mut image = cleared(size, size);
+    let mut depth = vec![1.0f32; (size * size) as usize];
+    for triangle in indices.chunks(3) {
+        let corner = |i: u32| {
+            let p = vertices[i as usize].position;
+            let clip = apply(to_clip, [p[0], p[1], p[2], 1.0]);
+            to_screen(clip[0] / clip[3], clip[1] / clip[3], clip[2] / clip[3], size, size)
+        };
+        /* The faces are flat, so the first vertex's normal and color do for the whole triangle. */
+        let vertex = vertices[triangle[0] as usize];
+        let n = apply(transform.model, [vertex.normal[0], vertex.normal[1], vertex.normal[2], 0.0]);
+        let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        let diffuse = ((n[0] * light[0] + n[1] * light[1] + n[2] * light[2]) / length).max(0.0);
+        let k = AMBIENT + (1.0 - AMBIENT) * diffuse;
+        let color = rgba([vertex.color[0] * k, vertex.color[1] * k, vertex.color[2] * k, 1.0]);
+
+        let corners = [corner(triangle[0]), corner(triangle[1]), corner(triangle[2])];
+        rasterize(size, size, corners, |x, y, z| {
+            let nearest = &mut depth[(y * size + x) as usize];
+            if z < *nearest {
+                *nearest = z;
+                image.put_pixel(x, y, color);
+            }
+        });
+    }
+    image
+}
@@ -0,0 +1,202 @@
+use std::iter;
+use std::mem;
+use std::sync::Arc;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::command_buffer::submit::SubmitCommandBufferBuilder;
+use vulkano::command_buffer::sys::{Flags, Kind, UnsafeCommandBufferBuilder, UnsafeCommandBufferBuilderBufferImageCopy,
+    UnsafeCommandBufferBuilderImageAspect, UnsafeCommandBufferBuilderPipelineBarrier};
+use vulkano::descriptor::descriptor::ShaderStages;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::descriptor::DescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, ImageLayout, StorageImage};
+use vulkano::pipeline::{ComputePipeline, ComputePipelineAbstract};
+use vulkano::sync::{AccessFlagBits, Fence, GpuFuture, PipelineStages};
+
+use crate::mandelbrot;
+use crate::memory;
+use crate::readback;
+use crate::storage;
+
+const SIZE: u32 = 512;
+const LOCAL_SIZE: u32 = 8;
+
+/* Two compute passes where the second reads what the first wrote: the mandelbrot shader draws
+ * into image A, then the invert shader reads A and writes the inverted colors to image B. */
+struct PingPong {
+    a: Arc<StorageImage<Format>>,
+    b: Arc<StorageImage<Format>>,
+    buf: Arc<CpuAccessibleBuffer<[u8]>>,
+    mandelbrot: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+    invert: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+}
+
+impl PingPong {
+    fn new(device: Arc<Device>, queue: &Queue) -> PingPong {
+        let image = || storage::image(queue, Dimensions::Dim2d { width: SIZE, height: SIZE }, Format::R8G8B8A8Unorm);
+
+        let pixels = (SIZE * SIZE) as usize * readback::RGBA8_BYTES;
+        let buf = memory::readback::<u8>(queue, pixels).expect("Failed to create buffer");
+
+        let shader = invert::Shader::load(device.clone()).expect("Failed to create invert shader module");
+        let invert = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+        PingPong { a: image(), b: image(), buf, mandelbrot: mandelbrot::pipeline_with(device.clone(), mandelbrot::Params::PushConstants), invert }
+    }
+}
+
+/* Runs both passes recorded explicitly and implicitly, and checks they agree. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let implicit = implicit(device.clone(), queue.clone());
+    let explicit = explicit(device, queue);
+    assert!(implicit == explicit, "Explicitly synchronized ping-pong differs from the implicit version");
+}
+
+/* The way the rest of the sandbox does it: each pass is its own command buffer and we wait for
+ * the first to finish before submitting the second. Waiting on the CPU is a heavy-handed but
+ * correct way to make the second pass see the first pass's writes. */
+fn implicit(device: Arc<Device>, queue: Arc<Queue>) -> Vec<u8> {
+    let pp = PingPong::new(device.clone(), &queue);
+    let groups = [SIZE / LOCAL_SIZE, SIZE / LOCAL_SIZE, 1];
+
+    let set_a = Arc::new(PersistentDescriptorSet::start(pp.mandelbrot.clone(), 0)
+        .add_image(pp.a.clone()).unwrap()
+        .build().unwrap());
+    let set_ab = Arc::new(PersistentDescriptorSet::start(pp.invert.clone(), 0)
+        .add_image(pp.a.clone()).unwrap()
+        .add_image(pp.b.clone()).unwrap()
+        .build().unwrap());
+
+    let push_constants = mandelbrot::View::default().push_constants(SIZE, SIZE);
+    AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .dispatch(groups, pp.mandelbrot.clone(), set_a, push_constants).unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .dispatch(groups, pp.invert.clone(), set_ab, ()).unwrap()
+        .copy_image_to_buffer(pp.b.clone(), pp.buf.clone()).unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    let content = pp.buf.read().unwrap();
+    content.to_vec()
+}
+
+/* Both passes and the readback in a single command buffer, with every barrier written out by
+ * hand. `AutoCommandBufferBuilder` works these out for us and doesn't let us add our own, so
+ * this records into vulkano's unsafe builder, which does no synchronization at all. Commands
+ * in one command buffer may overlap and their writes may sit in caches the next command can't
+ * see, so each barrier says which earlier work (source stage and access) must finish and be
+ * made visible before which later work (destination stage and access) may start. Image
+ * barriers also move the image into the layout its next use requires. */
+fn explicit(device: Arc<Device>, queue: Arc<Queue>) -> Vec<u8> {
+    let pp = PingPong::new(device.clone(), &queue);
+    let groups = [SIZE / LOCAL_SIZE, SIZE / LOCAL_SIZE, 1];
+
+    let set_a = PersistentDescriptorSet::start(pp.mandelbrot.clone(), 0)
+        .add_image(pp.a.clone()).unwrap()
+        .build().unwrap();
+    let set_ab = PersistentDescriptorSet::start(pp.invert.clone(), 0)
+        .add_image(pp.a.clone()).unwrap()
+        .add_image(pp.b.clone()).unwrap()
+        .build().unwrap();
+
+    let compute = PipelineStages { compute_shader: true, .. PipelineStages::none() };
+    let transfer = PipelineStages { transfer: true, .. PipelineStages::none() };
+    let shader_read = AccessFlagBits { shader_read: true, .. AccessFlagBits::none() };
+    let shader_write = AccessFlagBits { shader_write: true, .. AccessFlagBits::none() };
+    let transfer_read = AccessFlagBits { transfer_read: true, .. AccessFlagBits::none() };
+
+    let pool = Device::standard_command_pool(&device, queue.family());
+    let command_buffer = unsafe {
+        let mut builder = UnsafeCommandBufferBuilder::new(&pool, Kind::primary(), Flags::OneTimeSubmit).unwrap();
+
+        /* Neither image has been used, so there is nothing to wait for; we only need them in
+         * the General layout, the one storage images are read and written in. Coming from
+         * Undefined tells the driver it may throw away the old contents. */
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        for image in &[&pp.a, &pp.b] {
+            barrier.add_image_memory_barrier(*image, 0..1, 0..1,
+                PipelineStages { top_of_pipe: true, .. PipelineStages::none() }, AccessFlagBits::none(),
+                compute, shader_write, false, None, ImageLayout::Undefined, ImageLayout::General);
+        }
+        builder.pipeline_barrier(&barrier);
+
+        let push_constants = mandelbrot::View::default().push_constants(SIZE, SIZE);
+        builder.bind_pipeline_compute(&pp.mandelbrot);
+        builder.bind_descriptor_sets(false, &pp.mandelbrot, 0, iter::once(set_a.inner()), iter::empty());
+        builder.push_constants(&pp.mandelbrot, ShaderStages { compute: true, .. ShaderStages::none() }, 0,
+            mem::size_of::<mandelbrot::PushConstants>() as u32, &push_constants);
+        builder.dispatch(groups);
+
+        /* The read-after-write hazard the barrier exists for: without it the invert pass could
+         * start reading A before the mandelbrot pass has finished writing it, or read stale
+         * cached texels. A stays in General since it is still used as a storage image. */
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        barrier.add_image_memory_barrier(&pp.a, 0..1, 0..1, compute, shader_write, compute, shader_read,
+            false, None, ImageLayout::General, ImageLayout::General);
+        builder.pipeline_barrier(&barrier);
+
+        builder.bind_pipeline_compute(&pp.invert);
+        builder.bind_descriptor_sets(false, &pp.invert, 0, iter::once(set_ab.inner()), iter::empty());
+        builder.dispatch(groups);
+
+        /* B must be completely written before the copy reads it, and copies are fastest from
+         * the TransferSrcOptimal layout. */
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        barrier.add_image_memory_barrier(&pp.b, 0..1, 0..1, compute, shader_write, transfer, transfer_read,
+            false, None, ImageLayout::General, ImageLayout::TransferSrcOptimal);
+        builder.pipeline_barrier(&barrier);
+
+        builder.copy_image_to_buffer(&pp.b, ImageLayout::TransferSrcOptimal, &pp.buf, iter::once(
+            UnsafeCommandBufferBuilderBufferImageCopy {
+                buffer_offset: 0,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_aspect: UnsafeCommandBufferBuilderImageAspect { color: true, depth: false, stencil: false },
+                image_mip_level: 0,
+                image_base_array_layer: 0,
+                image_layer_count: 1,
+                image_offset: [0, 0, 0],
+                image_extent: [SIZE, SIZE, 1],
+            }));
+
+        /* Finally make the copied bytes visible to the CPU once the fence signals. */
+        let size = (SIZE * SIZE) as usize * readback::RGBA8_BYTES;
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        barrier.add_buffer_memory_barrier(&pp.buf, transfer, AccessFlagBits { transfer_write: true, .. AccessFlagBits::none() },
+            PipelineStages { host: true, .. PipelineStages::none() }, AccessFlagBits { host_read: true, .. AccessFlagBits::none() },
+            false, None, 0, size);
+        builder.pipeline_barrier(&barrier);
+
+        builder.build().unwrap()
+    };
+
+    /* Nothing keeps the resources alive or waits for us here either: submit with a fence and
+     * block on it before any of them can be dropped. */
+    let fence = Fence::alloc(device.clone()).unwrap();
+    unsafe {
+        let mut submit = SubmitCommandBufferBuilder::new();
+        submit.add_command_buffer(&command_buffer);
+        submit.set_fence_signal(&fence);
+        submit.submit(&queue).unwrap();
+    }
+    fence.wait(None).unwrap();
+
+    let content = pp.buf.read().unwrap();
+    content.to_vec()
+}
+
+mod invert {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/invert.glsl"
+    }
+}
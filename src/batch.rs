@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::command_buffer::pool::standard::StandardCommandPoolBuilder;
+use vulkano::command_buffer::submit::SubmitCommandBufferBuilder;
+use vulkano::command_buffer::sys::{Flags, Kind, UnsafeCommandBufferBuilder};
+use vulkano::device::{Device, Queue};
+use vulkano::sync::{self, Fence, GpuFuture};
+
+/* Submits every command buffer and waits for all of them at once. Waiting on each one before
+ * submitting the next leaves the GPU idle while the CPU wakes up and submits again, and
+ * independent work could have been running side by side anyway. Joining the futures gives one
+ * future that is done when all of them are, so there is a single fence and a single wait. The
+ * command buffers must not depend on each other: nothing orders them relative to one another. */
+pub fn submit_all<Cb>(device: Arc<Device>, queue: Arc<Queue>, command_buffers: Vec<Cb>)
+    where Cb: CommandBuffer + Send + Sync + 'static
+{
+    let mut future: Box<dyn GpuFuture> = Box::new(sync::now(device));
+    for command_buffer in command_buffers {
+        future = Box::new(future.join(command_buffer.execute(queue.clone()).unwrap()));
+    }
+
+    future.then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+}
+
+/* Records a one time submit command buffer with `record`, submits it and waits for it, for the
+ * one-off copies and clears that are otherwise the same five lines of building, executing,
+ * flushing and waiting every time. `record` gets the empty builder and hands it back with its
+ * commands added. */
+pub fn submit_and_wait<F>(device: Arc<Device>, queue: Arc<Queue>, record: F)
+    where F: FnOnce(AutoCommandBufferBuilder) -> AutoCommandBufferBuilder
+{
+    let builder = AutoCommandBufferBuilder::primary_one_time_submit(device, queue.family()).unwrap();
+    record(builder).build().unwrap()
+        .execute(queue).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+}
+
+/* Records a command buffer with the unsafe builder, for commands `AutoCommandBufferBuilder` has
+ * no way of recording, then submits it by hand and waits on a fence so it is done before it is
+ * dropped. Nothing tracks what these commands touch, so `record` has to leave alone anything
+ * vulkano is synchronizing for work that may still be running. */
+pub fn submit_unsafe<F>(device: Arc<Device>, queue: &Queue, record: F)
+    where F: FnOnce(&mut UnsafeCommandBufferBuilder<StandardCommandPoolBuilder>)
+{
+    let pool = Device::standard_command_pool(&device, queue.family());
+    let command_buffer = unsafe {
+        let mut builder = UnsafeCommandBufferBuilder::new(&pool, Kind::primary(), Flags::OneTimeSubmit).unwrap();
+        record(&mut builder);
+        builder.build().unwrap()
+    };
+
+    let fence = Fence::alloc(device).unwrap();
+    unsafe {
+        let mut submit = SubmitCommandBufferBuilder::new();
+        submit.add_command_buffer(&command_buffer);
+        submit.set_fence_signal(&fence);
+        submit.submit(queue).unwrap();
+    }
+    fence.wait(None).unwrap();
+}
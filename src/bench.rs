@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use std::time::Instant;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+
+use crate::scene::{Runner, Scene, SCENES};
+use crate::timing::{GpuTimer, Timing};
+use crate::watchdog;
+
+/* How many untimed and timed runs `--bench` does unless `--warmup` and `--runs` say otherwise. */
+pub const DEFAULT_WARMUP: u32 = 3;
+pub const DEFAULT_RUNS: u32 = 20;
+
+/* Checks `name` is a scene or `all`. */
+pub fn parse(name: &str) -> Result<&'static str, String> {
+    if name == "all" {
+        return Ok("all");
+    }
+    SCENES.iter().find(|&&s| s == name).cloned()
+        .ok_or_else(|| format!("Can't benchmark `{}`, expected all or one of {}", name, SCENES.join(", ")))
+}
+
+/* The scenes `name` stands for. */
+pub fn scenes(name: &'static str) -> Vec<&'static str> {
+    if name == "all" { SCENES.to_vec() } else { vec![name] }
+}
+
+/* Records `scene` once and runs it `warmup` times untimed, so the timings don't include the GPU
+ * getting up to speed or the driver doing first-use work, then `runs` times with each run
+ * between a pair of timestamps. The command buffer is the same every run, so the only thing
+ * that differs between them is the GPU. */
+pub fn run(runner: &Runner, scene: &dyn Scene, warmup: u32, runs: u32) -> Vec<Timing> {
+    let builder = AutoCommandBufferBuilder::new(runner.device.clone(), runner.queue.family()).unwrap();
+    let command_buffer = Arc::new(scene.record(builder).build().unwrap());
+    let submit = || watchdog::wait(command_buffer.clone().execute(runner.queue.clone()).unwrap(), scene.name());
+
+    for _ in 0..warmup {
+        submit();
+    }
+
+    let timer = GpuTimer::new(runner.device.clone(), runner.queue.clone());
+    (0..runs).map(|iteration| {
+        let start = Instant::now();
+        let gpu_ms = match timer {
+            Some(ref timer) => Some(timer.time(submit).1),
+            None => {
+                submit();
+                None
+            }
+        };
+        Timing { iteration, host_ms: start.elapsed().as_secs_f64() * 1000.0, gpu_ms }
+    }).collect()
+}
+
+/* The fastest, median and mean of `ms`, which mustn't be empty. */
+fn stats(ms: &mut [f64]) -> (f64, f64, f64) {
+    ms.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let median = if ms.len() % 2 == 1 {
+        ms[ms.len() / 2]
+    } else {
+        (ms[ms.len() / 2 - 1] + ms[ms.len() / 2]) / 2.0
+    };
+    (ms[0], median, ms.iter().sum::<f64>() / ms.len() as f64)
+}
+
+/* `per_second` with a metric prefix, like 1.25 G. */
+fn rate(per_second: f64) -> String {
+    let prefixes = [(1e9, "G"), (1e6, "M"), (1e3, "k")];
+    match prefixes.iter().find(|&&(scale, _)| per_second >= scale) {
+        Some(&(scale, prefix)) => format!("{:.2} {}", per_second / scale, prefix),
+        None => format!("{:.2} ", per_second),
+    }
+}
+
+/* Prints a row per scene with the fastest, median and mean of its runs and what it gets
+ * through per second at the median. The times are the GPU's where the queue has timestamps, and
+ * the host's, marked with a *, where it doesn't. */
+pub fn print_table(results: &[(&dyn Scene, Vec<Timing>)]) {
+    println!("{:<12}  {:>4}  {:>10}  {:>10}  {:>10}  {:>20}", "Scene", "Runs", "Min ms", "Median ms", "Mean ms",
+        "Throughput");
+    let mut host_only = false;
+    for &(scene, ref timings) in results {
+        if timings.is_empty() {
+            continue;
+        }
+        let gpu: Option<Vec<f64>> = timings.iter().map(|t| t.gpu_ms).collect();
+        let marker = if gpu.is_some() { " " } else { "*" };
+        host_only |= gpu.is_none();
+        let mut ms = gpu.unwrap_or_else(|| timings.iter().map(|t| t.host_ms).collect());
+        let (min, median, mean) = stats(&mut ms);
+        let (items, unit) = scene.work();
+        let throughput = format!("{}{}/s", rate(items as f64 / (median / 1000.0)), unit);
+        println!("{:<12}  {:>4}  {:>9.4}{}  {:>9.4}{}  {:>9.4}{}  {:>20}", scene.name(), timings.len(), min, marker,
+            median, marker, mean, marker, throughput);
+    }
+    if host_only {
+        println!("* host time, the queue can't write timestamps");
+    }
+}
@@ -0,0 +1,35 @@
+#version 450
+
+layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) buffer Data {
+    uint data[];
+} buf;
+
+/* Which pass this is: `stage` is the size of the sequences being merged and `step` the distance
+ * between the elements compared. */
+layout(push_constant) uniform PushConstants {
+    uint stage;
+    uint step;
+} pc;
+
+void main() {
+    uint i = gl_GlobalInvocationID.x;
+    uint partner = i ^ pc.step;
+
+    /* Each pair is compared once, by its lower element. The length is a power of two, so the
+     * partner of an element in range is always in range too. */
+    if (i >= buf.data.length() || partner <= i) {
+        return;
+    }
+
+    /* Alternate blocks of `stage` elements sort in opposite directions, so that merging two
+     * neighbours gives a bitonic sequence for the next stage to sort. */
+    bool ascending = (i & pc.stage) == 0;
+    uint a = buf.data[i];
+    uint b = buf.data[partner];
+    if ((a > b) == ascending) {
+        buf.data[i] = b;
+        buf.data[partner] = a;
+    }
+}
@@ -0,0 +1,66 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, ImageAccess, StorageImage};
+use vulkano::sampler::Filter;
+
+use crate::batch;
+use crate::readback;
+use crate::storage;
+
+/* Scales `source` to fill all of `destination`, blending neighbouring texels with `filter`.
+ * Unlike a copy, a blit may change the size of the region and convert between formats, but
+ * only between formats the device can blit between, so this sandbox sticks to blitting images
+ * of the same format and says so instead of leaving it to a validation error. */
+pub fn scaled_copy(device: Arc<Device>, queue: Arc<Queue>, source: Arc<StorageImage<Format>>,
+                   destination: Arc<StorageImage<Format>>, filter: Filter) -> Result<(), String> {
+    if source.format() != destination.format() {
+        return Err(format!("Cannot blit a {:?} image into a {:?} image", source.format(), destination.format()));
+    }
+
+    let corner = |image: &StorageImage<Format>| {
+        let [width, height] = image.dimensions().width_height();
+        [width as i32, height as i32, 1]
+    };
+    let (source_corner, destination_corner) = (corner(&source), corner(&destination));
+
+    batch::submit_and_wait(device.clone(), queue.clone(), |builder| builder
+        .blit_image(source, [0, 0, 0], source_corner, 0, 0,
+                    destination, [0, 0, 0], destination_corner, 0, 0, 1, filter).unwrap());
+
+    Ok(())
+}
+
+/* Uploads `original`, blits it down to half its size with linear filtering and reads the
+ * result back. At exactly half size every destination texel sits on the corner shared by four
+ * source texels, so linear filtering averages them, which we check against on the CPU. */
+pub fn half_size(device: Arc<Device>, queue: Arc<Queue>, original: &ImageBuffer<Rgba<u8>, Vec<u8>>)
+    -> ImageBuffer<Rgba<u8>, Vec<u8>>
+{
+    let (width, height) = (original.width() / 2, original.height() / 2);
+    let image = |width, height, format| storage::image(&queue, Dimensions::Dim2d { width, height }, format);
+
+    let source = readback::upload_rgba8(device.clone(), queue.clone(), original);
+    let destination = image(width, height, Format::R8G8B8A8Unorm);
+
+    /* Blitting into an image of another format is refused up front. */
+    let hdr = image(width, height, Format::R16G16B16A16Sfloat);
+    assert!(scaled_copy(device.clone(), queue.clone(), source.clone(), hdr, Filter::Linear).is_err());
+
+    scaled_copy(device.clone(), queue.clone(), source, destination.clone(), Filter::Linear).unwrap();
+    let scaled = readback::download_rgba8(device, queue, destination);
+
+    for (x, y, pixel) in scaled.enumerate_pixels() {
+        for channel in 0..4 {
+            let sum: u32 = [(0, 0), (1, 0), (0, 1), (1, 1)].iter()
+                .map(|&(dx, dy)| original.get_pixel(x * 2 + dx, y * 2 + dy)[channel] as u32)
+                .sum();
+            let expected = sum as f32 / 4.0;
+            assert!((pixel[channel] as f32 - expected).abs() <= 2.0,
+                "Scaled pixel ({}, {}) is {:?}, expected the average of its four source pixels", x, y, pixel);
+        }
+    }
+
+    scaled
+}
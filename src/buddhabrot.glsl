@@ -0,0 +1,82 @@
+#version 450
+
+layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
+
+/* How many times an escaping orbit passed through each pixel, row by row. */
+layout(set = 0, binding = 0) buffer Histogram {
+    uint hits[];
+} histogram;
+
+layout(push_constant) uniform PushConstants {
+    uint samples;
+    uint seed;
+    uint max_iter;
+    uint width;
+    uint height;
+} pc;
+
+/* A PCG hash, which turns consecutive numbers into ones that look random. The same as `pcg`
+ * in random.rs, so the CPU can pick the same points. */
+uint pcg(uint v) {
+    uint state = v * 747796405u + 2891336453u;
+    uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+/* The seed moves where in the hash's sequence the samples start, so each seed picks its own
+ * points. */
+float random(uint v) {
+    return float(pcg(v + pcg(pc.seed))) / 4294967296.0;
+}
+
+/* Points in the main cardioid and the bulb left of it never escape, and they are where orbits
+ * take longest to find that out, so they are skipped without iterating. */
+bool in_bulbs(vec2 c) {
+    float x = c.x - 0.25;
+    float q = x * x + c.y * c.y;
+    return q * (q + x) <= 0.25 * c.y * c.y || (c.x + 1.0) * (c.x + 1.0) + c.y * c.y <= 0.0625;
+}
+
+vec2 iterate(vec2 z, vec2 c) {
+    return vec2(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+}
+
+/* The plane from -2 to 1 on the real axis and -1.5 to 1.5 on the imaginary one, turned so the
+ * real axis runs down the image, which is what makes it look like a seated Buddha. */
+void plot(vec2 z) {
+    int x = int(floor((z.y + 1.5) / 3.0 * float(pc.width)));
+    int y = int(floor((z.x + 2.0) / 3.0 * float(pc.height)));
+    if (x >= 0 && y >= 0 && x < int(pc.width) && y < int(pc.height)) {
+        atomicAdd(histogram.hits[y * int(pc.width) + x], 1u);
+    }
+}
+
+/* Every invocation takes samples a whole dispatch apart. For each it picks a random c and
+ * iterates it like the mandelbrot does, but only the ones that escape count: those go through
+ * their orbit a second time, adding a hit to every pixel it passes through. Many orbits cross
+ * the same pixels at once, so the adds have to be atomic. */
+void main() {
+    uint stride = gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+    for (uint i = gl_GlobalInvocationID.x; i < pc.samples; i += stride) {
+        vec2 c = vec2(-2.0 + 3.0 * random(2u * i), -1.5 + 3.0 * random(2u * i + 1u));
+        if (in_bulbs(c)) {
+            continue;
+        }
+
+        vec2 z = vec2(0.0);
+        uint n = 0;
+        while (n < pc.max_iter && dot(z, z) <= 4.0) {
+            z = iterate(z, c);
+            n++;
+        }
+        if (dot(z, z) <= 4.0) {
+            continue;
+        }
+
+        z = vec2(0.0);
+        for (uint k = 0; k < n; k++) {
+            z = iterate(z, c);
+            plot(z);
+        }
+    }
+}
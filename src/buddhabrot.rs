@@ -0,0 +1,138 @@
+use image::{ImageBuffer, Luma};
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::pipeline::ComputePipeline;
+
+use crate::compute::ComputeTask;
+use crate::memory;
+use crate::random::pcg;
+use crate::watchdog;
+
+/* Enough workgroups to keep a GPU busy. Each invocation loops over its share of the samples, so
+ * any number of samples fits in one dispatch. */
+const GROUPS: u32 = 1024;
+
+const SIZE: u32 = 512;
+const MAX_ITER: u32 = 200;
+
+/* What `--samples` defaults to. */
+pub const DEFAULT_SAMPLES: u32 = 1 << 22;
+
+/* How many times the escaping orbits of `samples` random points pass through each pixel, row by
+ * row. Which points are picked depends only on `seed`. Summing many small contributions into an image would normally be done in floats, but
+ * atomic float adds need an extension vulkano 0.11 doesn't know about, so the hits are counted
+ * in integers and only become floats once they are read back. */
+pub fn histogram(device: Arc<Device>, queue: Arc<Queue>, samples: u32, seed: u32, width: u32, height: u32,
+                 max_iter: u32) -> Vec<u32>
+{
+    let shader = cs::Shader::load(device.clone()).expect("Could not load buddhabrot shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    /* The counts start out as whatever was in the memory, so they are zeroed first, in the same
+     * command buffer as the dispatch and the copy back. */
+    let pixels = (width * height) as usize;
+    let buffer = memory::device_local::<u32>(&queue, memory::storage_buffer(), pixels)
+        .expect("Failed to create buffer");
+    let readback = memory::readback::<u32>(&queue, pixels).expect("Failed to create buffer");
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_buffer(buffer.clone()).unwrap()
+        .build().unwrap());
+
+    let push_constants = cs::ty::PushConstants { samples, seed, max_iter, width, height };
+    let task = ComputeTask::new("buddhabrot", pipeline, set, [GROUPS, 1, 1], push_constants);
+    let builder = AutoCommandBufferBuilder::new(device, queue.family()).unwrap()
+        .fill_buffer(buffer.clone(), 0).unwrap();
+    let command_buffer = task.record(builder)
+        .copy_buffer(buffer, readback.clone()).unwrap()
+        .build().unwrap();
+    watchdog::wait(command_buffer.execute(queue).unwrap(), "buddhabrot");
+
+    let content = readback.read().unwrap();
+    content.to_vec()
+}
+
+/* Renders the Buddhabrot: where the mandelbrot colors each c by how long it takes to escape,
+ * this plots where the orbits of the escaping ones go. The busiest pixels are hit thousands of
+ * times more often than the quiet ones, so the counts are scaled to 0..1 by the largest and
+ * tone mapped with a square root to bring out the faint detail. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>, samples: u32, seed: u32) -> ImageBuffer<Luma<u8>, Vec<u8>> {
+    let hits = histogram(device, queue, samples, seed, SIZE, SIZE, MAX_ITER);
+    let max = hits.iter().cloned().max().unwrap_or(0).max(1) as f32;
+    let pixels = hits.iter().map(|&count| ((count as f32 / max).sqrt() * 255.0).round() as u8).collect();
+    ImageBuffer::from_raw(SIZE, SIZE, pixels).unwrap()
+}
+
+/* Counts the same samples on the CPU, skipping the bulbs and plotting the same way the shader
+ * does. */
+fn cpu_histogram(samples: u32, seed: u32, width: u32, height: u32, max_iter: u32) -> Vec<u32> {
+    let offset = pcg(seed);
+    let random = |v: u32| pcg(v.wrapping_add(offset)) as f32 / 4_294_967_296.0;
+    let iterate = |(x, y): (f32, f32), (cx, cy): (f32, f32)| (x * x - y * y + cx, 2.0 * x * y + cy);
+    let mut hits = vec![0u32; (width * height) as usize];
+
+    for i in 0..samples {
+        let c = (-2.0 + 3.0 * random(2 * i), -1.5 + 3.0 * random(2 * i + 1));
+        let x = c.0 - 0.25;
+        let q = x * x + c.1 * c.1;
+        if q * (q + x) <= 0.25 * c.1 * c.1 || (c.0 + 1.0) * (c.0 + 1.0) + c.1 * c.1 <= 0.0625 {
+            continue;
+        }
+
+        let mut z = (0.0, 0.0);
+        let mut n = 0;
+        while n < max_iter && z.0 * z.0 + z.1 * z.1 <= 4.0 {
+            z = iterate(z, c);
+            n += 1;
+        }
+        if z.0 * z.0 + z.1 * z.1 <= 4.0 {
+            continue;
+        }
+
+        z = (0.0, 0.0);
+        for _ in 0..n {
+            z = iterate(z, c);
+            let px = ((z.1 + 1.5) / 3.0 * width as f32).floor();
+            let py = ((z.0 + 2.0) / 3.0 * height as f32).floor();
+            if px >= 0.0 && py >= 0.0 && px < width as f32 && py < height as f32 {
+                hits[(py as u32 * width + px as u32) as usize] += 1;
+            }
+        }
+    }
+    hits
+}
+
+/* Checks the GPU against the CPU for a smaller run. The orbits are chaotic, so the odd one can
+ * end up somewhere else with the GPU's rounding, and the totals are only compared to within a
+ * few percent. The set is symmetric about the real axis, which runs down the middle of the
+ * image, so the left half has to get about as many hits as the right. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let (samples, seed, width, height) = (1 << 16, 7, 128, 128);
+    let gpu = histogram(device, queue, samples, seed, width, height, MAX_ITER);
+    let cpu = cpu_histogram(samples, seed, width, height, MAX_ITER);
+
+    let total = |hits: &[u32]| hits.iter().map(|&count| count as u64).sum::<u64>();
+    let (gpu_total, cpu_total) = (total(&gpu), total(&cpu));
+    assert!(cpu_total > 0, "No orbit escaped");
+    assert!((gpu_total as f64 - cpu_total as f64).abs() / (cpu_total as f64) < 0.05,
+        "The GPU counted {} hits, the CPU {}", gpu_total, cpu_total);
+
+    let (mut left, mut right) = (0u64, 0u64);
+    for (index, &count) in gpu.iter().enumerate() {
+        if (index as u32 % width) < width / 2 {
+            left += count as u64;
+        } else {
+            right += count as u64;
+        }
+    }
+    assert!((left as f64 - right as f64).abs() / (gpu_total as f64) < 0.1,
+        "The left half got {} hits and the right half {}", left, right);
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/buddhabrot.glsl"
+    }
+}
@@ -0,0 +1,44 @@
+use image::{ImageBuffer, Rgba};
+
+/* The two grays image editors draw behind transparency. */
+const LIGHT: [u8; 3] = [204, 204, 204];
+const DARK: [u8; 3] = [153, 153, 153];
+
+/* Composites `image` over a checkerboard of `size` pixel squares, starting with a light one in
+ * the top left, and returns it opaque. Fully transparent pixels look just like black ones in
+ * most viewers, so this makes them stand out, and shows how much of what is behind a partly
+ * transparent one would come through. The alpha is straight, not premultiplied, as everything
+ * the demos render is. */
+pub fn composite(image: &ImageBuffer<Rgba<u8>, Vec<u8>>, size: u32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    ImageBuffer::from_fn(image.width(), image.height(), |x, y| {
+        let background = if (x / size + y / size).is_multiple_of(2) { LIGHT } else { DARK };
+        let pixel = image.get_pixel(x, y);
+        let alpha = pixel[3] as u32;
+        let over = |c: usize| ((pixel[c] as u32 * alpha + background[c] as u32 * (255 - alpha) + 127) / 255) as u8;
+        Rgba([over(0), over(1), over(2), 255])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* Opaque pixels are kept, transparent ones show the checkerboard and half transparent ones
+     * land halfway between. */
+    #[test]
+    fn composite_shows_the_checkerboard_through_transparency() {
+        let mut image = ImageBuffer::from_pixel(8, 8, Rgba([0u8, 0, 0, 0]));
+        image.put_pixel(0, 0, Rgba([10, 20, 30, 255]));
+        image.put_pixel(1, 0, Rgba([255, 255, 255, 128]));
+
+        let composited = composite(&image, 2);
+        assert!(composited.pixels().all(|pixel| pixel[3] == 255), "The composite isn't opaque");
+        assert!(composited.get_pixel(0, 0).data == [10, 20, 30, 255], "An opaque pixel changed");
+        assert!(composited.get_pixel(0, 1).data[..3] == LIGHT && composited.get_pixel(2, 1).data[..3] == DARK
+            && composited.get_pixel(2, 2).data[..3] == LIGHT, "Transparent pixels don't show a 2 pixel checkerboard");
+
+        let half = composited.get_pixel(1, 0)[0] as i32;
+        assert!((half - (255 + LIGHT[0] as i32) / 2).abs() <= 1,
+            "Half transparent white over light gray came out {}", half);
+    }
+}
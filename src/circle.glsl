@@ -0,0 +1,27 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, r32f) uniform writeonly image2D img;
+
+/* In pixels, with (0, 0) the top left corner of the image. */
+layout(push_constant) uniform PushConstants {
+    vec2 center;
+    float radius;
+} pc;
+
+/* Every invocation shades one pixel from the signed distance from its center to the circle's
+ * edge, negative inside. Pixels more than half a pixel inside are fully covered, pixels more
+ * than half a pixel outside not at all, and the ones the edge runs through in between, which
+ * is what smooths the edge. */
+void main() {
+    if (any(greaterThanEqual(gl_GlobalInvocationID.xy, uvec2(imageSize(img))))) {
+        return;
+    }
+
+    vec2 pixel = vec2(gl_GlobalInvocationID.xy) + vec2(0.5);
+    float distance = length(pixel - pc.center) - pc.radius;
+    float coverage = clamp(0.5 - distance, 0.0, 1.0);
+
+    imageStore(img, ivec2(gl_GlobalInvocationID.xy), vec4(coverage));
+}
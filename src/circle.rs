@@ -0,0 +1,76 @@
+use image::{ImageBuffer, Luma};
+use std::f32::consts::PI;
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::Dimensions;
+use vulkano::pipeline::ComputePipeline;
+use vulkano::sync::GpuFuture;
+
+use crate::readback;
+use crate::storage;
+
+/* Must match the local size in circle.glsl. */
+const LOCAL_SIZE: u32 = 8;
+
+const SIZE: u32 = 512;
+
+/* The circle the full run draws, in pixels. */
+pub const CENTER: [f32; 2] = [256.0, 256.0];
+pub const RADIUS: f32 = 180.0;
+
+/* Draws a filled white circle on black with a compute shader and reads it back. The graphics
+ * pipeline would turn the circle into triangles and have the rasterizer work out which pixels
+ * they cover, and anti-alias by multisampling. Here every pixel works out its own coverage from
+ * how far it is from the edge, so the edge is smooth with one sample a pixel.
+ *
+ * The coverage is all there is to store, so the image has a single float channel. Every device
+ * can store to those from a shader, which isn't true of single channel 8 bit images. */
+pub fn draw_circle(device: Arc<Device>, queue: Arc<Queue>, center: [f32; 2], radius: f32)
+    -> ImageBuffer<Luma<u8>, Vec<u8>>
+{
+    let shader = cs::Shader::load(device.clone()).expect("Could not load circle shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let image = storage::image(&queue, Dimensions::Dim2d { width: SIZE, height: SIZE }, Format::R32Sfloat);
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_image(image.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = [SIZE.div_ceil(LOCAL_SIZE), SIZE.div_ceil(LOCAL_SIZE), 1];
+    AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .dispatch(groups, pipeline, set, cs::ty::PushConstants { center, radius }).unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    readback::download_luma8(device, queue, image).unwrap()
+}
+
+/* Checks the circle is filled inside, empty outside, only partly covers the pixels along its
+ * edge, and that the coverage adds up to its area. */
+pub fn verify(circle: &ImageBuffer<Luma<u8>, Vec<u8>>) {
+    let [x, y] = CENTER;
+    let brightness = |dx: f32| circle.get_pixel((x + dx) as u32, y as u32)[0];
+
+    assert!(brightness(0.0) == 255, "The middle of the circle isn't filled");
+    assert!(brightness(RADIUS - 2.0) == 255, "Just inside the edge isn't filled");
+    assert!(brightness(RADIUS + 2.0) == 0, "Just outside the edge isn't empty");
+
+    let edge = circle.pixels().filter(|pixel| pixel[0] > 0 && pixel[0] < 255).count();
+    assert!(edge > 0, "The edge isn't anti-aliased");
+
+    let area: f32 = circle.pixels().map(|pixel| pixel[0] as f32 / 255.0).sum();
+    let expected = PI * RADIUS * RADIUS;
+    assert!((area - expected).abs() / expected < 0.01, "The circle covers {} pixels, expected {}", area, expected);
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/circle.glsl"
+    }
+}
@@ -0,0 +1,40 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::Dimensions;
+
+use crate::batch;
+use crate::readback;
+use crate::storage;
+
+/* Colors to clear to, picked so every channel takes an extreme and an in-between value. */
+const COLORS: &[[u8; 4]] = &[[0, 0, 0, 0], [255, 255, 255, 255], [255, 0, 128, 255], [12, 200, 64, 100]];
+
+/* Creates a width x height storage image and fills it with `color` by clearing it. Render
+ * passes clear their attachments when they load them, but a storage image that is only ever
+ * written by compute shaders is never in a render pass, and `clear_color_image` is how it gets
+ * a known starting value instead. It's a transfer command, like a copy, so it can go anywhere
+ * outside a render pass. The color is given as floats, which the image's format decides how
+ * to store. */
+pub fn cleared(device: Arc<Device>, queue: Arc<Queue>, width: u32, height: u32, color: [u8; 4])
+    -> ImageBuffer<Rgba<u8>, Vec<u8>>
+{
+    let image = storage::image(&queue, Dimensions::Dim2d { width, height }, Format::R8G8B8A8Unorm);
+
+    let unorm = |c: u8| c as f32 / 255.0;
+    let value = ClearValue::Float([unorm(color[0]), unorm(color[1]), unorm(color[2]), unorm(color[3])]);
+    batch::submit_and_wait(device.clone(), queue.clone(), |builder| builder
+        .clear_color_image(image.clone(), value).unwrap());
+
+    readback::download_rgba8(device, queue, image)
+}
+
+/* Clears an image of an awkward size to each of the colors and checks every pixel of it. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    for &color in COLORS {
+        let image = cleared(device.clone(), queue.clone(), 129, 67, color);
+        let wrong = image.pixels().filter(|pixel| pixel.data != color).count();
+        assert!(wrong == 0, "{} pixels of an image cleared to {:?} aren't that color", wrong, color);
+    }
+}
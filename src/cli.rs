@@ -0,0 +1,857 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use vulkano::format::Format;
+use vulkano::instance::PhysicalDeviceType;
+
+use crate::bench;
+use crate::colorspace::ColorSpace;
+use crate::emit::Emit;
+use crate::explore;
+use crate::fxaa::Antialias;
+use crate::hdr;
+use crate::input;
+use crate::instance::{self, DebugLevel};
+use crate::layered;
+use crate::mandelbrot::{self, Adaptive, Coloring, Palette, Trap, View};
+use crate::median;
+use crate::pacing;
+use crate::postfx;
+use crate::quantize;
+use crate::raw;
+use crate::terrain::{self, Style};
+use crate::readback::Channels;
+use crate::record;
+use crate::repeat;
+use crate::scene;
+use crate::triangle;
+use crate::volume;
+use crate::watchdog::Deadlines;
+use crate::window;
+
+pub const USAGE: &str = "\
+Usage: vulkano-tut [options]
+
+Options:
+    --shader-spv <path>    Use a precompiled SPIR-V compute or fragment shader in place of
+                           the built-in mandelbrot or triangle shader
+    --device-name <name>   Use the device whose name contains name, ignoring case, instead
+                           of the best one, discrete GPUs first. VULKANO_TUT_DEVICE_NAME
+                           does the same when no device flag is given
+    --device-type <type>   Only consider devices of this type, discrete, integrated, cpu or
+                           virtual, with or without --device-name
+    --device-index <n>     Use device n, as --list-devices numbers them. So does
+                           VULKANO_TUT_DEVICE_INDEX when no device flag is given
+    --list-devices         Print every device with its type, limits and score, marking the
+                           one that would be used, and exit
+    --transfer-queue       Upload on a transfer-only queue family, if the device has one,
+                           handing what was uploaded over to the main queue afterwards
+    --compute-queue        Multiply the queues demo's numbers on a compute-only queue
+                           family, if the device has one
+    --overlap              Multiply --elements numbers in pieces, each uploaded on a
+                           transfer-only family and multiplied on a compute-only one where
+                           the device has them, without waiting in between, print how long
+                           that took against waiting after every submission, and exit
+    --watch <path>         Render a GLSL fragment or compute shader to <name>.png, again
+                           every time the file changes, until interrupted. Can be given
+                           more than once, and works on the built-in shaders in src too.
+                           With --window, swap it into the scene live instead
+    --dry-run              Build what the multiply, mandelbrot and triangle demos need,
+                           without running anything, and report whether it all worked
+    --scene <name>         Run only the copy-buffer, multiply, mandelbrot, triangle or
+                           instancing scene, printing what it computed or saving what it
+                           drew as <name>.png
+    --dump-after <scene>   Run only that scene, then write its output and the images and
+                           buffers it made along the way to dump/, images as PNG and buffers
+                           as .bin and .csv
+    --dump-dir <path>      With --dump-after, write to this directory instead of dump/
+    --dump-spirv           Write the SPIR-V of every built-in shader to <name>.spv and exit
+    --limits               Print the chosen device's limits, like the largest image it can
+                           render, and exit
+    --list-layers          Print the Vulkan instance layers that are installed and exit
+    --instance-layer <name>
+                           Enable the instance layer called name, like
+                           VK_LAYER_KHRONOS_validation. Can be given more than once
+    --debug                Enable the validation layer and print what it reports to stderr,
+                           carrying on without it if it isn't installed
+    --debug-level <level>  With --debug, print error, warning (the default), perf, info or
+                           debug messages and everything more severe. Implies --debug
+    --frames <n>           Render n frames of the triangle cycling through hues to
+                           triangle_NNNN.png
+    --particles <n>        Simulate n particles swirling around the center, saving --frames
+                           frames (120 by default) to particles_NNNN.png
+    --output <path>        With --particles, add all the frames up in one float image instead,
+                           like a long exposure, and save it to path as a Radiance .hdr file
+    --point-cloud          Render --frames frames (120 by default) of a camera circling a
+                           compute-generated point cloud to pointcloud_NNNN.png
+    --terrain <style>      Render --frames frames (120 by default) of a camera circling a
+                           noise heightmap, drawn as a wireframe or shaded, to
+                           terrain_NNNN.png
+    --cube                 Render --frames frames (120 by default) of a lit cube spinning
+                           once, drawn with a depth buffer, to cube_NNNN.png
+    --model <path>         Render the OBJ model at path, lit and fitted to the cube's size, to
+                           <name>.png, or with --frames spinning to <name>_NNNN.png
+    --texture <path>       Upload the PNG or JPEG at path with its mip chain and draw it on a
+                           quad to <name>_textured.png next to it
+    --window <scene>       Show the triangle, the mandelbrot or the cube live in a window, --size
+                           big to start with, instead of saving it, until the window is closed,
+                           or explore the fractals in one with `explore`. F1 hides the overlay
+                           with its controls
+    --fullscreen           With --window, cover the whole primary monitor without a border,
+                           at the size it already is. Escape goes back to a window
+    --monitor <n>          With --fullscreen, cover monitor n instead, numbered from 0 in the
+                           order the window system lists them
+    --swapchain-images <n> With --window, present from n swapchain images instead of one more
+                           than the fewest the window takes, as far as it allows n
+    --explore <events>     Play mouse and keyboard events, like `drag:40,0 scroll:2@100,80
+                           key:+ key:j`, through the fractal explorer, starting from the
+                           mandelbrot view, and save a frame after each to explore_NNNN.png.
+                           Keys: + and - iterations, p palette, j julia set, r reset
+    --record <animation>   Render --frames frames (120 by default) of the cube spinning or the
+                           mandelbrot zooming into --center offscreen, encoding each while
+                           the next is drawn, to frame_NNNN.png
+    --in-flight <1|2|3>    With --record, keep n frames in flight instead of 2, and print
+                           how long the CPU waited for them and the GPU took. 1 waits for
+                           every frame before starting the next, to compare with
+    --ffmpeg <path>        With --record, pipe the frames to ffmpeg to encode into the video
+                           at path instead
+    --octaves <n>          Make the terrain out of n octaves of noise, up to 12, instead of 5
+    --noise-scale <s>      Fit s cells of the terrain's first octave across it instead of 3
+    --descent-steps <n>    Walk the gradient descent demo's particles n steps downhill
+                           instead of 100
+    --step-size <s>        Move each particle s times the gradient per step instead of 0.01.
+                           Over about 0.02 they overshoot the minima and never settle
+    --samples <n>          Trace the orbits of n random points for the Buddhabrot instead of
+                           about four million
+    --seed <n>             Seed everything picked at random, the particles and the
+                           Buddhabrot's points, with n instead of 0. The same seed gives the
+                           same images on the same device
+    --elements <n>         Multiply n elements in the compute demo instead of checking a
+                           few sizes
+    --instances <n>        Draw n quads in the instancing demo's one draw call instead of
+                           10000
+    --format <format>      Render the triangle to an rgba8 (the default), rgba16f or bgra8
+                           target
+    --msaa <1|2|4|8>       Draw the triangle and the meshes with n samples per pixel, or the
+                           most the device supports below that
+    --antialias <none|fxaa>
+                           Smooth the triangle's and the mandelbrot's edges with FXAA before
+                           saving them, which works for compute renders where --msaa can't
+    --stdout <json|csv>    Print the multiply demo's output with each element's index and
+                           exit
+    --raw-output <demo>=<path>
+                           Also write the output buffer of demo (multiply, matmul, sort, or
+                           mandelbrot for its escape counts) to path as raw little endian
+                           values, with the type, count and dimensions in <path>.meta
+    --config <path>        Read any of the settings below, and output paths, from a TOML
+                           file. Flags given on the command line win. Without it
+                           sandbox.toml is read, if it is in the current directory
+    --no-config            Don't read sandbox.toml
+    --size <w>x<h>         Render the mandelbrot at w x h instead of 512x512, in tiles if
+                           it is bigger than the device's largest image. --resolution
+                           does the same
+    --width <w>, --height <h>
+                           Change just one side of the mandelbrot, keeping the other from
+                           --size or the config, or 512
+    --tile-size <n>        Render the mandelbrot in tiles of at most n x n pixels, which use
+                           less memory than the device's largest image but take longer
+    --center <x>,<y>       Center the mandelbrot on x + yi instead of -1 + 0i
+    --zoom <z>             Magnify the mandelbrot z times
+    --mandelbrot-region <x0>,<y0>,<x1>,<y1>
+                           Draw the rectangle from x0 + y0i at the top left to x1 + y1i at
+                           the bottom right, instead of using --center and --zoom
+    --max-iter <n>         Iterate each mandelbrot pixel at most n times instead of 200.
+                           --iters does the same
+    --max-iter-adaptive    Pick the mandelbrot's iterations from its zoom instead, as
+                           base + scale * ln(zoom), so deep zooms get more
+    --iter-base <n>        With --max-iter-adaptive, iterate n times at zoom 1, instead of 200
+    --iter-scale <k>       With --max-iter-adaptive, add k iterations for every e times the
+                           zoom grows, instead of 100
+    --palette <name>       Color the mandelbrot blue (the default) or grey
+    --coloring <mode>      Shade the mandelbrot by escape time (the default) or, with
+                           orbit-trap, by how close each orbit comes to --trap
+    --trap <shape>         The orbit trap: point:<x>,<y>, or line:<x>,<y>,<degrees> for the
+                           line through x + yi at that angle. By default the point 0,0
+    --light <x>,<y>,<z>    Light the normal map demo from direction x,y,z instead of 1,1,1,
+                           with x right, y up and z towards the viewer
+    --text <text>          Write text instead of \"Hello, Vulkan!\" in the text demo
+    --layer <n>            Draw into layer n of the 6 layer array image instead of layer 0
+    --slice <z>            Save slice z of the 64 slice distance volume instead of slice 32
+    --count-invocations    Count the invocations a mandelbrot-sized dispatch runs, to check
+                           it covers the whole image
+    --num-iterations       Render the mandelbrot at 50 to 5000 iterations, timing each one
+    --repeat <demo>        Run the mandelbrot (at --size, in tiles if needed) or the multiply
+                           demo over and over until Ctrl-C, printing how many runs it has
+                           done and how long they take, to check a GPU stays stable
+    --bench <scene>        Time the copy-buffer, multiply, mandelbrot, triangle or
+                           instancing scene, or all of them, printing the fastest, median
+                           and mean GPU time and the throughput of --runs runs after
+                           --warmup untimed ones
+    --runs <n>             With --bench, time n runs of each scene instead of 20
+    --benchmark-csv <path> With --num-iterations or --bench, also write the timings to a CSV
+                           file with demo, iteration, host_ms and gpu_ms columns
+    --warmup <n>           With --num-iterations, run the sweep n times untimed first, instead
+                           of twice, so the timings don't include the GPU getting up to speed.
+                           With --bench, run each scene n times untimed instead of 3
+    --input <path>         Load an image for --process or --filters, telling its format from
+                           its contents
+    --process <demo>       Run downsample, convolve, quantize, dither, median, rotate or
+                           colorspace on the --input image. Each also has a flag of its own
+                           taking the image's path, which is short for the two together
+    --downsample <path>    Save a half size copy of an image as <name>_half.png and exit
+    --convolve <path>      Save a copy of an image convolved with --kernel as
+                           <name>_convolved.png and exit
+    --kernel <w>,<w>,...   The weights of a square kernel of up to 7x7, row by row, instead
+                           of a 3x3 box blur. They are scaled to add up to one unless they
+                           add up to zero
+    --quantize <path>      Save a copy of an image reduced to --colors colors as
+                           <name>_quantized.png and exit
+    --colors <n>           Quantize to n colors, up to 64, instead of 16
+    --dither <path>        Save a copy of an image reduced to --bits bits per channel with
+                           ordered dithering as <name>_dithered.png and exit
+    --bits <n>             Dither to n bits per channel, from 1 to 8, instead of 4
+    --rotate <path>        Save a copy of an image rotated about its center by --degrees as
+                           <name>_rotated.png and exit
+    --degrees <d>          Rotate by d degrees counterclockwise instead of 30
+    --median <path>        Save a copy of an image with each pixel replaced by the median of
+                           a --median-window sized square around it as <name>_median.png
+                           and exit
+    --median-window <n>    Take the median over 5x5 pixels instead of 3x3
+    --colorspace <path>    Save a copy of an image converted from --from to --to as
+                           <name>_<to>.png and exit
+    --from <space>         Convert from rgb, hsv or yuv instead of rgb
+    --to <space>           Convert to rgb, hsv or yuv instead of hsv
+    --filters <f>,<f>,...  Run the compute filters blur, sobel, tonemap and invert in the
+                           order given over the --input image, saving <name>_filtered.png,
+                           or over the mandelbrot, saving mandelbrot_filtered.png, and exit
+    --compare <a> <b>      Print how much two images differ and exit, failing if any pixel
+                           differs by more than --threshold
+    --threshold <n>        Let compared pixels differ by up to n of 255 in each channel
+    --diff <path>          With --compare, save an image showing where they differ
+    --gamma <g>            Brighten (above 1) or darken (below 1) every saved image's
+                           midtones with a gamma curve, instead of leaving them at 1
+    --output-size <w>x<h>  Resize every saved image to w x h, stretching it to fit
+    --aspect-fit           With --output-size, keep the aspect ratio and letterbox instead
+    --checkerboard <n>     Save images composited over a checkerboard of n pixel squares, so
+                           transparent parts can be told apart from black ones
+    --channels <c>         Save images with one value per pixel, like the circle's coverage,
+                           as gray or as rgba, which is the default
+    --deadline [<demo>=]<s>
+                           Warn when a demo's GPU work runs longer than s seconds. Without
+                           a demo name (buddhabrot, mandelbrot, matmul, multiply, reduce or
+                           sort) it applies to all
+    --no-save              Run everything but don't write any images, for benchmarking. The
+                           time spent saving is printed when they are written
+    --profile-markers      Label each demo's GPU work with its name, for capture tools like
+                           RenderDoc. Needs VK_EXT_debug_marker, which they provide
+    --hash                 Print a hash of every image the demos save, to spot changes
+    -v, --verbose          Print extra detail, like every buffer and image allocated, how much
+                           GPU memory was in use at most and how many descriptor sets the
+                           animation reused
+    -h, --help             Print this message";
+
+/* Everything that can be configured from the command line. With no arguments the demos run
+ * exactly as they always have. */
+#[derive(Debug, Default)]
+pub struct Options {
+    pub shader_spv: Option<PathBuf>,
+    pub device_name: Option<String>,
+    pub device_type: Option<PhysicalDeviceType>,
+    pub device_index: Option<usize>,
+    pub list_devices: bool,
+    pub transfer_queue: bool,
+    pub compute_queue: bool,
+    pub overlap: bool,
+    pub dump_spirv: bool,
+    pub dry_run: bool,
+    pub scene: Option<&'static str>,
+    pub dump_after: Option<&'static str>,
+    pub dump_dir: Option<PathBuf>,
+    pub watch: Vec<PathBuf>,
+    pub limits: bool,
+    pub list_layers: bool,
+    pub instance_layers: Vec<String>,
+    pub debug: bool,
+    pub debug_level: Option<DebugLevel>,
+    pub num_iterations: bool,
+    pub repeat: Option<&'static str>,
+    pub benchmark_csv: Option<PathBuf>,
+    pub bench: Option<&'static str>,
+    pub runs: Option<u32>,
+    pub warmup: Option<u32>,
+    pub count_invocations: bool,
+    pub frames: Option<u32>,
+    pub particles: Option<u32>,
+    pub output: Option<PathBuf>,
+    pub point_cloud: bool,
+    pub terrain: Option<Style>,
+    pub cube: bool,
+    pub model: Option<PathBuf>,
+    pub texture: Option<PathBuf>,
+    pub explore: Option<Vec<explore::Event>>,
+    pub window: Option<&'static str>,
+    pub fullscreen: bool,
+    pub monitor: Option<usize>,
+    pub swapchain_images: Option<u32>,
+    pub record: Option<&'static str>,
+    pub in_flight: Option<usize>,
+    pub ffmpeg: Option<PathBuf>,
+    pub octaves: Option<u32>,
+    pub noise_scale: Option<f32>,
+    pub descent_steps: Option<u32>,
+    pub step_size: Option<f32>,
+    pub samples: Option<u32>,
+    pub seed: Option<u32>,
+    pub elements: Option<u32>,
+    pub instances: Option<u32>,
+    pub verbose: bool,
+    pub format: Option<Format>,
+    pub msaa: Option<u32>,
+    pub antialias: Option<Antialias>,
+    pub stdout: Option<Emit>,
+    pub raw_outputs: Vec<(String, PathBuf)>,
+    pub hash: bool,
+    pub profile_markers: bool,
+    pub no_save: bool,
+    pub input: Option<PathBuf>,
+    pub process: Option<&'static str>,
+    pub filters: Option<Vec<postfx::Filter>>,
+    pub kernel: Option<Vec<f32>>,
+    pub colors: Option<u32>,
+    pub bits: Option<u32>,
+    pub degrees: Option<f32>,
+    pub median_window: Option<u32>,
+    pub from: Option<ColorSpace>,
+    pub to: Option<ColorSpace>,
+    pub compare: Option<(PathBuf, PathBuf)>,
+    pub threshold: Option<u8>,
+    pub diff: Option<PathBuf>,
+    pub gamma: Option<f32>,
+    pub output_size: Option<(u32, u32)>,
+    pub aspect_fit: bool,
+    pub checkerboard: Option<u32>,
+    pub channels: Option<Channels>,
+    pub config: Option<PathBuf>,
+    pub no_config: bool,
+    pub size: Option<(u32, u32)>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub tile_size: Option<u32>,
+    pub center: Option<[f32; 2]>,
+    pub zoom: Option<f32>,
+    pub region: Option<[f32; 4]>,
+    pub max_iter: Option<u32>,
+    pub adaptive: bool,
+    pub iter_base: Option<u32>,
+    pub iter_scale: Option<f32>,
+    pub palette: Option<Palette>,
+    pub coloring: Option<Coloring>,
+    pub trap: Option<Trap>,
+    pub light: Option<[f32; 3]>,
+    pub text: Option<String>,
+    pub layer: Option<u32>,
+    pub slice: Option<u32>,
+    /* Only settable from a config file. */
+    pub mandelbrot_output: Option<PathBuf>,
+    pub triangle_output: Option<PathBuf>,
+    pub scene_output: Option<PathBuf>,
+    pub deadlines: Deadlines,
+}
+
+impl Options {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--shader-spv" => options.shader_spv = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--device-name" => options.device_name = Some(value(&arg, &mut args)?),
+                "--transfer-queue" => options.transfer_queue = true,
+                "--compute-queue" => options.compute_queue = true,
+                "--overlap" => options.overlap = true,
+                "--device-type" => options.device_type = Some(device_type(&arg, &value(&arg, &mut args)?)?),
+                "--device-index" => options.device_index = Some(count(&arg, &value(&arg, &mut args)?)? as usize),
+                "--dump-spirv" => options.dump_spirv = true,
+                "--dry-run" => options.dry_run = true,
+                "--scene" => options.scene = Some(scene::parse(&value(&arg, &mut args)?)?),
+                "--dump-after" => options.dump_after = Some(scene::parse(&value(&arg, &mut args)?)?),
+                "--dump-dir" => options.dump_dir = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--watch" => options.watch.push(PathBuf::from(value(&arg, &mut args)?)),
+                "--limits" => options.limits = true,
+                "--list-layers" => options.list_layers = true,
+                "--list-devices" => options.list_devices = true,
+                "--instance-layer" => options.instance_layers.push(value(&arg, &mut args)?),
+                "--debug" => options.debug = true,
+                "--debug-level" => options.debug_level = Some(DebugLevel::parse(&value(&arg, &mut args)?)?),
+                "--num-iterations" => options.num_iterations = true,
+                "--repeat" => options.repeat = Some(repeat::parse(&value(&arg, &mut args)?)?),
+                "--bench" => options.bench = Some(bench::parse(&value(&arg, &mut args)?)?),
+                "--runs" => options.runs = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--benchmark-csv" => options.benchmark_csv = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--warmup" => options.warmup = Some(count(&arg, &value(&arg, &mut args)?)?),
+                "--count-invocations" => options.count_invocations = true,
+                "--particles" => options.particles = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--output" => options.output = Some(hdr_path(&arg, &value(&arg, &mut args)?)?),
+                "--point-cloud" => options.point_cloud = true,
+                "--terrain" => options.terrain = Some(Style::parse(&value(&arg, &mut args)?)?),
+                "--cube" => options.cube = true,
+                "--model" => options.model = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--texture" => options.texture = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--explore" => options.explore = Some(explore::parse_script(&value(&arg, &mut args)?)?),
+                "--window" => options.window = Some(window::parse(&value(&arg, &mut args)?)?),
+                "--fullscreen" => options.fullscreen = true,
+                "--monitor" => options.monitor = Some(count(&arg, &value(&arg, &mut args)?)? as usize),
+                "--swapchain-images" => options.swapchain_images = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--record" => options.record = Some(record::parse(&value(&arg, &mut args)?)?),
+                "--in-flight" => options.in_flight = Some(in_flight(&arg, &value(&arg, &mut args)?)?),
+                "--ffmpeg" => options.ffmpeg = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--octaves" => options.octaves = Some(octaves(&arg, &value(&arg, &mut args)?)?),
+                "--noise-scale" => options.noise_scale = Some(positive_f32(&arg, &value(&arg, &mut args)?)?),
+                "--descent-steps" => options.descent_steps = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--step-size" => options.step_size = Some(positive_f32(&arg, &value(&arg, &mut args)?)?),
+                "--samples" => options.samples = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--seed" => options.seed = Some(count(&arg, &value(&arg, &mut args)?)?),
+                "--elements" => options.elements = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--instances" => options.instances = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--frames" => options.frames = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--format" => options.format = Some(format(&value(&arg, &mut args)?)?),
+                "--msaa" => options.msaa = Some(samples(&arg, &value(&arg, &mut args)?)?),
+                "--antialias" => options.antialias = Some(Antialias::parse(&value(&arg, &mut args)?)?),
+                "--stdout" => options.stdout = Some(Emit::parse(&value(&arg, &mut args)?)?),
+                "--raw-output" => options.raw_outputs.push(raw_output(&arg, &value(&arg, &mut args)?)?),
+                "--downsample" => options.process_flag(&arg, "downsample", &value(&arg, &mut args)?)?,
+                "--convolve" => options.process_flag(&arg, "convolve", &value(&arg, &mut args)?)?,
+                "--quantize" => options.process_flag(&arg, "quantize", &value(&arg, &mut args)?)?,
+                "--colors" => options.colors = Some(colors(&arg, &value(&arg, &mut args)?)?),
+                "--dither" => options.process_flag(&arg, "dither", &value(&arg, &mut args)?)?,
+                "--bits" => options.bits = Some(bits(&arg, &value(&arg, &mut args)?)?),
+                "--rotate" => options.process_flag(&arg, "rotate", &value(&arg, &mut args)?)?,
+                "--degrees" => options.degrees = Some(degrees(&arg, &value(&arg, &mut args)?)?),
+                "--median" => options.process_flag(&arg, "median", &value(&arg, &mut args)?)?,
+                "--median-window" => options.median_window = Some(median_window(&arg, &value(&arg, &mut args)?)?),
+                "--colorspace" => options.process_flag(&arg, "colorspace", &value(&arg, &mut args)?)?,
+                "--from" => options.from = Some(ColorSpace::parse(&value(&arg, &mut args)?)?),
+                "--to" => options.to = Some(ColorSpace::parse(&value(&arg, &mut args)?)?),
+                "--input" => options.input = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--process" => options.process = Some(input::parse(&value(&arg, &mut args)?)?),
+                "--filters" => options.filters = Some(postfx::parse_list(&value(&arg, &mut args)?)?),
+                "--kernel" => options.kernel = Some(weights(&arg, &value(&arg, &mut args)?)?),
+                "--compare" => {
+                    let a = PathBuf::from(value(&arg, &mut args)?);
+                    let b = PathBuf::from(args.next().ok_or_else(|| format!("`{}` expects two images", arg))?);
+                    options.compare = Some((a, b));
+                }
+                "--threshold" => options.threshold = Some(threshold(&arg, &value(&arg, &mut args)?)?),
+                "--diff" => options.diff = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--gamma" => options.gamma = Some(positive_f32(&arg, &value(&arg, &mut args)?)?),
+                "--output-size" => options.output_size = Some(size(&arg, &value(&arg, &mut args)?)?),
+                "--aspect-fit" => options.aspect_fit = true,
+                "--checkerboard" => options.checkerboard = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--channels" => options.channels = Some(Channels::parse(&value(&arg, &mut args)?)?),
+                "--hash" => options.hash = true,
+                "--profile-markers" => options.profile_markers = true,
+                "--no-save" => options.no_save = true,
+                "--deadline" => deadline(&arg, &value(&arg, &mut args)?, &mut options.deadlines)?,
+                "--config" => options.config = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--no-config" => options.no_config = true,
+                "--size" | "--resolution" => options.size = Some(size(&arg, &value(&arg, &mut args)?)?),
+                "--width" => options.width = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--height" => options.height = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--tile-size" => options.tile_size = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--center" => options.center = Some(point(&arg, &value(&arg, &mut args)?)?),
+                "--zoom" => options.zoom = Some(positive_f32(&arg, &value(&arg, &mut args)?)?),
+                "--mandelbrot-region" => options.region = Some(region(&arg, &value(&arg, &mut args)?)?),
+                "--max-iter" | "--iters" => options.max_iter = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--max-iter-adaptive" => options.adaptive = true,
+                "--iter-base" => options.iter_base = Some(positive(&arg, &value(&arg, &mut args)?)?),
+                "--iter-scale" => options.iter_scale = Some(positive_f32(&arg, &value(&arg, &mut args)?)?),
+                "--palette" => options.palette = Some(Palette::parse(&value(&arg, &mut args)?)?),
+                "--coloring" => options.coloring = Some(Coloring::parse(&value(&arg, &mut args)?)?),
+                "--trap" => options.trap = Some(Trap::parse(&value(&arg, &mut args)?)?),
+                "--text" => options.text = Some(value(&arg, &mut args)?),
+                "--layer" => options.layer = Some(layer(&arg, &value(&arg, &mut args)?)?),
+                "--slice" => options.slice = Some(slice(&arg, &value(&arg, &mut args)?)?),
+                "--light" => options.light = Some(direction(&arg, &value(&arg, &mut args)?)?),
+                "-v" | "--verbose" => options.verbose = true,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ => return Err(format!("Unknown argument `{}`", arg)),
+            }
+        }
+
+        if options.device_index.is_some() && (options.device_name.is_some() || options.device_type.is_some()) {
+            return Err("`--device-index` can't be combined with `--device-name` or `--device-type`".to_string());
+        }
+        if options.dump_dir.is_some() && options.dump_after.is_none() {
+            return Err("`--dump-dir` only applies with `--dump-after`".to_string());
+        }
+        if options.dump_after.is_some() && options.scene.is_some() {
+            return Err("`--dump-after` already runs its scene, so it can't be combined with `--scene`".to_string());
+        }
+        if options.config.is_some() && options.no_config {
+            return Err("`--config` can't be combined with `--no-config`".to_string());
+        }
+        if options.in_flight.is_some() && options.record.is_none() {
+            return Err("`--in-flight` needs `--record`".to_string());
+        }
+        if options.ffmpeg.is_some() && options.record.is_none() {
+            return Err("`--ffmpeg` needs `--record`".to_string());
+        }
+        if options.process.is_some() && options.filters.is_some() {
+            return Err("`--filters` can't be combined with a processing demo".to_string());
+        }
+        if options.input.is_some() != (options.process.is_some() || options.filters.is_some()) {
+            return Err("`--input` and `--process` or `--filters` have to be given together".to_string());
+        }
+        if options.fullscreen && options.window.is_none() {
+            return Err("`--fullscreen` needs `--window`".to_string());
+        }
+        if options.swapchain_images.is_some() && options.window.is_none() {
+            return Err("`--swapchain-images` needs `--window`".to_string());
+        }
+        if options.monitor.is_some() && !options.fullscreen {
+            return Err("`--monitor` only applies with `--fullscreen`".to_string());
+        }
+        if options.region.is_some() && (options.center.is_some() || options.zoom.is_some()) {
+            return Err("`--mandelbrot-region` can't be combined with `--center` or `--zoom`".to_string());
+        }
+        if options.output.is_some() && options.particles.is_none() {
+            return Err("`--output` only applies with `--particles`".to_string());
+        }
+        if options.adaptive && options.max_iter.is_some() {
+            return Err("`--max-iter-adaptive` can't be combined with `--max-iter`".to_string());
+        }
+        if !options.adaptive && (options.iter_base.is_some() || options.iter_scale.is_some()) {
+            return Err("`--iter-base` and `--iter-scale` only apply with `--max-iter-adaptive`".to_string());
+        }
+
+        Ok(options)
+    }
+
+    /* Handles a processing demo's own flag, like `--dither <path>`, which is short for
+     * `--input <path> --process dither`. */
+    fn process_flag(&mut self, flag: &str, demo: &'static str, path: &str) -> Result<(), String> {
+        if self.process.is_some() {
+            return Err(format!("`{}` can't be combined with another processing demo", flag));
+        }
+        self.input = Some(PathBuf::from(path));
+        self.process = Some(demo);
+        Ok(())
+    }
+
+    /* Where `--raw-output` asked for `demo`'s output to go, if anywhere. */
+    pub fn raw_output(&self, demo: &str) -> Option<&Path> {
+        self.raw_outputs.iter().rev().find(|(name, _)| name == demo).map(|(_, path)| path.as_path())
+    }
+
+    /* What the validation layer should report, if `--debug` or `--debug-level` turned it on. */
+    pub fn debug(&self) -> Option<DebugLevel> {
+        if self.debug || self.debug_level.is_some() {
+            Some(self.debug_level.unwrap_or(instance::DEFAULT_DEBUG_LEVEL))
+        } else {
+            None
+        }
+    }
+
+    /* The size to render the mandelbrot at, taking each side from `--width` or `--height` over
+     * `--size`, and 512 for whatever none of them give. */
+    pub fn dimensions(&self) -> (u32, u32) {
+        let (width, height) = self.size.unwrap_or((512, 512));
+        (self.width.unwrap_or(width), self.height.unwrap_or(height))
+    }
+
+    /* The mandelbrot view the options ask for, defaulting whatever they leave out. */
+    pub fn view(&self) -> View {
+        let defaults = View::default();
+        let mut view = View {
+            center: self.center.unwrap_or(defaults.center),
+            zoom: self.zoom.unwrap_or(defaults.zoom),
+            region: self.region,
+            max_iter: self.max_iter.unwrap_or(defaults.max_iter),
+            palette: self.palette.unwrap_or(defaults.palette),
+            coloring: self.coloring.unwrap_or(defaults.coloring),
+            trap: self.trap.unwrap_or(defaults.trap),
+        };
+        if let Some(adaptive) = self.adaptive() {
+            view.max_iter = adaptive.max_iter(view.magnification());
+        }
+        view
+    }
+
+    /* How `--max-iter-adaptive` picks the iterations, if it was given. */
+    pub fn adaptive(&self) -> Option<Adaptive> {
+        if !self.adaptive {
+            return None;
+        }
+        Some(Adaptive {
+            base: self.iter_base.unwrap_or(mandelbrot::DEFAULT_ITER_BASE),
+            scale: self.iter_scale.unwrap_or(mandelbrot::DEFAULT_ITER_SCALE),
+        })
+    }
+}
+
+/* Takes the value following a flag, e.g. the path after `--shader-spv`. */
+fn value<I: Iterator<Item = String>>(flag: &str, args: &mut I) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("`{}` expects a value", flag))
+}
+
+/* Parses a count that has to be at least one, like the number of frames to render. */
+fn positive(flag: &str, value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("`{}` expects a positive integer, got `{}`", flag, value)),
+    }
+}
+
+/* Parses a count that can be zero, like the number of warmup runs. */
+fn count(flag: &str, value: &str) -> Result<u32, String> {
+    value.parse::<u32>().map_err(|_| format!("`{}` expects a whole number, got `{}`", flag, value))
+}
+
+/* Like `positive`, for values that needn't be whole, like the zoom. */
+fn positive_f32(flag: &str, value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(x) if x > 0.0 && x.is_finite() => Ok(x),
+        _ => Err(format!("`{}` expects a positive number, got `{}`", flag, value)),
+    }
+}
+
+/* Parses `30`, which sets the deadline for every demo, or `mandelbrot=30`, which sets one. */
+fn deadline(flag: &str, value: &str, deadlines: &mut Deadlines) -> Result<(), String> {
+    let mut parts = value.rsplitn(2, '=');
+    let seconds = Duration::from_secs_f32(positive_f32(flag, parts.next().unwrap())?);
+    match parts.next() {
+        Some(demo) => deadlines.per_demo.push((demo.to_string(), seconds)),
+        None => deadlines.default = Some(seconds),
+    }
+    Ok(())
+}
+
+/* Parses the path of an HDR image to save. */
+fn hdr_path(flag: &str, value: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(value);
+    hdr::check(&path).map_err(|e| format!("`{}`: {}", flag, e))?;
+    Ok(path)
+}
+
+/* Parses `sort=sorted.bin`: a demo with an output buffer, and where to write it. */
+fn raw_output(flag: &str, value: &str) -> Result<(String, PathBuf), String> {
+    let mut parts = value.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(demo), Some(path)) if raw::DEMOS.contains(&demo) && !path.is_empty() =>
+            Ok((demo.to_string(), PathBuf::from(path))),
+        (Some(demo), Some(_)) if !raw::DEMOS.contains(&demo) =>
+            Err(format!("`{}` can't save {}, only {}", flag, demo, raw::DEMOS.join(", "))),
+        _ => Err(format!("`{}` expects <demo>=<path>, got `{}`", flag, value)),
+    }
+}
+
+/* Parses a point like `-0.75,0.1`. */
+fn point(flag: &str, value: &str) -> Result<[f32; 2], String> {
+    let parsed: Vec<_> = value.split(',').map(|part| part.trim().parse::<f32>()).collect();
+    match parsed.as_slice() {
+        [Ok(x), Ok(y)] => Ok([*x, *y]),
+        _ => Err(format!("`{}` expects a point like -0.75,0.1, got `{}`", flag, value)),
+    }
+}
+
+/* Parses a rectangle like `-2,-1.5,1,1.5`, which has to have its first corner above and to the
+ * left of its second. */
+fn region(flag: &str, value: &str) -> Result<[f32; 4], String> {
+    let parsed: Vec<_> = value.split(',').map(|part| part.trim().parse::<f32>()).collect();
+    match parsed.as_slice() {
+        [Ok(x0), Ok(y0), Ok(x1), Ok(y1)] if [x0, y0, x1, y1].iter().all(|c| c.is_finite()) => {
+            if x0 >= x1 || y0 >= y1 {
+                return Err(format!("`{}` expects x0 < x1 and y0 < y1, got `{}`", flag, value));
+            }
+            Ok([*x0, *y0, *x1, *y1])
+        }
+        _ => Err(format!("`{}` expects a rectangle like -2,-1.5,1,1.5, got `{}`", flag, value)),
+    }
+}
+
+/* Parses a direction like `1,1,1`. It doesn't have to be unit length, but it can't be zero. */
+fn direction(flag: &str, value: &str) -> Result<[f32; 3], String> {
+    let parsed: Vec<_> = value.split(',').map(|part| part.trim().parse::<f32>()).collect();
+    match parsed.as_slice() {
+        [Ok(x), Ok(y), Ok(z)] if (x * x + y * y + z * z).is_normal() => Ok([*x, *y, *z]),
+        _ => Err(format!("`{}` expects a non-zero direction like 1,1,1, got `{}`", flag, value)),
+    }
+}
+
+/* Parses a list of numbers like `0,-1,0,-1,5,-1,0,-1,0`. */
+fn weights(flag: &str, value: &str) -> Result<Vec<f32>, String> {
+    value.split(',').map(|part| match part.trim().parse::<f32>() {
+        Ok(w) if w.is_finite() => Ok(w),
+        _ => Err(format!("`{}` expects numbers separated by commas, got `{}`", flag, value)),
+    }).collect()
+}
+
+/* Parses a size like `640x480`. */
+fn size(flag: &str, value: &str) -> Result<(u32, u32), String> {
+    let mut parts = value.splitn(2, 'x');
+    match (parts.next(), parts.next()) {
+        (Some(width), Some(height)) => Ok((positive(flag, width)?, positive(flag, height)?)),
+        _ => Err(format!("`{}` expects a size like 640x480, got `{}`", flag, value)),
+    }
+}
+
+/* Parses how far apart two compared channels may be. */
+fn threshold(flag: &str, value: &str) -> Result<u8, String> {
+    value.parse::<u8>().map_err(|_| format!("`{}` expects a number from 0 to 255, got `{}`", flag, value))
+}
+
+/* Parses a palette size for the quantizer. */
+fn colors(flag: &str, value: &str) -> Result<u32, String> {
+    match positive(flag, value)? {
+        n if n <= quantize::MAX_COLORS => Ok(n),
+        n => Err(format!("`{}` can be at most {}, got {}", flag, quantize::MAX_COLORS, n)),
+    }
+}
+
+/* Parses a bit depth for the dither. */
+fn bits(flag: &str, value: &str) -> Result<u32, String> {
+    match positive(flag, value)? {
+        n if n <= 8 => Ok(n),
+        n => Err(format!("`{}` can be at most 8 bits, got {}", flag, n)),
+    }
+}
+
+/* Parses a number of noise octaves for the terrain. */
+fn octaves(flag: &str, value: &str) -> Result<u32, String> {
+    match positive(flag, value)? {
+        n if n <= terrain::MAX_OCTAVES => Ok(n),
+        n => Err(format!("`{}` can be at most {}, got {}", flag, terrain::MAX_OCTAVES, n)),
+    }
+}
+
+/* Parses how many frames to keep in flight, 1 to 3. */
+fn in_flight(flag: &str, value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(n) if (pacing::MIN_FRAMES..=pacing::MAX_FRAMES).contains(&n) => Ok(n),
+        _ => Err(format!("`{}` expects {} to {} frames, got `{}`", flag, pacing::MIN_FRAMES, pacing::MAX_FRAMES,
+            value)),
+    }
+}
+
+/* Parses a median window size, 3 or 5. */
+fn median_window(flag: &str, value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(n) if median::WINDOWS.contains(&n) => Ok(n),
+        _ => Err(format!("`{}` expects 3 or 5, got `{}`", flag, value)),
+    }
+}
+
+/* Parses an angle in degrees, which can be anything but infinite. */
+fn degrees(flag: &str, value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(d) if d.is_finite() => Ok(d),
+        _ => Err(format!("`{}` expects an angle in degrees, got `{}`", flag, value)),
+    }
+}
+
+/* Parses a layer of the layered demo's array image. */
+fn layer(flag: &str, value: &str) -> Result<u32, String> {
+    let layer = value.parse::<u32>().map_err(|_| format!("`{}` expects a layer number, got `{}`", flag, value))?;
+    if layer >= layered::LAYERS {
+        return Err(format!("`{}` can be at most {}, the array image has {} layers",
+            flag, layered::LAYERS - 1, layered::LAYERS));
+    }
+    Ok(layer)
+}
+
+/* Parses a slice of the volume demo's 3D image. */
+fn slice(flag: &str, value: &str) -> Result<u32, String> {
+    let z = value.parse::<u32>().map_err(|_| format!("`{}` expects a slice number, got `{}`", flag, value))?;
+    if z >= volume::SIDE {
+        return Err(format!("`{}` can be at most {}, the volume has {} slices", flag, volume::SIDE - 1, volume::SIDE));
+    }
+    Ok(z)
+}
+
+/* Parses a sample count, which Vulkan only allows to be a power of two. */
+pub fn samples(flag: &str, value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(n) if triangle::SAMPLE_COUNTS.contains(&n) => Ok(n),
+        _ => Err(format!("`{}` expects 1, 2, 4 or 8, got `{}`", flag, value)),
+    }
+}
+
+/* Parses a `--device-type`. */
+pub fn device_type(flag: &str, value: &str) -> Result<PhysicalDeviceType, String> {
+    match value {
+        "discrete" => Ok(PhysicalDeviceType::DiscreteGpu),
+        "integrated" => Ok(PhysicalDeviceType::IntegratedGpu),
+        "cpu" => Ok(PhysicalDeviceType::Cpu),
+        "virtual" => Ok(PhysicalDeviceType::VirtualGpu),
+        _ => Err(format!("`{}` expects discrete, integrated, cpu or virtual, got `{}`", flag, value)),
+    }
+}
+
+/* Looks up a `--format` name. */
+pub fn format(name: &str) -> Result<Format, String> {
+    triangle::FORMATS.iter().find(|&&(n, _)| n == name).map(|&(_, format)| format).ok_or_else(|| {
+        let names: Vec<_> = triangle::FORMATS.iter().map(|&(n, _)| n).collect();
+        format!("Unknown format `{}`, expected one of {}", name, names.join(", "))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_leave_the_defaults() {
+        let options = parse(&[]).unwrap();
+        assert_eq!(options.dimensions(), (512, 512));
+        assert!(options.debug().is_none() && options.adaptive().is_none());
+        assert_eq!(options.view().max_iter, View::default().max_iter);
+    }
+
+    #[test]
+    fn flags_take_their_values() {
+        let options = parse(&["--size", "640x480", "--height", "200", "--zoom", "2.5", "--seed", "0",
+            "--raw-output", "sort=sorted.bin", "--compare", "a.png", "b.png", "--deadline", "mandelbrot=2"]).unwrap();
+        assert_eq!(options.dimensions(), (640, 200));
+        assert_eq!(options.view().zoom, 2.5);
+        assert_eq!(options.seed, Some(0));
+        assert_eq!(options.raw_output("sort"), Some(Path::new("sorted.bin")));
+        assert_eq!(options.compare, Some((PathBuf::from("a.png"), PathBuf::from("b.png"))));
+        assert_eq!(options.deadlines.per_demo, vec![("mandelbrot".to_string(), Duration::from_secs(2))]);
+    }
+
+    #[test]
+    fn bad_values_are_errors() {
+        for args in &[&["--size"][..], &["--size", "640"], &["--frames", "0"], &["--zoom", "inf"],
+                      &["--mandelbrot-region", "1,0,-1,1"], &["--bits", "9"], &["--median-window", "4"],
+                      &["--msaa", "3"], &["--raw-output", "triangle=out.bin"], &["--light", "0,0,0"],
+                      &["--layer", "6"], &["--window", "sphere"], &["--nonsense"]] {
+            assert!(parse(args).is_err(), "{:?} parsed", args);
+        }
+    }
+
+    #[test]
+    fn conflicting_flags_are_errors() {
+        for args in &[&["--device-index", "0", "--device-name", "gpu"][..], &["--dump-dir", "out"],
+                      &["--in-flight", "2"], &["--config", "a.toml", "--no-config"],
+                      &["--mandelbrot-region", "-2,-1,1,1", "--zoom", "2"],
+                      &["--max-iter-adaptive", "--max-iter", "100"], &["--iter-base", "100"],
+                      &["--dither", "a.png", "--quantize", "b.png"], &["--input", "a.png"], &["--fullscreen"],
+                      &["--window", "triangle", "--monitor", "1"], &["--swapchain-images", "3"]] {
+            assert!(parse(args).is_err(), "{:?} parsed", args);
+        }
+    }
+
+    #[test]
+    fn adaptive_iterations_follow_the_zoom() {
+        let options = parse(&["--max-iter-adaptive", "--iter-base", "100", "--iter-scale", "50"]).unwrap();
+        assert_eq!(options.view().max_iter, 100);
+        let zoomed = parse(&["--max-iter-adaptive", "--zoom", "1000"]).unwrap();
+        assert!(zoomed.view().max_iter > mandelbrot::DEFAULT_ITER_BASE);
+    }
+}
@@ -0,0 +1,37 @@
+#version 450
+
+/* The points straight out of the compute shader's buffer. There is no vertex input: each
+ * vertex fetches its own point by index. */
+layout(set = 0, binding = 0) readonly buffer Points {
+    vec4 points[];
+} buf;
+
+layout(push_constant) uniform PushConstants {
+    float angle;
+} pc;
+
+layout(location = 0) out float height;
+
+/* Must match the camera in pointcloud.rs. */
+const float AMPLITUDE = 0.35;
+const float TILT = 0.5;
+const float DISTANCE = 4.0;
+const float FOCAL = 1.5;
+
+/* A camera circling the surface: the points are turned by `angle` about the vertical axis and
+ * tilted towards the camera, which sits DISTANCE along +z looking back at the origin. */
+void main() {
+    vec3 p = buf.points[gl_VertexIndex].xyz;
+    float c = cos(pc.angle), s = sin(pc.angle);
+    p = vec3(c * p.x + s * p.z, p.y, -s * p.x + c * p.z);
+    c = cos(TILT);
+    s = sin(TILT);
+    p = vec3(p.x, c * p.y - s * p.z, s * p.y + c * p.z);
+
+    /* Clip space y points down, so up is flipped to stay up on screen. */
+    float w = DISTANCE - p.z;
+    gl_Position = vec4(FOCAL * p.x, -FOCAL * p.y, 0.0, w);
+    /* Anything bigger needs the large points feature, which the device isn't created with. */
+    gl_PointSize = 1.0;
+    height = buf.points[gl_VertexIndex].y / AMPLITUDE * 0.5 + 0.5;
+}
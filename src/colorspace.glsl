@@ -0,0 +1,74 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D src;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D dst;
+
+/* Must match Op in colorspace.rs. */
+const uint RGB_TO_HSV = 0;
+const uint HSV_TO_RGB = 1;
+const uint RGB_TO_YUV = 2;
+const uint YUV_TO_RGB = 3;
+
+layout(push_constant) uniform PushConstants {
+    uint op;
+} pc;
+
+/* Hue, saturation and value, each 0 to 1. The value is the largest channel and the saturation
+ * how far the smallest is below it. The hue says which channel is largest, a sixth of the way
+ * round the wheel per primary or secondary color, and where between its neighbours it is. */
+vec3 rgb_to_hsv(vec3 c) {
+    float high = max(c.r, max(c.g, c.b));
+    float low = min(c.r, min(c.g, c.b));
+    float chroma = high - low;
+    float h = 0.0;
+    if (chroma > 0.0) {
+        if (high == c.r) {
+            h = mod((c.g - c.b) / chroma, 6.0);
+        } else if (high == c.g) {
+            h = (c.b - c.r) / chroma + 2.0;
+        } else {
+            h = (c.r - c.g) / chroma + 4.0;
+        }
+    }
+    return vec3(h / 6.0, high > 0.0 ? chroma / high : 0.0, high);
+}
+
+/* The same as hue.glsl's. */
+vec3 hsv_to_rgb(vec3 c) {
+    vec3 p = abs(fract(c.xxx + vec3(0.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - 3.0);
+    return c.z * mix(vec3(1.0), clamp(p - 1.0, 0.0, 1.0), c.y);
+}
+
+/* Full range BT.601, as JPEG uses: Y is the brightness, weighted by how bright each primary
+ * looks, and U and V the blue and red differences from it, which are -0.5 to 0.5 and so are
+ * stored offset by a half to fit in an unsigned image. */
+vec3 rgb_to_yuv(vec3 c) {
+    float y = dot(c, vec3(0.299, 0.587, 0.114));
+    return vec3(y, (c.b - y) * 0.564 + 0.5, (c.r - y) * 0.713 + 0.5);
+}
+
+vec3 yuv_to_rgb(vec3 c) {
+    float u = c.y - 0.5;
+    float v = c.z - 0.5;
+    return vec3(c.x + 1.402 * v, c.x - 0.344136 * u - 0.714136 * v, c.x + 1.772 * u);
+}
+
+/* Converts one pixel, leaving alpha alone. */
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coords, imageSize(dst)))) {
+        return;
+    }
+
+    vec4 color = imageLoad(src, coords);
+    vec3 converted;
+    switch (pc.op) {
+        case RGB_TO_HSV: converted = rgb_to_hsv(color.rgb); break;
+        case HSV_TO_RGB: converted = hsv_to_rgb(color.rgb); break;
+        case RGB_TO_YUV: converted = rgb_to_yuv(color.rgb); break;
+        default: converted = yuv_to_rgb(color.rgb); break;
+    }
+    imageStore(dst, coords, vec4(converted, color.a));
+}
@@ -0,0 +1,131 @@
+use image::{ImageBuffer, Rgba};
+use std::path::PathBuf;
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+use vulkano::pipeline::ComputePipeline;
+
+use crate::input::{self, Input};
+use crate::pattern;
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/* What the three channels of an image hold. HSV keeps hue, saturation and value in red, green
+ * and blue, and YUV brightness and the two color differences, so either can be saved as a PNG
+ * like any other image, just not looked at as one. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorSpace {
+    Rgb,
+    Hsv,
+    Yuv,
+}
+
+impl ColorSpace {
+    pub fn parse(name: &str) -> Result<ColorSpace, String> {
+        match name {
+            "rgb" => Ok(ColorSpace::Rgb),
+            "hsv" => Ok(ColorSpace::Hsv),
+            "yuv" => Ok(ColorSpace::Yuv),
+            _ => Err(format!("Unknown color space `{}`, expected rgb, hsv or yuv", name)),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ColorSpace::Rgb => "rgb",
+            ColorSpace::Hsv => "hsv",
+            ColorSpace::Yuv => "yuv",
+        }
+    }
+}
+
+/* What `--from` and `--to` default to. */
+pub const DEFAULT_FROM: ColorSpace = ColorSpace::Rgb;
+pub const DEFAULT_TO: ColorSpace = ColorSpace::Hsv;
+
+/* The conversions colorspace.glsl knows, as its `op` push constant. */
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    RgbToHsv = 0,
+    HsvToRgb = 1,
+    RgbToYuv = 2,
+    YuvToRgb = 3,
+}
+
+/* Runs one conversion over every pixel of `pixels`. */
+fn run(device: Arc<Device>, queue: Arc<Queue>, pixels: &Image, op: Op) -> Image {
+    let shader = cs::Shader::load(device.clone()).expect("Could not load color space shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let push_constants = cs::ty::PushConstants { op: op as u32 };
+    input::filter("colorspace", device, queue, pixels, pipeline, push_constants)
+}
+
+/* Converts `pixels` from one color space to another, going by way of RGB when neither end is,
+ * and leaving alpha alone. Taking an image to HSV, changing its hue or saturation channel and
+ * bringing it back is the usual way to recolor it without touching its brightness. Each trip
+ * rounds to 8 bits, and a hue a 256th of the way round the wheel out moves a saturated color by
+ * up to three levels, so a round trip can be a few levels off. */
+pub fn convert(device: Arc<Device>, queue: Arc<Queue>, pixels: &Image, from: ColorSpace, to: ColorSpace) -> Image {
+    if from == to {
+        return pixels.clone();
+    }
+    let rgb = match from {
+        ColorSpace::Rgb => pixels.clone(),
+        ColorSpace::Hsv => run(device.clone(), queue.clone(), pixels, Op::HsvToRgb),
+        ColorSpace::Yuv => run(device.clone(), queue.clone(), pixels, Op::YuvToRgb),
+    };
+    match to {
+        ColorSpace::Rgb => rgb,
+        ColorSpace::Hsv => run(device, queue, &rgb, Op::RgbToHsv),
+        ColorSpace::Yuv => run(device, queue, &rgb, Op::RgbToYuv),
+    }
+}
+
+/* Converts the image loaded for `--input` and saves the result next to it as <name>_<to>.png,
+ * returning where it went. */
+pub fn convert_colorspace(device: Arc<Device>, queue: Arc<Queue>, input: &Input, from: ColorSpace, to: ColorSpace)
+    -> Result<PathBuf, String>
+{
+    input.save(&convert(device, queue, &input.pixels, from, to), to.name())
+}
+
+/* The largest difference between two images in any channel of any pixel. */
+fn largest_difference(a: &Image, b: &Image) -> u8 {
+    a.iter().zip(b.iter()).map(|(&x, &y)| (x as i16 - y as i16).unsigned_abs() as u8).max().unwrap_or(0)
+}
+
+/* Checks a few colors come out of HSV and YUV where they should, then round trips the UV
+ * pattern through each and checks it comes back within a few levels, with alpha untouched. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let colors: Vec<([u8; 4], [u8; 3], [u8; 3])> = vec![
+        ([255, 0, 0, 255], [0, 255, 255], [76, 85, 255]),
+        ([0, 255, 0, 255], [85, 255, 255], [150, 43, 21]),
+        ([0, 0, 255, 255], [170, 255, 255], [29, 255, 107]),
+        ([128, 128, 128, 100], [0, 0, 128], [128, 128, 128]),
+    ];
+    let swatch = ImageBuffer::from_fn(colors.len() as u32, 1, |x, _| Rgba(colors[x as usize].0));
+    let hsv = convert(device.clone(), queue.clone(), &swatch, ColorSpace::Rgb, ColorSpace::Hsv);
+    let yuv = convert(device.clone(), queue.clone(), &swatch, ColorSpace::Rgb, ColorSpace::Yuv);
+    for (x, &(rgb, expected_hsv, expected_yuv)) in colors.iter().enumerate() {
+        let (h, y) = (hsv.get_pixel(x as u32, 0), yuv.get_pixel(x as u32, 0));
+        assert!((0..3).all(|c| (h[c] as i16 - expected_hsv[c] as i16).abs() <= 1) && h[3] == rgb[3],
+            "{:?} is {:?} in HSV, expected {:?}", rgb, h, expected_hsv);
+        assert!((0..3).all(|c| (y[c] as i16 - expected_yuv[c] as i16).abs() <= 1) && y[3] == rgb[3],
+            "{:?} is {:?} in YUV, expected {:?}", rgb, y, expected_yuv);
+    }
+
+    let source = pattern::uv(device.clone(), queue.clone(), 64, 64);
+    for &space in &[ColorSpace::Hsv, ColorSpace::Yuv] {
+        let there = convert(device.clone(), queue.clone(), &source, ColorSpace::Rgb, space);
+        let back = convert(device.clone(), queue.clone(), &there, space, ColorSpace::Rgb);
+        let off = largest_difference(&source, &back);
+        assert!(off <= 6, "RGB to {} and back is up to {} levels off", space.name(), off);
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/colorspace.glsl"
+    }
+}
@@ -0,0 +1,115 @@
+use image::{ImageBuffer, Rgba};
+use std::path::Path;
+
+/* How far apart two images are. Differences are per channel, in 0-255 steps, and a pixel counts
+ * as differing if any of its channels is further apart than the threshold. */
+#[derive(Debug)]
+pub struct Difference {
+    pub max: u8,
+    pub mean: f64,
+    pub differing: usize,
+    pub pixels: usize,
+}
+
+impl Difference {
+    pub fn print(&self, threshold: u8) {
+        println!("Max difference:  {}", self.max);
+        println!("Mean difference: {:.4}", self.mean);
+        println!("Pixels differing by more than {}: {} of {} ({:.2}%)", threshold, self.differing, self.pixels,
+            100.0 * self.differing as f64 / self.pixels.max(1) as f64);
+    }
+}
+
+/* The largest difference between any channel of two pixels. */
+fn pixel_difference(a: &Rgba<u8>, b: &Rgba<u8>) -> u8 {
+    (0..4).map(|c| (a[c] as i32 - b[c] as i32).unsigned_abs() as u8).max().unwrap()
+}
+
+pub fn compare(a: &ImageBuffer<Rgba<u8>, Vec<u8>>, b: &ImageBuffer<Rgba<u8>, Vec<u8>>, threshold: u8)
+    -> Result<Difference, String>
+{
+    if a.dimensions() != b.dimensions() {
+        return Err(format!("Can't compare a {}x{} image with a {}x{} one",
+            a.width(), a.height(), b.width(), b.height()));
+    }
+
+    let channels = a.iter().zip(b.iter()).map(|(x, y)| (*x as i32 - *y as i32).unsigned_abs() as u64);
+    let (sum, max) = channels.fold((0, 0), |(sum, max), d| (sum + d, max.max(d)));
+    let differing = a.pixels().zip(b.pixels()).filter(|(x, y)| pixel_difference(x, y) > threshold).count();
+
+    Ok(Difference {
+        max: max as u8,
+        mean: sum as f64 / a.len().max(1) as f64,
+        differing,
+        pixels: (a.width() * a.height()) as usize,
+    })
+}
+
+/* Shows where two images of the same size differ: pixels over the threshold in red, brighter the
+ * bigger the difference, on top of a faded grey copy of `a` to make them easy to place. */
+pub fn visualize(a: &ImageBuffer<Rgba<u8>, Vec<u8>>, b: &ImageBuffer<Rgba<u8>, Vec<u8>>, threshold: u8)
+    -> ImageBuffer<Rgba<u8>, Vec<u8>>
+{
+    ImageBuffer::from_fn(a.width(), a.height(), |x, y| {
+        let (pa, pb) = (a.get_pixel(x, y), b.get_pixel(x, y));
+        let difference = pixel_difference(pa, pb);
+        if difference > threshold {
+            Rgba([128 + difference / 2, 0, 0, 255])
+        } else {
+            let grey = ((pa[0] as u32 + pa[1] as u32 + pa[2] as u32) / 3 / 4) as u8;
+            Rgba([grey, grey, grey, 255])
+        }
+    })
+}
+
+/* Loads and compares two image files, writing the visualization to `diff` if given. */
+pub fn compare_files(a: &Path, b: &Path, threshold: u8, diff: Option<&Path>) -> Result<Difference, String> {
+    let load = |path: &Path| image::open(path)
+        .map(|image| image.to_rgba())
+        .map_err(|e| format!("Could not load {}: {}", path.display(), e));
+    let (a_pixels, b_pixels) = (load(a)?, load(b)?);
+
+    let difference = compare(&a_pixels, &b_pixels, threshold)
+        .map_err(|e| format!("{} and {}: {}", a.display(), b.display(), e))?;
+
+    if let Some(path) = diff {
+        visualize(&a_pixels, &b_pixels, threshold).save(path)
+            .map_err(|e| format!("Could not save {}: {}", path.display(), e))?;
+    }
+    Ok(difference)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_images_dont_differ() {
+        let a = ImageBuffer::from_fn(4, 4, |x, y| Rgba([x as u8 * 60, y as u8 * 60, 0, 255]));
+        let difference = compare(&a, &a.clone(), 0).unwrap();
+        assert_eq!((difference.max, difference.mean, difference.differing, difference.pixels), (0, 0.0, 0, 16));
+    }
+
+    #[test]
+    fn only_pixels_past_the_threshold_count() {
+        let a = ImageBuffer::from_pixel(2, 2, Rgba([100u8, 100, 100, 255]));
+        let mut b = a.clone();
+        b.put_pixel(0, 0, Rgba([110, 100, 100, 255]));
+        b.put_pixel(1, 1, Rgba([100, 97, 100, 255]));
+
+        let difference = compare(&a, &b, 5).unwrap();
+        assert_eq!((difference.max, difference.differing), (10, 1));
+        assert!((difference.mean - 13.0 / 16.0).abs() < 1e-9, "The mean difference is {}", difference.mean);
+
+        let diff = visualize(&a, &b, 5);
+        assert_eq!(diff.get_pixel(0, 0).data, [133, 0, 0, 255]);
+        assert_eq!(diff.get_pixel(1, 1).data, [25, 25, 25, 255]);
+    }
+
+    #[test]
+    fn different_sizes_are_an_error() {
+        let a = ImageBuffer::from_pixel(2, 2, Rgba([0u8; 4]));
+        let b = ImageBuffer::from_pixel(2, 3, Rgba([0u8; 4]));
+        assert!(compare(&a, &b, 0).is_err());
+    }
+}
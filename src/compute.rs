@@ -0,0 +1,57 @@
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::DescriptorSet;
+use vulkano::device::Queue;
+use vulkano::pipeline::ComputePipelineAbstract;
+
+use crate::watchdog;
+
+/* The local size of the shaders that work on an image a pixel an invocation, 8x8 in every one
+ * of them: mandelbrot.glsl and the image filters. */
+pub const IMAGE_LOCAL_SIZE: u32 = 8;
+
+/* The workgroups a dispatch of one of those over a width x height image needs. Round up so that
+ * sizes that aren't a multiple of the workgroup size are fully covered. */
+pub fn group_count(width: u32, height: u32) -> [u32; 3] {
+    [width.div_ceil(IMAGE_LOCAL_SIZE), height.div_ceil(IMAGE_LOCAL_SIZE), 1]
+}
+
+/* One dispatch of a compute shader: the pipeline, what is bound to it, how many workgroups to
+ * run and the push constants to run them with, which is `()` for shaders without any. Most of
+ * the compute demos are exactly one of these followed by reading a buffer back. `name` is the
+ * demo the work belongs to, for --deadline. */
+pub struct ComputeTask<Pc> {
+    name: &'static str,
+    pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+    set: Arc<dyn DescriptorSet + Send + Sync>,
+    groups: [u32; 3],
+    push_constants: Pc,
+}
+
+impl<Pc: Clone> ComputeTask<Pc> {
+    pub fn new(name: &'static str, pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+               set: Arc<dyn DescriptorSet + Send + Sync>, groups: [u32; 3], push_constants: Pc) -> ComputeTask<Pc>
+    {
+        ComputeTask { name, pipeline, set, groups, push_constants }
+    }
+
+    /* Adds the dispatch to `builder`, for command buffers that do more than dispatch, like
+     * copying the result somewhere afterwards. */
+    pub fn record(&self, builder: AutoCommandBufferBuilder) -> AutoCommandBufferBuilder {
+        builder.dispatch(self.groups, self.pipeline.clone(), self.set.clone(), self.push_constants.clone())
+            .unwrap()
+    }
+
+    /* A command buffer holding just the dispatch, for submitting later or along with others. */
+    pub fn build(&self, queue: &Queue) -> AutoCommandBuffer {
+        let builder = AutoCommandBufferBuilder::new(queue.device().clone(), queue.family()).unwrap();
+        self.record(builder).build().unwrap()
+    }
+
+    /* Runs the dispatch on `queue` and waits for it to finish, so whatever it wrote can be read
+     * straight away. */
+    pub fn submit(&self, queue: Arc<Queue>) {
+        let command_buffer = self.build(&queue);
+        watchdog::wait(command_buffer.execute(queue).unwrap(), self.name);
+    }
+}
@@ -0,0 +1,235 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{self, Options};
+use crate::fxaa::Antialias;
+use crate::mandelbrot::{Coloring, Palette, Trap};
+use crate::postfx;
+use crate::scene;
+
+/* The config file read when `--config` doesn't name one, if it is in the current directory. */
+pub const DEFAULT_PATH: &str = "sandbox.toml";
+
+/* A config file holds the same settings as the command line flags, so a good set of parameters
+ * can be saved and shared. Every key is optional, and unknown keys are an error so typos don't
+ * go unnoticed. See configs/seahorse.toml and configs/sandbox.toml for examples. */
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    scene: Option<String>,
+    size: Option<[u32; 2]>,
+    device: DeviceChoice,
+    render: Render,
+    mandelbrot: Mandelbrot,
+    postfx: Postfx,
+    output: Output,
+}
+
+/* Which device to run on, as `--device-index`, `--device-name` and `--device-type` pick it. */
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DeviceChoice {
+    index: Option<usize>,
+    name: Option<String>,
+    #[serde(rename = "type")]
+    ty: Option<String>,
+}
+
+/* How the triangle and the meshes are drawn, as `--format`, `--msaa` and `--antialias` set. */
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Render {
+    format: Option<String>,
+    msaa: Option<u32>,
+    antialias: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Mandelbrot {
+    center: Option<[f32; 2]>,
+    zoom: Option<f32>,
+    iterations: Option<u32>,
+    palette: Option<String>,
+    coloring: Option<String>,
+    trap: Option<String>,
+    region: Option<[f32; 4]>,
+}
+
+/* A chain of filters to run over an image, as `--input` and `--filters` give it. */
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Postfx {
+    input: Option<PathBuf>,
+    filters: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Output {
+    mandelbrot: Option<PathBuf>,
+    triangle: Option<PathBuf>,
+    scene: Option<PathBuf>,
+}
+
+impl Config {
+    /* The config `--config` names, or else DEFAULT_PATH if there is one and `--no-config` wasn't
+     * given. */
+    pub fn path(options: &Options) -> Option<PathBuf> {
+        options.config.clone().or_else(|| {
+            let default = PathBuf::from(DEFAULT_PATH);
+            if options.no_config || !default.is_file() {
+                return None;
+            }
+            eprintln!("Reading settings from {}", DEFAULT_PATH);
+            Some(default)
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| format!("Could not parse {}: {}", path.display(), e))
+    }
+
+    /* Fills in whatever `options` didn't get from the command line, holding the values to the
+     * same rules as the flags. */
+    pub fn apply(self, options: &mut Options) -> Result<(), String> {
+        if let Some(ref name) = self.scene {
+            options.scene = options.scene.or(Some(scene::parse(name)?));
+        }
+        if let Some([width, height]) = self.size {
+            if width == 0 || height == 0 {
+                return Err(format!("`size` must be positive, got {}x{}", width, height));
+            }
+            options.size = options.size.or(Some((width, height)));
+        }
+
+        /* The device is one choice, so a flag picking it some way overrides all of the config's. */
+        let device = self.device;
+        if device.index.is_some() && (device.name.is_some() || device.ty.is_some()) {
+            return Err("`device.index` can't be combined with `device.name` or `device.type`".to_string());
+        }
+        if options.device_index.is_none() && options.device_name.is_none() && options.device_type.is_none() {
+            options.device_index = device.index;
+            options.device_name = device.name;
+            if let Some(ref ty) = device.ty {
+                options.device_type = Some(cli::device_type("device.type", ty)?);
+            }
+        }
+
+        let render = self.render;
+        if let Some(ref format) = render.format {
+            options.format = options.format.or(Some(cli::format(format)?));
+        }
+        if let Some(msaa) = render.msaa {
+            options.msaa = options.msaa.or(Some(cli::samples("render.msaa", &msaa.to_string())?));
+        }
+        if let Some(ref antialias) = render.antialias {
+            options.antialias = options.antialias.or(Some(Antialias::parse(antialias)?));
+        }
+
+        let mandelbrot = self.mandelbrot;
+        if mandelbrot.region.is_some() && (mandelbrot.center.is_some() || mandelbrot.zoom.is_some()) {
+            return Err("`mandelbrot.region` can't be combined with `mandelbrot.center` or `mandelbrot.zoom`"
+                .to_string());
+        }
+        if let Some([x0, y0, x1, y1]) = mandelbrot.region {
+            if x0 >= x1 || y0 >= y1 {
+                return Err(format!("`mandelbrot.region` must have x0 < x1 and y0 < y1, got {:?}",
+                    [x0, y0, x1, y1]));
+            }
+            if options.center.is_none() && options.zoom.is_none() {
+                options.region = options.region.or(mandelbrot.region);
+            }
+        }
+        options.center = options.center.or(mandelbrot.center);
+        if let Some(zoom) = mandelbrot.zoom {
+            if !(zoom > 0.0 && zoom.is_finite()) {
+                return Err(format!("`mandelbrot.zoom` must be positive, got {}", zoom));
+            }
+            options.zoom = options.zoom.or(Some(zoom));
+        }
+        if let Some(iterations) = mandelbrot.iterations {
+            if iterations == 0 {
+                return Err("`mandelbrot.iterations` must be positive".to_string());
+            }
+            options.max_iter = options.max_iter.or(Some(iterations));
+        }
+        if let Some(ref palette) = mandelbrot.palette {
+            options.palette = options.palette.or(Some(Palette::parse(palette)?));
+        }
+        if let Some(ref coloring) = mandelbrot.coloring {
+            options.coloring = options.coloring.or(Some(Coloring::parse(coloring)?));
+        }
+        if let Some(ref trap) = mandelbrot.trap {
+            options.trap = options.trap.or(Some(Trap::parse(trap)?));
+        }
+
+        /* Like the device, the chain only applies if no flag processes an image some other way. */
+        let postfx = self.postfx;
+        if postfx.input.is_some() != postfx.filters.is_some() {
+            return Err("`postfx.input` and `postfx.filters` have to be given together".to_string());
+        }
+        if let (Some(input), Some(filters)) = (postfx.input, postfx.filters) {
+            if filters.is_empty() {
+                return Err(format!("`postfx.filters` has to list at least one of {}", postfx::FILTERS.join(", ")));
+            }
+            if options.input.is_none() && options.process.is_none() && options.filters.is_none() {
+                let filters: Result<Vec<_>, _> = filters.iter().map(|name| postfx::Filter::parse(name)).collect();
+                options.filters = Some(filters?);
+                options.input = Some(input);
+            }
+        }
+
+        options.mandelbrot_output = options.mandelbrot_output.take().or(self.output.mandelbrot);
+        options.triangle_output = options.triangle_output.take().or(self.output.triangle);
+        options.scene_output = options.scene_output.take().or(self.output.scene);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(text: &str, options: &mut Options) -> Result<(), String> {
+        toml::from_str::<Config>(text).map_err(|e| e.to_string())?.apply(options)
+    }
+
+    #[test]
+    fn the_example_configs_apply() {
+        for text in &[include_str!("../configs/seahorse.toml"), include_str!("../configs/sandbox.toml")] {
+            apply(text, &mut Options::default()).unwrap();
+        }
+    }
+
+    #[test]
+    fn flags_take_precedence() {
+        let mut options = Options { palette: Some(Palette::Blue), size: Some((64, 64)), .. Options::default() };
+        apply(include_str!("../configs/seahorse.toml"), &mut options).unwrap();
+        assert_eq!(options.palette, Some(Palette::Blue));
+        assert_eq!(options.size, Some((64, 64)));
+        assert_eq!(options.zoom, Some(40.0));
+        assert_eq!(options.max_iter, Some(1000));
+        assert_eq!(options.mandelbrot_output, Some(PathBuf::from("seahorse.png")));
+    }
+
+    #[test]
+    fn a_flag_picking_the_device_overrides_the_configs_choice() {
+        let mut options = Options { device_index: Some(1), .. Options::default() };
+        apply("[device]\nname = \"llvmpipe\"", &mut options).unwrap();
+        assert_eq!((options.device_index, options.device_name), (Some(1), None));
+    }
+
+    #[test]
+    fn bad_configs_are_errors() {
+        for text in &["colour = \"blue\"", "size = [0, 10]", "[mandelbrot]\nzoom = -1.0",
+                      "[mandelbrot]\nregion = [1.0, 0.0, -1.0, 1.0]",
+                      "[mandelbrot]\nregion = [-2.0, -1.0, 1.0, 1.0]\nzoom = 2.0", "[mandelbrot]\niterations = 0",
+                      "[render]\nmsaa = 3", "[device]\nindex = 0\nname = \"gpu\"", "[postfx]\ninput = \"a.png\"",
+                      "[postfx]\ninput = \"a.png\"\nfilters = []"] {
+            assert!(apply(text, &mut Options::default()).is_err(), "{:?} applied", text);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D src;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D dst;
+
+/* Up to a 7x7 kernel, row by row. Arrays in uniform blocks are padded to 16 bytes an element,
+ * so the weights are packed four to a vec4 rather than wasting three quarters of the space. */
+layout(set = 0, binding = 2) uniform Kernel {
+    vec4 weights[13];
+} kernel;
+
+/* The kernel is size x size. */
+layout(push_constant) uniform PushConstants {
+    int size;
+} pc;
+
+/* Each destination pixel is the weighted sum of the source pixels under the kernel, centred on
+ * it, or just above and to the left of centre for an even size. Pixels past the edge are clamped
+ * to it. Only the color is convolved; the alpha is the source pixel's, so kernels that sum to
+ * zero, like edge detectors, don't come out transparent. */
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coords, imageSize(dst)))) {
+        return;
+    }
+
+    ivec2 last = imageSize(src) - 1;
+    int offset = (pc.size - 1) / 2;
+
+    vec3 sum = vec3(0.0);
+    for (int ky = 0; ky < pc.size; ky++) {
+        for (int kx = 0; kx < pc.size; kx++) {
+            int i = ky * pc.size + kx;
+            ivec2 at = clamp(coords + ivec2(kx, ky) - offset, ivec2(0), last);
+            sum += kernel.weights[i / 4][i % 4] * imageLoad(src, at).rgb;
+        }
+    }
+
+    imageStore(dst, coords, vec4(sum, imageLoad(src, coords).a));
+}
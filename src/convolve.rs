@@ -0,0 +1,86 @@
+use image::{ImageBuffer, Rgba};
+use std::path::PathBuf;
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::device::{Device, Queue};
+use vulkano::pipeline::ComputePipeline;
+
+use crate::downsample;
+use crate::input::{self, Input};
+use crate::memory;
+use crate::pattern;
+
+/* The largest kernel convolve.glsl has room for, and the number of floats its array holds. */
+pub const MAX_KERNEL_SIZE: usize = 7;
+const KERNEL_FLOATS: usize = 13 * 4;
+
+/* What `--kernel` defaults to: a 3x3 box blur, before normalizing. */
+pub const DEFAULT_KERNEL: &[f32] = &[1.0; 9];
+
+/* Checks that `kernel` is square and small enough, and scales it so its weights add up to one.
+ * That way a kernel can be given as whole numbers, like 1,2,1,2,4,2,1,2,1 for a gaussian blur,
+ * without brightening the image. Kernels that add up to zero, like edge detectors, are left
+ * alone. Returns the normalized kernel and its size. */
+fn normalize(kernel: &[f32]) -> Result<(Vec<f32>, usize), String> {
+    let size = (kernel.len() as f64).sqrt().round() as usize;
+    if size == 0 || size * size != kernel.len() || size > MAX_KERNEL_SIZE {
+        return Err(format!("A kernel has to be square and at most {}x{}, got {} weights",
+            MAX_KERNEL_SIZE, MAX_KERNEL_SIZE, kernel.len()));
+    }
+
+    let sum: f32 = kernel.iter().sum();
+    let scale = if sum.abs() > 1e-6 { 1.0 / sum } else { 1.0 };
+    Ok((kernel.iter().map(|w| w * scale).collect(), size))
+}
+
+/* Convolves `pixels` with `kernel`, a list of size x size weights row by row, on the GPU. */
+pub fn convolve_image(device: Arc<Device>, queue: Arc<Queue>, pixels: &ImageBuffer<Rgba<u8>, Vec<u8>>, kernel: &[f32])
+    -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String>
+{
+    let (weights, size) = normalize(kernel)?;
+
+    let shader = cs::Shader::load(device.clone()).expect("Could not load convolution shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let mut padded = weights;
+    padded.resize(KERNEL_FLOATS, 0.0);
+    let kernel_buffer = memory::buffer(CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::uniform_buffer(),
+        padded.into_iter()).expect("Failed to create buffer"));
+
+    let push_constants = cs::ty::PushConstants { size: size as i32 };
+    Ok(input::filter_with_buffer("convolve", device, queue, pixels, pipeline, kernel_buffer, push_constants))
+}
+
+/* Convolves the image loaded for `--input` with `kernel` and saves the result next to it as
+ * <name>_convolved.png, returning where it went. */
+pub fn convolve(device: Arc<Device>, queue: Arc<Queue>, input: &Input, kernel: &[f32]) -> Result<PathBuf, String> {
+    input.save(&convolve_image(device, queue, &input.pixels, kernel)?, "convolved")
+}
+
+/* An identity kernel has to hand the image back untouched. A 2x2 box blur averages the same
+ * blocks the downsample demo does, with the same clamping at the edges, so every other pixel of
+ * the blurred image has to match the downsampled one, give or take a rounding. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let source = pattern::uv(device.clone(), queue.clone(), 255, 129);
+
+    let identity = [0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0];
+    let unchanged = convolve_image(device.clone(), queue.clone(), &source, &identity).unwrap();
+    assert!(unchanged.into_raw() == source.clone().into_raw(), "The identity kernel changed the image");
+
+    let blurred = convolve_image(device.clone(), queue.clone(), &source, &[1.0; 4]).unwrap();
+    let halved = downsample::half(device, queue, &source);
+    for (x, y, pixel) in halved.enumerate_pixels() {
+        let blurred = blurred.get_pixel(x * 2, y * 2);
+        for channel in 0..4 {
+            assert!((pixel[channel] as i32 - blurred[channel] as i32).abs() <= 1,
+                "Box blurred pixel ({}, {}) is {:?}, but the downsample made it {:?}", x * 2, y * 2, blurred, pixel);
+        }
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/convolve.glsl"
+    }
+}
@@ -0,0 +1,22 @@
+#version 450
+
+/* Must match the local size in mandelbrot.glsl, so the dispatch is the same shape. */
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) buffer Counters {
+    uint invocations;
+    uint inside;
+} counters;
+
+layout(push_constant) uniform PushConstants {
+    uvec2 size;
+} pc;
+
+/* Every invocation the dispatch launches bumps the first counter, and the ones that land on a
+ * pixel of the size x size image also bump the second. */
+void main() {
+    atomicAdd(counters.invocations, 1);
+    if (all(lessThan(gl_GlobalInvocationID.xy, pc.size))) {
+        atomicAdd(counters.inside, 1);
+    }
+}
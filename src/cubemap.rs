@@ -0,0 +1,113 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, StorageImage};
+use vulkano::pipeline::ComputePipeline;
+use vulkano::sampler::Sampler;
+
+use crate::compute::ComputeTask;
+use crate::layered;
+use crate::readback;
+use crate::storage;
+
+/* Must match the local size in sky.glsl and sphere.glsl. */
+const LOCAL_SIZE: u32 = 8;
+
+const FACE_SIZE: u32 = 128;
+const SIZE: u32 = 512;
+
+/* Must match RADIUS in sphere.glsl. */
+const RADIUS: f32 = 0.8;
+
+/* The colors sky.glsl mixes between. */
+const ZENITH: [f32; 3] = [0.15, 0.35, 0.8];
+const HORIZON: [f32; 3] = [0.8, 0.85, 0.9];
+const GROUND: [f32; 3] = [0.25, 0.2, 0.15];
+
+/* Faces in the order Vulkan numbers them. */
+const FACES: [&str; 6] = ["+x", "-x", "+y", "-y", "+z", "-z"];
+
+/* A cubemap of the sky in sky.glsl. It is the same six layers as the array image in layered.rs,
+ * just made as a cubemap so shaders can see it as one: a store says which face it writes with
+ * a z coordinate, and a lookup takes a direction and works out the face and texel itself. */
+pub fn sky(device: Arc<Device>, queue: Arc<Queue>, size: u32) -> Arc<StorageImage<Format>> {
+    let shader = sky_cs::Shader::load(device.clone()).expect("Could not load sky shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let image = storage::image(&queue, Dimensions::Cubemap { size }, Format::R8G8B8A8Unorm);
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_image(image.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = size.div_ceil(LOCAL_SIZE);
+    ComputeTask::new("cubemap", pipeline, set, [groups, groups, 6], ()).submit(queue);
+    image
+}
+
+/* Renders a mirror ball reflecting the sky, by sampling the cubemap in the direction each ray
+ * bounces off the ball. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let environment = sky(device.clone(), queue.clone(), FACE_SIZE);
+
+    let shader = sphere_cs::Shader::load(device.clone()).expect("Could not load sphere shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let image = storage::image(&queue, Dimensions::Dim2d { width: SIZE, height: SIZE }, Format::R8G8B8A8Unorm);
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_sampled_image(environment, Sampler::simple_repeat_linear_no_mipmap(device.clone())).unwrap()
+        .add_image(image.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = SIZE.div_ceil(LOCAL_SIZE);
+    ComputeTask::new("cubemap", pipeline, set, [groups, groups, 1], ()).submit(queue.clone());
+    readback::download_rgba8(device, queue, image)
+}
+
+/* Whether `pixel` is `color` give or take a little rounding. */
+fn close(pixel: &Rgba<u8>, color: [f32; 3]) -> bool {
+    (0..3).all(|c| (pixel[c] as f32 - color[c] * 255.0).abs() <= 3.0)
+}
+
+/* Checks each face of the sky looks the way its direction should, then that the ball reflects
+ * the right parts of it: the middle of the ball faces the viewer and reflects the horizon
+ * behind them, while its top reflects the sky and its bottom the ground. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let faces = layered::download_layers(device.clone(), queue.clone(), sky(device.clone(), queue.clone(), FACE_SIZE));
+    assert!(faces.len() == 6, "Read back {} faces, expected 6", faces.len());
+
+    let middle = FACE_SIZE / 2;
+    assert!(close(faces[2].get_pixel(middle, middle), ZENITH), "The +y face is {:?}, not the zenith",
+        faces[2].get_pixel(middle, middle));
+    assert!(close(faces[3].get_pixel(middle, middle), GROUND), "The -y face is {:?}, not the ground",
+        faces[3].get_pixel(middle, middle));
+    for &face in &[0, 1, 4, 5] {
+        /* The sides go from sky at the top to ground at the bottom. */
+        let (top, bottom) = (faces[face].get_pixel(middle, 0), faces[face].get_pixel(middle, FACE_SIZE - 1));
+        assert!(top[2] > bottom[2], "The {} face is no bluer at the top than the bottom", FACES[face]);
+    }
+
+    let ball = render(device, queue);
+    let center = SIZE / 2;
+    let edge = (RADIUS * 0.95 * center as f32) as u32;
+    assert!(close(ball.get_pixel(center, center), HORIZON), "The middle of the ball is {:?}, not the horizon",
+        ball.get_pixel(center, center));
+    let (top, bottom) = (ball.get_pixel(center, center - edge), ball.get_pixel(center, center + edge));
+    assert!(top[2] > bottom[2],
+        "The top of the ball ({:?}) doesn't reflect more sky than the bottom ({:?})", top, bottom);
+}
+
+mod sky_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/sky.glsl"
+    }
+}
+
+mod sphere_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/sphere.glsl"
+    }
+}
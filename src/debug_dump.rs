@@ -0,0 +1,144 @@
+use image::{ImageBuffer, Rgba};
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, TypedBufferAccess};
+use vulkano::device::{Device, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::{AttachmentImage, ImageAccess, ImageUsage};
+use vulkano::memory::Content;
+
+use crate::batch;
+use crate::memory;
+use crate::raw::{self, Element};
+use crate::readback;
+use crate::scene::{Scene, SceneOutput};
+
+/* Where `--dump-after` writes when `--dump-dir` doesn't say. */
+pub const DEFAULT_DIR: &str = "dump";
+
+/* Something a scene makes on the way to its output that is worth looking at when it goes wrong:
+ * an image in any format `readback::to_rgba8` converts, or a buffer of numbers. */
+#[derive(Clone)]
+pub enum Intermediate {
+    Image(Arc<dyn ImageAccess + Send + Sync>),
+    U32(Arc<dyn TypedBufferAccess<Content = [u32]> + Send + Sync>),
+    F32(Arc<dyn TypedBufferAccess<Content = [f32]> + Send + Sync>),
+}
+
+/* Copies `image` to host memory and converts it to RGBA8. The copy is tightly packed, so the
+ * row pitch is just the width in texels, and the format decides how each texel is unpacked,
+ * BGRA swapped round and floats clamped. Only the first layer and mip level are read. */
+pub fn read_image(queue: Arc<Queue>, image: Arc<dyn ImageAccess + Send + Sync>)
+    -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String>
+{
+    let format = image.format();
+    let [width, height] = image.dimensions().width_height();
+    let texel_bytes = format.size().ok_or_else(|| format!("{:?} has no fixed texel size", format))?;
+    let buf = memory::readback::<u8>(&queue, (width * height) as usize * texel_bytes)?;
+
+    let device = queue.device().clone();
+    batch::submit_and_wait(device, queue, |builder| builder
+        .copy_image_to_buffer(image, buf.clone()).unwrap());
+
+    let buffer_content = buf.read().unwrap();
+    readback::to_rgba8(format, width, height, width as usize * texel_bytes, &buffer_content)
+}
+
+/* Copies `buffer` to host memory and writes it to `path` the way `--raw-output` does, with its
+ * .meta beside it, and to `path` with .csv in place of its extension, one `index,value` line per
+ * element for reading without numpy. Returns the paths written. */
+pub fn write_values<T>(queue: Arc<Queue>, buffer: Arc<dyn TypedBufferAccess<Content = [T]> + Send + Sync>,
+                       path: &Path) -> Result<Vec<PathBuf>, String>
+    where T: Element + Content + Display + Clone + Default + Send + Sync + 'static
+{
+    let values = memory::download_from_device_local(queue, Arc::new(buffer))?;
+    let failed = |path: &Path, e: std::io::Error| format!("Could not write {}: {}", path.display(), e);
+    let meta = raw::write(path, &values, &[values.len()]).map_err(|e| failed(path, e))?;
+
+    let csv = path.with_extension("csv");
+    let write_csv = || -> std::io::Result<()> {
+        let mut out = BufWriter::new(File::create(&csv)?);
+        writeln!(out, "index,value")?;
+        for (i, value) in values.iter().enumerate() {
+            writeln!(out, "{},{}", i, value)?;
+        }
+        out.flush()
+    };
+    write_csv().map_err(|e| failed(&csv, e))?;
+    Ok(vec![path.to_path_buf(), meta, csv])
+}
+
+/* Writes `intermediate` to `dir` as `stem`.png or `stem`.bin, .bin.meta and .csv. */
+pub fn write(queue: Arc<Queue>, intermediate: &Intermediate, dir: &Path, stem: &str) -> Result<Vec<PathBuf>, String> {
+    match *intermediate {
+        Intermediate::Image(ref image) => {
+            let path = dir.join(format!("{}.png", stem));
+            read_image(queue, image.clone())?.save(&path)
+                .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+            Ok(vec![path])
+        }
+        Intermediate::U32(ref buffer) => write_values(queue, buffer.clone(), &dir.join(format!("{}.bin", stem))),
+        Intermediate::F32(ref buffer) => write_values(queue, buffer.clone(), &dir.join(format!("{}.bin", stem))),
+    }
+}
+
+/* Writes everything `scene` made once it has run to `dir`: its output as <scene>.png or
+ * <scene>.bin, and each intermediate it lists as <scene>.<name>. One that can't be written, like
+ * an image in a format there is no conversion for, is reported and the rest still are, since
+ * when debugging whatever can be looked at helps. Returns the paths written. */
+pub fn dump(queue: Arc<Queue>, scene: &dyn Scene, output: &SceneOutput, dir: &Path) -> Result<Vec<PathBuf>, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+    let mut written = Vec::new();
+    match *output {
+        SceneOutput::Image(ref image) => {
+            let path = dir.join(format!("{}.png", scene.name()));
+            image.save(&path).map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+            written.push(path);
+        }
+        SceneOutput::Values(ref values) => {
+            let path = dir.join(format!("{}.bin", scene.name()));
+            let meta = raw::write(&path, values, &[values.len()])
+                .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+            written.extend(vec![path, meta]);
+        }
+    }
+    for (name, intermediate) in scene.intermediates() {
+        match write(queue.clone(), &intermediate, dir, &format!("{}.{}", scene.name(), name)) {
+            Ok(paths) => written.extend(paths),
+            Err(e) => eprintln!("Warning: couldn't dump {} of {}: {}", name, scene.name(), e),
+        }
+    }
+    Ok(written)
+}
+
+/* Dumps an image stored as BGRA and a buffer to the temporary directory and checks they come back
+ * as written: the image's channels in RGBA order, and the buffer's values in the binary file and
+ * the CSV alike. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let dir = std::env::temp_dir().join("vulkano_tut_dump");
+    let usage = ImageUsage { transfer_source: true, transfer_destination: true, .. ImageUsage::none() };
+    let image = memory::image(AttachmentImage::with_usage(device.clone(), [6, 4], Format::B8G8R8A8Unorm, usage)
+        .unwrap());
+    batch::submit_and_wait(device.clone(), queue.clone(), |builder| builder
+        .clear_color_image(image.clone(), ClearValue::Float([1.0, 0.0, 0.0, 1.0])).unwrap());
+    fs::create_dir_all(&dir).unwrap();
+    let paths = write(queue.clone(), &Intermediate::Image(image), &dir, "bgra").unwrap();
+    let dumped = image::open(&paths[0]).unwrap().to_rgba();
+    assert!(dumped.dimensions() == (6, 4), "The dumped image is {:?}, expected 6x4", dumped.dimensions());
+    assert!(dumped.pixels().all(|pixel| pixel.data == [255, 0, 0, 255]),
+        "The BGRA image was dumped as {:?}, expected red", dumped.get_pixel(0, 0).data);
+
+    let values: Vec<u32> = (0..10).map(|n| n * 3).collect();
+    let buffer = memory::buffer(CpuAccessibleBuffer::from_iter(device, BufferUsage::transfer_source(),
+        values.iter().cloned()).unwrap());
+    let paths = write(queue, &Intermediate::U32(buffer), &dir, "values").unwrap();
+    let bytes = fs::read(&paths[0]).unwrap();
+    let read: Vec<u32> = bytes.chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    assert!(read == values, "The dumped buffer holds {:?}, expected {:?}", read, values);
+    let csv = fs::read_to_string(&paths[2]).unwrap();
+    let rows: Vec<u32> = csv.lines().skip(1).map(|line| line.split(',').nth(1).unwrap().parse().unwrap()).collect();
+    assert!(rows == values, "The dumped CSV holds {:?}, expected {:?}", rows, values);
+}
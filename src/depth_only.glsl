@@ -0,0 +1,5 @@
+#version 450
+
+/* The depth prepass writes no color, only the depth the fixed-function stages compute. */
+void main() {
+}
@@ -0,0 +1,50 @@
+#version 450
+
+layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
+
+/* Every particle's position after each step, step by step: all of them after step 0, then all
+ * of them after step 1 and so on, steps + 1 in all counting where they started. */
+layout(set = 0, binding = 0) writeonly buffer Paths {
+    vec2 paths[];
+} buf;
+
+layout(push_constant) uniform PushConstants {
+    uint side;
+    uint steps;
+    float step_size;
+} pc;
+
+/* Must match descent.rs. */
+const float EXTENT = 5.0;
+const float MAX_STEP = 0.5;
+
+/* The gradient of Himmelblau's function, (x^2 + y - 11)^2 + (x + y^2 - 7)^2. */
+vec2 gradient(vec2 p) {
+    float a = p.x * p.x + p.y - 11.0;
+    float b = p.x + p.y * p.y - 7.0;
+    return vec2(4.0 * p.x * a + 2.0 * b, 2.0 * a + 4.0 * p.y * b);
+}
+
+/* Walks one particle downhill from its place on a side x side grid over the plot. Each step is
+ * the gradient times step_size, but no longer than MAX_STEP: out by the edges the function is
+ * so steep that a plain step would throw the particle right off the plot. */
+void main() {
+    uint idx = gl_GlobalInvocationID.x;
+    uint count = pc.side * pc.side;
+    if (idx >= count) {
+        return;
+    }
+
+    vec2 cell = (vec2(idx % pc.side, idx / pc.side) + 0.5) / float(pc.side);
+    vec2 p = (cell * 2.0 - 1.0) * EXTENT;
+    buf.paths[idx] = p;
+    for (uint i = 1; i <= pc.steps; i++) {
+        vec2 delta = -pc.step_size * gradient(p);
+        float len = length(delta);
+        if (len > MAX_STEP) {
+            delta *= MAX_STEP / len;
+        }
+        p += delta;
+        buf.paths[i * count + idx] = p;
+    }
+}
@@ -0,0 +1,244 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::buffer::DeviceLocalBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, Subpass};
+use vulkano::image::{AttachmentImage, ImageUsage};
+use vulkano::pipeline::{ComputePipeline, GraphicsPipeline};
+use vulkano::pipeline::vertex::{BufferlessDefinition, BufferlessVertices};
+use vulkano::sync::GpuFuture;
+
+use crate::compute::ComputeTask;
+use crate::memory;
+use crate::readback;
+use crate::triangle::{self, SIZE};
+
+/* Must match the local size in descent.glsl. */
+const LOCAL_SIZE: u32 = 64;
+
+/* The particles start on a SIDE x SIDE grid over the plot. */
+const SIDE: u32 = 32;
+
+/* Must match descent.glsl, path.glsl and himmelblau.glsl: the plot runs from -EXTENT to EXTENT
+ * on both axes. */
+const EXTENT: f32 = 5.0;
+
+/* Must match descent.glsl. */
+const MAX_STEP: f32 = 0.5;
+
+/* What `--descent-steps` and `--step-size` default to. Enough for every particle to settle,
+ * with a step small enough not to overshoot the steep minima. */
+pub const DEFAULT_STEPS: u32 = 100;
+pub const DEFAULT_STEP_SIZE: f32 = 0.01;
+
+/* Where Himmelblau's function is at its lowest, zero at all four. */
+const MINIMA: [[f32; 2]; 4] = [[3.0, 2.0], [-2.805_118, 3.131_312], [-3.779_31, -3.283_186], [3.584_428, -1.848_126]];
+
+/* How the particles walk downhill. */
+#[derive(Debug, Clone, Copy)]
+pub struct Descent {
+    pub steps: u32,
+    pub step_size: f32,
+}
+
+/* The gradient descent.glsl follows. */
+fn gradient([x, y]: [f32; 2]) -> [f32; 2] {
+    let a = x * x + y - 11.0;
+    let b = x + y * y - 7.0;
+    [4.0 * x * a + 2.0 * b, 2.0 * a + 4.0 * y * b]
+}
+
+/* Where descent.glsl starts particle `index`. */
+fn start(index: u32, side: u32) -> [f32; 2] {
+    let cell = |i: u32| (i as f32 + 0.5) / side as f32 * 2.0 - 1.0;
+    [cell(index % side) * EXTENT, cell(index / side) * EXTENT]
+}
+
+/* The step descent.glsl takes from `p`, on the CPU. */
+fn step(p: [f32; 2], step_size: f32) -> [f32; 2] {
+    let [gx, gy] = gradient(p);
+    let (mut dx, mut dy) = (-step_size * gx, -step_size * gy);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length > MAX_STEP {
+        dx *= MAX_STEP / length;
+        dy *= MAX_STEP / length;
+    }
+    [p[0] + dx, p[1] + dy]
+}
+
+/* Walks side x side particles downhill with a compute shader, one invocation each, and returns
+ * where every one of them was after every step: all of them after step 0, then after step 1,
+ * and so on. Each invocation runs all of its steps in a loop, since no particle depends on any
+ * other. */
+fn paths(device: Arc<Device>, queue: Arc<Queue>, side: u32, descent: Descent) -> Arc<DeviceLocalBuffer<[[f32; 2]]>> {
+    let shader = cs::Shader::load(device.clone()).expect("Could not load descent shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let points = side * side * (descent.steps + 1);
+    let paths = memory::device_local(&queue, memory::storage_buffer(), points as usize)
+        .expect("Failed to create buffer");
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_buffer(paths.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = [(side * side).div_ceil(LOCAL_SIZE), 1, 1];
+    let push_constants = cs::ty::PushConstants { side, steps: descent.steps, step_size: descent.step_size };
+    ComputeTask::new("descent", pipeline, set, groups, push_constants).submit(queue);
+    paths
+}
+
+/* Draws the paths as points over a heatmap of Himmelblau's function. Both draws go in the
+ * same subpass: the fullscreen triangle shades every pixel with the function first, then the
+ * points land on top. The paths are read straight out of the compute shader's buffer, bound
+ * as a storage buffer like the point cloud's. */
+fn draw(device: Arc<Device>, queue: Arc<Queue>, paths: Arc<DeviceLocalBuffer<[[f32; 2]]>>, count: u32, steps: u32)
+    -> ImageBuffer<Rgba<u8>, Vec<u8>>
+{
+    let render_pass = triangle::render_pass(device.clone(), Format::R8G8B8A8Unorm);
+    let fullscreen = fullscreen::Shader::load(device.clone()).expect("Failed to create fullscreen vertex shader");
+    let himmelblau = himmelblau::Shader::load(device.clone()).expect("Failed to create heatmap shader");
+    let heatmap = Arc::new(GraphicsPipeline::start()
+        .vertex_input(BufferlessDefinition)
+        .vertex_shader(fullscreen.main_entry_point(), ())
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(himmelblau.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let vs = vs::Shader::load(device.clone()).expect("Failed to create path vertex shader");
+    let fs = fs::Shader::load(device.clone()).expect("Failed to create trail shader");
+    let points = Arc::new(GraphicsPipeline::start()
+        .vertex_input(BufferlessDefinition)
+        .vertex_shader(vs.main_entry_point(), ())
+        .point_list()
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+    let set = Arc::new(PersistentDescriptorSet::start(points.clone(), 0)
+        .add_buffer(paths).unwrap()
+        .build().unwrap());
+
+    let usage = ImageUsage { color_attachment: true, transfer_source: true, .. ImageUsage::none() };
+    let image = memory::image(AttachmentImage::with_usage(device.clone(), [SIZE, SIZE], Format::R8G8B8A8Unorm,
+        usage).unwrap());
+    let framebuffer = Arc::new(Framebuffer::start(render_pass)
+        .add(image.clone()).unwrap()
+        .build().unwrap());
+
+    let vertices = BufferlessVertices { vertices: (count * (steps + 1)) as usize, instances: 1 };
+    AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer, false, vec![triangle::CLEAR_COLOR.into()]).unwrap()
+        .draw(heatmap, &triangle::dynamic_state(), BufferlessVertices { vertices: 3, instances: 1 }, (), ()).unwrap()
+        .draw(points, &triangle::dynamic_state(), vertices, set, vs::ty::PushConstants { count, steps }).unwrap()
+        .end_render_pass().unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    readback::download_rgba8(device, queue, image)
+}
+
+/* Visualizes gradient descent on Himmelblau's function, which has four minima for particles
+ * to find. They start evenly spread over the plot and each takes `descent.steps` steps
+ * downhill, red where they set out and white where they end up. Which minimum a particle
+ * finds depends on which valley it starts in, and the paths bend as the valleys do. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>, descent: Descent) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let paths = paths(device.clone(), queue.clone(), SIDE, descent);
+    draw(device, queue, paths, SIDE * SIDE, descent.steps)
+}
+
+/* The minimum closest to `p`, and how far it is. */
+fn nearest_minimum(p: [f32; 2]) -> (usize, f32) {
+    MINIMA.iter().map(|m| ((p[0] - m[0]).powi(2) + (p[1] - m[1]).powi(2)).sqrt()).enumerate()
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap()).unwrap()
+}
+
+/* Checks the compute shader's first steps against the CPU's, that with the default step every
+ * particle settles on a minimum and every minimum gets some, and that a step too big for the
+ * steep walls of the valleys keeps overshooting instead. Then that the drawing puts the
+ * heatmap's highest point in the top right corner and the particles' resting places on top. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let side = 16;
+    let count = (side * side) as usize;
+    let descent = Descent { steps: DEFAULT_STEPS, step_size: DEFAULT_STEP_SIZE };
+    let buffer = paths(device.clone(), queue.clone(), side, descent);
+    let gpu = memory::download_from_device_local(queue.clone(), buffer.clone()).expect("Failed to create buffer");
+    for index in 0..side * side {
+        let mut expected = start(index, side);
+        for i in 0..4 {
+            let p = gpu[i * count + index as usize];
+            assert!((0..2).all(|c| (p[c] - expected[c]).abs() < 1e-3),
+                "Particle {} is at {:?} after {} steps on the GPU but {:?} on the CPU", index, p, i, expected);
+            expected = step(expected, descent.step_size);
+        }
+    }
+
+    let mut found = [false; 4];
+    for (index, &p) in gpu[descent.steps as usize * count..].iter().enumerate() {
+        let (minimum, distance) = nearest_minimum(p);
+        assert!(distance < 1e-2, "Particle {} stopped at {:?}, {} from the nearest minimum", index, p, distance);
+        found[minimum] = true;
+    }
+    assert!(found.iter().all(|&f| f), "Only the minima {:?} were found", found);
+
+    let overshooting = paths(device.clone(), queue.clone(), side, Descent { step_size: 0.03, .. descent });
+    let overshot = memory::download_from_device_local(queue.clone(), overshooting).expect("Failed to create buffer");
+    let settled = overshot[descent.steps as usize * count..].iter()
+        .filter(|&&p| nearest_minimum(p).1 < 1e-2).count();
+    assert!(settled == 0, "{} particles settled with a step size of 0.03, which should overshoot", settled);
+
+    let image = draw(device, queue, buffer, side * side, descent.steps);
+    let pixel = |[x, y]: [f32; 2]| {
+        let to_pixel = |v: f32| (((v / EXTENT + 1.0) / 2.0 * SIZE as f32) as u32).min(SIZE - 1);
+        *image.get_pixel(to_pixel(x), to_pixel(-y))
+    };
+    let (top_right, bottom_left) = (pixel([EXTENT, EXTENT]), pixel([-EXTENT, -EXTENT]));
+    assert!(top_right[2] > 100 && top_right[0] > bottom_left[0],
+        "The heatmap's top right is {:?} and its bottom left {:?}", top_right, bottom_left);
+    for &minimum in &MINIMA {
+        let drawn = pixel(minimum);
+        assert!(drawn.data == [255; 4], "The minimum at {:?} is drawn {:?}, not white", minimum, drawn);
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/descent.glsl"
+    }
+}
+
+mod fullscreen {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/fullscreen.glsl"
+    }
+}
+
+mod himmelblau {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/himmelblau.glsl"
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/path.glsl"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/trail.glsl"
+    }
+}
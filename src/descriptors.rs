@@ -0,0 +1,185 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use vulkano::buffer::{BufferAccess, CpuBufferPool};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::descriptor::pipeline_layout::PipelineLayoutAbstract;
+use vulkano::descriptor::DescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::image::ImageViewAccess;
+use vulkano::sampler::Sampler;
+use vulkano::VulkanObject;
+
+use crate::memory;
+use crate::multiply;
+
+/* The most bindings `Descriptors::set` can fill in one set. */
+pub const MAX_BINDINGS: usize = 4;
+
+/* Something bound at one binding of a set: a uniform or storage buffer, a storage image, or an
+ * image with the sampler to sample it with. */
+#[derive(Clone)]
+pub enum Resource {
+    Buffer(Arc<dyn BufferAccess + Send + Sync>),
+    Image(Arc<dyn ImageViewAccess + Send + Sync>),
+    Sampled(Arc<dyn ImageViewAccess + Send + Sync>, Arc<Sampler>),
+}
+
+impl Resource {
+    pub fn buffer<B>(buffer: Arc<B>) -> Resource where B: BufferAccess + Send + Sync + 'static {
+        Resource::Buffer(buffer)
+    }
+
+    pub fn image<I>(image: Arc<I>) -> Resource where I: ImageViewAccess + Send + Sync + 'static {
+        Resource::Image(image)
+    }
+
+    pub fn sampled<I>(image: Arc<I>, sampler: Arc<Sampler>) -> Resource
+        where I: ImageViewAccess + Send + Sync + 'static
+    {
+        Resource::Sampled(image, sampler)
+    }
+
+    /* Which resource this is, for telling sets apart: the same buffer or image, and sampler,
+     * is the same resource. */
+    fn key(&self) -> (usize, usize) {
+        match *self {
+            Resource::Buffer(ref buffer) => (Arc::as_ptr(buffer) as *const () as usize, 0),
+            Resource::Image(ref image) => (Arc::as_ptr(image) as *const () as usize, 0),
+            Resource::Sampled(ref image, ref sampler) =>
+                (Arc::as_ptr(image) as *const () as usize, Arc::as_ptr(sampler) as usize),
+        }
+    }
+}
+
+/* Adds the resources to `$builder` one binding at a time and builds the set. Every binding
+ * added changes the builder's type, so there is no looping over them: instead this unrolls one
+ * level per token in the brackets, MAX_BINDINGS of them, each going three ways. */
+macro_rules! bind {
+    ($builder:expr, $resources:expr, $binding:expr, []) => {
+        match $resources.len() {
+            0 => $builder.build().map(|set| Arc::new(set) as Arc<dyn DescriptorSet + Send + Sync>)
+                .map_err(|e| format!("Could not build the descriptor set: {}", e)),
+            _ => Err(format!("Can't bind more than {} resources in one set", MAX_BINDINGS)),
+        }
+    };
+    ($builder:expr, $resources:expr, $binding:expr, [$level:tt $($rest:tt)*]) => {
+        match $resources.split_first() {
+            None => $builder.build().map(|set| Arc::new(set) as Arc<dyn DescriptorSet + Send + Sync>)
+                .map_err(|e| format!("Could not build the descriptor set: {}", e)),
+            Some((resource, rest)) => {
+                let failed = |e| format!("Could not bind binding {}: {}", $binding, e);
+                match *resource {
+                    Resource::Buffer(ref buffer) => bind!($builder.add_buffer(buffer.clone()).map_err(failed)?,
+                        rest, $binding + 1, [$($rest)*]),
+                    Resource::Image(ref image) => bind!($builder.add_image(image.clone()).map_err(failed)?,
+                        rest, $binding + 1, [$($rest)*]),
+                    Resource::Sampled(ref image, ref sampler) => bind!($builder
+                        .add_sampled_image(image.clone(), sampler.clone()).map_err(failed)?,
+                        rest, $binding + 1, [$($rest)*]),
+                }
+            }
+        }
+    };
+}
+
+/* A set is the same set for as long as it is for the same pipeline, at the same set number,
+ * with the same resources at the same bindings. */
+type Key = (usize, usize, Vec<(usize, usize)>);
+
+/* Hands out descriptor sets, so a scene says what it binds where and leaves making the sets to
+ * this. Each is made the first time it is asked for and handed out again for as long as its
+ * key stays the same, rather than a `PersistentDescriptorSet` being built by hand for every
+ * pipeline and every binding. Per frame uniforms come from `uniform`, each value in a buffer of
+ * its own sub-allocated from a `CpuBufferPool`, and since that is a new resource every time, so
+ * are the sets holding them: they are kept by the frame they were made in and let go once that
+ * frame comes round again, which `frames` in flight at once, like a `FramePool`'s, have
+ * finished with by then. Sets made with only one frame, or before any frame begins, are kept
+ * for as long as this is. */
+pub struct Descriptors {
+    device: Arc<Device>,
+    frames: Vec<HashMap<Key, Arc<dyn DescriptorSet + Send + Sync>>>,
+    frame: usize,
+    uniforms: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    built: usize,
+}
+
+impl Descriptors {
+    pub fn new(device: Arc<Device>, frames: usize) -> Descriptors {
+        assert!(frames > 0, "Descriptors need at least one frame");
+        Descriptors { device, frames: (0..frames).map(|_| HashMap::new()).collect(), frame: 0,
+            uniforms: HashMap::new(), built: 0 }
+    }
+
+    /* Starts frame `index`, letting go of the sets and uniforms of the frame before it that
+     * shares its place. With only one frame nothing is let go. */
+    pub fn begin_frame(&mut self, index: u64) {
+        self.frame = (index % self.frames.len() as u64) as usize;
+        if self.frames.len() > 1 {
+            self.frames[self.frame].clear();
+        }
+    }
+
+    /* Set `set` of `pipeline`, with `resources` at bindings 0, 1 and so on. Fails, naming the
+     * binding, when a resource isn't what the pipeline expects there, or when one is missing. */
+    pub fn set<P>(&mut self, pipeline: &P, set: usize, resources: &[Resource])
+        -> Result<Arc<dyn DescriptorSet + Send + Sync>, String>
+        where P: PipelineLayoutAbstract + Clone + Send + Sync + 'static
+    {
+        let layout = pipeline.sys().internal_object() as usize;
+        let key = (layout, set, resources.iter().map(Resource::key).collect());
+        if let Some(found) = self.frames.iter().find_map(|sets| sets.get(&key)) {
+            return Ok(found.clone());
+        }
+        if set >= pipeline.num_sets() {
+            return Err(format!("The pipeline has no set {}, only {}", set, pipeline.num_sets()));
+        }
+
+        let built = bind!(PersistentDescriptorSet::start(pipeline.clone(), set), resources, 0, [_ _ _ _])?;
+        self.built += 1;
+        self.frames[self.frame].insert(key, built.clone());
+        Ok(built)
+    }
+
+    /* `value` in a uniform buffer of its own, for this frame. */
+    pub fn uniform<T>(&mut self, value: T) -> Resource where T: Send + Sync + 'static {
+        let device = self.device.clone();
+        let pool = self.uniforms.entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(CpuBufferPool::<T>::uniform_buffer(device)))
+            .downcast_ref::<CpuBufferPool<T>>().unwrap();
+        Resource::buffer(Arc::new(pool.next(value).expect("Failed to allocate uniform buffer")))
+    }
+
+    /* How many sets have been made, for seeing how many were handed out again instead. */
+    pub fn built(&self) -> usize {
+        self.built
+    }
+}
+
+/* Checks that the same resources get the same set and different ones a new one, that a set made
+ * in a frame is let go when that frame comes round again, and that sets which don't fit the
+ * pipeline are errors rather than panics. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let pipeline = multiply::pipeline(device.clone());
+    let buffers: Vec<_> = (0..2).map(|_| memory::device_local::<u32>(&queue, memory::storage_buffer(), 64)
+        .expect("Failed to create buffer")).collect();
+    let mut descriptors = Descriptors::new(device, 2);
+
+    let first = descriptors.set(&pipeline, 0, &[Resource::buffer(buffers[0].clone())]).unwrap();
+    let again = descriptors.set(&pipeline, 0, &[Resource::buffer(buffers[0].clone())]).unwrap();
+    assert!(Arc::ptr_eq(&first, &again) && descriptors.built() == 1, "The same resources got a new set");
+    let other = descriptors.set(&pipeline, 0, &[Resource::buffer(buffers[1].clone())]).unwrap();
+    assert!(!Arc::ptr_eq(&first, &other) && descriptors.built() == 2, "Different resources got the same set");
+
+    descriptors.begin_frame(1);
+    descriptors.set(&pipeline, 0, &[Resource::buffer(buffers[0].clone())]).unwrap();
+    assert!(descriptors.built() == 2, "A set from the frame before was made again");
+    descriptors.begin_frame(2);
+    descriptors.set(&pipeline, 0, &[Resource::buffer(buffers[0].clone())]).unwrap();
+    assert!(descriptors.built() == 3, "A set was kept past its frame coming round again");
+
+    assert!(descriptors.set(&pipeline, 0, &[]).is_err(), "A set missing its binding was made");
+    assert!(descriptors.set(&pipeline, 1, &[]).is_err(), "A set the pipeline doesn't have was made");
+    let uniform = descriptors.uniform(0u32);
+    assert!(descriptors.set(&pipeline, 0, &[uniform]).is_err(), "A uniform buffer was bound as a storage buffer");
+}
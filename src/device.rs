@@ -0,0 +1,198 @@
+use std::cmp::Reverse;
+use std::sync::Arc;
+use vulkano::device::{DeviceExtensions, Features};
+use vulkano::instance::{Instance, PhysicalDevice, PhysicalDeviceType};
+
+use crate::error::SandboxError;
+use crate::queues;
+
+/* Pick the device when the flags don't, for setting once in the shell on a machine with more
+ * than one. */
+pub const INDEX_VAR: &str = "VULKANO_TUT_DEVICE_INDEX";
+pub const NAME_VAR: &str = "VULKANO_TUT_DEVICE_NAME";
+
+/* Chooses the physical device to run on. Taking the first one Vulkan lists often picks the
+ * wrong GPU: on a laptop with an integrated and a discrete GPU either can come first, and which
+ * does can change between drivers. So every device is scored instead, by its type, discrete
+ * first and software last, and one that can't run what was asked for, for want of a queue
+ * family, an extension or a feature, isn't a candidate at all. Ties go to whichever Vulkan lists
+ * first. `index`, as `--list-devices` numbers them, or part of a `name` overrides the scoring,
+ * and `ty` narrows it down to one type of device. */
+#[derive(Debug, Clone)]
+pub struct DeviceSelector {
+    pub index: Option<usize>,
+    pub name: Option<String>,
+    pub ty: Option<PhysicalDeviceType>,
+    pub graphics: bool,
+    pub compute: bool,
+    pub extensions: DeviceExtensions,
+    pub features: Features,
+}
+
+/* How `--device-type` and `--list-devices` name the types. */
+pub fn type_name(ty: PhysicalDeviceType) -> &'static str {
+    match ty {
+        PhysicalDeviceType::DiscreteGpu => "discrete",
+        PhysicalDeviceType::IntegratedGpu => "integrated",
+        PhysicalDeviceType::VirtualGpu => "virtual",
+        PhysicalDeviceType::Cpu => "cpu",
+        PhysicalDeviceType::Other => "other",
+    }
+}
+
+/* The devices' names and types, for errors listing the candidates. */
+fn describe(devices: &[PhysicalDevice]) -> String {
+    devices.iter().map(|p| format!("{} ({:?})", p.name(), p.ty())).collect::<Vec<_>>().join(", ")
+}
+
+impl DeviceSelector {
+    /* Picks by score alone, for a device that can do `graphics` and `compute` as asked and needs
+     * no extensions or features. */
+    pub fn new(graphics: bool, compute: bool) -> DeviceSelector {
+        DeviceSelector {
+            index: None,
+            name: None,
+            ty: None,
+            graphics,
+            compute,
+            extensions: DeviceExtensions::none(),
+            features: Features::none(),
+        }
+    }
+
+    /* Fills in the index or name from the environment, unless a flag already picked the device
+     * some way. */
+    pub fn with_env(mut self) -> Result<DeviceSelector, String> {
+        if self.index.is_some() || self.name.is_some() || self.ty.is_some() {
+            return Ok(self);
+        }
+        if let Ok(index) = std::env::var(INDEX_VAR) {
+            let parsed = index.parse().map_err(|_| format!("{} has to be a device index, not `{}`", INDEX_VAR, index));
+            self.index = Some(parsed?);
+        }
+        if let Ok(name) = std::env::var(NAME_VAR) {
+            self.name = Some(name);
+        }
+        Ok(self)
+    }
+
+    /* Why `physical` can't run what was asked for, if it can't. */
+    pub fn problems(&self, physical: PhysicalDevice) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Err(e) = queues::choose_family(physical, self.graphics, self.compute) {
+            problems.push(e.to_string());
+        }
+        let missing = self.extensions.difference(&DeviceExtensions::supported_by_device(physical));
+        if missing != DeviceExtensions::none() {
+            problems.push(format!("it doesn't support {:?}", missing));
+        }
+        if !physical.supported_features().superset_of(&self.features) {
+            problems.push(format!("it doesn't support {:?}", self.features.difference(physical.supported_features())));
+        }
+        problems
+    }
+
+    /* How much `physical` is preferred, higher being better, or None if it can't be used. */
+    pub fn score(&self, physical: PhysicalDevice) -> Option<u32> {
+        if !self.problems(physical).is_empty() {
+            return None;
+        }
+        Some(match physical.ty() {
+            PhysicalDeviceType::DiscreteGpu => 4,
+            PhysicalDeviceType::IntegratedGpu => 3,
+            PhysicalDeviceType::VirtualGpu => 2,
+            PhysicalDeviceType::Other => 1,
+            PhysicalDeviceType::Cpu => 0,
+        })
+    }
+
+    /* The device to run on, as `choose` picks it, warning when that falls back to a software
+     * renderer because there is no hardware device to be had. */
+    pub fn select<'a>(&self, instance: &'a Arc<Instance>) -> Result<PhysicalDevice<'a>, SandboxError> {
+        let physical = self.choose(instance).map_err(SandboxError::NoDevice)?;
+        let automatic = self.index.is_none() && self.name.is_none() && self.ty.is_none();
+        if automatic && physical.ty() == PhysicalDeviceType::Cpu {
+            eprintln!("Warning: no hardware device available, falling back to a software renderer.");
+        }
+        Ok(physical)
+    }
+
+    /* The device to run on. An index has to name a device that can run what was asked for. A
+     * name has to be part of exactly one device's, ignoring case, out of those of the type
+     * asked for if any. Anything else picks the best scoring device of that type. Failing any
+     * of these is an error listing the candidates. */
+    fn choose<'a>(&self, instance: &'a Arc<Instance>) -> Result<PhysicalDevice<'a>, String> {
+        let all: Vec<_> = PhysicalDevice::enumerate(instance).collect();
+        if all.is_empty() {
+            return Err("No device available.".to_string());
+        }
+
+        if let Some(index) = self.index {
+            let physical = PhysicalDevice::from_index(instance, index).ok_or_else(|| {
+                format!("No device has index {}, the devices are 0 to {}: {}", index, all.len() - 1, describe(&all))
+            })?;
+            let problems = self.problems(physical);
+            if !problems.is_empty() {
+                return Err(format!("Device {}, {}, can't be used: {}", index, physical.name(),
+                    problems.join(", ")));
+            }
+            return Ok(physical);
+        }
+
+        let candidates: Vec<_> = all.iter().cloned().filter(|p| self.ty.is_none() || self.ty == Some(p.ty())).collect();
+        if candidates.is_empty() {
+            return Err(format!("No device is of type {:?}, the devices are: {}", self.ty.unwrap(), describe(&all)));
+        }
+
+        if let Some(ref name) = self.name {
+            let name = name.to_lowercase();
+            let mut matches: Vec<_> = candidates.iter().cloned()
+                .filter(|p| p.name().to_lowercase().contains(&name))
+                .collect();
+            return match matches.len() {
+                1 => Ok(matches.remove(0)),
+                0 => Err(format!("No device's name contains `{}`, the candidates are: {}", name,
+                    describe(&candidates))),
+                _ => Err(format!("More than one device's name contains `{}`: {}", name, describe(&matches))),
+            };
+        }
+
+        let best = candidates.iter().cloned()
+            .filter_map(|p| self.score(p).map(|score| (score, p)))
+            .max_by_key(|&(score, p)| (score, Reverse(p.index())));
+        match best {
+            Some((_, physical)) => Ok(physical),
+            None => {
+                let reasons: Vec<_> = candidates.iter()
+                    .map(|&p| format!("{}: {}", p.name(), self.problems(p).join(", ")))
+                    .collect();
+                Err(format!("No device can run this: {}", reasons.join("; ")))
+            }
+        }
+    }
+
+    /* Prints a table of every device, with the limits that most often decide whether a demo
+     * fits, its score and the one `choose` would pick marked with a *, or why it couldn't be
+     * used. */
+    pub fn list(&self, instance: &Arc<Instance>) {
+        let all: Vec<_> = PhysicalDevice::enumerate(instance).collect();
+        let chosen = self.choose(instance).ok().map(|p| p.index());
+        let width = all.iter().map(|p| p.name().len()).max().unwrap_or(0).max("Device".len());
+
+        println!("  {:>2}  {:<width$}  {:<10}  {:<8}  {:>6}  {:>11}  {:>11}  {:>9}  {:>5}", "#", "Device", "Type",
+            "Vulkan", "Max 2D", "Invocations", "Shared", "Memory", "Score", width = width);
+        for &physical in &all {
+            let limits = physical.limits();
+            let local: usize = physical.memory_heaps().filter(|h| h.is_device_local()).map(|h| h.size()).sum();
+            let score = self.score(physical).map_or("-".to_string(), |s| s.to_string());
+            println!("{} {:>2}  {:<width$}  {:<10}  {:<8}  {:>6}  {:>11}  {:>7} KiB  {:>5} MiB  {:>5}",
+                if chosen == Some(physical.index()) { "*" } else { " " },
+                physical.index(), physical.name(), type_name(physical.ty()), physical.api_version().to_string(),
+                limits.max_image_dimension_2d(), limits.max_compute_work_group_invocations(),
+                limits.max_compute_shared_memory_size() / 1024, local / (1024 * 1024), score, width = width);
+            for problem in self.problems(physical) {
+                println!("      can't be used: {}", problem);
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+#version 450
+
+layout(location = 0) out vec4 f_color;
+
+/* Must match Frame in frame.rs. */
+layout(set = 0, binding = 0) uniform Frame {
+    vec2 resolution;
+    float time;
+} frame;
+
+/* Must match DISC_RADIUS in fullscreen.rs. */
+const float RADIUS = 0.4;
+
+/* A white disc in the middle of the target. Positions are measured from the center in units of
+ * the target's height in both directions, so the disc stays round on a target of any shape
+ * rather than stretching with it the way it would in uv. */
+void main() {
+    vec2 p = (gl_FragCoord.xy - 0.5 * frame.resolution) / frame.resolution.y;
+    f_color = vec4(vec3(length(p) < RADIUS ? 1.0 : 0.0), 1.0);
+}
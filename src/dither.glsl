@@ -0,0 +1,37 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D src;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D dst;
+
+/* How many steps there are above zero, one less than the number of levels per channel. */
+layout(push_constant) uniform PushConstants {
+    uint steps;
+} pc;
+
+/* The 4x4 Bayer matrix. Each entry is where that pixel's threshold falls among the sixteen, and
+ * neighbouring entries are as far apart as they can be, so whatever fraction of the pixels in a
+ * flat patch rounds up, the ones that do are spread evenly rather than clumped. */
+const uint BAYER[16] = uint[](
+     0,  8,  2, 10,
+    12,  4, 14,  6,
+     3, 11,  1,  9,
+    15,  7, 13,  5
+);
+
+/* Ordered dithering: instead of every pixel rounding to the nearest level, each one rounds up
+ * past its own threshold from the matrix, so a color between two levels comes out as a pattern
+ * of both that averages to it. Alpha is left alone. */
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coords, imageSize(dst)))) {
+        return;
+    }
+
+    vec4 color = imageLoad(src, coords);
+    float threshold = (float(BAYER[(coords.y % 4) * 4 + coords.x % 4]) + 0.5) / 16.0;
+    float steps = float(pc.steps);
+    vec3 level = min(floor(color.rgb * steps + threshold), vec3(steps));
+    imageStore(dst, coords, vec4(level / steps, color.a));
+}
@@ -0,0 +1,68 @@
+use image::{ImageBuffer, Rgba};
+use std::path::PathBuf;
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+use vulkano::pipeline::ComputePipeline;
+
+use crate::input::{self, Input};
+use crate::pattern;
+
+/* What `--bits` defaults to. */
+pub const DEFAULT_BITS: u32 = 4;
+
+/* The byte values a channel can take at `bits` bits, from 0 to 255 in equal steps. */
+pub fn levels(bits: u32) -> Vec<u8> {
+    let steps = (1 << bits) - 1;
+    (0..=steps).map(|level| (level as f32 * 255.0 / steps as f32).round() as u8).collect()
+}
+
+/* Reduces every channel of `pixels` to `bits` bits, from 1 to 8, with ordered dithering, for the
+ * banded but speckled look of old hardware with few colors to spare. */
+pub fn dither(device: Arc<Device>, queue: Arc<Queue>, pixels: &ImageBuffer<Rgba<u8>, Vec<u8>>, bits: u32)
+    -> ImageBuffer<Rgba<u8>, Vec<u8>>
+{
+    assert!((1..=8).contains(&bits), "Can only dither to between 1 and 8 bits, not {}", bits);
+
+    let shader = cs::Shader::load(device.clone()).expect("Could not load dither shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let push_constants = cs::ty::PushConstants { steps: (1 << bits) - 1 };
+    input::filter("dither", device, queue, pixels, pipeline, push_constants)
+}
+
+/* Dithers the image loaded for `--input` and saves the result next to it as <name>_dithered.png,
+ * returning where it went. */
+pub fn dither_file(device: Arc<Device>, queue: Arc<Queue>, input: &Input, bits: u32) -> Result<PathBuf, String> {
+    input.save(&dither(device, queue, &input.pixels, bits), "dithered")
+}
+
+/* Dithers the UV pattern to each depth and checks every channel only uses that depth's levels,
+ * with alpha untouched. Then dithers a flat gray that falls between two levels, which should
+ * come out as a mix of the two averaging to the gray rather than all one or the other. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let source = pattern::uv(device.clone(), queue.clone(), 64, 64);
+    for bits in 1..=8 {
+        let allowed = levels(bits);
+        let dithered = dither(device.clone(), queue.clone(), &source, bits);
+        for (pixel, original) in dithered.pixels().zip(source.pixels()) {
+            assert!((0..3).all(|c| allowed.contains(&pixel[c])),
+                "Dithering to {} bits left {:?}, which isn't one of its levels", bits, pixel);
+            assert!(pixel[3] == original[3], "Dithering changed alpha from {} to {}", original[3], pixel[3]);
+        }
+    }
+
+    let gray = ImageBuffer::from_pixel(16, 16, Rgba([100u8, 100, 100, 255]));
+    let dithered = dither(device, queue, &gray, 2);
+    let used: Vec<u8> = levels(2).into_iter().filter(|&level| dithered.pixels().any(|pixel| pixel[0] == level)).collect();
+    assert!(used == [85, 170], "Dithering 100 to 2 bits used levels {:?}, expected 85 and 170", used);
+
+    let mean = dithered.pixels().map(|pixel| pixel[0] as f32).sum::<f32>() / (16.0 * 16.0);
+    assert!((mean - 100.0).abs() < 4.0, "Dithering 100 to 2 bits averaged {}", mean);
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/dither.glsl"
+    }
+}
@@ -0,0 +1,28 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D src;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D dst;
+
+/* Each destination pixel is the average of the 2x2 block of source pixels it covers. An odd
+ * sized source has a last row or column with no neighbour, where the block is clamped to the
+ * edge so the edge pixel counts twice. */
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coords, imageSize(dst)))) {
+        return;
+    }
+
+    ivec2 last = imageSize(src) - 1;
+    ivec2 base = coords * 2;
+
+    vec4 sum = vec4(0.0);
+    for (int dy = 0; dy < 2; dy++) {
+        for (int dx = 0; dx < 2; dx++) {
+            sum += imageLoad(src, min(base + ivec2(dx, dy), last));
+        }
+    }
+
+    imageStore(dst, coords, sum / 4.0);
+}
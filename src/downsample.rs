@@ -0,0 +1,83 @@
+use image::{ImageBuffer, Rgba};
+use std::path::PathBuf;
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::Dimensions;
+use vulkano::pipeline::ComputePipeline;
+use vulkano::sync::GpuFuture;
+
+use crate::input::{self, Input};
+use crate::pattern;
+use crate::readback;
+use crate::storage;
+
+/* Must match the local size in downsample.glsl. */
+const LOCAL_SIZE: u32 = 8;
+
+/* Halves `pixels` with a 2x2 box filter on the GPU. Odd sizes round up, so the last row or
+ * column of the source is never dropped. */
+pub fn half(device: Arc<Device>, queue: Arc<Queue>, pixels: &ImageBuffer<Rgba<u8>, Vec<u8>>)
+    -> ImageBuffer<Rgba<u8>, Vec<u8>>
+{
+    let (width, height) = (pixels.width().div_ceil(2), pixels.height().div_ceil(2));
+
+    let shader = cs::Shader::load(device.clone()).expect("Could not load downsample shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let source = input::upload(device.clone(), queue.clone(), pixels);
+    let destination = storage::image(&queue, Dimensions::Dim2d { width, height }, Format::R8G8B8A8Unorm);
+
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_image(source).unwrap()
+        .add_image(destination.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = [width.div_ceil(LOCAL_SIZE), height.div_ceil(LOCAL_SIZE), 1];
+    AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .dispatch(groups, pipeline, set, ()).unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    readback::download_rgba8(device, queue, destination)
+}
+
+/* Halves the image loaded for `--input` and saves the result next to it as <name>_half.png,
+ * returning where it went. */
+pub fn downsample(device: Arc<Device>, queue: Arc<Queue>, input: &Input) -> Result<PathBuf, String> {
+    input.save(&half(device, queue, &input.pixels), "half")
+}
+
+/* Downsamples an odd sized UV pattern and checks every pixel against the same box filter run
+ * on the CPU. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let source = pattern::uv(device.clone(), queue.clone(), 255, 129);
+    let halved = half(device, queue, &source);
+    assert_eq!(halved.dimensions(), (128, 65));
+
+    for (x, y, pixel) in halved.enumerate_pixels() {
+        for channel in 0..4 {
+            let sum: u32 = [(0, 0), (1, 0), (0, 1), (1, 1)].iter()
+                .map(|&(dx, dy)| {
+                    let sx = (x * 2 + dx).min(source.width() - 1);
+                    let sy = (y * 2 + dy).min(source.height() - 1);
+                    source.get_pixel(sx, sy)[channel] as u32
+                })
+                .sum();
+            let expected = sum as f32 / 4.0;
+            assert!((pixel[channel] as f32 - expected).abs() <= 1.0,
+                "Downsampled pixel ({}, {}) is {:?}, expected {} in channel {}", x, y, pixel, expected, channel);
+        }
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/downsample.glsl"
+    }
+}
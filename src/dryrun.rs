@@ -0,0 +1,61 @@
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::pipeline::ComputePipelineAbstract;
+
+use crate::mandelbrot::{self, View};
+use crate::multiply;
+use crate::spirv::SpvShader;
+use crate::triangle;
+
+/* What the main demos of a full run are given. */
+pub struct Plan<'a> {
+    pub elements: u32,
+    pub width: u32,
+    pub height: u32,
+    pub view: View,
+    pub mandelbrot: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+    pub spv_shader: Option<&'a SpvShader>,
+    pub format: Format,
+    pub samples: u32,
+    pub tile: u32,
+    pub instances: u32,
+}
+
+/* Builds the buffers, images, pipelines and command buffers of the multiply, mandelbrot and
+ * triangle demos exactly as a full run would, then drops them instead of submitting them. Most
+ * of what can go wrong with a set of options, like a shader whose bindings don't match, a size
+ * that is too big or a target format the device can't render to, goes wrong while building, so
+ * this catches it in a fraction of the time. Prints what would have run and returns whether
+ * everything built. */
+pub fn run(device: Arc<Device>, queue: Arc<Queue>, plan: &Plan) -> bool {
+    let tiles = mandelbrot::tile_count(plan.width, plan.height, plan.tile);
+
+    let steps: Vec<(String, Result<(), String>)> = vec![
+        (format!("multiply {} elements", plan.elements),
+            multiply::prepare(queue.clone(), multiply::pipeline(device.clone()), plan.elements).map(drop)
+                .map_err(String::from)),
+        (format!("render the mandelbrot at {}x{} in {} tile{}", plan.width, plan.height, tiles,
+                if tiles == 1 { "" } else { "s" }),
+            mandelbrot::prepare(device.clone(), queue.clone(), plan.mandelbrot.clone(), plan.width, plan.height,
+                plan.view, plan.tile).map(drop)),
+        (format!("draw the triangle to a {:?} target with {} sample{} per pixel", plan.format, plan.samples,
+                if plan.samples == 1 { "" } else { "s" }),
+            triangle::prepare(device.clone(), queue.clone(), plan.spv_shader, plan.format, plan.samples).map(drop)),
+    ];
+
+    let mut ok = true;
+    for (step, result) in steps {
+        match result {
+            Ok(()) => println!("Would {}", step),
+            Err(e) => {
+                println!("Can't {}: {}", step, e);
+                ok = false;
+            }
+        }
+    }
+    if ok {
+        println!("Everything built. Nothing was submitted.");
+    }
+    ok
+}
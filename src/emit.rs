@@ -0,0 +1,42 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+
+/* How `--stdout` writes a demo's output buffer, so it can be piped into other tools. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Emit {
+    Json,
+    Csv,
+}
+
+impl Emit {
+    pub fn parse(name: &str) -> Result<Emit, String> {
+        match name {
+            "json" => Ok(Emit::Json),
+            "csv" => Ok(Emit::Csv),
+            _ => Err(format!("Unknown output format `{}`, expected json or csv", name)),
+        }
+    }
+}
+
+/* Writes every element of `values` along with its index in the buffer. JSON comes out as an
+ * array of `{"index": i, "value": v}` objects, CSV as an `index,value` header and one row per
+ * element. */
+pub fn write<T: Display, W: Write>(mut out: W, format: Emit, values: &[T]) -> io::Result<()> {
+    match format {
+        Emit::Json => {
+            writeln!(out, "[")?;
+            for (index, value) in values.iter().enumerate() {
+                let comma = if index + 1 < values.len() { "," } else { "" };
+                writeln!(out, "  {{\"index\": {}, \"value\": {}}}{}", index, value, comma)?;
+            }
+            writeln!(out, "]")
+        }
+        Emit::Csv => {
+            writeln!(out, "index,value")?;
+            for (index, value) in values.iter().enumerate() {
+                writeln!(out, "{},{}", index, value)?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::error::Error;
+use std::fmt;
+use vulkano::device::DeviceCreationError;
+use vulkano::instance::{InstanceCreationError, PhysicalDevice};
+use vulkano::memory::DeviceMemoryAllocError;
+
+/* What can go wrong getting the sandbox running and running a scene, each with what to try
+ * about it, for `main` to print instead of panicking with a backtrace. The demos themselves
+ * still panic: what they do with the device is the same every time, so when one of them fails
+ * it is a bug in the demo, not something to tell whoever ran it. Written out by hand rather than
+ * with thiserror, to keep the dependencies as they are. */
+#[derive(Debug)]
+pub enum SandboxError {
+    /* Vulkan couldn't be started at all. */
+    Instance(InstanceCreationError),
+    /* No device could be, or was, picked, and why. */
+    NoDevice(String),
+    /* The device has no queue family that does what is needed, and the families it has. */
+    QueueFamily { device: String, needed: &'static str, families: Vec<String> },
+    /* The device was picked, but couldn't be opened. */
+    Device(DeviceCreationError),
+    /* A shader couldn't be loaded or doesn't fit where it is used. */
+    Shader(String),
+    /* The device can't do what a scene asks of it, like an image format or a sample count. */
+    Unsupported(String),
+    /* There wasn't the memory for a buffer or image. */
+    Memory(DeviceMemoryAllocError),
+    /* Recording or submitting a command buffer failed. */
+    Submit(String),
+    /* A window couldn't be opened, or its swapchain made or drawn to. */
+    Window(String),
+}
+
+impl SandboxError {
+    /* The error for `physical` having no queue family with `needed`, listing the ones it has. */
+    pub fn queue_family(physical: PhysicalDevice, needed: &'static str) -> SandboxError {
+        let families = physical.queue_families().map(|family| {
+            let mut can = Vec::new();
+            if family.supports_graphics() {
+                can.push("graphics");
+            }
+            if family.supports_compute() {
+                can.push("compute");
+            }
+            if family.supports_transfers() {
+                can.push("transfer");
+            }
+            let can = if can.is_empty() { "nothing else".to_string() } else { can.join(", ") };
+            format!("{} ({}, {} queues)", family.id(), can, family.queues_count())
+        }).collect();
+        SandboxError::QueueFamily { device: physical.name().to_string(), needed, families }
+    }
+}
+
+impl fmt::Display for SandboxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SandboxError::Instance(ref e) => write!(f, "Could not start Vulkan: {}. Check a Vulkan driver is \
+                installed, or a software one like lavapipe where there is no GPU, and that `--instance-layer` only \
+                names layers `--list-layers` shows", e),
+            SandboxError::NoDevice(ref e) => write!(f, "{}. `--list-devices` shows every device and why it can't be \
+                used", e),
+            SandboxError::QueueFamily { ref device, needed, ref families } =>
+                write!(f, "{} has no queue family that supports {}, its families are {}", device, needed,
+                    families.join(", ")),
+            SandboxError::Device(ref e) => write!(f, "Could not open the device: {}. If it is out of memory or was \
+                lost, close whatever else is using the GPU and try again", e),
+            SandboxError::Shader(ref e) => write!(f, "{}", e),
+            SandboxError::Unsupported(ref e) => write!(f, "{}", e),
+            SandboxError::Memory(ref e) => write!(f, "Could not allocate GPU memory: {}. Try a smaller `--size` or \
+                `--tile-size`", e),
+            SandboxError::Submit(ref e) => write!(f, "Could not run the commands: {}", e),
+            SandboxError::Window(ref e) => write!(f, "{}. `--window` needs a display to open the window on, and a \
+                driver that can present to it", e),
+        }
+    }
+}
+
+impl Error for SandboxError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SandboxError::Instance(ref e) => Some(e),
+            SandboxError::Device(ref e) => Some(e),
+            SandboxError::Memory(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<InstanceCreationError> for SandboxError {
+    fn from(e: InstanceCreationError) -> SandboxError {
+        SandboxError::Instance(e)
+    }
+}
+
+impl From<DeviceCreationError> for SandboxError {
+    fn from(e: DeviceCreationError) -> SandboxError {
+        SandboxError::Device(e)
+    }
+}
+
+impl From<DeviceMemoryAllocError> for SandboxError {
+    fn from(e: DeviceMemoryAllocError) -> SandboxError {
+        SandboxError::Memory(e)
+    }
+}
+
+/* The errors the scenes already describe themselves, which are all about the device not doing
+ * what they ask. */
+impl From<String> for SandboxError {
+    fn from(e: String) -> SandboxError {
+        SandboxError::Unsupported(e)
+    }
+}
+
+/* And the other way round, for the demos and helpers that still report what went wrong as a string. */
+impl From<SandboxError> for String {
+    fn from(e: SandboxError) -> String {
+        e.to_string()
+    }
+}
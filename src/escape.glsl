@@ -0,0 +1,42 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+/* One escape count per pixel, row by row. */
+layout(set = 0, binding = 0) buffer Counts {
+    uint counts[];
+} buf;
+
+/* The same view as mandelbrot.glsl, plus the image size it would otherwise get from the image. */
+layout(push_constant) uniform PushConstants {
+    vec2 center;
+    vec2 extent;
+    uint max_iter;
+    uvec2 size;
+} pc;
+
+/* The iteration at the heart of mandelbrot.glsl, writing how many steps each point took to
+ * escape, or max_iter if it never did, instead of a color. */
+void main() {
+    if (any(greaterThanEqual(gl_GlobalInvocationID.xy, pc.size))) {
+        return;
+    }
+
+    vec2 norm_coords = (gl_GlobalInvocationID.xy + vec2(0.5) ) / vec2(pc.size);
+    vec2 c = pc.center + (norm_coords - vec2(0.5)) * pc.extent;
+
+    vec2 z = vec2(0.0, 0.0);
+    uint n;
+    for (n = 0; n < pc.max_iter; n++) {
+        z = vec2(
+            z.x * z.x - z.y * z.y + c.x,
+            z.y * z.x + z.x * z.y + c.y
+        );
+
+        if (length(z) > 8.0) {
+            break;
+        }
+    }
+
+    buf.counts[gl_GlobalInvocationID.y * pc.size.x + gl_GlobalInvocationID.x] = n;
+}
@@ -0,0 +1,308 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::DescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, StorageImage};
+use vulkano::pipeline::{ComputePipeline, ComputePipelineAbstract};
+
+use crate::compute::{self, ComputeTask};
+use crate::mandelbrot::{self, Palette, Params, View};
+use crate::memory;
+use crate::readback::{self, RGBA8_BYTES};
+use crate::storage;
+use crate::watchdog;
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/* How many times closer one notch of the wheel zooms. */
+const ZOOM_PER_NOTCH: f32 = 1.25;
+
+/* One piece of input to the explorer, the way a window would hand it over. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    /* The mouse moved this many pixels right and down with the button held. */
+    Drag([f32; 2]),
+    /* The wheel turned this many notches, away from the user being in, with the cursor at this
+     * pixel, or in the middle of the image. */
+    Scroll(f32, Option<[f32; 2]>),
+    /* + and - double and halve the iterations, p switches palette, j switches between the
+     * mandelbrot and the julia set of the point in its middle, and r goes back to the start. */
+    Key(char),
+}
+
+/* The keys `Event::Key` knows. */
+const KEYS: &str = "+-pjr";
+
+impl Event {
+    /* Parses `drag:<dx>,<dy>`, `scroll:<notches>`, `scroll:<notches>@<x>,<y>` or `key:<key>`. */
+    pub fn parse(word: &str) -> Result<Event, String> {
+        let error = || format!("Expected drag:<dx>,<dy>, scroll:<notches>[@<x>,<y>] or key:<key>, got `{}`", word);
+        let pair = |value: &str| -> Result<[f32; 2], String> {
+            let numbers = value.split(',').map(|n| n.trim().parse::<f32>()).collect::<Result<Vec<_>, _>>()
+                .map_err(|_| error())?;
+            match numbers.as_slice() {
+                &[x, y] => Ok([x, y]),
+                _ => Err(error()),
+            }
+        };
+
+        let mut parts = word.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some("drag"), Some(value)) => Ok(Event::Drag(pair(value)?)),
+            (Some("scroll"), Some(value)) => {
+                let mut parts = value.splitn(2, '@');
+                let notches = parts.next().unwrap().parse::<f32>().map_err(|_| error())?;
+                let cursor = match parts.next() {
+                    Some(cursor) => Some(pair(cursor)?),
+                    None => None,
+                };
+                Ok(Event::Scroll(notches, cursor))
+            }
+            (Some("key"), Some(key)) if key.chars().count() == 1 && KEYS.contains(key) =>
+                Ok(Event::Key(key.chars().next().unwrap())),
+            (Some("key"), Some(key)) => Err(format!("Unknown key `{}`, expected one of {}", key, KEYS)),
+            _ => Err(error()),
+        }
+    }
+}
+
+/* Parses a whitespace separated list of events, in the order they happen. */
+pub fn parse_script(script: &str) -> Result<Vec<Event>, String> {
+    if script.trim().is_empty() {
+        return Err("Expected a list of events like `drag:40,0 scroll:2 key:j`".to_string());
+    }
+    script.split_whitespace().map(Event::parse).collect()
+}
+
+/* Which fractal is being explored. The julia set holds on to the point of the mandelbrot it was
+ * seeded with, and to the mandelbrot view, to go back to. */
+#[derive(Debug, Clone, Copy)]
+pub enum Fractal {
+    Mandelbrot,
+    Julia { seed: [f32; 2], mandelbrot: View },
+}
+
+/* Where the explorer is and what it is showing, over a width x height image. The view is always
+ * a center and a zoom, whatever it started as, since that is what dragging and zooming move. */
+pub struct Explorer {
+    pub width: u32,
+    pub height: u32,
+    pub view: View,
+    pub fractal: Fractal,
+    start: View,
+}
+
+impl Explorer {
+    pub fn new(width: u32, height: u32, view: View) -> Explorer {
+        let view = View { center: view.center(), zoom: view.magnification(), region: None, .. view };
+        Explorer { width, height, view, fractal: Fractal::Mandelbrot, start: view }
+    }
+
+    /* The point of the plane at pixel `pixel`. */
+    fn point(&self, pixel: [f32; 2]) -> [f32; 2] {
+        let (center, extent) = self.view.bounds(self.width, self.height);
+        [center[0] + (pixel[0] / self.width as f32 - 0.5) * extent[0],
+         center[1] + (pixel[1] / self.height as f32 - 0.5) * extent[1]]
+    }
+
+    pub fn apply(&mut self, event: Event) {
+        match event {
+            /* The plane moves with the mouse, so the view moves the other way. */
+            Event::Drag([dx, dy]) => {
+                let (_, extent) = self.view.bounds(self.width, self.height);
+                self.view.center[0] -= dx / self.width as f32 * extent[0];
+                self.view.center[1] -= dy / self.height as f32 * extent[1];
+            }
+            /* The point under the cursor stays where it is, so zooming goes towards it. */
+            Event::Scroll(notches, cursor) => {
+                let cursor = cursor.unwrap_or([self.width as f32 / 2.0, self.height as f32 / 2.0]);
+                let before = self.point(cursor);
+                self.view.zoom *= ZOOM_PER_NOTCH.powf(notches);
+                let after = self.point(cursor);
+                self.view.center[0] += before[0] - after[0];
+                self.view.center[1] += before[1] - after[1];
+            }
+            Event::Key('+') => self.view.max_iter = self.view.max_iter.saturating_mul(2),
+            Event::Key('-') => self.view.max_iter = (self.view.max_iter / 2).max(1),
+            Event::Key('p') => {
+                self.view.palette = if self.view.palette == Palette::Blue { Palette::Grey } else { Palette::Blue };
+            }
+            Event::Key('j') => match self.fractal {
+                Fractal::Mandelbrot => {
+                    self.fractal = Fractal::Julia { seed: self.view.center, mandelbrot: self.view };
+                    self.view = View { center: [0.0, 0.0], zoom: 1.0, .. self.view };
+                }
+                Fractal::Julia { mandelbrot, .. } => {
+                    self.fractal = Fractal::Mandelbrot;
+                    self.view = View { max_iter: self.view.max_iter, palette: self.view.palette, .. mandelbrot };
+                }
+            },
+            Event::Key('r') => {
+                self.view = self.start;
+                self.fractal = Fractal::Mandelbrot;
+            }
+            Event::Key(_) => {}
+        }
+    }
+
+    /* One line about where the explorer is, for printing after every frame. */
+    pub fn describe(&self) -> String {
+        let what = match self.fractal {
+            Fractal::Mandelbrot => "mandelbrot".to_string(),
+            Fractal::Julia { seed, .. } => format!("julia set of {} + {}i", seed[0], seed[1]),
+        };
+        format!("{} at {} + {}i, zoom {}, {} iterations", what, self.view.center[0], self.view.center[1],
+            self.view.zoom, self.view.max_iter)
+    }
+}
+
+/* What draws the explorer's frames. Everything is made once, the pipelines, the image they write,
+ * a descriptor set binding it to each and the buffer it is read back through, and every frame is
+ * the same dispatch with the push constants of wherever the explorer has got to since. */
+pub struct FrameLoop {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    mandelbrot: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+    julia: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+    mandelbrot_set: Arc<dyn DescriptorSet + Send + Sync>,
+    julia_set: Arc<dyn DescriptorSet + Send + Sync>,
+    image: Arc<StorageImage<Format>>,
+    readback: Arc<CpuAccessibleBuffer<[u8]>>,
+    width: u32,
+    height: u32,
+}
+
+impl FrameLoop {
+    pub fn new(device: Arc<Device>, queue: Arc<Queue>, width: u32, height: u32) -> FrameLoop {
+        let mandelbrot = mandelbrot::pipeline_with(device.clone(), Params::PushConstants);
+        let shader = cs::Shader::load(device.clone()).expect("Could not load julia shader");
+        let julia: Arc<dyn ComputePipelineAbstract + Send + Sync> =
+            Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+        let image = storage::image(&queue, Dimensions::Dim2d { width, height }, Format::R8G8B8A8Unorm);
+        let mandelbrot_set = mandelbrot::image_set(mandelbrot.clone(), image.clone()).unwrap();
+        let julia_set = mandelbrot::image_set(julia.clone(), image.clone()).unwrap();
+        let readback = memory::readback(&queue, (width * height) as usize * RGBA8_BYTES)
+            .expect("Failed to create buffer");
+
+        FrameLoop { device, queue, mandelbrot, julia, mandelbrot_set, julia_set, image, readback, width, height }
+    }
+
+    /* Adds the dispatch drawing where `explorer` is into the loop's image to `builder`. */
+    pub fn record(&self, builder: AutoCommandBufferBuilder, explorer: &Explorer) -> AutoCommandBufferBuilder {
+        let (width, height) = (self.width, self.height);
+        let groups = compute::group_count(width, height);
+        match explorer.fractal {
+            Fractal::Mandelbrot => ComputeTask::new("explore", self.mandelbrot.clone(), self.mandelbrot_set.clone(),
+                groups, explorer.view.push_constants(width, height)).record(builder),
+            Fractal::Julia { seed, .. } => {
+                let (center, extent) = explorer.view.bounds(width, height);
+                let push_constants = cs::ty::PushConstants {
+                    center,
+                    extent,
+                    seed,
+                    max_iter: explorer.view.max_iter,
+                    palette: explorer.view.palette as u32,
+                };
+                ComputeTask::new("explore", self.julia.clone(), self.julia_set.clone(), groups, push_constants)
+                    .record(builder)
+            }
+        }
+    }
+
+    /* The image every frame is drawn into. */
+    pub fn image(&self) -> Arc<StorageImage<Format>> {
+        self.image.clone()
+    }
+
+    /* Draws where `explorer` is and reads it back. */
+    pub fn draw(&self, explorer: &Explorer) -> Image {
+        let (width, height) = (self.width, self.height);
+        let builder = AutoCommandBufferBuilder::new(self.device.clone(), self.queue.family()).unwrap();
+        let command_buffer = self.record(builder, explorer)
+            .copy_image_to_buffer(self.image.clone(), self.readback.clone()).unwrap()
+            .build().unwrap();
+        watchdog::wait(command_buffer.execute(self.queue.clone()).unwrap(), "explore");
+
+        let content = self.readback.read().unwrap();
+        readback::rgba8_image(width, height, width as usize * RGBA8_BYTES, &content)
+    }
+}
+
+/* Starts at `view` and plays `events` through the explorer, saving where it starts and every
+ * frame after an event as explore_NNNN.png. Without `--window explore` there is no window to take
+ * the events from, so they come from the command line instead, but each is handled the way a
+ * window's is: the view moves and the next frame is dispatched with it. */
+pub fn run(device: Arc<Device>, queue: Arc<Queue>, width: u32, height: u32, view: View, events: &[Event]) {
+    let mut explorer = Explorer::new(width, height, view);
+    let frames = FrameLoop::new(device, queue, width, height);
+    for index in 0..=events.len() {
+        if index > 0 {
+            explorer.apply(events[index - 1]);
+        }
+        let path = format!("explore_{:04}.png", index);
+        frames.draw(&explorer).save(&path).unwrap();
+        println!("Wrote {}: {}", path, explorer.describe());
+    }
+}
+
+fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+    (a[0] - b[0]).abs() < 1e-5 && (a[1] - b[1]).abs() < 1e-5
+}
+
+/* Checks the event parsing, that dragging across the whole image moves the view by its width,
+ * that zooming keeps the point under the cursor in place and zooming back out undoes it, that a
+ * frame of the mandelbrot is exactly what rendering the same view on its own gives, that the
+ * palette key changes the frame, and that the julia set of -1 is drawn with the origin, which
+ * it contains, inside it and the corners, which escape straight away, outside. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    assert!(parse_script("drag:10,-5 scroll:2@0,0 key:j") ==
+        Ok(vec![Event::Drag([10.0, -5.0]), Event::Scroll(2.0, Some([0.0, 0.0])), Event::Key('j')]),
+        "The events didn't parse");
+    for &bad in &["drag:1", "scroll:x", "key:", "key:q", "wheel:1", ""] {
+        assert!(parse_script(bad).is_err(), "`{}` parsed", bad);
+    }
+
+    let (width, height) = (64, 64);
+    let mut explorer = Explorer::new(width, height, View::default());
+    explorer.apply(Event::Drag([width as f32, 0.0]));
+    assert!(close(explorer.view.center, [-3.0, 0.0]), "Dragging across the image moved to {:?}", explorer.view.center);
+
+    let cursor = [16.0, 48.0];
+    let under = explorer.point(cursor);
+    explorer.apply(Event::Scroll(3.0, Some(cursor)));
+    assert!(close(explorer.point(cursor), under), "Zooming moved the point under the cursor");
+    explorer.apply(Event::Scroll(-3.0, Some(cursor)));
+    assert!(close(explorer.view.center, [-3.0, 0.0]) && (explorer.view.zoom - 1.0).abs() < 1e-5,
+        "Zooming in and out again ended up at {:?}, zoom {}", explorer.view.center, explorer.view.zoom);
+
+    let frames = FrameLoop::new(device.clone(), queue.clone(), width, height);
+    let mut explorer = Explorer::new(width, height, View::default());
+    let on_its_own = mandelbrot::render(device.clone(), queue.clone(),
+        mandelbrot::pipeline_with(device, Params::PushConstants), width, height, explorer.view).unwrap();
+    let frame = frames.draw(&explorer);
+    assert!(frame.clone().into_raw() == on_its_own.into_raw(), "The explorer draws the mandelbrot differently");
+
+    explorer.apply(Event::Key('p'));
+    assert!(frames.draw(&explorer).into_raw() != frame.into_raw(), "Switching palette changed nothing");
+
+    explorer.apply(Event::Key('p'));
+    explorer.apply(Event::Key('j'));
+    let julia = frames.draw(&explorer);
+    let (inside, outside) = (julia.get_pixel(width / 2, height / 2), julia.get_pixel(0, 0));
+    assert!(inside[0] == 255 && outside[0] < 64,
+        "The julia set of -1 is {:?} in the middle and {:?} in the corner", inside, outside);
+
+    explorer.apply(Event::Key('j'));
+    assert!(close(explorer.view.center, [-1.0, 0.0]), "Leaving the julia set didn't go back to the mandelbrot");
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/julia.glsl"
+    }
+}
@@ -0,0 +1,51 @@
+use image::{imageops, FilterType, ImageBuffer, Rgba};
+
+/* What letterboxing pads with. */
+pub const BORDER: Rgba<u8> = Rgba { data: [0, 0, 0, 255] };
+
+/* Resizes `image` to exactly width x height. Without `aspect_fit` the image is stretched to
+ * fill it. With it the image is scaled as large as it fits without changing its aspect ratio
+ * and centered, with BORDER filling the bars left over on two sides. */
+pub fn resize(image: &ImageBuffer<Rgba<u8>, Vec<u8>>, width: u32, height: u32, aspect_fit: bool)
+    -> ImageBuffer<Rgba<u8>, Vec<u8>>
+{
+    if !aspect_fit {
+        return imageops::resize(image, width, height, FilterType::Triangle);
+    }
+
+    let scale = (width as f64 / image.width() as f64).min(height as f64 / image.height() as f64);
+    let scaled_width = ((image.width() as f64 * scale).round() as u32).max(1).min(width);
+    let scaled_height = ((image.height() as f64 * scale).round() as u32).max(1).min(height);
+    let scaled = imageops::resize(image, scaled_width, scaled_height, FilterType::Triangle);
+
+    let mut letterboxed = ImageBuffer::from_pixel(width, height, BORDER);
+    imageops::replace(&mut letterboxed, &scaled, (width - scaled_width) / 2, (height - scaled_height) / 2);
+    letterboxed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Rgba<u8> = Rgba { data: [255, 255, 255, 255] };
+
+    #[test]
+    fn stretching_fills_the_size() {
+        let image = ImageBuffer::from_pixel(100, 50, WHITE);
+        let resized = resize(&image, 40, 40, false);
+        assert_eq!(resized.dimensions(), (40, 40));
+        /* Filtering rounds white down a step or two, but nothing is left black. */
+        assert!(resized.pixels().all(|pixel| pixel.data.iter().all(|&c| c >= 250)), "Stretching left a border");
+    }
+
+    #[test]
+    fn aspect_fit_letterboxes_the_short_side() {
+        let image = ImageBuffer::from_pixel(100, 50, WHITE);
+        let resized = resize(&image, 40, 40, true);
+        assert_eq!(resized.dimensions(), (40, 40));
+        for y in 0..40 {
+            let expected = if (10..30).contains(&y) { WHITE } else { BORDER };
+            assert!((0..40).all(|x| *resized.get_pixel(x, y) == expected), "Row {} should be {:?}", y, expected);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::descriptor::DescriptorSet;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::descriptor::pipeline_layout::PipelineLayoutAbstract;
+use vulkano::device::Device;
+
+use crate::memory;
+
+/* The set the frame uniform is always bound at, at binding 0. */
+pub const SET: usize = 0;
+
+/* What a fragment shader gets to know about the render it is part of, the way Shadertoy hands
+ * its shaders iResolution and iTime. Every fragment shader that wants it declares the same
+ * block at the same place,
+ *
+ *     layout(set = 0, binding = 0) uniform Frame {
+ *         vec2 resolution;
+ *         float time;
+ *     } frame;
+ *
+ * and the demo fills in a fresh one for every frame it draws. `resolution` is the size of the
+ * target in pixels, so gl_FragCoord.xy / frame.resolution goes from 0 to 1 across it whatever
+ * its size. `time` is whatever the demo animates by, and 0 for still images. The fields are in
+ * the order and with the padding std140 gives the block. */
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    pub resolution: [f32; 2],
+    pub time: f32,
+}
+
+impl Frame {
+    pub fn new(width: u32, height: u32, time: f32) -> Frame {
+        Frame { resolution: [width as f32, height as f32], time }
+    }
+
+    /* A descriptor set holding the frame in a uniform buffer, for a pipeline whose fragment
+     * shader declares the block. */
+    pub fn set<L>(self, device: Arc<Device>, pipeline: L) -> Arc<dyn DescriptorSet + Send + Sync>
+        where L: PipelineLayoutAbstract + Send + Sync + 'static
+    {
+        let buffer = memory::buffer(CpuAccessibleBuffer::from_data(device, BufferUsage::uniform_buffer(), self)
+            .expect("Failed to create buffer"));
+        Arc::new(PersistentDescriptorSet::start(pipeline, SET)
+            .add_buffer(buffer).unwrap()
+            .build().unwrap())
+    }
+}
+
+/* The frame uniforms a frame loop draws with, and the descriptor sets holding them, recycled
+ * from one frame to the next instead of a fresh buffer and set being allocated for every frame
+ * the way `Frame::set` would. A descriptor set only records which buffer is bound where, not
+ * what is in it, so a set can be reused for as long as its bindings stay the same: the same
+ * buffer, at the same binding, for a pipeline with the same layout. Only the buffer's contents
+ * change. Nor can a buffer be refilled while a frame the GPU hasn't finished still reads it, but
+ * vulkano won't let the CPU write to one the GPU has locked, so `next` takes the first whose
+ * write succeeds and only allocates another when every one of them is still in use. A loop that
+ * waits for each frame before the next gets by with one. */
+pub struct FrameSets<L> {
+    device: Arc<Device>,
+    pipeline: L,
+    buffers: Vec<Arc<CpuAccessibleBuffer<Frame>>>,
+    sets: Vec<Arc<dyn DescriptorSet + Send + Sync>>,
+}
+
+impl<L> FrameSets<L> where L: PipelineLayoutAbstract + Clone + Send + Sync + 'static {
+    pub fn new(device: Arc<Device>, pipeline: L) -> FrameSets<L> {
+        FrameSets { device, pipeline, buffers: Vec::new(), sets: Vec::new() }
+    }
+
+    /* A descriptor set holding `frame`, for the pipeline the pool was made for. */
+    pub fn next(&mut self, frame: Frame) -> Arc<dyn DescriptorSet + Send + Sync> {
+        for (buffer, set) in self.buffers.iter().zip(&self.sets) {
+            if let Ok(mut contents) = buffer.write() {
+                *contents = frame;
+                return set.clone();
+            }
+        }
+
+        let buffer = memory::buffer(CpuAccessibleBuffer::from_data(self.device.clone(), BufferUsage::uniform_buffer(),
+            frame).expect("Failed to create buffer"));
+        let set = Arc::new(PersistentDescriptorSet::start(self.pipeline.clone(), SET)
+            .add_buffer(buffer.clone()).unwrap()
+            .build().unwrap());
+        self.buffers.push(buffer);
+        self.sets.push(set.clone());
+        set
+    }
+
+    /* How many sets the pool has had to allocate. */
+    pub fn allocated(&self) -> usize {
+        self.sets.len()
+    }
+}
@@ -0,0 +1,12 @@
+#version 450
+
+layout(location = 0) out vec2 v_uv;
+
+/* No vertex buffer: the three vertices are made up from their index alone. They land at uv
+ * (0, 0), (2, 0) and (0, 2), a triangle twice the size of the screen whose hypotenuse passes
+ * through the far corner, so the screen is covered with no diagonal seam. */
+void main() {
+    vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    v_uv = uv;
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
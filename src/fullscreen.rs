@@ -0,0 +1,132 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::pipeline_layout::PipelineLayoutAbstract;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, RenderPassAbstract, Subpass};
+use vulkano::image::{AttachmentImage, ImageUsage};
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::vertex::{BufferlessDefinition, BufferlessVertices};
+use vulkano::sync::GpuFuture;
+
+use crate::frame::Frame;
+use crate::memory;
+use crate::readback;
+use crate::triangle::{self, SIZE};
+
+/* Must match RADIUS in disc.glsl. */
+const DISC_RADIUS: f32 = 0.4;
+
+type Pipeline = GraphicsPipeline<BufferlessDefinition, Box<dyn PipelineLayoutAbstract + Send + Sync>,
+    Arc<dyn RenderPassAbstract + Send + Sync>>;
+
+/* Runs gradient.glsl over every pixel by drawing one triangle that covers the screen, the usual
+ * way to start a post-processing pass. The pipeline has no vertex input at all, and drawing
+ * `BufferlessVertices` just tells the GPU how many vertices to run the vertex shader for. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    render_to(device, queue, Format::R8G8B8A8Unorm)
+}
+
+/* Like `render`, to a target of any four byte RGBA `format`. The bytes come back exactly as
+ * they are stored, with no conversion. */
+pub fn render_to(device: Arc<Device>, queue: Arc<Queue>, format: Format) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let render_pass = triangle::render_pass(device.clone(), format);
+    let fs = gradient::Shader::load(device.clone()).expect("Failed to create gradient shader");
+    let pipeline = pipeline(device.clone(), render_pass, fs.main_entry_point());
+    draw(device, queue, pipeline, format, SIZE, SIZE)
+}
+
+/* Draws disc.glsl into a width x height target. */
+pub fn render_disc(device: Arc<Device>, queue: Arc<Queue>, width: u32, height: u32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let format = Format::R8G8B8A8Unorm;
+    let render_pass = triangle::render_pass(device.clone(), format);
+    let fs = disc::Shader::load(device.clone()).expect("Failed to create disc shader");
+    let pipeline = pipeline(device.clone(), render_pass, fs.main_entry_point());
+    draw(device, queue, pipeline, format, width, height)
+}
+
+/* The fullscreen triangle with `fs` shading it. */
+fn pipeline<Fs>(device: Arc<Device>, render_pass: Arc<dyn RenderPassAbstract + Send + Sync>, fs: Fs) -> Arc<Pipeline>
+    where Fs: vulkano::pipeline::shader::GraphicsEntryPointAbstract<SpecializationConstants = ()>,
+          Fs::PipelineLayout: Clone + Send + Sync + 'static,
+          vertex::MainInput: vulkano::pipeline::shader::ShaderInterfaceDefMatch<Fs::InputDefinition>
+{
+    let vs = vertex::Shader::load(device.clone()).expect("Failed to create fullscreen vertex shader");
+    Arc::new(GraphicsPipeline::start()
+        .vertex_input(BufferlessDefinition)
+        .vertex_shader(vs.main_entry_point(), ())
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fs, ())
+        .render_pass(Subpass::from(render_pass, 0).unwrap())
+        .build(device)
+        .unwrap())
+}
+
+/* Draws the fullscreen triangle with `pipeline` into a fresh width x height target, with the
+ * frame uniform telling the fragment shader how big that is, and reads it back. */
+fn draw(device: Arc<Device>, queue: Arc<Queue>, pipeline: Arc<Pipeline>, format: Format, width: u32, height: u32)
+    -> ImageBuffer<Rgba<u8>, Vec<u8>>
+{
+    let usage = ImageUsage { color_attachment: true, transfer_source: true, .. ImageUsage::none() };
+    let image = memory::image(AttachmentImage::with_usage(device.clone(), [width, height], format, usage).unwrap());
+    let framebuffer = Arc::new(Framebuffer::start(pipeline.render_pass().clone())
+        .add(image.clone()).unwrap()
+        .build().unwrap());
+
+    let set = Frame::new(width, height, 0.0).set(device.clone(), pipeline.clone());
+    AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer, false, vec![triangle::CLEAR_COLOR.into()]).unwrap()
+        .draw(pipeline, &triangle::sized_dynamic_state(width, height), BufferlessVertices { vertices: 3, instances: 1 },
+            set, ())
+        .unwrap()
+        .end_render_pass().unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    readback::download_rgba8(device, queue, image)
+}
+
+/* The clear color is transparent and the gradient opaque, so any pixel the triangle missed
+ * still has an alpha of zero. */
+pub fn verify(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) {
+    let missed = image.pixels().filter(|pixel| pixel[3] != 255).count();
+    assert!(missed == 0, "The fullscreen triangle missed {} pixels", missed);
+}
+
+/* Draws the disc on a target twice as wide as it is tall and checks it comes out round: as
+ * many pixels across as down, both matching the radius in units of the height. Scaled by the
+ * resolution alone it would be twice as wide as it is tall. */
+pub fn verify_disc(device: Arc<Device>, queue: Arc<Queue>) {
+    let (width, height) = (2 * SIZE, SIZE);
+    let disc = render_disc(device, queue, width, height);
+
+    let across = (0..width).filter(|&x| disc.get_pixel(x, height / 2)[0] == 255).count() as f32;
+    let down = (0..height).filter(|&y| disc.get_pixel(width / 2, y)[0] == 255).count() as f32;
+    let expected = 2.0 * DISC_RADIUS * height as f32;
+    assert!((across - down).abs() <= 1.0, "The disc is {} pixels across but {} down", across, down);
+    assert!((down - expected).abs() <= 2.0, "The disc is {} pixels across, expected {}", down, expected);
+}
+
+pub mod vertex {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/fullscreen.glsl"
+    }
+}
+
+mod gradient {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/gradient.glsl"
+    }
+}
+
+mod disc {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/disc.glsl"
+    }
+}
@@ -0,0 +1,80 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D src;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D dst;
+
+/* Edges with less contrast than this, relative to the brightest pixel around them, are left
+ * alone, as are any with less than EDGE_MIN at all. */
+const float EDGE_THRESHOLD = 0.125;
+const float EDGE_MIN = 1.0 / 16.0;
+
+/* How far along an edge to look, at most, and how much the blur direction is kept from
+ * blowing up where the edge is faint. */
+const float SPAN_MAX = 8.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec4 color) {
+    return dot(color.rgb, vec3(0.299, 0.587, 0.114));
+}
+
+vec4 texel(ivec2 coords) {
+    return imageLoad(src, clamp(coords, ivec2(0), imageSize(src) - 1));
+}
+
+/* The source between texels, mixed from the four around `position` like a linear sampler
+ * would, clamped at the edges. */
+vec4 sample_at(vec2 position) {
+    position -= 0.5;
+    ivec2 base = ivec2(floor(position));
+    vec2 t = position - vec2(base);
+    vec4 top = mix(texel(base), texel(base + ivec2(1, 0)), t.x);
+    vec4 bottom = mix(texel(base + ivec2(0, 1)), texel(base + ivec2(1, 1)), t.x);
+    return mix(top, bottom, t.y);
+}
+
+/* Fast approximate anti-aliasing: instead of shading several samples per pixel like MSAA, it
+ * looks for edges in the finished image and blurs along them. Where the brightness around a
+ * pixel barely changes there is no edge, and the pixel is kept as it is. Otherwise the
+ * differences between the diagonal neighbours give the direction across the edge, and the
+ * pixel is replaced by samples taken along the edge at right angles to that, two close ones or,
+ * when it stays within the local range of brightness, four spread further out. */
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coords, imageSize(dst)))) {
+        return;
+    }
+
+    vec4 center = texel(coords);
+    float lumaM = luma(center);
+    float lumaNW = luma(texel(coords + ivec2(-1, -1)));
+    float lumaNE = luma(texel(coords + ivec2(1, -1)));
+    float lumaSW = luma(texel(coords + ivec2(-1, 1)));
+    float lumaSE = luma(texel(coords + ivec2(1, 1)));
+    float lumaN = luma(texel(coords + ivec2(0, -1)));
+    float lumaS = luma(texel(coords + ivec2(0, 1)));
+    float lumaW = luma(texel(coords + ivec2(-1, 0)));
+    float lumaE = luma(texel(coords + ivec2(1, 0)));
+
+    float lumaMin = min(lumaM, min(min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)),
+                                   min(min(lumaN, lumaS), min(lumaW, lumaE))));
+    float lumaMax = max(lumaM, max(max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)),
+                                   max(max(lumaN, lumaS), max(lumaW, lumaE))));
+    if (lumaMax - lumaMin < max(EDGE_MIN, lumaMax * EDGE_THRESHOLD)) {
+        imageStore(dst, coords, center);
+        return;
+    }
+
+    vec2 dir = vec2(-((lumaNW + lumaNE) - (lumaSW + lumaSE)), (lumaNW + lumaSW) - (lumaNE + lumaSE));
+    float reduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+    dir = clamp(dir * scale, vec2(-SPAN_MAX), vec2(SPAN_MAX));
+
+    vec2 p = vec2(coords) + 0.5;
+    vec4 near = 0.5 * (sample_at(p + dir * (1.0 / 3.0 - 0.5)) + sample_at(p + dir * (2.0 / 3.0 - 0.5)));
+    vec4 far = 0.5 * near + 0.25 * (sample_at(p - dir * 0.5) + sample_at(p + dir * 0.5));
+    float lumaFar = luma(far);
+    imageStore(dst, coords, (lumaFar < lumaMin || lumaFar > lumaMax) ? near : far);
+}
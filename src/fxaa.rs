@@ -0,0 +1,85 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::pipeline::ComputePipeline;
+
+use crate::input;
+use crate::triangle;
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/* How `--antialias` smooths the triangle and the mandelbrot before they are saved. MSAA has a
+ * flag of its own, `--msaa`, since it changes how the triangle is drawn rather than what is done
+ * with it afterwards, and it can't help the compute demos at all: they write their pixels
+ * straight into a storage image, with no rasterizer to take extra samples. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Antialias {
+    None,
+    Fxaa,
+}
+
+impl Antialias {
+    pub fn parse(name: &str) -> Result<Antialias, String> {
+        match name {
+            "none" => Ok(Antialias::None),
+            "fxaa" => Ok(Antialias::Fxaa),
+            _ => Err(format!("Unknown anti-aliasing `{}`, expected none or fxaa", name)),
+        }
+    }
+
+    /* `pixels` smoothed this way, or as they are. */
+    pub fn apply(self, device: Arc<Device>, queue: Arc<Queue>, pixels: &Image) -> Image {
+        match self {
+            Antialias::None => pixels.clone(),
+            Antialias::Fxaa => fxaa(device, queue, pixels),
+        }
+    }
+}
+
+/* Runs FXAA over `pixels` with a compute shader. It only needs the finished image, so it costs
+ * one cheap pass whatever drew it, where MSAA shades and stores several samples per pixel for
+ * the whole render. The catch is that it can only guess where the edges were from the colors:
+ * it softens the stair steps along them, but can't bring back detail thinner than a pixel. */
+pub fn fxaa(device: Arc<Device>, queue: Arc<Queue>, pixels: &Image) -> Image {
+    let shader = cs::Shader::load(device.clone()).expect("Could not load FXAA shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    input::filter("fxaa", device, queue, pixels, pipeline, ())
+}
+
+/* How far apart two images are, summed over every channel of every pixel. */
+fn difference(a: &Image, b: &Image) -> u64 {
+    a.iter().zip(b.iter()).map(|(&x, &y)| (x as i64 - y as i64).unsigned_abs()).sum()
+}
+
+/* Checks that a flat image comes through untouched, then compares the triangle with FXAA to
+ * the same triangle drawn without anti-aliasing and with 4x MSAA: FXAA should only change
+ * pixels along the edges, and bring them closer to what MSAA makes of them. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let flat = ImageBuffer::from_pixel(64, 64, Rgba([200, 60, 40, 255]));
+    assert!(*fxaa(device.clone(), queue.clone(), &flat) == *flat, "FXAA changed a flat image");
+
+    let format = Format::R8G8B8A8Unorm;
+    let aliased = triangle::render(device.clone(), queue.clone(), None, format, 1).unwrap();
+    let smoothed = fxaa(device.clone(), queue.clone(), &aliased);
+    let changed = aliased.pixels().zip(smoothed.pixels()).filter(|(a, b)| a != b).count();
+    let total = (aliased.width() * aliased.height()) as usize;
+    assert!(changed > 0 && changed < total / 20, "FXAA changed {} of the triangle's {} pixels", changed, total);
+
+    let supported = device.physical_device().limits().framebuffer_color_sample_counts();
+    let samples = triangle::sample_count(supported, 4).unwrap();
+    if samples == 1 {
+        return;
+    }
+    let multisampled = triangle::render(device, queue, None, format, samples).unwrap();
+    let (before, after) = (difference(&aliased, &multisampled), difference(&smoothed, &multisampled));
+    assert!(after < before, "FXAA took the triangle from {} to {} away from {}x MSAA", before, after, samples);
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/fxaa.glsl"
+    }
+}
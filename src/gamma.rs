@@ -0,0 +1,91 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+
+use crate::fullscreen;
+use crate::pattern;
+
+/* The two ways of storing the same 8 bit color. */
+const LINEAR: Format = Format::R8G8B8A8Unorm;
+const SRGB: Format = Format::R8G8B8A8Srgb;
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/* The shader writes linear values and the render target decides how they are stored. A UNORM
+ * target stores them as they are, while an sRGB target gamma encodes them on the way in, which
+ * spends more of the 256 steps on darks, where eyes are most sensitive. Image files and screens
+ * expect gamma encoded values, so the sRGB render is the one that looks right: the UNORM one,
+ * shown as if it were encoded, comes out too dark. A swapchain works the same way, its format
+ * deciding whether what the shader writes gets encoded before it is displayed.
+ *
+ * Renders the fullscreen gradient to one target of each and returns them, linear first. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>) -> (Image, Image) {
+    let linear = fullscreen::render_to(device.clone(), queue.clone(), LINEAR);
+    let srgb = fullscreen::render_to(device, queue, SRGB);
+    (linear, srgb)
+}
+
+/* The gradient's blue channel is 0.5 everywhere. Checks each target stored it as expected and
+ * prints what each made of it. */
+pub fn verify(linear: &Image, srgb: &Image) {
+    let expected_linear = unorm(0.5);
+    let expected_srgb = unorm(encode(0.5));
+
+    for (format, image, expected) in &[(LINEAR, linear, expected_linear), (SRGB, srgb, expected_srgb)] {
+        let blue = image.get_pixel(image.width() / 2, image.height() / 2)[2];
+        println!("{:?} target stores 0.5 as {}", format, blue);
+
+        /* Allow for rounding, which the spec leaves some freedom in. */
+        let off = image.pixels().filter(|pixel| (pixel[2] as i32 - *expected as i32).abs() > 1).count();
+        assert!(off == 0, "{} pixels of the {:?} target don't store 0.5 as {}", off, format, expected);
+    }
+}
+
+/* Applies a gamma curve to the color channels of `image`, mapping each value v in 0..1 to
+ * v^(1/gamma). Above 1 that brightens the midtones and below 1 darkens them, while black and
+ * white stay where they are. There are only 256 possible inputs, so they are looked up. */
+pub fn adjust(image: &Image, gamma: f32) -> Image {
+    let mut table = [0u8; 256];
+    for (value, entry) in table.iter_mut().enumerate() {
+        *entry = unorm((value as f32 / 255.0).powf(1.0 / gamma));
+    }
+
+    let mut adjusted = image.clone();
+    for pixel in adjusted.pixels_mut() {
+        for channel in 0..3 {
+            pixel[channel] = table[pixel[channel] as usize];
+        }
+    }
+    adjusted
+}
+
+/* Checks that a gamma of 1 changes nothing, and that 2.2 brightens every value of the UV
+ * pattern's red ramp but black and white, taking the middle of it to 0.5^(1/2.2), about 0.73. */
+pub fn verify_adjust(device: Arc<Device>, queue: Arc<Queue>) {
+    let uv = pattern::uv(device, queue, 256, 1);
+    assert!(adjust(&uv, 1.0).into_raw() == uv.clone().into_raw(), "A gamma of 1 changed the image");
+
+    let adjusted = adjust(&uv, 2.2);
+    for (before, after) in uv.pixels().zip(adjusted.pixels()) {
+        let (before, after) = (before[0], after[0]);
+        assert!(after > before || (after == before && (before == 0 || before == 255)),
+            "A gamma of 2.2 took {} to {}", before, after);
+    }
+
+    let middle = adjusted.get_pixel(128, 0)[0];
+    assert!((middle as i32 - unorm(0.73) as i32).abs() <= 1, "A gamma of 2.2 took the middle of the ramp to {}", middle);
+}
+
+/* The sRGB transfer function, from linear to encoded. */
+fn encode(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn unorm(value: f32) -> u8 {
+    (value * 255.0).round() as u8
+}
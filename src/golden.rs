@@ -0,0 +1,90 @@
+use image::{ImageBuffer, Rgba};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::compare;
+
+/* Set to anything to write every golden image afresh from what is rendered, after a change
+ * that is meant to change the pictures. */
+pub const BLESS_VAR: &str = "VULKANO_TUT_BLESS";
+
+/* Overrides how far apart, in 0-255 steps per channel, a pixel may be from its golden image,
+ * for GPUs that round differently from the one the images were made on. */
+pub const TOLERANCE_VAR: &str = "VULKANO_TUT_TOLERANCE";
+
+/* How closely an image has to match: no pixel may differ by more than `threshold` in any
+ * channel, bar at most `outliers` of them, a fraction of the image. */
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    pub threshold: u8,
+    pub outliers: f64,
+}
+
+impl Tolerance {
+    /* Exact, for images every GPU has to draw the same. */
+    pub fn exact() -> Tolerance {
+        Tolerance { threshold: 0, outliers: 0.0 }
+    }
+
+    /* The threshold from TOLERANCE_VAR when it is set. */
+    pub fn with_env(self) -> Result<Tolerance, String> {
+        match std::env::var(TOLERANCE_VAR) {
+            Ok(value) => {
+                let threshold = value.parse()
+                    .map_err(|_| format!("{} has to be a number from 0 to 255, not `{}`", TOLERANCE_VAR, value))?;
+                Ok(Tolerance { threshold, .. self })
+            }
+            Err(_) => Ok(self),
+        }
+    }
+
+    /* Whether `a` and `b` match this closely, and if not how far apart they are. */
+    pub fn check(&self, a: &ImageBuffer<Rgba<u8>, Vec<u8>>, b: &ImageBuffer<Rgba<u8>, Vec<u8>>)
+        -> Result<(), String>
+    {
+        let difference = compare::compare(a, b, self.threshold)?;
+        if difference.differing as f64 > self.outliers * difference.pixels as f64 {
+            return Err(format!("{} of {} pixels differ by more than {}, by up to {}", difference.differing,
+                difference.pixels, self.threshold, difference.max));
+        }
+        Ok(())
+    }
+}
+
+/* Where the golden image called `name` is kept. */
+pub fn path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{}.png", name))
+}
+
+/* Compares `image` with the golden image called `name`. With BLESS_VAR set it is written from
+ * `image` instead, to be looked over and committed; without, a missing one is an error, so a
+ * checkout that lost its goldens can't pass by writing whatever it rendered. When they don't
+ * match, what was rendered and where it differs are saved to the temporary directory, to see
+ * what went wrong. */
+pub fn check(name: &str, image: &ImageBuffer<Rgba<u8>, Vec<u8>>, tolerance: Tolerance) -> Result<(), String> {
+    let golden = path(name);
+    if std::env::var_os(BLESS_VAR).is_some() {
+        fs::create_dir_all(golden.parent().unwrap())
+            .map_err(|e| format!("Could not create {}: {}", golden.parent().unwrap().display(), e))?;
+        image.save(&golden).map_err(|e| format!("Could not write {}: {}", golden.display(), e))?;
+        eprintln!("Wrote {}", golden.display());
+        return Ok(());
+    }
+
+    if !golden.exists() {
+        return Err(format!("{} has no golden image, run with {}=1 to write it", golden.display(), BLESS_VAR));
+    }
+    let expected = image::open(&golden)
+        .map_err(|e| format!("Could not load {}: {}", golden.display(), e))?
+        .to_rgba();
+    let tolerance = tolerance.with_env()?;
+    tolerance.check(image, &expected).map_err(|e| {
+        let actual = std::env::temp_dir().join(format!("{}.actual.png", name));
+        let diff = std::env::temp_dir().join(format!("{}.diff.png", name));
+        let _ = image.save(&actual);
+        if image.dimensions() == expected.dimensions() {
+            let _ = compare::visualize(image, &expected, tolerance.threshold).save(&diff);
+        }
+        format!("{} doesn't match {}: {}, see {}", name, golden.display(), e, actual.display())
+    })
+}
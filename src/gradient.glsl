@@ -0,0 +1,14 @@
+#version 450
+
+layout(location = 0) out vec4 f_color;
+
+/* Must match Frame in frame.rs. */
+layout(set = 0, binding = 0) uniform Frame {
+    vec2 resolution;
+    float time;
+} frame;
+
+/* Red across and green down, from the pixel's own position rather than an interpolated uv. */
+void main() {
+    f_color = vec4(gl_FragCoord.xy / frame.resolution, 0.5, 1.0);
+}
@@ -0,0 +1,11 @@
+use fnv::FnvHasher;
+use std::hash::Hasher;
+
+/* A hash of raw pixel data for spotting changes in a demo's output without keeping the image
+ * around. `DefaultHasher` may change between Rust releases, FNV-1a never does, and feeding the
+ * bytes in directly rather than through `Hash` keeps a length prefix out of the result. */
+pub fn pixels(data: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(data);
+    hasher.finish()
+}
@@ -0,0 +1,29 @@
+use image::Rgb;
+use image::hdr::HDREncoder;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/* The extensions an HDR image can be saved with. OpenEXR would need the exr crate, which isn't
+ * a dependency, so it is only Radiance's RGBE format, which most HDR viewers and tools read. */
+pub const EXTENSIONS: &[&str] = &["hdr"];
+
+/* Checks `path` names a file `save` can write. */
+pub fn check(path: &Path) -> Result<(), String> {
+    match path.extension().and_then(|e| e.to_str()).map(str::to_lowercase) {
+        Some(ref e) if EXTENSIONS.contains(&e.as_str()) => Ok(()),
+        Some(ref e) if e == "exr" => Err(format!("Can't save {}: OpenEXR isn't supported, use .hdr instead",
+            path.display())),
+        _ => Err(format!("Can't save {}: HDR images can only be saved as .hdr", path.display())),
+    }
+}
+
+/* Saves width x height RGBA floats, row by row, as a Radiance .hdr file. The values are kept as
+ * they are, over one or not, for tone mapping later. The format has no alpha, so it is dropped. */
+pub fn save(path: &Path, width: u32, height: u32, rgba: &[f32]) -> Result<(), String> {
+    check(path)?;
+    let pixels: Vec<_> = rgba.chunks(4).map(|pixel| Rgb([pixel[0], pixel[1], pixel[2]])).collect();
+    let file = File::create(path).map_err(|e| format!("Could not create {}: {}", path.display(), e))?;
+    HDREncoder::new(BufWriter::new(file)).encode(&pixels, width as usize, height as usize)
+        .map_err(|e| format!("Could not save {}: {}", path.display(), e))
+}
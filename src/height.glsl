@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) in float height;
+
+layout(location = 0) out vec4 f_color;
+
+/* The lowest points are blue and the highest red. */
+void main() {
+    float t = clamp(height, 0.0, 1.0);
+    f_color = vec4(t, 0.3, 1.0 - t, 1.0);
+}
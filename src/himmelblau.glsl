@@ -0,0 +1,23 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+/* Must match descent.rs. */
+const float EXTENT = 5.0;
+const float HIGHEST = 890.0;
+
+/* Himmelblau's function over the plot, with y up. It is 0 at its four minima and 890 at the
+ * top right corner, so it is shaded on a log scale to show the shape of the valleys as well
+ * as the walls: dark blue at the bottom up through teal to pale yellow at the top. */
+void main() {
+    vec2 p = vec2(v_uv.x * 2.0 - 1.0, 1.0 - v_uv.y * 2.0) * EXTENT;
+    float a = p.x * p.x + p.y - 11.0;
+    float b = p.x + p.y * p.y - 7.0;
+    float t = clamp(log(1.0 + a * a + b * b) / log(1.0 + HIGHEST), 0.0, 1.0);
+
+    vec3 color = mix(vec3(0.05, 0.05, 0.3), vec3(0.1, 0.55, 0.55), smoothstep(0.0, 0.5, t));
+    color = mix(color, vec3(0.95, 0.9, 0.6), smoothstep(0.5, 1.0, t));
+    f_color = vec4(color, 1.0);
+}
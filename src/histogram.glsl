@@ -0,0 +1,51 @@
+#version 450
+
+layout(local_size_x = 256, local_size_y = 1, local_size_z = 1) in;
+
+/* Must match LOCAL_SIZE and MAX_BINS in primitives.rs. */
+#define LOCAL_SIZE 256
+#define MAX_BINS 1024
+
+layout(set = 0, binding = 0) readonly buffer Input {
+    uint values[];
+} src;
+
+layout(set = 0, binding = 1) buffer Counts {
+    uint counts[];
+} dst;
+
+layout(push_constant) uniform PushConstants {
+    uint count;
+    uint bins;
+} pc;
+
+shared uint local_counts[MAX_BINS];
+
+/* Every workgroup counts the values it gets into a histogram of its own in shared memory,
+ * stepping over the input a whole dispatch at a time, and then adds its counts into the real
+ * one. Atomics in shared memory are cheap next to global ones, and this way the global counts
+ * are added to once per bin per workgroup instead of once per value. Values that aren't below
+ * `bins` aren't counted. */
+void main() {
+    uint local = gl_LocalInvocationID.x;
+
+    for (uint bin = local; bin < pc.bins; bin += LOCAL_SIZE) {
+        local_counts[bin] = 0;
+    }
+    barrier();
+
+    uint stride = gl_NumWorkGroups.x * LOCAL_SIZE;
+    for (uint i = gl_GlobalInvocationID.x; i < pc.count; i += stride) {
+        uint value = src.values[i];
+        if (value < pc.bins) {
+            atomicAdd(local_counts[value], 1);
+        }
+    }
+    barrier();
+
+    for (uint bin = local; bin < pc.bins; bin += LOCAL_SIZE) {
+        if (local_counts[bin] > 0) {
+            atomicAdd(dst.counts[bin], local_counts[bin]);
+        }
+    }
+}
@@ -0,0 +1,21 @@
+#version 450
+
+layout(location = 0) out vec4 f_color;
+
+/* Must match Frame in frame.rs. */
+layout(set = 0, binding = 0) uniform Frame {
+    vec2 resolution;
+    float time;
+} frame;
+
+/* Converts hue/saturation/value, each in [0, 1], to RGB. Each channel is a piecewise linear
+ * function of the hue, offset by a third of the color wheel from the others. */
+vec3 hsv_to_rgb(vec3 c) {
+    vec3 p = abs(fract(c.xxx + vec3(0.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - 3.0);
+    return c.z * mix(vec3(1.0), clamp(p - 1.0, 0.0, 1.0), c.y);
+}
+
+void main() {
+    /* One unit of time takes the hue all the way around the wheel. */
+    f_color = vec4(hsv_to_rgb(vec3(fract(frame.time), 0.6, 0.9)), 1.0);
+}
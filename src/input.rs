@@ -0,0 +1,135 @@
+use image::{ImageBuffer, ImageError, Rgba};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer};
+use vulkano::descriptor::DescriptorSet;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, ImageAccess, ImageLayout, ImageUsage, ImmutableImage, MipmapsCount, StorageImage};
+use vulkano::pipeline::ComputePipelineAbstract;
+
+use crate::batch;
+use crate::compute::{self, ComputeTask};
+use crate::memory;
+use crate::readback;
+use crate::storage;
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/* The demos `--process` can run on the image from `--input`. Each also has a flag of its own
+ * taking the path, `--dither <path>` being short for `--input <path> --process dither`. */
+pub const DEMOS: [&str; 7] = ["downsample", "convolve", "quantize", "dither", "median", "rotate", "colorspace"];
+
+/* Checks `demo` is one `--process` knows. */
+pub fn parse(demo: &str) -> Result<&'static str, String> {
+    DEMOS.iter().find(|&&d| d == demo).cloned()
+        .ok_or_else(|| format!("Can't process an image with `{}`, expected one of {}", demo, DEMOS.join(", ")))
+}
+
+/* An image loaded for one of the processing demos, and where it came from, so the result can
+ * be saved next to it. */
+pub struct Input {
+    pub path: PathBuf,
+    pub pixels: Image,
+}
+
+/* Loads the image at `path` once for whichever demo is processing it. The format is guessed
+ * from the file's first few bytes rather than its extension, so a PNG named .jpg still loads,
+ * and whatever it is, it is converted to 8 bit RGBA, which is what every processing demo works
+ * on. A missing file and one the image crate can't decode get errors saying which it was. */
+pub fn load(path: &Path) -> Result<Input, String> {
+    let bytes = std::fs::read(path).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => format!("Could not load {}: there is no such file", path.display()),
+        _ => format!("Could not load {}: {}", path.display(), e),
+    })?;
+    let decoded = image::load_from_memory(&bytes).map_err(|e| match e {
+        ImageError::UnsupportedError(_) | ImageError::UnsupportedColor(_) =>
+            format!("Could not load {}: its format isn't supported ({})", path.display(), e),
+        _ => format!("Could not load {}: {}", path.display(), e),
+    })?;
+    Ok(Input { path: path.to_path_buf(), pixels: decoded.to_rgba() })
+}
+
+impl Input {
+    /* Saves `pixels` next to the input as <name>_<suffix>.png, returning where it went. */
+    pub fn save(&self, pixels: &Image, suffix: &str) -> Result<PathBuf, String> {
+        let stem = self.path.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+        let output_path = self.path.with_file_name(format!("{}_{}.png", stem, suffix));
+        pixels.save(&output_path).map_err(|e| format!("Could not save {}: {}", output_path.display(), e))?;
+        Ok(output_path)
+    }
+}
+
+/* Puts `pixels` in an immutable image for a processing demo's compute shader to read. Nothing
+ * writes to the source image once it is uploaded, so it needn't be a storage image the way the
+ * destination is: this is created uninitialized with storage usage, copied into once, and left
+ * in the general layout that storage images are read in. */
+pub fn upload(device: Arc<Device>, queue: Arc<Queue>, pixels: &Image) -> Arc<ImmutableImage<Format>> {
+    let dimensions = Dimensions::Dim2d { width: pixels.width(), height: pixels.height() };
+    let usage = ImageUsage { storage: true, transfer_destination: true, .. ImageUsage::none() };
+    let (image, initialization) = ImmutableImage::uninitialized(device.clone(), dimensions, Format::R8G8B8A8Unorm,
+        MipmapsCount::One, usage, ImageLayout::General, Some(queue.family())).expect("Failed to create image");
+
+    let buf = memory::buffer(CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(),
+        pixels.iter().cloned()).expect("Failed to create buffer"));
+    batch::submit_and_wait(device, queue, |builder| builder
+        .copy_buffer_to_image(buf, initialization).unwrap());
+
+    memory::image(image)
+}
+
+/* Runs one of the image filters over `pixels` and reads back what it made. Every filter's
+ * shader reads the source image at binding 0 and writes a destination the same size at binding
+ * 1, a pixel an invocation, so all that differs from one to the next is `pipeline` and the
+ * `push_constants` it runs with. `name` is the demo's, for --deadline. */
+pub fn filter<P, Pc>(name: &'static str, device: Arc<Device>, queue: Arc<Queue>, pixels: &Image, pipeline: Arc<P>,
+                     push_constants: Pc) -> Image
+    where P: ComputePipelineAbstract + Send + Sync + 'static,
+          Pc: Clone
+{
+    let (source, destination) = images(device, &queue, pixels);
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_image(source).unwrap()
+        .add_image(destination.clone()).unwrap()
+        .build().unwrap());
+    run(name, queue, pipeline, set, destination, push_constants)
+}
+
+/* Like `filter`, for a shader that also reads `buffer` at binding 2, like convolve.glsl's
+ * kernel. */
+pub fn filter_with_buffer<P, B, Pc>(name: &'static str, device: Arc<Device>, queue: Arc<Queue>, pixels: &Image,
+                                    pipeline: Arc<P>, buffer: B, push_constants: Pc) -> Image
+    where P: ComputePipelineAbstract + Send + Sync + 'static,
+          B: BufferAccess + Send + Sync + 'static,
+          Pc: Clone
+{
+    let (source, destination) = images(device, &queue, pixels);
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_image(source).unwrap()
+        .add_image(destination.clone()).unwrap()
+        .add_buffer(buffer).unwrap()
+        .build().unwrap());
+    run(name, queue, pipeline, set, destination, push_constants)
+}
+
+/* `pixels` uploaded for a filter to read, and the storage image it writes. */
+fn images(device: Arc<Device>, queue: &Arc<Queue>, pixels: &Image)
+    -> (Arc<ImmutableImage<Format>>, Arc<StorageImage<Format>>)
+{
+    let (width, height) = pixels.dimensions();
+    let destination = storage::image(queue, Dimensions::Dim2d { width, height }, Format::R8G8B8A8Unorm);
+    (upload(device, queue.clone(), pixels), destination)
+}
+
+/* Dispatches `pipeline` over every pixel of `destination`, waits, and downloads it. */
+fn run<P, Pc>(name: &'static str, queue: Arc<Queue>, pipeline: Arc<P>, set: Arc<dyn DescriptorSet + Send + Sync>,
+              destination: Arc<StorageImage<Format>>, push_constants: Pc) -> Image
+    where P: ComputePipelineAbstract + Send + Sync + 'static,
+          Pc: Clone
+{
+    let [width, height] = destination.dimensions().width_height();
+    ComputeTask::new(name, pipeline, set, compute::group_count(width, height), push_constants).submit(queue.clone());
+    readback::download_rgba8(queue.device().clone(), queue, destination)
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use vulkano::instance::debug::{DebugCallback, Message, MessageTypes};
+use vulkano::instance::{layers_list, Instance, InstanceExtensions};
+
+use crate::error::SandboxError;
+
+/* The layer `--debug` turns on. */
+pub const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+/* How much of what the validation layer says `--debug-level` passes on, each level including
+ * the ones before it. */
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum DebugLevel {
+    Error,
+    Warning,
+    Performance,
+    Info,
+    Debug,
+}
+
+impl DebugLevel {
+    pub fn parse(name: &str) -> Result<DebugLevel, String> {
+        match name {
+            "error" => Ok(DebugLevel::Error),
+            "warning" => Ok(DebugLevel::Warning),
+            "perf" => Ok(DebugLevel::Performance),
+            "info" => Ok(DebugLevel::Info),
+            "debug" => Ok(DebugLevel::Debug),
+            _ => Err(format!("Unknown debug level `{}`, expected error, warning, perf, info or debug", name)),
+        }
+    }
+
+    fn message_types(self) -> MessageTypes {
+        MessageTypes {
+            error: true,
+            warning: self >= DebugLevel::Warning,
+            performance_warning: self >= DebugLevel::Performance,
+            information: self >= DebugLevel::Info,
+            debug: self >= DebugLevel::Debug,
+        }
+    }
+}
+
+/* What `--debug` defaults to: everything that points at a mistake, but not the chatter. */
+pub const DEFAULT_DEBUG_LEVEL: DebugLevel = DebugLevel::Warning;
+
+/* An instance, and the callback printing what the validation layer reports about it. Nothing
+ * is reported once the callback is dropped, so it lives as long as the instance. */
+pub struct DebugInstance {
+    pub instance: Arc<Instance>,
+    pub callback: Option<DebugCallback>,
+}
+
+/* The first of a message's types, for the prefix it is printed with. */
+fn level_name(message: &Message) -> &'static str {
+    let ty = message.ty;
+    if ty.error {
+        "error"
+    } else if ty.warning {
+        "warning"
+    } else if ty.performance_warning {
+        "perf"
+    } else if ty.information {
+        "info"
+    } else {
+        "debug"
+    }
+}
+
+/* Creates the instance with the layers asked for with --instance-layer and the `extensions` a
+ * run can't do without, like the ones for presenting to a window. With `debug` it also
+ * turns on the validation layer and VK_EXT_debug_report, and prints every message at `debug`
+ * or more severe to stderr as it happens, so bad API usage is pointed out at the call that
+ * made it instead of misbehaving quietly. Validation is a development tool: when the layer or
+ * the extension isn't installed this says so and carries on without it, rather than refusing
+ * to run at all. Failing to create the instance itself, for want of a driver, is an error. */
+pub fn create(layers: &[String], mut extensions: InstanceExtensions, debug: Option<DebugLevel>)
+    -> Result<DebugInstance, SandboxError>
+{
+    let mut layers: Vec<&str> = layers.iter().map(String::as_str).collect();
+
+    if debug.is_some() {
+        let installed = layers_list()
+            .map(|mut list| list.any(|layer| layer.name() == VALIDATION_LAYER))
+            .unwrap_or(false);
+        if !installed {
+            eprintln!("Warning: {} isn't installed, running without validation.", VALIDATION_LAYER);
+        } else if !layers.contains(&VALIDATION_LAYER) {
+            layers.push(VALIDATION_LAYER);
+        }
+        extensions.ext_debug_report = InstanceExtensions::supported_by_core()
+            .map(|supported| supported.ext_debug_report)
+            .unwrap_or(false);
+        if !extensions.ext_debug_report {
+            eprintln!("Warning: VK_EXT_debug_report isn't supported, validation messages can't be shown.");
+        }
+    }
+
+    let instance = Instance::new(None, &extensions, layers)?;
+
+    let callback = match debug {
+        Some(level) if extensions.ext_debug_report => {
+            let callback = DebugCallback::new(&instance, level.message_types(), |message| {
+                eprintln!("[vulkan {}] {}: {}", level_name(message), message.layer_prefix, message.description);
+            });
+            Some(callback.expect("Failed to install the debug callback"))
+        }
+        _ => None,
+    };
+
+    Ok(DebugInstance { instance, callback })
+}
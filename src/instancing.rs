@@ -0,0 +1,203 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, ImmutableBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::pipeline_layout::PipelineLayoutAbstract;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass};
+use vulkano::image::{AttachmentImage, ImageUsage};
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::vertex::OneVertexOneInstanceDefinition;
+use vulkano::sync::GpuFuture;
+
+use crate::debug_dump::Intermediate;
+use crate::error::SandboxError;
+use crate::memory;
+use crate::readback;
+use crate::scene::{Scene, SceneOutput};
+use crate::triangle::{self, Vertex, SIZE};
+use crate::vertices;
+
+/* How many quads `--instances` draws when it isn't given. */
+pub const DEFAULT_INSTANCES: u32 = 10000;
+
+/* How much of its cell of the grid each quad covers, across and down, leaving a gap between. */
+const FILL: f32 = 0.7;
+
+/* What changes from one quad to the next, read once per instance rather than once per vertex.
+ * The names have to match the vertex shader's inputs, which is how vulkano lines them up. */
+#[derive(Debug, Clone, Copy)]
+pub struct Instance {
+    pub offset: [f32; 2],
+    pub scale: f32,
+    pub color: [f32; 4],
+}
+vulkano::impl_vertex!(Instance, offset, scale, color);
+
+type Pipeline = GraphicsPipeline<OneVertexOneInstanceDefinition<Vertex, Instance>,
+    Box<dyn PipelineLayoutAbstract + Send + Sync>, Arc<dyn RenderPassAbstract + Send + Sync>>;
+
+/* The six corners of the two triangles making up a quad from -1 to 1, shared by every instance. */
+fn quad(device: Arc<Device>) -> Arc<CpuAccessibleBuffer<[Vertex]>> {
+    let corners = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [1.0, 1.0], [-1.0, 1.0], [-1.0, -1.0]];
+    vertices::make_vertex_buffer(device, corners.iter().map(|&position| Vertex { position }))
+}
+
+/* The columns and rows of the smallest grid, about as wide as it is tall, with a cell for each
+ * of `count` quads. */
+fn grid(count: u32) -> (u32, u32) {
+    let columns = (count as f32).sqrt().ceil().max(1.0) as u32;
+    (columns, count.div_ceil(columns))
+}
+
+/* `count` quads laid out left to right and top to bottom in a grid filling the target, each
+ * centered in its cell and shading from red across to green down. The colors are whole steps of
+ * 1/255, so an rgba8 target stores them exactly. */
+pub fn instances(count: u32) -> Vec<Instance> {
+    let (columns, rows) = grid(count);
+    let (width, height) = (2.0 / columns as f32, 2.0 / rows as f32);
+    let step = |i: u32, n: u32| if n > 1 { (i * 255 / (n - 1)) as f32 / 255.0 } else { 1.0 };
+    (0..count).map(|i| {
+        let (column, row) = (i % columns, i / columns);
+        Instance {
+            offset: [-1.0 + (column as f32 + 0.5) * width, -1.0 + (row as f32 + 0.5) * height],
+            scale: FILL * 0.5 * width.min(height),
+            color: [step(column, columns), step(row, rows), 0.5, 1.0],
+        }
+    }).collect()
+}
+
+/* Draws `count` quads in one draw call. The pipeline takes two vertex buffers, which
+ * `vertex_input_single_buffer` can't describe: the quad's corners, stepped through for every
+ * vertex, and the instances, stepped through once per instance, so the vertex shader runs for
+ * each of the six corners of each of the `count` instances with that instance's offset, scale
+ * and color. Everything that varies lives in the second buffer, so drawing ten times as many
+ * quads is ten times as big a buffer rather than ten times as many draws. */
+pub fn scene(queue: Arc<Queue>, count: u32) -> Result<InstancingScene, SandboxError> {
+    let device = queue.device().clone();
+    let format = Format::R8G8B8A8Unorm;
+    let render_pass = triangle::render_pass(device.clone(), format);
+    let vs = vs::Shader::load(device.clone()).expect("Failed to create instancing vertex shader");
+    let fs = fs::Shader::load(device.clone()).expect("Failed to create instancing fragment shader");
+    let pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input(OneVertexOneInstanceDefinition::<Vertex, Instance>::new())
+        .vertex_shader(vs.main_entry_point(), ())
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let usage = ImageUsage { color_attachment: true, transfer_source: true, .. ImageUsage::none() };
+    let image = memory::image(AttachmentImage::with_usage(device.clone(), [SIZE, SIZE], format, usage).unwrap());
+    let framebuffer = Arc::new(Framebuffer::start(render_pass)
+        .add(image.clone()).unwrap()
+        .build().unwrap());
+    let buf = memory::readback::<u8>(&queue, (SIZE * SIZE) as usize * readback::RGBA8_BYTES)?;
+    let instances = memory::upload_immutable(queue, BufferUsage::vertex_buffer(), instances(count))?;
+
+    Ok(InstancingScene { pipeline, framebuffer, image, buf, quad: quad(device), instances, count })
+}
+
+/* Renders `count` instanced quads and reads them back. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>, count: u32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let scene = scene(queue.clone(), count).expect("Failed to create buffer");
+    let builder = AutoCommandBufferBuilder::primary_one_time_submit(device, queue.family()).unwrap();
+    scene.record(builder).build().unwrap()
+        .execute(queue).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+    scene.pixels()
+}
+
+/* The quads drawn once into an offscreen target and copied back. */
+pub struct InstancingScene {
+    pipeline: Arc<Pipeline>,
+    framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    image: Arc<AttachmentImage<Format>>,
+    buf: Arc<CpuAccessibleBuffer<[u8]>>,
+    quad: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    instances: Arc<ImmutableBuffer<[Instance]>>,
+    count: u32,
+}
+
+impl InstancingScene {
+    fn pixels(&self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let buffer_content = self.buf.read().unwrap();
+        readback::to_rgba8(Format::R8G8B8A8Unorm, SIZE, SIZE, SIZE as usize * 4, &buffer_content).unwrap()
+    }
+}
+
+impl Scene for InstancingScene {
+    fn name(&self) -> &'static str {
+        "instancing"
+    }
+
+    /* The two buffers go to the draw as a pair, the per vertex one first, as the definition
+     * lists them. */
+    fn record(&self, builder: AutoCommandBufferBuilder) -> AutoCommandBufferBuilder {
+        builder
+            .begin_render_pass(self.framebuffer.clone(), false, vec![triangle::CLEAR_COLOR.into()]).unwrap()
+            .draw(self.pipeline.clone(), &triangle::dynamic_state(), (self.quad.clone(), self.instances.clone()),
+                (), ())
+            .unwrap()
+            .end_render_pass().unwrap()
+            .copy_image_to_buffer(self.image.clone(), self.buf.clone()).unwrap()
+    }
+
+    fn output(&self) -> SceneOutput {
+        SceneOutput::Image(self.pixels())
+    }
+
+    fn work(&self) -> (u64, &'static str) {
+        (self.count as u64, "instances")
+    }
+
+    fn intermediates(&self) -> Vec<(&'static str, Intermediate)> {
+        vec![("target", Intermediate::Image(self.image.clone()))]
+    }
+}
+
+/* Draws a grid of quads and checks the middle of each has its own instance's color, so each
+ * instance read its own data, while the gaps between them were left clear, so each was drawn
+ * at its own offset and scale. The cells past the last quad must be empty too: the draw is for
+ * exactly as many instances as the buffer holds. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    for &count in &[1, 30, 1000] {
+        let image = render(device.clone(), queue.clone(), count);
+        let (columns, rows) = grid(count);
+        let pixel = |ndc: f32| ((ndc + 1.0) * 0.5 * SIZE as f32) as u32;
+        let half_cell = SIZE / columns / 2;
+        for (i, instance) in instances(count).iter().enumerate() {
+            let (x, y) = (pixel(instance.offset[0]), pixel(instance.offset[1]));
+            let expected: Vec<u8> = instance.color.iter().map(|&c| (c * 255.0).round() as u8).collect();
+            let center = image.get_pixel(x, y);
+            assert!(center.data.iter().zip(&expected).all(|(&a, &b)| (a as i32 - b as i32).abs() <= 1),
+                "Instance {} of {} is {:?} in the middle, expected {:?}", i, count, center.data, expected);
+            let gap = image.get_pixel(x + 1 - half_cell, y);
+            assert!(gap[3] == 0, "The gap left of instance {} of {} was drawn over", i, count);
+        }
+        let width = SIZE as f32 / columns as f32;
+        let height = SIZE as f32 / rows as f32;
+        for cell in count..columns * rows {
+            let x = ((cell % columns) as f32 + 0.5) * width;
+            let y = ((cell / columns) as f32 + 0.5) * height;
+            assert!(image.get_pixel(x as u32, y as u32)[3] == 0, "Cell {} of {} has a quad in it", cell, count);
+        }
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/instancing.vert"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/instancing.frag"
+    }
+}
@@ -0,0 +1,16 @@
+#version 450
+
+/* Per vertex: a corner of the quad, from -1 to 1. */
+layout(location = 0) in vec2 position;
+
+/* Per instance: where the quad goes, how big it is and its color. */
+layout(location = 1) in vec2 offset;
+layout(location = 2) in float scale;
+layout(location = 3) in vec4 color;
+
+layout(location = 0) out vec4 v_color;
+
+void main() {
+    v_color = color;
+    gl_Position = vec4(offset + position * scale, 0.0, 1.0);
+}
@@ -0,0 +1,40 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform writeonly image2D img;
+
+/* The same iteration as mandelbrot.glsl, but every pixel starts z at its own point and adds the
+ * same c, the seed, where the mandelbrot starts every z at 0 and adds the pixel's point. */
+layout(push_constant) uniform PushConstants {
+    vec2 center;
+    vec2 extent;
+    vec2 seed;
+    uint max_iter;
+    uint palette;
+} pc;
+
+void main() {
+    if (any(greaterThanEqual(gl_GlobalInvocationID.xy, uvec2(imageSize(img))))) {
+        return;
+    }
+
+    vec2 norm_coords = (gl_GlobalInvocationID.xy + vec2(0.5)) / vec2(imageSize(img));
+    vec2 z = pc.center + (norm_coords - vec2(0.5)) * pc.extent;
+
+    uint n;
+    for (n = 0; n < pc.max_iter; n++) {
+        z = vec2(
+            z.x * z.x - z.y * z.y + pc.seed.x,
+            z.y * z.x + z.x * z.y + pc.seed.y
+        );
+
+        if (length(z) > 8.0) {
+            break;
+        }
+    }
+
+    float i = float(n) / float(pc.max_iter);
+    vec4 to_write = pc.palette == 1 ? vec4(vec3(i), 1.0) : vec4(i, i / 2, 1.0, 1.0);
+    imageStore(img, ivec2(gl_GlobalInvocationID.xy), to_write);
+}
@@ -0,0 +1,22 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+/* All the layers of an array image are bound at once, and each store picks one. */
+layout(set = 0, binding = 0, rgba8) uniform writeonly image2DArray img;
+
+layout(push_constant) uniform PushConstants {
+    uint layer;
+} pc;
+
+/* Draws the UV ramp with blue at full, so nothing it writes can be mistaken for the black the
+ * image was cleared to, into the one layer `layer`. */
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coords, imageSize(img).xy))) {
+        return;
+    }
+
+    vec2 uv = vec2(coords % 256) / 255.0;
+    imageStore(img, ivec3(coords, pc.layer), vec4(uv, 1.0, 1.0));
+}
@@ -0,0 +1,110 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::{Dimensions, ImageAccess, StorageImage};
+use vulkano::pipeline::ComputePipeline;
+use vulkano::sync::GpuFuture;
+
+use crate::batch;
+use crate::memory;
+use crate::readback;
+use crate::storage;
+
+/* Must match the local size in layered.glsl. */
+const LOCAL_SIZE: u32 = 8;
+
+const SIZE: u32 = 256;
+
+/* As many layers as a cubemap has faces. */
+pub const LAYERS: u32 = 6;
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/* A size x size storage image with `layers` layers, all cleared to transparent black. An array
+ * image is one image with several 2D slices of the same size and format, and each command that
+ * uses it says which of them it means. Cubemaps are arrays of six, and texture arrays let a
+ * shader pick between many textures without binding each. */
+pub fn layered_image(device: Arc<Device>, queue: Arc<Queue>, size: u32, layers: u32) -> Arc<StorageImage<Format>> {
+    let dimensions = Dimensions::Dim2dArray { width: size, height: size, array_layers: layers };
+    let image = storage::image(&queue, dimensions, Format::R8G8B8A8Unorm);
+
+    batch::submit_and_wait(device, queue, |builder| builder
+        .clear_color_image(image.clone(), ClearValue::Float([0.0; 4])).unwrap());
+
+    image
+}
+
+/* Copies every layer of an array image into one buffer, where they come out one after
+ * another, and splits it back into an image per layer. A cubemap counts as its six faces. */
+pub fn download_layers(device: Arc<Device>, queue: Arc<Queue>, image: Arc<StorageImage<Format>>) -> Vec<Image> {
+    let dimensions = image.dimensions();
+    let [width, height] = dimensions.width_height();
+    let layers = dimensions.array_layers();
+    let layer_bytes = (width * height) as usize * readback::RGBA8_BYTES;
+    let buf = memory::readback::<u8>(&queue, layer_bytes * layers as usize).expect("Failed to create buffer");
+
+    batch::submit_and_wait(device.clone(), queue, |builder| builder
+        .copy_image_to_buffer_dimensions(image, buf.clone(), [0, 0, 0], [width, height, 1], 0, layers, 0).unwrap());
+
+    let buffer_content = buf.read().unwrap();
+    buffer_content.chunks(layer_bytes)
+        .map(|layer| readback::rgba8_image(width, height, width as usize * readback::RGBA8_BYTES, layer))
+        .collect()
+}
+
+/* Draws the pattern in layered.glsl into layer `layer` of a fresh LAYERS layer image with a
+ * compute shader, and reads back all of the layers, the drawn one and the ones left clear. */
+pub fn render_layer(device: Arc<Device>, queue: Arc<Queue>, layer: u32) -> Result<Vec<Image>, String> {
+    if layer >= LAYERS {
+        return Err(format!("Layer {} is out of range, the image only has layers 0 to {}", layer, LAYERS - 1));
+    }
+
+    let shader = cs::Shader::load(device.clone()).expect("Could not load layered shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let image = layered_image(device.clone(), queue.clone(), SIZE, LAYERS);
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_image(image.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = [SIZE.div_ceil(LOCAL_SIZE), SIZE.div_ceil(LOCAL_SIZE), 1];
+    AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .dispatch(groups, pipeline, set, cs::ty::PushConstants { layer }).unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    Ok(download_layers(device, queue, image))
+}
+
+/* Draws into each layer in turn and checks that the pattern landed in that layer and nowhere
+ * else, and that a layer past the end is refused. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    for target in 0..LAYERS {
+        let layers = render_layer(device.clone(), queue.clone(), target).unwrap();
+        assert!(layers.len() == LAYERS as usize, "Read back {} layers, expected {}", layers.len(), LAYERS);
+
+        for (layer, image) in layers.iter().enumerate() {
+            if layer as u32 == target {
+                assert!(image.pixels().all(|pixel| pixel[2] == 255 && pixel[3] == 255),
+                    "Layer {} wasn't drawn into", layer);
+            } else {
+                assert!(image.pixels().all(|pixel| pixel.data == [0; 4]),
+                    "Drawing into layer {} changed layer {}", target, layer);
+            }
+        }
+    }
+
+    assert!(render_layer(device, queue, LAYERS).is_err(), "Drawing into layer {} wasn't refused", LAYERS);
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/layered.glsl"
+    }
+}
@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 color;
+
+layout(location = 0) out vec3 v_color;
+
+/* The depth prepass only works if both passes compute exactly the same depth for a fragment,
+ * otherwise the Equal test in the color pass drops it. */
+invariant gl_Position;
+
+void main() {
+    gl_Position = vec4(position, 1.0);
+    v_color = color;
+}
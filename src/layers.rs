@@ -0,0 +1,34 @@
+use vulkano::instance::layers_list;
+
+/* Prints the instance layers the Vulkan loader can find. Layers sit between the application and
+ * the driver and see every call, which is how validation, API dumps and capture tools hook in.
+ * Which ones are installed varies from machine to machine, so this is where to look for the
+ * name to pass to --instance-layer. */
+pub fn print() -> Result<(), String> {
+    let layers: Vec<_> = layers_list().map_err(|e| format!("Could not list instance layers: {}", e))?.collect();
+    if layers.is_empty() {
+        println!("No instance layers are installed");
+    }
+    for layer in layers {
+        println!("{} (Vulkan {}, version {})", layer.name(), layer.vulkan_version(), layer.implementation_version());
+        println!("    {}", layer.description());
+    }
+    Ok(())
+}
+
+/* Checks every layer in `requested` is installed. Enabling one that isn't fails instance
+ * creation with nothing but `LayerNotPresent`, so this names the missing one and the ones that
+ * are there. */
+pub fn check(requested: &[String]) -> Result<(), String> {
+    let available: Vec<String> = layers_list().map_err(|e| format!("Could not list instance layers: {}", e))?
+        .map(|layer| layer.name().to_string())
+        .collect();
+
+    match requested.iter().find(|name| !available.contains(name)) {
+        Some(missing) if available.is_empty() =>
+            Err(format!("There is no instance layer called `{}`, none are installed", missing)),
+        Some(missing) =>
+            Err(format!("There is no instance layer called `{}`, the installed ones are: {}", missing, available.join(", "))),
+        None => Ok(()),
+    }
+}
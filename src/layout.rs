@@ -0,0 +1,29 @@
+use vulkano::descriptor::descriptor::DescriptorType;
+use vulkano::descriptor::pipeline_layout::PipelineLayoutDesc;
+
+/* Checks that binding `bound` to descriptor set `set` is what `pipeline` expects, one type per
+ * binding in order. Binding the wrong kind of resource, or too few, only shows up as an error
+ * deep in vulkano otherwise; this says which binding is off and how. */
+pub fn check<L>(pipeline: &L, set: usize, bound: &[DescriptorType]) -> Result<(), String>
+    where L: PipelineLayoutDesc + ?Sized
+{
+    let bindings = pipeline.num_bindings_in_set(set)
+        .ok_or_else(|| format!("The pipeline has no descriptor set {}", set))?;
+
+    for binding in 0..bindings.max(bound.len()) {
+        let expected = pipeline.descriptor(set, binding).and_then(|desc| desc.ty.ty());
+        match (expected, bound.get(binding)) {
+            (Some(expected), Some(&bound)) if expected != bound => return Err(format!(
+                "Set {}, binding {}: the pipeline expects a {:?} but a {:?} is bound",
+                set, binding, expected, bound)),
+            (Some(expected), None) => return Err(format!(
+                "Set {}, binding {}: the pipeline expects a {:?} but nothing is bound",
+                set, binding, expected)),
+            (None, Some(bound)) => return Err(format!(
+                "Set {}, binding {}: a {:?} is bound but the pipeline doesn't use this binding",
+                set, binding, bound)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
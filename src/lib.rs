@@ -0,0 +1,95 @@
+/* The demos, each in a module of its own, and what they share: picking a device and queues,
+ * reading results back, checking formats and limits, timing and saving. main.rs only parses the
+ * options and decides what to run, so anything here can be used by another binary or from the
+ * tests without it. */
+
+pub mod barrier;
+pub mod batch;
+pub mod bench;
+pub mod blit;
+pub mod buddhabrot;
+pub mod checker;
+pub mod circle;
+pub mod clear;
+pub mod cli;
+pub mod colorspace;
+pub mod compare;
+pub mod compute;
+pub mod config;
+pub mod convolve;
+pub mod cubemap;
+pub mod debug_dump;
+pub mod descent;
+pub mod descriptors;
+pub mod device;
+pub mod dither;
+pub mod downsample;
+pub mod dryrun;
+pub mod emit;
+pub mod error;
+pub mod explore;
+pub mod fit;
+pub mod frame;
+pub mod fullscreen;
+pub mod fxaa;
+pub mod gamma;
+pub mod golden;
+pub mod hash;
+pub mod hdr;
+pub mod input;
+pub mod instance;
+pub mod instancing;
+pub mod layered;
+pub mod layers;
+pub mod layout;
+pub mod limits;
+pub mod mandelbrot;
+pub mod markers;
+pub mod matmul;
+pub mod median;
+pub mod memory;
+pub mod mesh;
+pub mod mipchain;
+pub mod model;
+pub mod multiply;
+pub mod normalmap;
+pub mod overlay;
+pub mod pacing;
+pub mod particles;
+pub mod pattern;
+pub mod pointcloud;
+pub mod postfx;
+pub mod prepass;
+pub mod primitives;
+pub mod quantize;
+pub mod queues;
+pub mod random;
+pub mod raw;
+pub mod readback;
+pub mod record;
+pub mod reduce;
+pub mod repeat;
+pub mod resolve;
+pub mod rotate;
+pub mod scene;
+pub mod scissor;
+pub mod setup;
+pub mod shaders;
+pub mod shutdown;
+pub mod sort;
+pub mod spirv;
+pub mod storage;
+pub mod subpass;
+pub mod summary;
+pub mod terrain;
+pub mod text;
+pub mod texture;
+pub mod timing;
+pub mod transfer;
+pub mod transition;
+pub mod triangle;
+pub mod vertices;
+pub mod volume;
+pub mod watch;
+pub mod watchdog;
+pub mod window;
@@ -0,0 +1,34 @@
+use vulkano::instance::PhysicalDevice;
+
+/* Prints the limits that decide what the demos can get away with: how big an image can be
+ * rendered, how large a dispatch can get and how much data fits in push constants and buffers.
+ * Vulkan only guarantees fairly small minimums for most of these, so the real numbers are worth
+ * knowing before cranking up --size or --elements. */
+pub fn print(physical: PhysicalDevice) {
+    let limits = physical.limits();
+    println!("{} ({:?}), Vulkan {}", physical.name(), physical.ty(), physical.api_version());
+
+    let rows: &[(&str, String)] = &[
+        ("max_image_dimension_2d", limits.max_image_dimension_2d().to_string()),
+        ("max_framebuffer_width", limits.max_framebuffer_width().to_string()),
+        ("max_framebuffer_height", limits.max_framebuffer_height().to_string()),
+        ("max_color_attachments", limits.max_color_attachments().to_string()),
+        ("max_compute_work_group_count", format!("{:?}", limits.max_compute_work_group_count())),
+        ("max_compute_work_group_size", format!("{:?}", limits.max_compute_work_group_size())),
+        ("max_compute_work_group_invocations", limits.max_compute_work_group_invocations().to_string()),
+        ("max_compute_shared_memory_size", bytes(limits.max_compute_shared_memory_size())),
+        ("max_push_constants_size", bytes(limits.max_push_constants_size())),
+        ("max_uniform_buffer_range", bytes(limits.max_uniform_buffer_range())),
+        ("max_storage_buffer_range", bytes(limits.max_storage_buffer_range())),
+        ("max_bound_descriptor_sets", limits.max_bound_descriptor_sets().to_string()),
+        ("max_memory_allocation_count", limits.max_memory_allocation_count().to_string()),
+    ];
+
+    for (name, value) in rows {
+        println!("    {:<36} {}", name, value);
+    }
+}
+
+fn bytes(n: u32) -> String {
+    format!("{} bytes", n)
+}
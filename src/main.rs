@@ -1,228 +1,1160 @@
-use image::{ImageBuffer, Rgba};
-use vulkano::instance::{Instance, InstanceExtensions, PhysicalDevice};
-use vulkano::device::{Device, DeviceExtensions, Features};
+use vulkano::device::{Device, DeviceExtensions};
 use vulkano::buffer::{CpuAccessibleBuffer, BufferUsage};
-use vulkano::command_buffer::{CommandBuffer, DynamicState, AutoCommandBufferBuilder};
-use vulkano::sync::GpuFuture;
+use vulkano::format::Format;
+use vulkano::instance::InstanceExtensions;
+use vulkano::command_buffer::{CommandBuffer, AutoCommandBufferBuilder};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use vulkano::pipeline::{viewport::Viewport, GraphicsPipeline, ComputePipeline};
-use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
-use vulkano::format::{ClearValue, Format};
-use vulkano::image::{StorageImage, Dimensions};
-use vulkano::framebuffer::{Framebuffer, Subpass};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use image::{ImageBuffer, Luma, Rgba};
+use vulkano::pipeline::{ComputePipeline, ComputePipelineAbstract};
 
-struct Vertex { position: [f32; 2] } 
-vulkano::impl_vertex!(Vertex, position);
+use vulkano_tut::{
+    barrier, bench, blit, buddhabrot, checker, circle, clear, cli, colorspace, compare, config, convolve, cubemap,
+    debug_dump, descent, descriptors, device, dither, downsample, dryrun, emit, explore, fit, fullscreen, fxaa,
+    gamma, hash, hdr, input, instance, instancing, layered, layers, limits, mandelbrot, markers, matmul, median,
+    memory, mesh, mipchain, model, multiply, normalmap, pacing, particles, pattern, pointcloud, postfx, prepass,
+    primitives, quantize, queues, random, raw, readback, record, reduce, repeat, resolve, rotate, scene, scissor,
+    setup, shaders, shutdown, sort, subpass, summary, terrain, text, texture, timing, transfer, transition, triangle,
+    volume, watch, watchdog, window,
+};
+use vulkano_tut::error::SandboxError;
+use vulkano_tut::readback::Channels;
+use vulkano_tut::spirv::{SpvShader, Stage};
 
 fn main() {
+    let mut options = cli::Options::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, cli::USAGE);
+        std::process::exit(2);
+    });
+
+    if let Some(path) = config::Config::path(&options) {
+        if let Err(e) = config::Config::load(&path).and_then(|config| config.apply(&mut options)) {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    }
+    watchdog::set_deadlines(options.deadlines.clone());
+    memory::set_verbose(options.verbose);
+    let view = options.view();
+    if options.adaptive && options.verbose {
+        println!("Iterating the mandelbrot {} times at a zoom of {}", view.max_iter, view.magnification());
+    }
+    let (width, height) = options.dimensions();
+
+    /* Dumping the bytecode doesn't need a GPU, so do it before touching Vulkan at all. */
+    if options.dump_spirv {
+        match shaders::dump_spirv(Path::new(".")) {
+            Ok(paths) => for path in paths {
+                println!("Wrote {}", path.display());
+            },
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
    
+    /* Neither does comparing two images. */
+    if let Some((ref a, ref b)) = options.compare {
+        let threshold = options.threshold.unwrap_or(0);
+        match compare::compare_files(a, b, threshold, options.diff.as_deref()) {
+            Ok(difference) => {
+                difference.print(threshold);
+                if let Some(ref path) = options.diff {
+                    println!("Wrote {}", path.display());
+                }
+                if difference.differing > 0 {
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
+    /* Listing the layers only needs the Vulkan loader, not an instance. */
+    if options.list_layers {
+        if let Err(e) = layers::print() {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     /* We create a Vulkano instance, which lets use use the underlying
-     * Vulkan API. */
-    let instance = Instance::new(None, &InstanceExtensions::none(), None)
-        .expect("Failed to create new instance.");
+     * Vulkan API. Any layers asked for with --instance-layer are enabled here, and have to be
+     * there: unlike extensions, they only exist at the instance level. --debug adds the
+     * validation layer if it can, and the callback reporting what it finds lives as long as
+     * the instance does. */
+    if let Err(e) = layers::check(&options.instance_layers) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    let extensions = if options.window.is_some() { window::extensions() } else { InstanceExtensions::none() };
+    let debug_instance = instance::create(&options.instance_layers, extensions, options.debug()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let instance = debug_instance.instance.clone();
+
+    /* --window opens its window now, before there is a device, so the device can be checked
+     * against it, on the whole of a monitor with --fullscreen. */
+    let windowed = options.window.map(|name| {
+        let title = format!("vulkano-tut: {}", name);
+        let fullscreen = if options.fullscreen {
+            Some(options.monitor.map_or(window::Monitor::Primary, window::Monitor::Index))
+        } else {
+            None
+        };
+        let opened = window::Window::open(instance.clone(), &title, width, height, fullscreen).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        (name, opened)
+    });
    
+    /* Every device that supports Vulkan is issued commands through queues. Queues are
+     * grouped by queue families, and some families support more than one queue. Some
+     * families only support a specific type of operations, like compute or rendering.
+     * The triangle animation only draws and the mandelbrot sweep and --stdout only dispatch
+     * compute shaders, but a normal run does both, so ask for a family that can do what we
+     * are about to ask of it instead of failing halfway through. A run of only some scenes
+     * needs what those scenes do. */
+    let scenes = options.scene.or(options.dump_after).map(|name| vec![name])
+        .or_else(|| options.bench.map(bench::scenes));
+    let (needs_graphics, needs_compute) = match (scenes, options.window) {
+        (Some(names), _) => names.iter().map(|&name| scene::needs(name))
+            .fold((false, false), |(graphics, compute), (g, c)| (graphics || g, compute || c)),
+        (None, Some(name)) => window::needs(name),
+        (None, None) => (
+            options.stdout.is_none() && !options.num_iterations && options.repeat.is_none()
+                && options.process.is_none() && !options.overlap && options.explore.is_none(),
+            options.frames.is_none() || options.particles.is_some() || options.point_cloud
+                || options.terrain.is_some() || options.record.is_some(),
+        ),
+    };
+
     /* There could be many devices that support Vulkan. For instance, a video card or an
      * integrated graphics unit. We need to select which one we want to use. Note: This
-     * would probably be a decision best made by the user. */
-    let physical = PhysicalDevice::enumerate(&instance).next().expect("No device available.");
+     * would probably be a decision best made by the user, and --device-index, --device-name
+     * and --device-type let them make it. Otherwise the best device that can run what we are
+     * about to ask of it wins, a discrete GPU over an integrated one. On CI and headless
+     * servers the only one is often a software implementation like lavapipe, which runs
+     * Vulkan on the CPU. That's slow, but still lets the demos run. */
+    let selector = device::DeviceSelector {
+        index: options.device_index,
+        name: options.device_name.clone(),
+        ty: options.device_type,
+        extensions: DeviceExtensions { khr_swapchain: options.window.is_some(), .. DeviceExtensions::none() },
+        .. device::DeviceSelector::new(needs_graphics, needs_compute)
+    }.with_env().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    if options.list_devices {
+        selector.list(&instance);
+        return;
+    }
+    let physical = selector.select(&instance).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    eprintln!("Using {} ({:?})", physical.name(), physical.ty());
 
-    /* Every device that supports Vulkan is issued commands through queues. Queues are
-     * grouped by queue families, and some families support more than one queue. Some
-     * families only support a specific type of operations, like compute or rendering.*/
-    let queue_family = physical.queue_families().find(|&q| q.supports_graphics()) 
-        .expect("Could not find a graphical queue family");
-   
-    /* Now we can create the device object. This will return the device itself along with
-     * a list of queue objects that we can use to submit operations. */
-    let (device, mut queues) = {
-        Device::new(physical, &Features::none(), &DeviceExtensions::none(),
-                    [(queue_family, 0.5)].iter().cloned()).expect("Failed to create device")
+    if options.limits {
+        limits::print(physical);
+        return;
+    }
+    let tile = mandelbrot::tile_size(physical, options.tile_size).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    /* Now we can create the device object, along with the queues we submit operations to.
+     * Optional features like --profile-markers need extensions, which are worth a warning
+     * rather than an error when they are missing: the demos run just as well without.
+     * --transfer-queue does the uploads on a family of their own, where there is one,
+     * --compute-queue does the queues demo's compute work on one too, and --overlap does both. */
+    let families = setup::Families {
+        transfer: options.transfer_queue || options.overlap,
+        compute: options.compute_queue || options.overlap,
+        optional_extensions: if options.profile_markers { markers::extensions() } else { DeviceExtensions::none() },
     };
-    let queue = queues.next().unwrap();
-   
-    /* We share memory with devices through buffers. Different buffers are optimized for 
-     * different things. For example, there are ImmutableBuffers and CpuBufferPools. 
-     * We specify the device this buffer will communicate with, since device is Arc<Device>,
-     * this will not be expensive. We can also give hints to the implementation using
-     * BufferUsage. Here we allow all types of use. */
-    let source = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), 0..64)
-        .unwrap();
-    let dest =   CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), (0..64).map(|_| 0u8))
-        .unwrap();
-    
-    /* We send commands to the GPU by using a command buffer. The AutoCommandBufferBuilder struct
-     * allows us to easily build command buffers to be sent. */
-    let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
-        .copy_buffer(source.clone(), dest.clone()).unwrap()
-        .build().unwrap();
-
-    /* We send the command down our queue and get the result in finished. We must wait for the
-     * results to be written back, so we fence, flush our caches, and then wait for the GPU
-     * to finish executing the command. */
-    let finished = command_buffer.execute(queue.clone()).unwrap();
-    finished.then_signal_fence_and_flush().unwrap()
-        .wait(None).unwrap();
-
-    /* Source and Dest are RwLocks, so reading them gives us an immutable reference.*/
-    let src_content = source.read().unwrap();
-    let dst_content = dest.read().unwrap();
-
-    assert_eq!(&*src_content, &*dst_content);
-
-    /* We will now perform an arbitrary operation using a compute shader. We will multiply each
-     * element of this buffer by 12. */
-    let data_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), 0..65536)
-        .unwrap();
-
-    let shader = cs::Shader::load(device.clone()).expect("Failed to create shader module");
-
-    let compute_pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())
-        .expect("failed to create compute pipeline"));
-   
-    let set = Arc::new(PersistentDescriptorSet::start(compute_pipeline.clone(), 0)
-        .add_buffer(data_buffer.clone()).unwrap()
-        .build().unwrap()
-    );
-
-    let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
-        .dispatch([1024,1,1], compute_pipeline.clone(), set.clone(), ()).unwrap()
-        .build().unwrap();
-
-    command_buffer.execute(queue.clone()).unwrap()
-        .then_signal_fence_and_flush().unwrap()
-        .wait(None).unwrap();
-    
-    let content = data_buffer.read().unwrap();
-    for (n, val) in content.iter().enumerate() {
-        assert_eq!(*val, n as u32 * 12);
-    }
-
-
-    println!("Success");
-
-    let image = StorageImage::new(device.clone(), Dimensions::Dim2d { width: 512, height: 512 },
-        Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
-
-    let buf = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), (0..512*512*4).map(|_| 0u8))
-        .expect("Failed to create buffer");
-
-    let shader = mandelbrot::Shader::load(device.clone()).expect("Could not load mandelbrot shader");
-
-    let compute_pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
-
-
-    let set = Arc::new(PersistentDescriptorSet::start(compute_pipeline.clone(), 0)
-        .add_image(image.clone()).unwrap()
-        .build().unwrap());
-
-    let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
-        .dispatch([512 / 8, 512 / 8, 1], compute_pipeline.clone(), set.clone(), ()).unwrap()
-        .copy_image_to_buffer(image.clone(), buf.clone()).unwrap()
-        .build().unwrap();
-
-    command_buffer.execute(queue.clone()).unwrap().then_signal_fence_and_flush().unwrap().wait(None).unwrap();
-    
-    let buffer_content = buf.read().unwrap();
-    let mand = ImageBuffer::<Rgba<u8>, _>::from_raw(512, 512, &buffer_content[..]).unwrap();
-    mand.save("mandelbor.png").unwrap();
-
-    let buf = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), (0..512*512*4).map(|_| 0u8))
-        .expect("Failed to create buffer");
-
-    /* Render a triangle! */
-    let v1 = Vertex { position: [-0.5, -0.5] };
-    let v2 = Vertex { position: [0.0, 0.5]   };
-    let v3 = Vertex { position: [0.5, -0.25] };
-    
-    let vertex_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
-        vec![v1, v2, v3].into_iter()).unwrap();
-
-    let render_pass = Arc::new(vulkano::single_pass_renderpass!(device.clone(),
-        attachments: {
-            color: {
-                load: Clear,
-                store: Store,
-                format: Format::R8G8B8A8Unorm,
-                samples: 1,
-            }
-        },
-        pass: {
-            color: [color],
-            depth_stencil: {}
-        }
-    ).unwrap());
-
-    let framebuffer = Arc::new(Framebuffer::start(render_pass.clone())
-        .add(image.clone()).unwrap()
-        .build().unwrap());
-
-    let vs = vertex::Shader::load(device.clone()).expect("Failed to create vertex shader");
-    let fs = frag::Shader::load(device.clone()).expect("Failed to create fragment shader");
-
-    let pipeline = Arc::new(GraphicsPipeline::start()
-        .vertex_input_single_buffer::<Vertex>()
-        .vertex_shader(vs.main_entry_point(), ())
-        .viewports_dynamic_scissors_irrelevant(1)
-        .fragment_shader(fs.main_entry_point(), ())
-        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
-        .build(device.clone())
-        .unwrap());
-
-    let dynamic_state = DynamicState {
-        viewports: Some(vec![Viewport {
-            origin: [0.0,0.0],
-            dimensions: [512.0, 512.0],
-            depth_range: 0.0 .. 1.0,
-        }]),
-        .. DynamicState::none()
+    let setup::Opened { device, queue, transfer_queue, compute_queue, extensions } =
+        setup::open(physical, &selector, &families).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+    /* Declared after the device and queues, so dropped before them, waiting for the GPU first. */
+    let _shutdown = shutdown::Shutdown::new(vec![queue.clone(), transfer_queue.clone(), compute_queue.clone()]);
+    if extensions.ext_debug_marker {
+        markers::enable(device.clone(), queue.clone());
+    }
+
+    if let Some((name, opened)) = windowed {
+        let settings = window::Settings { view, swapchain_images: options.swapchain_images,
+            watch: options.watch.clone() };
+        if let Err(e) = window::run(opened, device.clone(), queue.clone(), name, settings) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    /* A shader given on the command line replaces the built-in shader for the same stage. */
+    let spv_shader = options.shader_spv.as_ref().map(|path| {
+        let shader = SpvShader::load(device.clone(), path).map_err(SandboxError::Shader).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        println!("Using {:?} shader from {}", shader.stage, path.display());
+        shader
+    });
+
+    let format = options.format.unwrap_or(Format::R8G8B8A8Unorm);
+    let samples = triangle::sample_count(physical.limits().framebuffer_color_sample_counts(), options.msaa.unwrap_or(1))
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+    /* The mesh's depth buffer is multisampled too, which the device may support fewer counts of. */
+    let mesh_samples = triangle::sample_count(mesh::supported_samples(physical), options.msaa.unwrap_or(1))
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+
+    /* What the dry run builds and what --scene runs, sized and formatted as the flags say. */
+    let plan = || dryrun::Plan {
+        elements: options.elements.unwrap_or(multiply::DEFAULT_ELEMENTS),
+        width,
+        height,
+        view,
+        mandelbrot: mandelbrot_pipeline(device.clone(), spv_shader.as_ref()),
+        spv_shader: spv_shader.as_ref(),
+        format,
+        samples,
+        tile,
+        instances: options.instances.unwrap_or(instancing::DEFAULT_INSTANCES),
     };
 
-    let command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(
-        device.clone(), queue.family()).unwrap()
-        .begin_render_pass(framebuffer.clone(), false, vec![[0.0,0.0,0.0,0.0].into()])
-        .unwrap()
-        .draw(pipeline.clone(), &dynamic_state, vertex_buffer.clone(), (), ())
-        .unwrap()
-        .end_render_pass()
-        .unwrap()
-        .copy_image_to_buffer(image.clone(), buf.clone())
-        .unwrap()
-        .build()
-        .unwrap();
-
-    command_buffer.execute(queue.clone()).unwrap()
-        .then_signal_fence_and_flush().unwrap()
-        .wait(None).unwrap();
-
-    let buffer_content = buf.read().unwrap();
-    let image = ImageBuffer::<Rgba<u8>, _>::from_raw(512, 512, &buffer_content[..]).unwrap();
-    image.save("triangle.png").unwrap();
-}
+    if options.dry_run {
+        if !dryrun::run(device.clone(), queue.clone(), &plan()) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(name) = options.scene {
+        let mut runner = scene::Runner::new(instance.clone(), device.clone(), queue.clone());
+        let scene = runner.scene(name, &plan()).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        let output = runner.run(&*scene).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        match output {
+            scene::SceneOutput::Image(image) => {
+                let path = options.scene_output.clone().unwrap_or_else(|| PathBuf::from(format!("{}.png", name)));
+                if let Err(e) = save(&image, path, &options) {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            scene::SceneOutput::Values(values) => {
+                let shown: Vec<_> = values.iter().take(8).map(u32::to_string).collect();
+                println!("{}: {} values, starting {}{}", name, values.len(), shown.join(", "),
+                    if values.len() > shown.len() { ", ..." } else { "" });
+            }
+        }
+        return;
+    }
+
+    if let Some(name) = options.dump_after {
+        let mut runner = scene::Runner::new(instance.clone(), device.clone(), queue.clone());
+        let scene = runner.scene(name, &plan()).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        let output = runner.run(&*scene).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        let dir = options.dump_dir.clone().unwrap_or_else(|| PathBuf::from(debug_dump::DEFAULT_DIR));
+        match debug_dump::dump(queue.clone(), &*scene, &output, &dir) {
+            Ok(paths) => {
+                for path in paths {
+                    println!("Wrote {}", path.display());
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(name) = options.bench {
+        let mut runner = scene::Runner::new(instance.clone(), device.clone(), queue.clone());
+        let plan = plan();
+        let scenes: Vec<Box<dyn scene::Scene>> = bench::scenes(name).into_iter()
+            .map(|name| runner.scene(name, &plan))
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            });
+        let warmup = options.warmup.unwrap_or(bench::DEFAULT_WARMUP);
+        let runs = options.runs.unwrap_or(bench::DEFAULT_RUNS);
+        let results: Vec<_> = scenes.iter()
+            .map(|scene| (&**scene, bench::run(&runner, &**scene, warmup, runs)))
+            .collect();
+        bench::print_table(&results);
+        if let Some(ref path) = options.benchmark_csv {
+            let demos: Vec<_> = results.iter().map(|(scene, timings)| (scene.name(), &timings[..])).collect();
+            if let Err(e) = timing::write_csv_all(path, &demos) {
+                eprintln!("Could not write {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+            println!("Wrote {}", path.display());
+        }
+        return;
+    }
+
+    if !options.watch.is_empty() {
+        watch::watch(device.clone(), queue.clone(), &options.watch, format, width, height, view);
+    }
+
+    if let Some(count) = options.particles {
+        let frames = options.frames.unwrap_or(particles::DEFAULT_FRAMES);
+        let seed = options.seed.unwrap_or(random::DEFAULT_SEED);
+        match options.output {
+            Some(ref path) => {
+                let exposure = particles::expose(device.clone(), queue.clone(), count, frames, seed);
+                if let Err(e) = hdr::save(path, triangle::SIZE, triangle::SIZE, &exposure) {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+                println!("Wrote {}", path.display());
+            }
+            None => particles::animate(device.clone(), queue.clone(), count, frames, seed),
+        }
+        return;
+    }
+
+    if options.point_cloud {
+        pointcloud::animate(device.clone(), queue.clone(), options.frames.unwrap_or(pointcloud::DEFAULT_FRAMES));
+        return;
+    }
+
+    let noise = terrain::Noise {
+        octaves: options.octaves.unwrap_or(terrain::DEFAULT_OCTAVES),
+        scale: options.noise_scale.unwrap_or(terrain::DEFAULT_NOISE_SCALE),
+        seed: options.seed.unwrap_or(random::DEFAULT_SEED),
+    };
+    if let Some(style) = options.terrain {
+        terrain::animate(device.clone(), queue.clone(), noise, style, options.frames.unwrap_or(terrain::DEFAULT_FRAMES));
+        return;
+    }
+
+    if options.cube {
+        let frames = options.frames.unwrap_or(mesh::DEFAULT_FRAMES);
+        if let Err(e) = mesh::animate(device.clone(), queue.clone(), frames, mesh_samples) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(ref path) = options.model {
+        let loaded = model::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("model");
+        println!("Loaded {} triangles from {}", loaded.indices.len() / 3, path.display());
+        match options.frames {
+            Some(frames) => {
+                let mesh = mesh::Mesh::new(queue.clone(), loaded.vertices, loaded.indices);
+                if let Err(e) = mesh::spin(device.clone(), queue.clone(), &mesh, name, frames, mesh_samples) {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            None => {
+                let image = model::render(device.clone(), queue.clone(), loaded, mesh_samples).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                });
+                if let Err(e) = save(&image, format!("{}.png", name), &options) {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        return;
+    }
+
+    if let Some(name) = options.record {
+        let animation = match name {
+            "cube" => record::Animation::Cube,
+            _ => record::Animation::Mandelbrot { view, width, height },
+        };
+        let frames = options.frames.unwrap_or(record::DEFAULT_FRAMES);
+        let in_flight = options.in_flight.unwrap_or(pacing::DEFAULT_FRAMES);
+        record::save(device.clone(), queue.clone(), animation, frames, in_flight,
+            options.ffmpeg.as_deref()).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        return;
+    }
+
+    if let Some(ref events) = options.explore {
+        explore::run(device.clone(), queue.clone(), width, height, view, events);
+        return;
+    }
+
+    if let Some(ref path) = options.texture {
+        let result = input::load(path).and_then(|input| {
+            let uploaded = texture::upload(device.clone(), queue.clone(), &input.pixels);
+            println!("Uploaded {} x {} with {} mip levels", input.pixels.width(), input.pixels.height(),
+                uploaded.mipmap_levels());
+            input.save(&texture::render(device.clone(), queue.clone(), uploaded, texture::DEFAULT_SCALE), "textured")
+        });
+        match result {
+            Ok(output) => println!("Wrote {}", output.display()),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(frames) = options.frames {
+        triangle::animate(device.clone(), queue.clone(), frames, format, samples, options.verbose).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        return;
+    }
+
+    /* Print the multiply demo's output for scripts instead of running everything. */
+    if let Some(emit) = options.stdout {
+        let elements = options.elements.unwrap_or(multiply::DEFAULT_ELEMENTS);
+        let values = multiply::run(device.clone(), queue.clone(), elements);
+        if let Err(e) = emit::write(std::io::stdout().lock(), emit, &values) {
+            eprintln!("Could not write the values: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let (Some(demo), Some(path)) = (options.process, options.input.as_ref()) {
+        let result = input::load(path).and_then(|input| match demo {
+            "downsample" => downsample::downsample(device.clone(), queue.clone(), &input),
+            "convolve" => {
+                let kernel = options.kernel.as_ref().map_or(convolve::DEFAULT_KERNEL, |k| k.as_slice());
+                convolve::convolve(device.clone(), queue.clone(), &input, kernel)
+            }
+            "quantize" => quantize::quantize_file(device.clone(), queue.clone(), &input,
+                options.colors.unwrap_or(quantize::DEFAULT_COLORS)),
+            "dither" => dither::dither_file(device.clone(), queue.clone(), &input,
+                options.bits.unwrap_or(dither::DEFAULT_BITS)),
+            "median" => median::median_filter(device.clone(), queue.clone(), &input,
+                options.median_window.unwrap_or(median::DEFAULT_WINDOW)),
+            "rotate" => rotate::rotate(device.clone(), queue.clone(), &input,
+                options.degrees.unwrap_or(rotate::DEFAULT_DEGREES)),
+            _ => colorspace::convert_colorspace(device.clone(), queue.clone(), &input,
+                options.from.unwrap_or(colorspace::DEFAULT_FROM), options.to.unwrap_or(colorspace::DEFAULT_TO)),
+        });
+        match result {
+            Ok(output) => println!("Wrote {}", output.display()),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-mod cs {
-    vulkano_shaders::shader!{
-        ty: "compute",
-        path: "src/op.glsl"
+    if options.overlap {
+        let elements = options.elements.unwrap_or(multiply::DEFAULT_ELEMENTS);
+        for &(overlapped, how) in &[(false, "waiting after every submission"), (true, "overlapped")] {
+            let (_, elapsed) = queues::overlap(device.clone(), transfer_queue.clone(), compute_queue.clone(),
+                elements, overlapped);
+            println!("Multiplied {} numbers in {} pieces {}: {:.3} ms", elements, queues::CHUNKS, how,
+                elapsed.as_secs_f64() * 1000.0);
+        }
+        return;
     }
+
+    if let Some(ref filters) = options.filters {
+        match options.input {
+            Some(ref path) => {
+                let result = input::load(path).and_then(|input| {
+                    input.save(&postfx::apply(device.clone(), queue.clone(), &input.pixels, filters), "filtered")
+                });
+                match result {
+                    Ok(output) => println!("Wrote {}", output.display()),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            None => {
+                let mand = mandelbrot::render_in_tiles(device.clone(), queue.clone(),
+                    mandelbrot_pipeline(device.clone(), spv_shader.as_ref()), width, height, view, tile)
+                    .unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    });
+                let filtered = postfx::apply(device.clone(), queue.clone(), &mand, filters);
+                if let Err(e) = save(&filtered, "mandelbrot_filtered.png", &options) {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        return;
+    }
+
+    if let Some(demo) = options.repeat {
+        let command_buffer = match demo {
+            "mandelbrot" => mandelbrot::prepare(device.clone(), queue.clone(),
+                mandelbrot_pipeline(device.clone(), spv_shader.as_ref()), width, height, view, tile),
+            _ => {
+                let elements = options.elements.unwrap_or(multiply::DEFAULT_ELEMENTS);
+                multiply::prepare(queue.clone(), multiply::pipeline(device.clone()), elements)
+                    .map(|(command_buffer, _)| command_buffer).map_err(String::from)
+            }
+        };
+        match command_buffer {
+            Ok(command_buffer) => repeat::run(demo, queue.clone(), command_buffer),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        if options.verbose {
+            memory::print_summary();
+        }
+        return;
+    }
+
+    if options.num_iterations {
+        let sweep = mandelbrot::Sweep {
+            view,
+            warmup: options.warmup.unwrap_or(mandelbrot::DEFAULT_WARMUP),
+            save: !options.no_save,
+            verbose: options.verbose,
+        };
+        let timings = mandelbrot::iteration_sweep(device.clone(), queue.clone(), width, height, sweep);
+        if let Some(ref path) = options.benchmark_csv {
+            if let Err(e) = timing::write_csv(path, "mandelbrot", &timings) {
+                eprintln!("Could not write {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+        if options.verbose {
+            memory::print_summary();
+        }
+        return;
+    }
+   
+    let antialias = options.antialias.unwrap_or(fxaa::Antialias::None);
+    let start = Instant::now();
+    let mut summary = summary::Summary::default();
+
+    summary.run("copy", || {
+        /* We share memory with devices through buffers. Different buffers are optimized for 
+         * different things. For example, there are ImmutableBuffers and CpuBufferPools. 
+         * We specify the device this buffer will communicate with, since device is Arc<Device>,
+         * this will not be expensive. We can also give hints to the implementation using
+         * BufferUsage. Here the source is only ever copied from and the destination copied to.
+         * Both stay where the CPU can see them, so the copy can be checked by reading the two.
+         *
+         * copy_buffer wants the source and destination to have the same `Content = T`, so
+         * their element types are checked to match when this compiles. It also means an
+         * unannotated `0..64` would silently take its type from the destination, so the source
+         * spells out u32 itself. */
+        let source = memory::buffer(CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(),
+            0..64u32).unwrap());
+        let dest = memory::readback::<u32>(&queue, 64)?;
+
+        /* We send commands to the GPU by using a command buffer. The AutoCommandBufferBuilder struct
+         * allows us to easily build command buffers to be sent. */
+        let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .copy_buffer(source.clone(), dest.clone()).unwrap()
+            .build().unwrap();
+
+        /* We send the command down our queue and get the result in finished. We must wait for the
+         * results to be written back before looking at them, so read_after fences, flushes our
+         * caches, and waits for the GPU to finish executing the command before it hands out the
+         * contents. Source and Dest are RwLocks, so reading them gives us immutable references. */
+        let finished = command_buffer.execute(queue.clone()).unwrap();
+        let (src_content, dst_content) = readback::read_after(finished, &source, &dest);
+
+        assert_eq!(&*src_content, &*dst_content);
+        assert!(dst_content.iter().cloned().eq(0..64), "The copy didn't come out as 0 to 63");
+        Ok(())
+    });
+
+    /* We will now perform an arbitrary operation using a compute shader, multiplying every
+     * element of a buffer by 12. */
+    summary.run("multiply", || {
+        match options.elements {
+            Some(elements) => {
+                multiply::run(device.clone(), queue.clone(), elements);
+            }
+            None => {
+                multiply::verify(device.clone(), queue.clone());
+                multiply::verify_batched(device.clone(), queue.clone());
+            }
+        }
+        if options.raw_output("multiply").is_some() {
+            let elements = options.elements.unwrap_or(multiply::DEFAULT_ELEMENTS);
+            let values = multiply::run(device.clone(), queue.clone(), elements);
+            write_raw("multiply", &values, &[values.len()], &options)?;
+        }
+        println!("Success");
+        Ok(())
+    });
+
+    /* The multiply's descriptor set again, from the cache the scenes make theirs with. */
+    summary.run("descriptors", || {
+        descriptors::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* The same idea scaled up: multiply two matrices, checking against a CPU reference. */
+    summary.run("matmul", || {
+        matmul::verify(device.clone(), queue.clone());
+        if options.raw_output("matmul").is_some() {
+            let n = 64;
+            let (a, b) = matmul::example_inputs(n);
+            write_raw("matmul", &matmul::gpu_matmul(device.clone(), queue.clone(), &a, &b, n), &[n, n], &options)?;
+        }
+        println!("Matmul success");
+        Ok(())
+    });
+
+    /* Add up a long list the way GPUs do, a workgroup at a time in shared memory. */
+    summary.run("reduce", || {
+        reduce::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Scan, reduce and count on the GPU in several dispatches, checked against the CPU. */
+    summary.run("primitives", || {
+        primitives::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    summary.run("sort", || {
+        sort::verify(device.clone(), queue.clone());
+        if options.raw_output("sort").is_some() {
+            let sorted = sort::gpu_sort(device.clone(), queue.clone(), &sort::pseudo_random(4096));
+            write_raw("sort", &sorted, &[sorted.len()], &options)?;
+        }
+        println!("Sort success");
+        Ok(())
+    });
+
+    let mand = summary.run("mandelbrot", || {
+        let compute_pipeline = mandelbrot_pipeline(device.clone(), spv_shader.as_ref());
+        let started = Instant::now();
+        let mand = mandelbrot::render_in_tiles(device.clone(), queue.clone(), compute_pipeline, width, height, view, tile)?;
+        let tiles = mandelbrot::tile_count(width, height, tile);
+        if tiles > 1 || options.tile_size.is_some() {
+            println!("Rendered the mandelbrot at {}x{} in {} tile{} of up to {}x{} in {:.1} ms", width, height, tiles,
+                if tiles == 1 { "" } else { "s" }, tile, tile, started.elapsed().as_secs_f64() * 1000.0);
+        }
+        let path = options.mandelbrot_output.as_ref().map_or(Path::new("mandelbor.png"), |p| p.as_path());
+        save(&antialias.apply(device.clone(), queue.clone(), &mand), path, &options)?;
+
+        /* The same view again, with the GPU only counting iterations and the CPU doing the coloring. */
+        let counts = mandelbrot::escape_counts(device.clone(), queue.clone(), width, height, view);
+        save(&mandelbrot::colorize(&counts, width, height, view.max_iter, view.palette), "mandelbrot_cpu.png",
+            &options)?;
+        write_raw("mandelbrot", &counts, &[height as usize, width as usize], &options)?;
+        mandelbrot::verify_escape_counts(device.clone(), queue.clone());
+        if options.count_invocations {
+            mandelbrot::count_invocations(device.clone(), queue.clone(), width, height);
+        }
+
+        /* Make sure sizes that don't divide evenly come back unsheared too. */
+        mandelbrot::verify_odd_width(device.clone(), queue.clone());
+        /* And that rendering in tiles, for pictures too big for a single image, leaves no seams. */
+        mandelbrot::verify_tiles(device.clone(), queue.clone());
+        /* And that passing the view in a uniform buffer, for devices short on push constant
+         * space, draws the same picture. */
+        mandelbrot::verify_params(device.clone(), queue.clone());
+        /* And that orbit trap coloring follows the orbits to the trap. */
+        mandelbrot::verify_orbit_trap(device.clone(), queue.clone());
+        Ok(mand)
+    });
+
+    /* Plot where the orbits of the points that escape the mandelbrot go instead. */
+    summary.run("buddhabrot", || {
+        buddhabrot::verify(device.clone(), queue.clone());
+        let buddhabrot = buddhabrot::render(device.clone(), queue.clone(),
+            options.samples.unwrap_or(buddhabrot::DEFAULT_SAMPLES), options.seed.unwrap_or(random::DEFAULT_SEED));
+        save_gray(&buddhabrot, "buddhabrot.png", &options)?;
+        Ok(())
+    });
+
+    /* A pattern that comes out identical on every device, for checking --hash against. */
+    summary.run("uv", || {
+        let uv = pattern::uv(device.clone(), queue.clone(), 256, 256);
+        save(&uv, "uv.png", &options)?;
+        Ok(())
+    });
+
+    /* Halve an image with a box filter in a compute shader, including odd sizes. */
+    summary.run("downsample", || {
+        downsample::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Convolve an image with a kernel from a uniform buffer, checked against the downsample. */
+    summary.run("convolve", || {
+        convolve::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Reduce an image to a few colors with k-means, the GPU assigning and the CPU averaging. */
+    summary.run("quantize", || {
+        quantize::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Reduce an image to a few bits per channel, hiding the banding with a Bayer matrix. */
+    summary.run("dither", || {
+        dither::verify(device.clone(), queue.clone());
+        let source = pattern::uv(device.clone(), queue.clone(), 256, 256);
+        let dithered = dither::dither(device.clone(), queue.clone(), &source, options.bits.unwrap_or(dither::DEFAULT_BITS));
+        save(&dithered, "dither.png", &options)?;
+        Ok(())
+    });
+
+    /* Convert an image to HSV or YUV and back, a pixel per invocation. */
+    summary.run("colorspace", || {
+        colorspace::verify(device.clone(), queue.clone());
+        let source = pattern::uv(device.clone(), queue.clone(), 256, 256);
+        let converted = colorspace::convert(device.clone(), queue.clone(), &source, colorspace::ColorSpace::Rgb,
+            options.to.unwrap_or(colorspace::DEFAULT_TO));
+        save(&converted, "colorspace.png", &options)?;
+        Ok(())
+    });
+
+    /* Clean salt and pepper noise out of an image with a median filter. */
+    summary.run("median", || {
+        median::verify(device.clone(), queue.clone());
+        let source = pattern::uv(device.clone(), queue.clone(), 256, 256);
+        let noisy = median::salt_and_pepper(&source, 20, options.seed.unwrap_or(random::DEFAULT_SEED));
+        let filtered = median::median(device.clone(), queue.clone(), &noisy,
+            options.median_window.unwrap_or(median::DEFAULT_WINDOW));
+        save(&filtered, "median.png", &options)?;
+        Ok(())
+    });
+
+    /* Rotate an image about its center, each pixel looking up where it came from. */
+    summary.run("rotate", || {
+        rotate::verify(device.clone(), queue.clone());
+        let source = pattern::uv(device.clone(), queue.clone(), 256, 256);
+        let rotated = rotate::rotate_image(device.clone(), queue.clone(), &source,
+            options.degrees.unwrap_or(rotate::DEFAULT_DEGREES));
+        save(&rotated, "rotate.png", &options)?;
+        Ok(())
+    });
+
+    /* Move particles with a compute shader and draw them straight out of the same buffer. */
+    summary.run("particles", || {
+        particles::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Make a heightmap out of noise in a compute shader and lift a grid to it in a vertex shader. */
+    summary.run("terrain", || {
+        terrain::verify(device.clone(), queue.clone());
+        save(&terrain::render(device.clone(), queue.clone(), noise, terrain::Style::Wireframe), "terrain_wireframe.png",
+            &options)?;
+        save(&terrain::render(device.clone(), queue.clone(), noise, terrain::Style::Shaded), "terrain.png", &options)?;
+        Ok(())
+    });
+
+    /* Draw a cube that overlaps itself, sorted out per pixel by a depth buffer. */
+    summary.run("mesh", || {
+        mesh::verify(device.clone(), queue.clone());
+        save(&mesh::render(device.clone(), queue.clone(), mesh_samples)?, "cube.png", &options)?;
+        model::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Chain compute filters over an image, ping-ponging between two images. */
+    summary.run("postfx", || {
+        postfx::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Pan, zoom and switch fractals, re-dispatching with new push constants every frame. */
+    summary.run("explore", || {
+        explore::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Keep up to three frames in flight, each waited for only when its slot comes round again. */
+    summary.run("pacing", || {
+        pacing::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Render animations offscreen with frames in flight, encoding on another thread. */
+    summary.run("record", || {
+        record::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Upload a texture with its mip chain and sample it on a quad. */
+    summary.run("texture", || {
+        texture::verify(device.clone(), queue.clone());
+        let source = pattern::uv(device.clone(), queue.clone(), 256, 256);
+        let uploaded = texture::upload(device.clone(), queue.clone(), &source);
+        save(&texture::render(device.clone(), queue.clone(), uploaded, texture::DEFAULT_SCALE), "texture.png",
+            &options)?;
+        Ok(())
+    });
+
+    /* Fill a buffer with points in a compute shader and draw them with no vertex input at all. */
+    summary.run("pointcloud", || {
+        pointcloud::verify(device.clone(), queue.clone());
+        let cloud = pointcloud::render(device.clone(), queue.clone());
+        save(&cloud, "pointcloud.png", &options)?;
+        Ok(())
+    });
+
+    /* Walk particles down a function's gradient in a compute shader and draw their paths over it. */
+    summary.run("descent", || {
+        descent::verify(device.clone(), queue.clone());
+        let steps = options.descent_steps.unwrap_or(descent::DEFAULT_STEPS);
+        let step_size = options.step_size.unwrap_or(descent::DEFAULT_STEP_SIZE);
+        save(&descent::render(device.clone(), queue.clone(), descent::Descent { steps, step_size }), "descent.png",
+            &options)?;
+        Ok(())
+    });
+
+    /* Scale the render down on the GPU with a blit. */
+    match mand {
+        Some(ref mand) => {
+            summary.run("blit", || {
+                let half = blit::half_size(device.clone(), queue.clone(), mand);
+                save(&half, "mandelbrot_half.png", &options)?;
+                Ok(())
+            });
+        }
+        None => summary.skip("blit", "needs the mandelbrot"),
+    }
+
+    /* Blit a texture down level by level and lay its mip chain out in one image. */
+    summary.run("mipchain", || {
+        mipchain::verify(device.clone(), queue.clone());
+        let levels = mipchain::render(device.clone(), queue.clone());
+        save(&levels, "mipchain.png", &options)?;
+        Ok(())
+    });
+
+    /* Upload a buffer and a texture on another queue and hand them over to this one. */
+    summary.run("transfer", || {
+        transfer::verify(device.clone(), queue.clone(), transfer_queue.clone());
+        Ok(())
+    });
+
+    /* Upload on one queue and multiply on another, chained by semaphores instead of waits. */
+    summary.run("queues", || {
+        queues::verify(device.clone(), transfer_queue.clone(), compute_queue.clone());
+        Ok(())
+    });
+
+    /* And that targets other than RGBA8 can still be saved. */
+    summary.run("readback", || {
+        readback::verify_formats(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Write an image and a buffer out the way `--dump-after` does, and read them back. */
+    summary.run("dump", || {
+        debug_dump::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Rasterize a circle with a compute shader instead of the graphics pipeline. */
+    summary.run("circle", || {
+        let circle = circle::draw_circle(device.clone(), queue.clone(), circle::CENTER, circle::RADIUS);
+        save_gray(&circle, "circle.png", &options)?;
+        circle::verify(&circle);
+        Ok(())
+    });
+
+    /* Fill a storage image with a color without a render pass. */
+    summary.run("clear", || {
+        clear::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Draw into one layer of an array image, leaving the others alone. */
+    summary.run("layered", || {
+        layered::verify(device.clone(), queue.clone());
+        let layer = options.layer.unwrap_or(0);
+        let layers = layered::render_layer(device.clone(), queue.clone(), layer)?;
+        save(&layers[layer as usize], format!("layer_{}.png", layer), &options)?;
+        Ok(())
+    });
+
+    /* Fill a 3D image with a distance field and cut one slice out of it. */
+    summary.run("volume", || {
+        volume::verify(device.clone(), queue.clone());
+        let z = options.slice.unwrap_or(volume::DEFAULT_SLICE);
+        save_gray(&volume::slice(device.clone(), queue.clone(), z)?, format!("slice_{}.png", z), &options)?;
+        Ok(())
+    });
+
+    /* Render a sky into the six faces of a cubemap and reflect it in a mirror ball. */
+    summary.run("cubemap", || {
+        cubemap::verify(device.clone(), queue.clone());
+        let ball = cubemap::render(device.clone(), queue.clone());
+        save(&ball, "cubemap.png", &options)?;
+        Ok(())
+    });
+
+    /* Chain a second compute pass onto the mandelbrot, once synchronized by vulkano and once by
+     * hand with pipeline barriers, and check both come out the same. */
+    summary.run("barrier", || {
+        barrier::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Move an image through the layouts a clear, a copy and a compute shader need, with every
+     * transition written out by hand. */
+    summary.run("transition", || {
+        transition::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    summary.run("triangle", || {
+        let image = triangle::render(device.clone(), queue.clone(), spv_shader.as_ref(), format, samples)?;
+        let image = antialias.apply(device.clone(), queue.clone(), &image);
+        save(&image, options.triangle_output.as_ref().map_or(Path::new("triangle.png"), |p| p.as_path()), &options)?;
+        Ok(())
+    });
+
+    /* Smooth the triangle's edges after the fact with FXAA, and compare it with MSAA. */
+    summary.run("fxaa", || {
+        fxaa::verify(device.clone(), queue.clone());
+        let aliased = triangle::render(device.clone(), queue.clone(), None, Format::R8G8B8A8Unorm, 1)?;
+        save(&fxaa::fxaa(device.clone(), queue.clone(), &aliased), "triangle_fxaa.png", &options)?;
+        Ok(())
+    });
+
+    /* Draw the triangle with MSAA, resolve it and hand the resolved image to a compute shader. */
+    summary.run("resolve", || {
+        resolve::verify(device.clone(), queue.clone());
+        let inverted = resolve::render_default(device.clone(), queue.clone());
+        save(&inverted, "triangle_resolved.png", &options)?;
+        Ok(())
+    });
+
+    /* Draw the triangle again with a scissor rectangle cutting off all but its middle. */
+    summary.run("scissor", || {
+        let clipped = scissor::render(device.clone(), queue.clone());
+        save(&clipped, "triangle_scissor.png", &options)?;
+        scissor::verify(device.clone(), queue.clone(), &clipped);
+        Ok(())
+    });
+
+    /* Draw thousands of quads in one draw call, each instance's offset, scale and color read from a
+     * second vertex buffer. */
+    summary.run("instancing", || {
+        instancing::verify(device.clone(), queue.clone());
+        let count = options.instances.unwrap_or(instancing::DEFAULT_INSTANCES);
+        save(&instancing::render(device.clone(), queue.clone(), count), "instancing.png", &options)?;
+        Ok(())
+    });
+
+    /* Cover the screen with a triangle generated in the vertex shader, no vertex buffer. */
+    summary.run("fullscreen", || {
+        let gradient = fullscreen::render(device.clone(), queue.clone());
+        save(&gradient, "fullscreen.png", &options)?;
+        fullscreen::verify(&gradient);
+        fullscreen::verify_disc(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Draw the same gradient to a linear and an sRGB target to show the difference gamma makes. */
+    summary.run("gamma", || {
+        let (linear, srgb) = gamma::render(device.clone(), queue.clone());
+        save(&linear, "gamma_linear.png", &options)?;
+        save(&srgb, "gamma_srgb.png", &options)?;
+        gamma::verify(&linear, &srgb);
+        gamma::verify_adjust(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Tint the triangle in a second subpass that reads the first through an input attachment. */
+    summary.run("subpass", || {
+        let tinted = subpass::render(device.clone(), queue.clone());
+        save(&tinted, "triangle_tinted.png", &options)?;
+        subpass::verify(device.clone(), queue.clone(), &tinted);
+        Ok(())
+    });
+
+    /* Draw overlapping geometry with and without a depth prepass and check they agree. */
+    summary.run("prepass", || {
+        prepass::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Light a flat quad with a normal map, so it shades as if it had bumps. */
+    summary.run("normalmap", || {
+        let lit = normalmap::render(device.clone(), queue.clone(), options.light.unwrap_or(normalmap::DEFAULT_LIGHT));
+        save(&lit, "normalmap.png", &options)?;
+        normalmap::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    /* Write a line of text with quads cut from a font atlas. */
+    summary.run("text", || {
+        let line = options.text.as_ref().map_or(text::DEFAULT_TEXT, |t| t.as_str());
+        save(&text::render(device.clone(), queue.clone(), line)?, "text.png", &options)?;
+        text::verify(device.clone(), queue.clone());
+        Ok(())
+    });
+
+    summary.print();
+
+    /* Encoding and writing PNGs is slow next to the GPU work, so say how much of the run it took.
+     * Compare with a --no-save run to see the demos without it. */
+    let elapsed = start.elapsed();
+    let saving = Duration::from_nanos(SAVE_NANOS.load(Ordering::Relaxed));
+    if options.no_save {
+        println!("Ran the demos in {:.2} ms, saving nothing", elapsed.as_secs_f64() * 1000.0);
+    } else {
+        println!("Ran the demos in {:.2} ms, {:.2} ms of it saving images ({:.2} ms without)",
+            elapsed.as_secs_f64() * 1000.0, saving.as_secs_f64() * 1000.0, (elapsed - saving).as_secs_f64() * 1000.0);
+    }
+    if options.verbose {
+        memory::print_summary();
+    }
+
+    if summary.failed() {
+        std::process::exit(1);
+    }
+}
+
+/* The total time `save` has spent writing images. */
+static SAVE_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/* Saves a demo's output, with the --gamma curve applied and resized to --output-size if they
+ * are given. With --hash the hash of the rendered pixels is printed alongside the path. With
+ * --no-save the image is still hashed, but nothing is written. A file that can't be written is
+ * the demo's error, for the summary to report, rather than a panic. */
+fn save<P: AsRef<Path>>(image: &ImageBuffer<Rgba<u8>, Vec<u8>>, path: P, options: &cli::Options)
+    -> Result<(), String>
+{
+    save_as(image, path.as_ref(), options, false)
+}
+
+/* Saves the output of a demo that has one value per pixel. With `--channels gray` it is written
+ * as a grey PNG, otherwise the value goes in red, green and blue like any other image. */
+fn save_gray<P: AsRef<Path>>(image: &ImageBuffer<Luma<u8>, Vec<u8>>, path: P, options: &cli::Options)
+    -> Result<(), String>
+{
+    let rgba = ImageBuffer::from_fn(image.width(), image.height(), |x, y| {
+        let grey = image.get_pixel(x, y)[0];
+        Rgba([grey, grey, grey, 255])
+    });
+    save_as(&rgba, path.as_ref(), options, options.channels == Some(Channels::Gray))
 }
 
-mod mandelbrot {
-    vulkano_shaders::shader!{
-        ty: "compute",
-        path: "src/mandelbrot.glsl"
+fn save_as(image: &ImageBuffer<Rgba<u8>, Vec<u8>>, path: &Path, options: &cli::Options, gray: bool)
+    -> Result<(), String>
+{
+    if !options.no_save {
+        let start = Instant::now();
+        let adjusted;
+        let image = match options.gamma {
+            Some(value) if value != 1.0 => {
+                adjusted = gamma::adjust(image, value);
+                &adjusted
+            }
+            _ => image,
+        };
+        let resized;
+        let image = match options.output_size {
+            Some((width, height)) => {
+                resized = fit::resize(image, width, height, options.aspect_fit);
+                &resized
+            }
+            None => image,
+        };
+        /* Last, so the squares come out the size asked for whatever the resize did. */
+        let composited;
+        let image = match options.checkerboard {
+            Some(size) if !gray => {
+                composited = checker::composite(image, size);
+                &composited
+            }
+            _ => image,
+        };
+        /* Gamma and resizing treat every channel alike, so red still holds the grey level. */
+        if gray {
+            let grey: ImageBuffer<Luma<u8>, Vec<u8>> = ImageBuffer::from_fn(image.width(), image.height(),
+                |x, y| Luma([image.get_pixel(x, y)[0]]));
+            grey.save(path)
+        } else {
+            image.save(path)
+        }.map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+        SAVE_NANOS.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        summary::record_output(path);
     }
+    if options.hash {
+        println!("{:016x}  {}", hash::pixels(image), path.display());
+    }
+    Ok(())
 }
 
-mod vertex {
-    vulkano_shaders::shader!{
-        ty: "vertex",
-        path: "src/vertex.glsl"
+/* Writes a data demo's output buffer to the file `--raw-output` names for `demo`, if it names
+ * one, in the shape `dimensions`. */
+fn write_raw<T: raw::Element>(demo: &str, values: &[T], dimensions: &[usize], options: &cli::Options)
+    -> Result<(), String>
+{
+    if let Some(path) = options.raw_output(demo) {
+        let meta = raw::write(path, values, dimensions)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+        println!("Wrote {} and {}", path.display(), meta.display());
     }
+    Ok(())
 }
 
-mod frag {
-    vulkano_shaders::shader!{
-        ty: "fragment",
-        path: "src/frag.glsl"
+/* The built-in mandelbrot, or the compute shader given with --shader-spv. */
+fn mandelbrot_pipeline(device: Arc<Device>, spv_shader: Option<&SpvShader>)
+    -> Arc<dyn ComputePipelineAbstract + Send + Sync>
+{
+    match spv_shader {
+        Some(spv) if spv.stage == Stage::Compute => {
+            Arc::new(ComputePipeline::new(device.clone(), &spv.compute_entry_point(), &()).unwrap())
+        }
+        _ => mandelbrot::pipeline(device),
     }
 }
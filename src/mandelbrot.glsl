@@ -2,15 +2,38 @@
 
 layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
 
-layout(set = 0, binding = 0, rgba8) uniform writeonly image2D img;  
+layout(set = 0, binding = 0, rgba8) uniform writeonly image2D img;
+
+layout(push_constant) uniform PushConstants {
+    vec2 center;
+    vec2 extent;
+    uint max_iter;
+    uint palette;
+    /* Where the image starts within the full picture, and the full picture's size. They are
+     * only different from 0 and the image's size when the picture is rendered in tiles. */
+    uvec2 offset;
+    uvec2 size;
+    /* 0 colors by how long a point takes to escape, 1 by how close its orbit comes to the
+     * trap: the point trap.xy for a trap_shape of 0, and for 1 the line through it whose unit
+     * normal is trap.zw. */
+    uint coloring;
+    uint trap_shape;
+    vec4 trap;
+} pc;
 
 void main() {
-    vec2 norm_coords = (gl_GlobalInvocationID.xy + vec2(0.5) ) / vec2(imageSize(img));
-    vec2 c = (norm_coords - vec2(0.5)) * 2.0 - vec2(1.0, 0.0);
+    /* The dispatch is rounded up to whole workgroups, so skip the invocations past the edge. */
+    if (any(greaterThanEqual(gl_GlobalInvocationID.xy, uvec2(imageSize(img))))) {
+        return;
+    }
+
+    vec2 norm_coords = (gl_GlobalInvocationID.xy + pc.offset + vec2(0.5) ) / vec2(pc.size);
+    vec2 c = pc.center + (norm_coords - vec2(0.5)) * pc.extent;
 
     vec2 z = vec2(0.0, 0.0);
-    float i;
-    for (i = 0.0; i < 1.0; i += 0.005) {
+    float trapped = 1e20;
+    uint n;
+    for (n = 0; n < pc.max_iter; n++) {
         z = vec2(
             z.x * z.x - z.y * z.y + c.x,
             z.y * z.x + z.x * z.y + c.y
@@ -19,8 +42,12 @@ void main() {
         if (length(z) > 8.0) {
             break;
         }
+
+        vec2 d = z - pc.trap.xy;
+        trapped = min(trapped, pc.trap_shape == 0 ? length(d) : abs(dot(d, pc.trap.zw)));
     }
 
-    vec4 to_write = vec4(i, i / 2, 1.0, 1.0);
+    float i = pc.coloring == 1 ? exp(-4.0 * trapped) : float(n) / float(pc.max_iter);
+    vec4 to_write = pc.palette == 1 ? vec4(vec3(i), 1.0) : vec4(i, i / 2, 1.0, 1.0);
     imageStore(img, ivec2(gl_GlobalInvocationID.xy), to_write);
 }
@@ -0,0 +1,859 @@
+use image::{ImageBuffer, Rgba};
+use std::mem;
+use std::sync::Arc;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::DescriptorSet;
+use vulkano::descriptor::descriptor::DescriptorType;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::descriptor::pipeline_layout::PipelineLayoutDesc;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, StorageImage};
+use vulkano::instance::PhysicalDevice;
+use vulkano::pipeline::{ComputePipeline, ComputePipelineAbstract};
+use std::time::Instant;
+
+use crate::compute::{group_count, ComputeTask};
+use crate::debug_dump::Intermediate;
+use crate::descriptors::{Descriptors, Resource};
+use crate::layout;
+use crate::memory;
+use crate::readback;
+use crate::scene::{Scene, SceneOutput};
+use crate::storage;
+use crate::timing::{GpuTimer, Timing};
+use crate::watchdog;
+
+/* Must match the local size in mandelbrot.glsl, and `compute::IMAGE_LOCAL_SIZE` that `group_count` uses. */
+const LOCAL_SIZE: u32 = 8;
+
+/* The number of iterations the shader used to hardcode. */
+pub const DEFAULT_MAX_ITER: u32 = 200;
+
+/* The shader's push constants, for callers recording their own dispatches. */
+pub type PushConstants = cs::ty::PushConstants;
+
+/* The colorings mandelbrot.glsl knows. The numbers are what it gets in `palette`. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Palette {
+    Blue = 0,
+    Grey = 1,
+}
+
+impl Palette {
+    pub fn parse(name: &str) -> Result<Palette, String> {
+        match name {
+            "blue" => Ok(Palette::Blue),
+            "grey" => Ok(Palette::Grey),
+            _ => Err(format!("Unknown palette `{}`, expected blue or grey", name)),
+        }
+    }
+}
+
+/* How mandelbrot.glsl colors a point. The numbers are what it gets in `coloring`. Escape time
+ * counts the iterations until the orbit of c leaves, which is what gives the usual bands. An
+ * orbit trap instead follows the orbit and keeps how close it ever came to a shape, the trap,
+ * and points whose orbits pass near it come out bright. Since every point inside the set has
+ * an orbit too, that draws patterns inside it where escape time leaves it one flat color. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Coloring {
+    Escape = 0,
+    OrbitTrap = 1,
+}
+
+impl Coloring {
+    pub fn parse(name: &str) -> Result<Coloring, String> {
+        match name {
+            "escape" => Ok(Coloring::Escape),
+            "orbit-trap" => Ok(Coloring::OrbitTrap),
+            _ => Err(format!("Unknown coloring `{}`, expected escape or orbit-trap", name)),
+        }
+    }
+}
+
+/* The shape an orbit trap measures orbits against: a point, or the line through a point at an
+ * angle in degrees from the real axis. A point makes rings, a line makes streaks along it. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trap {
+    Point([f32; 2]),
+    Line { point: [f32; 2], angle: f32 },
+}
+
+impl Trap {
+    /* Parses `point:<x>,<y>` or `line:<x>,<y>,<degrees>`. */
+    pub fn parse(value: &str) -> Result<Trap, String> {
+        let error = || format!("Expected point:<x>,<y> or line:<x>,<y>,<degrees> for the trap, got `{}`", value);
+        let (shape, numbers) = match value.find(':') {
+            Some(colon) => (&value[..colon], &value[colon + 1..]),
+            None => return Err(error()),
+        };
+        let numbers = numbers.split(',').map(|n| n.trim().parse::<f32>()).collect::<Result<Vec<_>, _>>()
+            .map_err(|_| error())?;
+
+        match (shape, numbers.as_slice()) {
+            ("point", &[x, y]) => Ok(Trap::Point([x, y])),
+            ("line", &[x, y, angle]) => Ok(Trap::Line { point: [x, y], angle }),
+            _ => Err(error()),
+        }
+    }
+
+    /* The shader's `trap_shape` and `trap`: the point, and for a line its unit normal. */
+    fn push_constants(&self) -> (u32, [f32; 4]) {
+        match *self {
+            Trap::Point([x, y]) => (0, [x, y, 0.0, 0.0]),
+            Trap::Line { point: [x, y], angle } => {
+                let radians = angle.to_radians();
+                (1, [x, y, -radians.sin(), radians.cos()])
+            }
+        }
+    }
+}
+
+/* Which part of the complex plane to draw and how. `zoom` is how many times smaller than the
+ * default view the region is: at 1 the image is two units tall, and as wide as its aspect ratio
+ * makes that, so a pixel covers as much of the plane across as down. A `region` of [x0, y0, x1, y1]
+ * gives the rectangle directly instead, with x0 + y0i in the top left corner of the image and
+ * x1 + y1i in the bottom right, and takes precedence over the center and zoom. */
+#[derive(Debug, Clone, Copy)]
+pub struct View {
+    pub center: [f32; 2],
+    pub zoom: f32,
+    pub region: Option<[f32; 4]>,
+    pub max_iter: u32,
+    pub palette: Palette,
+    pub coloring: Coloring,
+    pub trap: Trap,
+}
+
+impl Default for View {
+    /* The view the shader used to hardcode. */
+    fn default() -> View {
+        View {
+            center: [-1.0, 0.0],
+            zoom: 1.0,
+            region: None,
+            max_iter: DEFAULT_MAX_ITER,
+            palette: Palette::Blue,
+            coloring: Coloring::Escape,
+            trap: Trap::Point([0.0, 0.0]),
+        }
+    }
+}
+
+impl View {
+    /* The center of the view, whether it was given directly or as a region. */
+    pub fn center(&self) -> [f32; 2] {
+        match self.region {
+            Some([x0, y0, x1, y1]) => [(x0 + x1) / 2.0, (y0 + y1) / 2.0],
+            None => self.center,
+        }
+    }
+
+    /* The center of the view and its width and height in the complex plane, drawn at width x
+     * height. A region is drawn as given, stretched if its shape isn't the image's. */
+    pub fn bounds(&self, width: u32, height: u32) -> ([f32; 2], [f32; 2]) {
+        match self.region {
+            Some([x0, y0, x1, y1]) => (self.center(), [x1 - x0, y1 - y0]),
+            None => (self.center, [2.0 / self.zoom * width as f32 / height as f32, 2.0 / self.zoom]),
+        }
+    }
+
+    /* How many times the view is magnified, whether it was given as a zoom or a region. */
+    pub fn magnification(&self) -> f32 {
+        match self.region {
+            Some([_, y0, _, y1]) => 2.0 / (y1 - y0),
+            None => self.zoom,
+        }
+    }
+
+    /* The push constants for rendering the view at width x height in one go. */
+    pub fn push_constants(&self, width: u32, height: u32) -> PushConstants {
+        let (center, extent) = self.bounds(width, height);
+        let (trap_shape, trap) = self.trap.push_constants();
+        PushConstants {
+            center,
+            extent,
+            max_iter: self.max_iter,
+            palette: self.palette as u32,
+            offset: [0, 0],
+            size: [width, height],
+            coloring: self.coloring as u32,
+            trap_shape,
+            trap,
+        }
+    }
+}
+
+/* What `--iter-base` and `--iter-scale` default to. */
+pub const DEFAULT_ITER_BASE: u32 = DEFAULT_MAX_ITER;
+pub const DEFAULT_ITER_SCALE: f32 = 100.0;
+
+/* Picks max_iter from how far in a view is zoomed, for `--max-iter-adaptive`. The further in,
+ * the longer the points near the edge of the set take to escape, so a deep zoom with the
+ * default 200 comes out as mostly flat black, while 5000 for the whole set is wasted time.
+ * The escape times grow roughly with the log of the magnification, so that is what the
+ * iterations follow: `base` at the default view, and `scale` more for every e times closer. */
+#[derive(Debug, Clone, Copy)]
+pub struct Adaptive {
+    pub base: u32,
+    pub scale: f32,
+}
+
+impl Adaptive {
+    /* Zooming out past the default view never goes below one iteration. */
+    pub fn max_iter(&self, magnification: f32) -> u32 {
+        (self.base as f32 + self.scale * magnification.ln()).round().max(1.0) as u32
+    }
+}
+
+/* The iteration counts rendered by `iteration_sweep`. */
+const SWEEP: &[u32] = &[50, 100, 500, 1000, 5000];
+
+/* How many untimed sweeps `--warmup` defaults to. */
+pub const DEFAULT_WARMUP: u32 = 2;
+
+/* What `iteration_sweep` renders, and how: the view, how many untimed sweeps go first, whether
+ * the images are saved, and whether the descriptor sets are counted too. */
+#[derive(Debug, Clone, Copy)]
+pub struct Sweep {
+    pub view: View,
+    pub warmup: u32,
+    pub save: bool,
+    pub verbose: bool,
+}
+
+/* How a render hands the view to the shader. Push constants are the cheapest way, but a device
+ * only has to allow 128 bytes of them; parameters that outgrow that go in a uniform buffer. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Params {
+    PushConstants,
+    Uniform,
+}
+
+impl Params {
+    /* Push constants if they fit on `device`. */
+    pub fn for_device(device: &Device) -> Params {
+        let limit = device.physical_device().limits().max_push_constants_size();
+        if mem::size_of::<PushConstants>() as u32 <= limit {
+            Params::PushConstants
+        } else {
+            Params::Uniform
+        }
+    }
+
+    /* Which of the two `pipeline` takes: the uniform at binding 1 if it has one. */
+    fn of<L: PipelineLayoutDesc + ?Sized>(pipeline: &L) -> Params {
+        match pipeline.descriptor(0, 1).and_then(|desc| desc.ty.ty()) {
+            Some(DescriptorType::UniformBuffer) => Params::Uniform,
+            _ => Params::PushConstants,
+        }
+    }
+}
+
+/* The built-in mandelbrot shader, in the variant that takes its parameters the way `device` can. */
+pub fn pipeline(device: Arc<Device>) -> Arc<dyn ComputePipelineAbstract + Send + Sync> {
+    let params = Params::for_device(&device);
+    pipeline_with(device, params)
+}
+
+/* The built-in mandelbrot shader taking its parameters as `params`. Callers that record their
+ * own dispatches with push constants want `Params::PushConstants`. */
+pub fn pipeline_with(device: Arc<Device>, params: Params) -> Arc<dyn ComputePipelineAbstract + Send + Sync> {
+    match params {
+        Params::PushConstants => {
+            let shader = cs::Shader::load(device.clone()).expect("Could not load mandelbrot shader");
+            Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap())
+        }
+        Params::Uniform => {
+            let shader = uniform::Shader::load(device.clone()).expect("Could not load mandelbrot shader");
+            Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap())
+        }
+    }
+}
+
+/* Binds `image` as set 0 of `pipeline`, after checking that a single storage image is all the
+ * pipeline wants there. */
+pub fn image_set(pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>, image: Arc<StorageImage<Format>>)
+    -> Result<Arc<dyn DescriptorSet + Send + Sync>, String>
+{
+    layout::check(&*pipeline, 0, &[DescriptorType::StorageImage])?;
+    Ok(Arc::new(PersistentDescriptorSet::start(pipeline, 0)
+        .add_image(image).unwrap()
+        .build().unwrap()))
+}
+
+/* Runs `pipeline`, which writes to a storage image at set 0, binding 0, over a width x height
+ * image and reads the result back. Shaders without the push constants ignore `view`. Fails,
+ * naming the binding, if the pipeline expects something else to be bound. Images bigger than
+ * the device can make are rendered in tiles that it can. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>, pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+              width: u32, height: u32, view: View) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String>
+{
+    let max = device.physical_device().limits().max_image_dimension_2d();
+    render_in_tiles(device, queue, pipeline, width, height, view, max)
+}
+
+/* Like `render`, but in tiles of at most tile x tile pixels, if the picture is any bigger. */
+pub fn render_in_tiles(device: Arc<Device>, queue: Arc<Queue>, pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+                       width: u32, height: u32, view: View, tile: u32) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String>
+{
+    if width > tile || height > tile {
+        return render_tiled(device, queue, pipeline, width, height, view, tile);
+    }
+
+    let push_constants = view.push_constants(width, height);
+    render_tile(device, queue, pipeline, width, height, push_constants)
+}
+
+/* The tile size for `--tile-size`: `requested` if it was given, as long as the device can make
+ * images that big, or else the biggest it can make, so pictures are only tiled when they have
+ * to be. Each tile is an image and a buffer of its own, so smaller tiles need less memory, but
+ * each one is another submit and another wait. */
+pub fn tile_size(physical: PhysicalDevice, requested: Option<u32>) -> Result<u32, String> {
+    let max = physical.limits().max_image_dimension_2d();
+    match requested {
+        Some(tile) if tile > max => Err(format!("Tiles of {0}x{0} are bigger than the largest image {1} can make, {2}x{2}",
+            tile, physical.name(), max)),
+        Some(tile) => Ok(tile),
+        None => Ok(max),
+    }
+}
+
+/* How many tiles of at most tile x tile pixels a width x height picture takes. */
+pub fn tile_count(width: u32, height: u32, tile: u32) -> u32 {
+    width.div_ceil(tile) * height.div_ceil(tile)
+}
+
+/* Renders a width x height picture as tiles of at most tile x tile pixels, one after another,
+ * and stitches them together on the CPU. Each tile is told where it sits in the whole picture,
+ * so its pixels map to exactly the same points as they would in one big render. */
+pub fn render_tiled(device: Arc<Device>, queue: Arc<Queue>, pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+                    width: u32, height: u32, view: View, tile: u32) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String>
+{
+    let mut picture = ImageBuffer::new(width, height);
+    for y in (0..height).step_by(tile as usize) {
+        for x in (0..width).step_by(tile as usize) {
+            let (tile_width, tile_height) = (tile.min(width - x), tile.min(height - y));
+            let push_constants = PushConstants { offset: [x, y], .. view.push_constants(width, height) };
+            let pixels = render_tile(device.clone(), queue.clone(), pipeline.clone(), tile_width, tile_height,
+                push_constants)?;
+            image::imageops::replace(&mut picture, &pixels, x, y);
+        }
+    }
+    Ok(picture)
+}
+
+/* Records rendering the view at width x height, in tiles of `tile` x `tile` if it needs them,
+ * without submitting anything. This makes and binds everything a real
+ * render would, so it fails the same way if the pipeline doesn't fit. */
+pub fn prepare(device: Arc<Device>, queue: Arc<Queue>, pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+               width: u32, height: u32, view: View, tile: u32) -> Result<AutoCommandBuffer, String>
+{
+    let mut descriptors = Descriptors::new(device.clone(), 1);
+    let scene = scene(queue.clone(), pipeline, &mut descriptors, width, height, view, tile)?;
+    let builder = AutoCommandBufferBuilder::new(device, queue.family()).unwrap();
+    Ok(scene.record(builder).build().unwrap())
+}
+
+/* The view at width x height as a scene, in tiles like `prepare`. */
+pub fn scene(queue: Arc<Queue>, pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+             descriptors: &mut Descriptors, width: u32, height: u32, view: View, tile: u32)
+    -> Result<MandelbrotScene, String>
+{
+    let push_constants = view.push_constants(width, height);
+    MandelbrotScene::new(&queue, pipeline, descriptors, width, height, tile, push_constants)
+}
+
+/* Renders one width x height image with `push_constants`. */
+fn render_tile(device: Arc<Device>, queue: Arc<Queue>, pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+               width: u32, height: u32, push_constants: PushConstants) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String>
+{
+    let mut descriptors = Descriptors::new(device.clone(), 1);
+    let scene = MandelbrotScene::new(&queue, pipeline, &mut descriptors, width, height, width.max(height),
+        push_constants)?;
+    let builder = AutoCommandBufferBuilder::new(device, queue.family()).unwrap();
+    let command_buffer = scene.record(builder).build().unwrap();
+    watchdog::wait(command_buffer.execute(queue).unwrap(), "mandelbrot");
+    Ok(scene.pixels())
+}
+
+/* The dispatch of a tile, with its parameters however the pipeline wants them. */
+enum TileDispatch {
+    PushConstants(ComputeTask<PushConstants>),
+    Uniform(ComputeTask<()>),
+}
+
+/* One tile: its dispatch, where it sits in the picture, and the buffer it is copied to. */
+struct Tile {
+    dispatch: TileDispatch,
+    buf: Arc<CpuAccessibleBuffer<[u8]>>,
+    offset: [u32; 2],
+    width: u32,
+    height: u32,
+}
+
+/* Rendering a width x height picture in tiles, or in one if it fits, and copying each to a
+ * buffer to be read back. The tiles take turns in one image the size of the biggest, each copied
+ * out before the next is drawn over it. */
+pub struct MandelbrotScene {
+    tiles: Vec<Tile>,
+    image: Arc<StorageImage<Format>>,
+    width: u32,
+    height: u32,
+}
+
+impl MandelbrotScene {
+    fn new(queue: &Queue, pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>, descriptors: &mut Descriptors,
+           width: u32, height: u32, tile: u32, push_constants: PushConstants) -> Result<MandelbrotScene, String>
+    {
+        let image = storage::image(queue, Dimensions::Dim2d { width: width.min(tile), height: height.min(tile) },
+            Format::R8G8B8A8Unorm);
+
+        /* The pipeline says how it wants the parameters, so the same pipeline and view render the
+         * same picture either way. */
+        let params = Params::of(&*pipeline);
+        match params {
+            Params::PushConstants => layout::check(&*pipeline, 0, &[DescriptorType::StorageImage])?,
+            Params::Uniform => layout::check(&*pipeline, 0,
+                &[DescriptorType::StorageImage, DescriptorType::UniformBuffer])?,
+        }
+
+        let mut tiles = Vec::new();
+        for y in (0..height).step_by(tile as usize) {
+            for x in (0..width).step_by(tile as usize) {
+                let (tile_width, tile_height) = (tile.min(width - x), tile.min(height - y));
+                let [x0, y0] = push_constants.offset;
+                let push_constants = PushConstants { offset: [x0 + x, y0 + y], .. push_constants };
+                let groups = group_count(tile_width, tile_height);
+                let dispatch = match params {
+                    Params::PushConstants => {
+                        let set = descriptors.set(&pipeline, 0, &[Resource::image(image.clone())])?;
+                        TileDispatch::PushConstants(ComputeTask::new("mandelbrot", pipeline.clone(), set, groups,
+                            push_constants))
+                    }
+                    Params::Uniform => {
+                        let params = descriptors.uniform(push_constants);
+                        let set = descriptors.set(&pipeline, 0, &[Resource::image(image.clone()), params])?;
+                        TileDispatch::Uniform(ComputeTask::new("mandelbrot", pipeline.clone(), set, groups, ()))
+                    }
+                };
+                let buf = memory::readback::<u8>(queue, (tile_width * tile_height) as usize * readback::RGBA8_BYTES)?;
+                tiles.push(Tile { dispatch, buf, offset: [x, y], width: tile_width, height: tile_height });
+            }
+        }
+        Ok(MandelbrotScene { tiles, image, width, height })
+    }
+
+    /* The rendered pixels, stitched together from the tiles once the recorded commands have run. */
+    fn pixels(&self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let mut picture = ImageBuffer::new(self.width, self.height);
+        for tile in &self.tiles {
+            let buffer_content = tile.buf.read().unwrap();
+            let pixels = readback::rgba8_image(tile.width, tile.height, tile.width as usize * readback::RGBA8_BYTES,
+                &buffer_content);
+            image::imageops::replace(&mut picture, &pixels, tile.offset[0], tile.offset[1]);
+        }
+        picture
+    }
+}
+
+impl Scene for MandelbrotScene {
+    fn name(&self) -> &'static str {
+        "mandelbrot"
+    }
+
+    fn record(&self, builder: AutoCommandBufferBuilder) -> AutoCommandBufferBuilder {
+        self.tiles.iter().fold(builder, |builder, tile| {
+            let builder = match tile.dispatch {
+                TileDispatch::PushConstants(ref task) => task.record(builder),
+                TileDispatch::Uniform(ref task) => task.record(builder),
+            };
+            builder.copy_image_to_buffer_dimensions(self.image.clone(), tile.buf.clone(), [0, 0, 0],
+                [tile.width, tile.height, 1], 0, 1, 0).unwrap()
+        })
+    }
+
+    fn output(&self) -> SceneOutput {
+        SceneOutput::Image(self.pixels())
+    }
+
+    fn work(&self) -> (u64, &'static str) {
+        (self.width as u64 * self.height as u64, "pixels")
+    }
+
+    /* The storage image the shader writes, before it is copied back. When there are several
+     * tiles it holds the last. */
+    fn intermediates(&self) -> Vec<(&'static str, Intermediate)> {
+        vec![("image", Intermediate::Image(self.image.clone()))]
+    }
+}
+
+/* Renders a picture whole and in tiles that don't divide it evenly, both one submission per
+ * tile and as a scene recording them all, and checks that they are identical, so there is no
+ * seam or offset where the tiles meet. */
+pub fn verify_tiles(device: Arc<Device>, queue: Arc<Queue>) {
+    let (width, height) = (300, 200);
+    let view = View::default();
+    let whole = render(device.clone(), queue.clone(), pipeline(device.clone()), width, height, view).unwrap();
+    let tiled = render_tiled(device.clone(), queue.clone(), pipeline(device.clone()), width, height, view, 64)
+        .unwrap();
+    assert!(*whole == *tiled, "The tiled mandelbrot doesn't match the whole one");
+
+    let mut descriptors = Descriptors::new(device.clone(), 1);
+    let scene = scene(queue.clone(), pipeline(device.clone()), &mut descriptors, width, height, view, 64).unwrap();
+    let command_buffer = scene.record(AutoCommandBufferBuilder::new(device, queue.family()).unwrap())
+        .build().unwrap();
+    watchdog::wait(command_buffer.execute(queue).unwrap(), "mandelbrot");
+    assert!(scene.pixels().into_raw() == whole.into_raw(), "The tiled mandelbrot scene doesn't match the whole one");
+}
+
+/* Renders a picture with the parameters passed each way, whichever the device would pick, and
+ * checks the two are identical. */
+pub fn verify_params(device: Arc<Device>, queue: Arc<Queue>) {
+    let (width, height) = (256, 256);
+    let view = View::default();
+    let render_with = |params| render(device.clone(), queue.clone(), pipeline_with(device.clone(), params),
+        width, height, view).unwrap();
+    let pushed = render_with(Params::PushConstants);
+    let uniform = render_with(Params::Uniform);
+    assert!(pushed.into_raw() == uniform.into_raw(),
+        "The mandelbrot with its parameters in a uniform buffer differs from the push constant one");
+}
+
+/* Colors the default view with orbit traps on the point at its center and on the real axis.
+ * The pixel in the middle is c right at the trap, so its orbit starts there and it has to come
+ * out as bright as the palette goes, but with a point trap the pixels further up can't, while
+ * with a line along the axis the whole middle row is on the trap. */
+pub fn verify_orbit_trap(device: Arc<Device>, queue: Arc<Queue>) {
+    let (width, height) = (256, 256);
+    let defaults = View::default();
+    let trap_with = |trap| {
+        let view = View { coloring: Coloring::OrbitTrap, trap, .. defaults };
+        render(device.clone(), queue.clone(), pipeline(device.clone()), width, height, view).unwrap()
+    };
+    let brightness = |image: &ImageBuffer<Rgba<u8>, Vec<u8>>, x, y| image.get_pixel(x, y)[0];
+
+    let point = trap_with(Trap::Point(defaults.center));
+    assert!(brightness(&point, width / 2, height / 2) > 240, "The pixel on the point trap isn't bright");
+    assert!(brightness(&point, width / 2, height / 8) < 240, "A pixel away from the point trap is as bright as on it");
+
+    let line = trap_with(Trap::Line { point: defaults.center, angle: 0.0 });
+    assert!((0..width).all(|x| brightness(&line, x, height / 2) > 240), "The pixels along the line trap aren't all bright");
+
+    let escape = render(device.clone(), queue, pipeline(device), width, height, defaults).unwrap();
+    assert!(point.into_raw() != escape.into_raw(), "Orbit trap coloring looks the same as escape time");
+}
+
+/* Renders the same view at increasing iteration counts to show how much detail each one buys
+ * and what it costs. This is the same shape as an interactive frame loop: the image and buffer
+ * are made once and reused, and every frame asks `Descriptors` for its set, which hands back the
+ * one it already made; only the push constant changes between renders. A descriptor set only
+ * records which resources are bound, and those never change here, so there is no reason to
+ * allocate a new one per frame. With `sweep.verbose` the sweep is run again the naive way, with
+ * a fresh `Descriptors` every frame, and the number of sets each approach allocated is printed.
+ * The iteration count in `sweep.view` is ignored in favour of the sweep's. Without `sweep.save`
+ * the images are read back but not written, so nothing but the render is timed. Returns how long
+ * each render of the sweep took, on the GPU too if the queue can tell.
+ *
+ * The first few renders are slower than the rest while the driver finishes preparing the
+ * pipeline and the GPU's clocks ramp up, which would make whatever comes first in the sweep
+ * look more expensive than it is. So the whole sweep is run `sweep.warmup` times beforehand,
+ * without timing or reading anything back. */
+pub fn iteration_sweep(device: Arc<Device>, queue: Arc<Queue>, width: u32, height: u32, sweep: Sweep) -> Vec<Timing> {
+    let Sweep { view, warmup, save, verbose } = sweep;
+    let timer = GpuTimer::new(device.clone(), queue.clone());
+    let mut timings = Vec::new();
+    let pipeline = pipeline_with(device.clone(), Params::PushConstants);
+
+    let image = storage::image(&queue, Dimensions::Dim2d { width, height }, Format::R8G8B8A8Unorm);
+
+    let buf = memory::readback::<u8>(&queue, (width * height) as usize * readback::RGBA8_BYTES)
+        .expect("Failed to create buffer");
+
+    let mut descriptors = Descriptors::new(device.clone(), 1);
+    let resources = [Resource::image(image.clone())];
+
+    for _ in 0..warmup {
+        for &max_iter in SWEEP {
+            let set = descriptors.set(&pipeline, 0, &resources).unwrap();
+            let push_constants = PushConstants { max_iter, .. view.push_constants(width, height) };
+            ComputeTask::new("mandelbrot", pipeline.clone(), set, group_count(width, height), push_constants)
+                .submit(queue.clone());
+        }
+    }
+    if warmup > 0 {
+        println!("Warmed up with {} untimed sweep{}", warmup, if warmup == 1 { "" } else { "s" });
+    }
+
+    for &max_iter in SWEEP {
+        let start = Instant::now();
+
+        let set = descriptors.set(&pipeline, 0, &resources).unwrap();
+        let push_constants = PushConstants { max_iter, .. view.push_constants(width, height) };
+        let task = ComputeTask::new("mandelbrot", pipeline.clone(), set, group_count(width, height), push_constants);
+        let command_buffer = task.record(AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap())
+            .copy_image_to_buffer(image.clone(), buf.clone()).unwrap()
+            .build().unwrap();
+
+        let run = || watchdog::wait(command_buffer.execute(queue.clone()).unwrap(), "mandelbrot");
+        let gpu_ms = match timer {
+            Some(ref timer) => Some(timer.time(run).1),
+            None => {
+                run();
+                None
+            }
+        };
+
+        /* Only the GPU work is timed, not encoding the PNG. */
+        let elapsed = start.elapsed();
+        timings.push(Timing { iteration: max_iter, host_ms: elapsed.as_secs_f64() * 1000.0, gpu_ms });
+        let gpu = gpu_ms.map(|ms| format!(" (GPU {:.2} ms)", ms)).unwrap_or_default();
+
+        let buffer_content = buf.read().unwrap();
+        let image = readback::rgba8_image(width, height, width as usize * readback::RGBA8_BYTES, &buffer_content);
+        if !save {
+            println!("{:>5} iterations: {:>8.2} ms{}", max_iter, elapsed.as_secs_f64() * 1000.0, gpu);
+            continue;
+        }
+
+        let path = format!("mandelbrot_{}.png", max_iter);
+        let start = Instant::now();
+        image.save(&path).unwrap();
+        println!("{:>5} iterations: {:>8.2} ms{}, saving {:>8.2} ms -> {}", max_iter,
+            elapsed.as_secs_f64() * 1000.0, gpu, start.elapsed().as_secs_f64() * 1000.0, path);
+    }
+
+    if !verbose {
+        return timings;
+    }
+
+    let mut rebuilt_sets = 0;
+    let start = Instant::now();
+    for &max_iter in SWEEP {
+        let mut fresh = Descriptors::new(device.clone(), 1);
+        let set = fresh.set(&pipeline, 0, &resources).unwrap();
+        rebuilt_sets += fresh.built();
+
+        let push_constants = PushConstants { max_iter, .. view.push_constants(width, height) };
+        ComputeTask::new("mandelbrot", pipeline.clone(), set, group_count(width, height), push_constants)
+            .submit(queue.clone());
+    }
+
+    println!("Descriptor sets allocated over {} frames: {} reused, {} rebuilt every frame ({:.2} ms)",
+        SWEEP.len(), descriptors.built(), rebuilt_sets, start.elapsed().as_secs_f64() * 1000.0);
+    timings
+}
+
+/* Runs just the iteration of mandelbrot.glsl and reads back each pixel's escape count, row by
+ * row, leaving the coloring to the CPU. The palette and coloring in `view` are ignored. */
+pub fn escape_counts(device: Arc<Device>, queue: Arc<Queue>, width: u32, height: u32, view: View) -> Vec<u32> {
+    let shader = escape::Shader::load(device.clone()).expect("Could not load escape count shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let counts = memory::device_local::<u32>(&queue, memory::storage_buffer(), (width * height) as usize)
+        .expect("Failed to create buffer");
+    let readback = memory::readback::<u32>(&queue, (width * height) as usize).expect("Failed to create buffer");
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_buffer(counts.clone()).unwrap()
+        .build().unwrap());
+
+    let (center, extent) = view.bounds(width, height);
+    let push_constants = escape::ty::PushConstants { center, extent, max_iter: view.max_iter, size: [width, height] };
+    let task = ComputeTask::new("mandelbrot", pipeline, set, group_count(width, height), push_constants);
+    let command_buffer = task.record(AutoCommandBufferBuilder::new(device, queue.family()).unwrap())
+        .copy_buffer(counts, readback.clone()).unwrap()
+        .build().unwrap();
+    watchdog::wait(command_buffer.execute(queue).unwrap(), "mandelbrot");
+
+    let content = readback.read().unwrap();
+    content.to_vec()
+}
+
+/* Colors escape counts the way mandelbrot.glsl does, on the CPU. This is the place to try out
+ * other colorings without touching any GLSL. */
+pub fn colorize(counts: &[u32], width: u32, height: u32, max_iter: u32, palette: Palette)
+    -> ImageBuffer<Rgba<u8>, Vec<u8>>
+{
+    let unorm = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let i = counts[(y * width + x) as usize] as f32 / max_iter as f32;
+        match palette {
+            Palette::Blue => Rgba([unorm(i), unorm(i / 2.0), 255, 255]),
+            Palette::Grey => Rgba([unorm(i), unorm(i), unorm(i), 255]),
+        }
+    })
+}
+
+/* What `escape_counts` should give, worked out on the CPU in the same f32 steps as the shader.
+ * GPUs may fuse or reorder those, so a point right on the edge of escaping can come out a step
+ * different here. */
+pub fn cpu_escape_counts(width: u32, height: u32, view: View) -> Vec<u32> {
+    let (center, extent) = view.bounds(width, height);
+    (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| {
+        let c = [center[0] + ((x as f32 + 0.5) / width as f32 - 0.5) * extent[0],
+            center[1] + ((y as f32 + 0.5) / height as f32 - 0.5) * extent[1]];
+        let mut z = [0.0f32, 0.0];
+        let mut n = 0;
+        while n < view.max_iter {
+            z = [z[0] * z[0] - z[1] * z[1] + c[0], z[1] * z[0] + z[0] * z[1] + c[1]];
+            if (z[0] * z[0] + z[1] * z[1]).sqrt() > 8.0 {
+                break;
+            }
+            n += 1;
+        }
+        n
+    }).collect()
+}
+
+/* What `render` should draw with the escape coloring, on the CPU. */
+pub fn cpu_render(width: u32, height: u32, view: View) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    colorize(&cpu_escape_counts(width, height, view), width, height, view.max_iter, view.palette)
+}
+
+/* Checks that the escape counts are all within max_iter, that both points in the set and points
+ * that escape turn up, and that coloring them on the CPU reproduces `render`, give or take the
+ * rounding of the float to byte conversion. */
+pub fn verify_escape_counts(device: Arc<Device>, queue: Arc<Queue>) {
+    let (width, height) = (256, 256);
+    let view = View::default();
+    let counts = escape_counts(device.clone(), queue.clone(), width, height, view);
+
+    assert!(counts.iter().all(|&n| n <= view.max_iter), "An escape count is over max_iter");
+    assert!(counts.contains(&view.max_iter), "No point is in the set");
+    assert!(counts.iter().any(|&n| n < 5), "No point escapes quickly");
+
+    let colored = colorize(&counts, width, height, view.max_iter, view.palette);
+    let rendered = render(device.clone(), queue, pipeline(device), width, height, view).unwrap();
+    let off = colored.pixels().zip(rendered.pixels())
+        .filter(|(a, b)| a.data.iter().zip(b.data.iter()).any(|(&a, &b)| (a as i32 - b as i32).abs() > 1))
+        .count();
+    assert!(off == 0, "{} pixels colored on the CPU don't match the shader", off);
+}
+
+/* Dispatches a counting shader over a width x height image exactly the way `render` dispatches
+ * the mandelbrot, and reads back how many invocations ran and how many of them covered a pixel.
+ * If the group count were rounded down instead of up, the edge rows and columns would be
+ * missing and the second count would come up short. */
+pub fn count_invocations(device: Arc<Device>, queue: Arc<Queue>, width: u32, height: u32) {
+    let shader = count::Shader::load(device.clone()).expect("Could not load invocation counting shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let counters = memory::upload_to_device_local(queue.clone(), memory::storage_buffer(), vec![0u32; 2])
+        .expect("Failed to create buffer");
+    let readback = memory::readback::<u32>(&queue, 2).expect("Failed to create buffer");
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_buffer(counters.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = group_count(width, height);
+    let push_constants = count::ty::PushConstants { size: [width, height] };
+    let task = ComputeTask::new("mandelbrot", pipeline, set, groups, push_constants);
+    let command_buffer = task.record(AutoCommandBufferBuilder::new(device, queue.family()).unwrap())
+        .copy_buffer(counters, readback.clone()).unwrap()
+        .build().unwrap();
+    watchdog::wait(command_buffer.execute(queue).unwrap(), "mandelbrot");
+
+    let content = readback.read().unwrap();
+    let (invocations, inside) = (content[0] as u64, content[1] as u64);
+    let expected = groups.iter().map(|&n| n as u64).product::<u64>() * (LOCAL_SIZE * LOCAL_SIZE) as u64;
+    println!("{}x{} dispatch ran {} invocations ({:?} groups of {}x{}), {} of them on the image",
+        width, height, invocations, groups, LOCAL_SIZE, LOCAL_SIZE, inside);
+
+    assert_eq!(invocations, expected, "The dispatch didn't run group_count * local_size invocations");
+    assert_eq!(inside, width as u64 * height as u64, "The dispatch didn't cover every pixel of the image");
+}
+
+/* The mandelbrot set is symmetric about the real axis, so row y of a correct render is the same
+ * as row (height - 1 - y). A sheared image breaks that badly, so rendering at an awkward width
+ * like 513 and checking the symmetry catches row pitch mistakes in the readback. A handful of
+ * pixels right on the boundary of the set may round differently, hence the tolerance. */
+pub fn verify_odd_width(device: Arc<Device>, queue: Arc<Queue>) {
+    let (width, height) = (513, 513);
+    let image = render(device.clone(), queue, pipeline(device), width, height, View::default()).unwrap();
+
+    let mismatched = (0..height / 2)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| image.get_pixel(x, y) != image.get_pixel(x, height - 1 - y))
+        .count();
+
+    assert!(mismatched < (width * height / 200) as usize,
+        "{}x{} mandelbrot is not symmetric ({} pixels differ), the readback is probably sheared",
+        width, height, mismatched);
+}
+
+mod escape {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/escape.glsl"
+    }
+}
+
+mod count {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/count.glsl"
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/mandelbrot.glsl"
+    }
+}
+
+mod uniform {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/mandelbrot_uniform.glsl"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* A pixel covers as much of the plane across as down, whatever the image's shape. */
+    #[test]
+    fn bounds_follow_the_aspect_ratio() {
+        let view = View { zoom: 4.0, .. View::default() };
+        let (center, extent) = view.bounds(400, 200);
+        assert_eq!(center, view.center);
+        assert_eq!(extent, [1.0, 0.5]);
+        assert_eq!(view.bounds(200, 200).1, [0.5, 0.5]);
+        assert_eq!(view.magnification(), 4.0);
+
+        /* A region is drawn as given. */
+        let region = View { region: Some([-2.0, -1.0, 1.0, 1.0]), .. View::default() };
+        assert_eq!(region.bounds(100, 100), ([-0.5, 0.0], [3.0, 2.0]));
+    }
+
+    /* A wide image reaches further out to the sides than a square one of the same height. */
+    #[test]
+    fn cpu_escape_counts_cover_the_wider_extent() {
+        let view = View { max_iter: 64, .. View::default() };
+        let wide = cpu_escape_counts(64, 16, view);
+        let square = cpu_escape_counts(16, 16, view);
+        let center_row = |counts: &[u32], width: usize| counts[8 * width..9 * width].to_vec();
+        assert_eq!(center_row(&wide, 64)[24..40], center_row(&square, 16)[..]);
+    }
+
+    /* Zooming in asks for more iterations and zooming out fewer, and the default view gets
+     * exactly the base. */
+    #[test]
+    fn adaptive_iterations_grow_with_the_zoom() {
+        let adaptive = Adaptive { base: DEFAULT_ITER_BASE, scale: DEFAULT_ITER_SCALE };
+        assert_eq!(adaptive.max_iter(1.0), DEFAULT_ITER_BASE);
+
+        let zooms = [0.01, 0.5, 1.0, 10.0, 1000.0, 1e6];
+        let iterations: Vec<_> = zooms.iter().map(|&zoom| adaptive.max_iter(zoom)).collect();
+        assert!(iterations.windows(2).all(|pair| pair[0] < pair[1]),
+            "Zooms of {:?} got {:?} iterations, which should only go up", zooms, iterations);
+        assert!(Adaptive { base: 1, scale: 100.0 }.max_iter(1e-6) == 1, "Zooming out went below one iteration");
+
+        let region = View { region: Some([-0.75, 0.1, -0.75 + 0.002, 0.1 + 0.002]), .. View::default() };
+        assert!((region.magnification() - 1000.0).abs() < 1.0, "A region 0.002 tall is magnified {} times",
+            region.magnification());
+    }
+}
@@ -0,0 +1,51 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform writeonly image2D img;
+
+/* mandelbrot.glsl with its parameters in a uniform buffer instead of push constants, for
+ * devices that can't fit them. The block has the same members, and under std140 the same
+ * offsets, so the same bytes can be copied into either. */
+layout(set = 0, binding = 1) uniform Params {
+    vec2 center;
+    vec2 extent;
+    uint max_iter;
+    uint palette;
+    uvec2 offset;
+    uvec2 size;
+    uint coloring;
+    uint trap_shape;
+    vec4 trap;
+} pc;
+
+void main() {
+    /* The dispatch is rounded up to whole workgroups, so skip the invocations past the edge. */
+    if (any(greaterThanEqual(gl_GlobalInvocationID.xy, uvec2(imageSize(img))))) {
+        return;
+    }
+
+    vec2 norm_coords = (gl_GlobalInvocationID.xy + pc.offset + vec2(0.5) ) / vec2(pc.size);
+    vec2 c = pc.center + (norm_coords - vec2(0.5)) * pc.extent;
+
+    vec2 z = vec2(0.0, 0.0);
+    float trapped = 1e20;
+    uint n;
+    for (n = 0; n < pc.max_iter; n++) {
+        z = vec2(
+            z.x * z.x - z.y * z.y + c.x,
+            z.y * z.x + z.x * z.y + c.y
+        );
+
+        if (length(z) > 8.0) {
+            break;
+        }
+
+        vec2 d = z - pc.trap.xy;
+        trapped = min(trapped, pc.trap_shape == 0 ? length(d) : abs(dot(d, pc.trap.zw)));
+    }
+
+    float i = pc.coloring == 1 ? exp(-4.0 * trapped) : float(n) / float(pc.max_iter);
+    vec4 to_write = pc.palette == 1 ? vec4(vec3(i), 1.0) : vec4(i, i / 2, 1.0, 1.0);
+    imageStore(img, ivec2(gl_GlobalInvocationID.xy), to_write);
+}
@@ -0,0 +1,49 @@
+use std::ffi::CString;
+use std::sync::{Arc, Mutex};
+use vulkano::command_buffer::pool::standard::StandardCommandPoolBuilder;
+use vulkano::command_buffer::sys::UnsafeCommandBufferBuilder;
+use vulkano::device::{Device, DeviceExtensions, Queue};
+
+use crate::batch;
+
+/* What capture tools like RenderDoc and Nsight color the regions. */
+const COLOR: [f32; 4] = [0.3, 0.6, 1.0, 1.0];
+
+/* Where markers go, once they have been enabled. */
+static QUEUE: Mutex<Option<(Arc<Device>, Arc<Queue>)>> = Mutex::new(None);
+
+/* The device extension `--profile-markers` needs. The newer debug utils extension does the same
+ * job, but vulkano 0.11 only knows the debug marker one it replaced. Drivers rarely offer either
+ * on their own: capture tools add it to the devices they inject themselves into, so without one
+ * attached it is normal for this to be missing. */
+pub fn extensions() -> DeviceExtensions {
+    DeviceExtensions { ext_debug_marker: true, .. DeviceExtensions::none() }
+}
+
+/* Starts labeling work submitted to `queue`, which must belong to a device created with
+ * `extensions`. */
+pub fn enable(device: Arc<Device>, queue: Arc<Queue>) {
+    *QUEUE.lock().unwrap() = Some((device, queue));
+}
+
+/* Opens a region called `name` on the queue. Everything submitted until the matching `end` is
+ * grouped under it in a capture. Does nothing unless markers are enabled. */
+pub fn begin(name: &str) {
+    let name = CString::new(name).unwrap();
+    submit(|builder| unsafe { builder.debug_marker_begin(&name, COLOR) });
+}
+
+/* Closes the region the last `begin` opened. */
+pub fn end() {
+    submit(|builder| unsafe { builder.debug_marker_end() });
+}
+
+/* A region can start in one command buffer and end in another, as long as both go to the same
+ * queue, so each marker is a command buffer of its own. That keeps them out of the demos'
+ * code. */
+fn submit<F: FnOnce(&mut UnsafeCommandBufferBuilder<StandardCommandPoolBuilder>)>(record: F) {
+    let guard = QUEUE.lock().unwrap();
+    if let Some((ref device, ref queue)) = *guard {
+        batch::submit_unsafe(device.clone(), queue, record);
+    }
+}
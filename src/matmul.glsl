@@ -0,0 +1,52 @@
+#version 450
+
+#define TILE 16
+
+layout(local_size_x = TILE, local_size_y = TILE, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) readonly buffer MatrixA {
+    float data[];
+} a;
+
+layout(set = 0, binding = 1) readonly buffer MatrixB {
+    float data[];
+} b;
+
+layout(set = 0, binding = 2) writeonly buffer MatrixC {
+    float data[];
+} c;
+
+layout(push_constant) uniform PushConstants {
+    uint n;
+} pc;
+
+shared float tile_a[TILE][TILE];
+shared float tile_b[TILE][TILE];
+
+void main() {
+    uint row = gl_GlobalInvocationID.y;
+    uint col = gl_GlobalInvocationID.x;
+    uint local_row = gl_LocalInvocationID.y;
+    uint local_col = gl_LocalInvocationID.x;
+
+    float sum = 0.0;
+    uint num_tiles = (pc.n + TILE - 1) / TILE;
+    for (uint t = 0; t < num_tiles; t++) {
+        /* Each invocation loads one element of each tile. Elements past the edge of the
+         * matrix are loaded as zero so they don't contribute to the sum. */
+        uint a_col = t * TILE + local_col;
+        uint b_row = t * TILE + local_row;
+        tile_a[local_row][local_col] = (row < pc.n && a_col < pc.n) ? a.data[row * pc.n + a_col] : 0.0;
+        tile_b[local_row][local_col] = (b_row < pc.n && col < pc.n) ? b.data[b_row * pc.n + col] : 0.0;
+        barrier();
+
+        for (uint k = 0; k < TILE; k++) {
+            sum += tile_a[local_row][k] * tile_b[k][local_col];
+        }
+        barrier();
+    }
+
+    if (row < pc.n && col < pc.n) {
+        c.data[row * pc.n + col] = sum;
+    }
+}
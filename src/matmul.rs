@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::pipeline::ComputePipeline;
+
+use crate::memory;
+use crate::watchdog;
+
+/* Must match TILE in matmul.glsl. */
+const TILE: usize = 16;
+
+/* Multiplies two row-major NxN matrices on the GPU. Every workgroup computes one TILExTILE
+ * block of the result, staging the tiles of `a` and `b` it needs in shared memory so each
+ * element is read from the buffers once per tile rather than once per multiply. */
+pub fn gpu_matmul(device: Arc<Device>, queue: Arc<Queue>, a: &[f32], b: &[f32], n: usize) -> Vec<f32> {
+    assert!(n > 0, "Matrices must be at least 1x1");
+    assert_eq!(a.len(), n * n, "Matrix a is not {}x{}", n, n);
+    assert_eq!(b.len(), n * n, "Matrix b is not {}x{}", n, n);
+
+    /* The inputs are only read, and the output only written, by the GPU, so all three live in
+     * device local memory and the CPU sees the output only once it has been copied back. */
+    let a_buf = memory::upload_immutable(queue.clone(), memory::storage_buffer(), a.iter().cloned())
+        .expect("Failed to create buffer");
+    let b_buf = memory::upload_immutable(queue.clone(), memory::storage_buffer(), b.iter().cloned())
+        .expect("Failed to create buffer");
+    let c_buf = memory::device_local::<f32>(&queue, memory::storage_buffer(), n * n).expect("Failed to create buffer");
+
+    let shader = cs::Shader::load(device.clone()).expect("Failed to create matmul shader module");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())
+        .expect("Failed to create matmul pipeline"));
+
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_buffer(a_buf.clone()).unwrap()
+        .add_buffer(b_buf.clone()).unwrap()
+        .add_buffer(c_buf.clone()).unwrap()
+        .build().unwrap());
+
+    /* Round up so that the last, partially filled tile is also covered when N isn't a
+     * multiple of TILE. The shader discards the invocations that fall outside the matrix. */
+    let groups = n.div_ceil(TILE) as u32;
+    let push_constants = cs::ty::PushConstants { n: n as u32 };
+
+    let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .dispatch([groups, groups, 1], pipeline.clone(), set.clone(), push_constants).unwrap()
+        .build().unwrap();
+
+    watchdog::wait(command_buffer.execute(queue.clone()).unwrap(), "matmul");
+
+    memory::download_from_device_local(queue, c_buf).expect("Failed to create buffer")
+}
+
+/* The straightforward triple loop, used to check the GPU result. */
+pub fn cpu_matmul(a: &[f32], b: &[f32], n: usize) -> Vec<f32> {
+    let mut c = vec![0f32; n * n];
+    for row in 0..n {
+        for col in 0..n {
+            c[row * n + col] = (0..n).map(|k| a[row * n + k] * b[k * n + col]).sum();
+        }
+    }
+    c
+}
+
+/* A pair of NxN matrices to multiply, the same for the same N every run. */
+pub fn example_inputs(n: usize) -> (Vec<f32>, Vec<f32>) {
+    let a = (0..n * n).map(|i| (i % 13) as f32 / 13.0).collect();
+    let b = (0..n * n).map(|i| ((i * 7) % 11) as f32 / 11.0 - 0.5).collect();
+    (a, b)
+}
+
+/* Runs the GPU multiply for a few sizes, including ones that aren't a multiple of the tile
+ * size, and checks each result against the CPU reference. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    for &n in &[1, 7, 16, 33, 100] {
+        let (a, b) = example_inputs(n);
+
+        let gpu = gpu_matmul(device.clone(), queue.clone(), &a, &b, n);
+        let cpu = cpu_matmul(&a, &b, n);
+
+        for (i, (g, c)) in gpu.iter().zip(cpu.iter()).enumerate() {
+            /* The GPU sums in a different order, so allow for some rounding error. */
+            assert!((g - c).abs() <= 1e-4 * n as f32,
+                "Matmul mismatch for N={} at ({}, {}): gpu {} vs cpu {}", n, i / n, i % n, g, c);
+        }
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/matmul.glsl"
+    }
+}
@@ -0,0 +1,47 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D src;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D dst;
+
+/* How far the window reaches on each side, 1 for 3x3 and 2 for 5x5. */
+layout(push_constant) uniform PushConstants {
+    int radius;
+} pc;
+
+/* The most pixels a window can hold. */
+const int MAX_COUNT = 25;
+
+/* Replaces each pixel with the median of the window around it, channel by channel. Outside the
+ * image the window takes the nearest texel on the edge. The window is sorted with odd-even
+ * transposition, a sorting network: each pass compares and swaps neighbouring pairs, the even
+ * ones then the odd ones, and as many passes as there are values always leave them sorted. No
+ * step depends on the values, so there is nothing to branch on, and a compare and swap is just
+ * a min and a max. Those work on each channel of a vec4 on its own, so one network sorts all
+ * four channels at once. */
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(dst);
+    if (any(greaterThanEqual(coords, size))) {
+        return;
+    }
+
+    vec4 window[MAX_COUNT];
+    int count = 0;
+    for (int y = -pc.radius; y <= pc.radius; y++) {
+        for (int x = -pc.radius; x <= pc.radius; x++) {
+            window[count++] = imageLoad(src, clamp(coords + ivec2(x, y), ivec2(0), size - 1));
+        }
+    }
+
+    for (int pass = 0; pass < count; pass++) {
+        for (int i = pass % 2; i + 1 < count; i += 2) {
+            vec4 low = min(window[i], window[i + 1]);
+            window[i + 1] = max(window[i], window[i + 1]);
+            window[i] = low;
+        }
+    }
+
+    imageStore(dst, coords, window[count / 2]);
+}
@@ -0,0 +1,100 @@
+use image::{ImageBuffer, Rgba};
+use std::path::PathBuf;
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+use vulkano::pipeline::ComputePipeline;
+
+use crate::input::{self, Input};
+use crate::pattern;
+use crate::random::pcg;
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/* The window sizes median.glsl has room for. */
+pub const WINDOWS: [u32; 2] = [3, 5];
+
+/* What `--median-window` defaults to. */
+pub const DEFAULT_WINDOW: u32 = 3;
+
+/* Replaces every pixel of `pixels` with the median of the `window` x `window` pixels around it,
+ * each channel on its own. Unlike a blur, which averages a stray black or white pixel into its
+ * neighbours, the median throws it away, as long as fewer than half of the window are strays,
+ * and edges stay sharp. */
+pub fn median(device: Arc<Device>, queue: Arc<Queue>, pixels: &Image, window: u32) -> Image {
+    assert!(WINDOWS.contains(&window), "Can only take the median of a 3x3 or 5x5 window, not {0}x{0}", window);
+
+    let shader = cs::Shader::load(device.clone()).expect("Could not load median shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let push_constants = cs::ty::PushConstants { radius: (window / 2) as i32 };
+    input::filter("median", device, queue, pixels, pipeline, push_constants)
+}
+
+/* Median filters the image loaded for `--input` and saves the result next to it as
+ * <name>_median.png, returning where it went. */
+pub fn median_filter(device: Arc<Device>, queue: Arc<Queue>, input: &Input, window: u32) -> Result<PathBuf, String> {
+    input.save(&median(device, queue, &input.pixels, window), "median")
+}
+
+/* Turns about one pixel in `one_in` of `pixels` black or white, picked with `seed`, for the
+ * filter to clean up. */
+pub fn salt_and_pepper(pixels: &Image, one_in: u32, seed: u32) -> Image {
+    let mut noisy = pixels.clone();
+    for (index, pixel) in noisy.pixels_mut().enumerate() {
+        let roll = pcg((index as u32).wrapping_add(pcg(seed)));
+        if roll.is_multiple_of(one_in) {
+            let value = if roll & (1 << 16) == 0 { 0 } else { 255 };
+            *pixel = Rgba([value, value, value, 255]);
+        }
+    }
+    noisy
+}
+
+/* The same filter on the CPU. */
+fn cpu_median(pixels: &Image, window: u32) -> Image {
+    let radius = (window / 2) as i32;
+    let (width, height) = pixels.dimensions();
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let mut result = [0; 4];
+        for (channel, value) in result.iter_mut().enumerate() {
+            let mut values = Vec::new();
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let sx = (x as i32 + dx).max(0).min(width as i32 - 1) as u32;
+                    let sy = (y as i32 + dy).max(0).min(height as i32 - 1) as u32;
+                    values.push(pixels.get_pixel(sx, sy)[channel]);
+                }
+            }
+            values.sort();
+            *value = values[values.len() / 2];
+        }
+        Rgba(result)
+    })
+}
+
+/* Checks both windows against the CPU on a noisy UV pattern, including the clamped borders.
+ * The median is always one of the values it was picked from, so they have to agree exactly.
+ * Then checks that a lone white pixel on gray is gone without a trace. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let noisy = salt_and_pepper(&pattern::uv(device.clone(), queue.clone(), 37, 29), 10, 3);
+    for &window in &WINDOWS {
+        let gpu = median(device.clone(), queue.clone(), &noisy, window);
+        let cpu = cpu_median(&noisy, window);
+        for ((x, y, gpu), cpu) in gpu.enumerate_pixels().zip(cpu.pixels()) {
+            assert!(gpu == cpu, "The {0}x{0} median of ({1}, {2}) is {3:?} on the GPU and {4:?} on the CPU",
+                window, x, y, gpu, cpu);
+        }
+    }
+
+    let mut gray = ImageBuffer::from_pixel(16, 16, Rgba([128u8, 128, 128, 255]));
+    gray.put_pixel(7, 7, Rgba([255, 255, 255, 255]));
+    let filtered = median(device, queue, &gray, DEFAULT_WINDOW);
+    assert!(filtered.pixels().all(|pixel| pixel.data == [128, 128, 128, 255]), "A lone white pixel survived the median");
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/median.glsl"
+    }
+}
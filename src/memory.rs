@@ -0,0 +1,187 @@
+use std::panic::Location;
+use std::sync::{Arc, Mutex, Weak};
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer, DeviceLocalBuffer, ImmutableBuffer,
+    TypedBufferAccess};
+use vulkano::device::Queue;
+use vulkano::image::ImageAccess;
+use vulkano::memory::Content;
+use vulkano::sync::GpuFuture;
+
+use crate::batch;
+use crate::error::SandboxError;
+
+/* A buffer or image that was made, where, and a way to tell whether it is still around. */
+struct Allocation {
+    location: &'static Location<'static>,
+    bytes: usize,
+    alive: Weak<dyn Send + Sync>,
+}
+
+struct Tracker {
+    allocations: Vec<Allocation>,
+    count: usize,
+    peak: usize,
+    verbose: bool,
+}
+
+impl Tracker {
+    /* Forgets whatever has been dropped since, and adds up the rest. */
+    fn in_use(&mut self) -> usize {
+        self.allocations.retain(|allocation| allocation.alive.strong_count() > 0);
+        self.allocations.iter().map(|allocation| allocation.bytes).sum()
+    }
+}
+
+static TRACKER: Mutex<Tracker> = Mutex::new(Tracker { allocations: Vec::new(), count: 0, peak: 0, verbose: false });
+
+/* With `verbose`, every allocation is printed as it is made. */
+pub fn set_verbose(verbose: bool) {
+    TRACKER.lock().unwrap().verbose = verbose;
+}
+
+/* Tracks the memory behind every buffer and image the demos make, for `--verbose`. Each one is
+ * handed through `buffer` or `image` as it is created, which remembers it by where that was,
+ * and it counts as in use until its last `Arc` is dropped. What is counted is the size asked
+ * for: the driver may round it up, and vulkano packs small allocations into bigger blocks, so
+ * this is a floor on what the GPU actually set aside rather than the exact figure. */
+#[track_caller]
+pub fn buffer<B: BufferAccess + Send + Sync + 'static>(buffer: Arc<B>) -> Arc<B> {
+    let alive: Weak<B> = Arc::downgrade(&buffer);
+    record(Location::caller(), "buffer", buffer.size(), alive);
+    buffer
+}
+
+/* The same for an image, counting every mip level, layer and sample of it. */
+#[track_caller]
+pub fn image<I: ImageAccess + Send + Sync + 'static>(image: Arc<I>) -> Arc<I> {
+    let dimensions = image.dimensions();
+    let texel = image.format().size().unwrap_or(0) * image.samples() as usize;
+    let bytes = (0..image.mipmap_levels())
+        .map(|level| {
+            let extent = |size: u32| (size >> level).max(1) as usize;
+            extent(dimensions.width()) * extent(dimensions.height()) * extent(dimensions.depth())
+        })
+        .sum::<usize>() * dimensions.array_layers() as usize * texel;
+
+    let alive: Weak<I> = Arc::downgrade(&image);
+    record(Location::caller(), "image", bytes, alive);
+    image
+}
+
+fn record(location: &'static Location<'static>, kind: &str, bytes: usize, alive: Weak<dyn Send + Sync>) {
+    let mut tracker = TRACKER.lock().unwrap();
+    tracker.allocations.push(Allocation { location, bytes, alive });
+    tracker.count += 1;
+    let in_use = tracker.in_use();
+    tracker.peak = tracker.peak.max(in_use);
+
+    if tracker.verbose {
+        println!("Allocated a {} {} at {}, {} in use", size(bytes), kind, location, size(in_use));
+    }
+}
+
+/* Prints how much was in use at most, and what is still around, which at the end of a run is
+ * whatever something held on to for longer than it needed. */
+pub fn print_summary() {
+    let mut tracker = TRACKER.lock().unwrap();
+    let in_use = tracker.in_use();
+    println!("GPU memory peaked at {} over {} buffers and images, {} still in use", size(tracker.peak), tracker.count,
+        size(in_use));
+    for allocation in &tracker.allocations {
+        println!("    {} from {}", size(allocation.bytes), allocation.location);
+    }
+}
+
+/* How many bytes the buffers and images that are still around take, counted the same way. */
+pub fn in_use() -> usize {
+    TRACKER.lock().unwrap().in_use()
+}
+
+/* What a buffer compute shaders read and write is used as. BufferUsage has no shorthand for it. */
+pub fn storage_buffer() -> BufferUsage {
+    BufferUsage { storage_buffer: true, .. BufferUsage::none() }
+}
+
+/* `usage`, plus being copied to and from, which every device local buffer is so it can be
+ * filled and read back through a staging buffer. */
+fn with_transfers(usage: BufferUsage) -> BufferUsage {
+    BufferUsage { transfer_source: true, transfer_destination: true, .. usage }
+}
+
+/* A buffer of `len` elements in device local memory, for the GPU to fill. Device local memory is
+ * the GPU's own, which on a discrete card is the only memory it reaches without going over the
+ * bus, but which the CPU usually can't see at all. What is in it to begin with is undefined.
+ * Running out of memory for it, or for any of the buffers below, is an error rather than a
+ * panic, so a scene can say what to try instead. */
+#[track_caller]
+pub fn device_local<T>(queue: &Queue, usage: BufferUsage, len: usize)
+    -> Result<Arc<DeviceLocalBuffer<[T]>>, SandboxError>
+    where T: Send + Sync + 'static
+{
+    Ok(buffer(DeviceLocalBuffer::array(queue.device().clone(), len, with_transfers(usage), Some(queue.family()))?))
+}
+
+/* Puts `data` in a new device local buffer for the GPU to read and write, by writing it to a
+ * staging buffer the CPU can see, which is only ever a transfer source, and copying it across on
+ * `queue`. The copy is waited for, so the staging buffer is dropped again straight away. */
+#[track_caller]
+pub fn upload_to_device_local<T, I>(queue: Arc<Queue>, usage: BufferUsage, data: I)
+    -> Result<Arc<DeviceLocalBuffer<[T]>>, SandboxError>
+    where T: Send + Sync + 'static, I: IntoIterator<Item = T>
+{
+    let data: Vec<T> = data.into_iter().collect();
+    let device = queue.device().clone();
+    let destination = device_local(&queue, usage, data.len())?;
+    let staging = buffer(CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(),
+        data.into_iter())?);
+    batch::submit_and_wait(device, queue, |builder| builder
+        .copy_buffer(staging, destination.clone()).unwrap());
+    Ok(destination)
+}
+
+/* The same for data the GPU only ever reads, like vertices and indices. Vulkano copies it from a
+ * staging buffer of its own, and the buffer can't be written again afterwards, which lets the
+ * driver keep it wherever reading it is fastest. */
+#[track_caller]
+pub fn upload_immutable<T, I>(queue: Arc<Queue>, usage: BufferUsage, data: I)
+    -> Result<Arc<ImmutableBuffer<[T]>>, SandboxError>
+    where T: Send + Sync + 'static, I: IntoIterator<Item = T>
+{
+    let data: Vec<T> = data.into_iter().collect();
+    let (immutable, upload) = ImmutableBuffer::from_iter(data.into_iter(), usage, queue)?;
+    upload.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+    Ok(buffer(immutable))
+}
+
+/* A buffer the CPU can read, for copying `len` elements out of a device local one into, as part
+ * of a command buffer that does more, like the dispatch that wrote them. */
+#[track_caller]
+pub fn readback<T>(queue: &Queue, len: usize) -> Result<Arc<CpuAccessibleBuffer<[T]>>, SandboxError>
+    where T: Content + Default + Send + Sync + 'static
+{
+    Ok(buffer(CpuAccessibleBuffer::from_iter(queue.device().clone(), BufferUsage::transfer_destination(),
+        (0..len).map(|_| T::default()))?))
+}
+
+/* Reads a device local buffer back by copying it into a `readback` buffer on `queue` and waiting
+ * for the copy. */
+#[track_caller]
+pub fn download_from_device_local<T, B>(queue: Arc<Queue>, source: Arc<B>) -> Result<Vec<T>, SandboxError>
+    where T: Content + Clone + Default + Send + Sync + 'static,
+          B: TypedBufferAccess<Content = [T]> + Send + Sync + 'static
+{
+    let staging = readback(&queue, source.len())?;
+    batch::submit_and_wait(queue.device().clone(), queue, |builder| builder
+        .copy_buffer(source, staging.clone()).unwrap());
+    let content = staging.read().unwrap();
+    Ok(content.to_vec())
+}
+
+/* `bytes` in whichever unit reads best. */
+pub fn size(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.2} MiB", b as f64 / (1 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KiB", b as f64 / (1 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
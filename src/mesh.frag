@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec3 v_normal;
+layout(location = 1) in vec3 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+/* Coming from above, to the left and behind the camera, in world space. */
+const vec3 LIGHT = normalize(vec3(-0.4, 0.8, 0.6));
+const float AMBIENT = 0.2;
+
+/* Lambert shading: as bright as the surface faces the light, plus a little so the faces
+ * turned away from it aren't black. */
+void main() {
+    float diffuse = max(dot(normalize(v_normal), LIGHT), 0.0);
+    f_color = vec4(v_color * (AMBIENT + (1.0 - AMBIENT) * diffuse), 1.0);
+}
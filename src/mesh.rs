@@ -0,0 +1,400 @@
+use image::{ImageBuffer, Rgba};
+use std::f32::consts::PI;
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuBufferPool, ImmutableBuffer, TypedBufferAccess};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass};
+use vulkano::image::{AttachmentImage, ImageUsage};
+use vulkano::instance::PhysicalDevice;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::sync::GpuFuture;
+
+use crate::memory;
+use crate::readback;
+use crate::triangle::{self, SIZE};
+
+/* What `--frames` defaults to with `--cube`. */
+pub const DEFAULT_FRAMES: u32 = 120;
+
+/* A vertex of a lit 3D mesh. Faces that meet at a hard edge, like a cube's, each need vertices
+ * of their own to have normals of their own. The texture coordinates come along from loaded
+ * models for shaders that want them; mesh.vert doesn't. */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Vertex3D {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub color: [f32; 3],
+    pub uv: [f32; 2],
+}
+vulkano::impl_vertex!(Vertex3D, position, normal, color, uv);
+
+/* The matrices mesh.vert transforms by, each a column after column like GLSL's. */
+pub type Transform = vs::ty::Transform;
+pub type Matrix = [[f32; 4]; 4];
+
+pub fn identity() -> Matrix {
+    [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]]
+}
+
+/* `a` applied after `b`. */
+pub fn multiply(a: Matrix, b: Matrix) -> Matrix {
+    let mut product = [[0.0; 4]; 4];
+    for (column, out) in product.iter_mut().enumerate() {
+        for (row, value) in out.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[column][k]).sum();
+        }
+    }
+    product
+}
+
+pub fn translation(x: f32, y: f32, z: f32) -> Matrix {
+    let mut matrix = identity();
+    matrix[3] = [x, y, z, 1.0];
+    matrix
+}
+
+/* Turns `angle` radians about the x axis, y towards z. */
+pub fn rotation_x(angle: f32) -> Matrix {
+    let (s, c) = angle.sin_cos();
+    [[1.0, 0.0, 0.0, 0.0], [0.0, c, s, 0.0], [0.0, -s, c, 0.0], [0.0, 0.0, 0.0, 1.0]]
+}
+
+/* Turns `angle` radians about the y axis, z towards x. */
+pub fn rotation_y(angle: f32) -> Matrix {
+    let (s, c) = angle.sin_cos();
+    [[c, 0.0, -s, 0.0], [0.0, 1.0, 0.0, 0.0], [s, 0.0, c, 0.0], [0.0, 0.0, 0.0, 1.0]]
+}
+
+/* A perspective projection for a camera looking down -z with y up, into Vulkan's clip space,
+ * where y points down and depth goes from 0 at `near` to 1 at `far`. */
+pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Matrix {
+    let f = 1.0 / (fov_y / 2.0).tan();
+    [
+        [f / aspect, 0.0, 0.0, 0.0],
+        [0.0, -f, 0.0, 0.0],
+        [0.0, 0.0, far / (near - far), -1.0],
+        [0.0, 0.0, near * far / (near - far), 0.0],
+    ]
+}
+
+/* The cube's model turned `angle` radians about y and half that about x, seen from a camera
+ * a few units back. */
+pub fn spinning(angle: f32) -> Transform {
+    Transform {
+        model: multiply(rotation_y(angle), rotation_x(angle * 0.5)),
+        view: translation(0.0, 0.0, -3.5),
+        projection: perspective(PI / 4.0, 1.0, 0.1, 10.0),
+    }
+}
+
+/* A cube from -0.5 to 0.5 on each axis, each face a color of its own, as 24 vertices, four a
+ * face, and the 36 indices of its 12 triangles. */
+pub fn cube() -> (Vec<Vertex3D>, Vec<u32>) {
+    /* Each face's normal, and two directions across it whose cross product is the normal. */
+    let faces = [
+        ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [0.9, 0.2, 0.2]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [0.2, 0.9, 0.9]),
+        ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.2, 0.9, 0.2]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.9, 0.2, 0.9]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.2, 0.2, 0.9]),
+        ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.9, 0.9, 0.2]),
+    ];
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+    for &(normal, u, v, color) in &faces {
+        let base = vertices.len() as u32;
+        for &(a, b) in &[(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)] {
+            let position = [
+                normal[0] * 0.5 + u[0] * a + v[0] * b,
+                normal[1] * 0.5 + u[1] * a + v[1] * b,
+                normal[2] * 0.5 + u[2] * a + v[2] * b,
+            ];
+            vertices.push(Vertex3D { position, normal, color, uv: [a + 0.5, b + 0.5] });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+    (vertices, indices)
+}
+
+/* A mesh's vertices and the indices of its triangles, ready to draw. Nothing changes them once
+ * they are uploaded, so they are immutable buffers in memory only the GPU needs to see. */
+pub struct Mesh {
+    pub vertices: Arc<ImmutableBuffer<[Vertex3D]>>,
+    pub indices: Arc<ImmutableBuffer<[u32]>>,
+}
+
+impl Mesh {
+    pub fn new(queue: Arc<Queue>, vertices: Vec<Vertex3D>, indices: Vec<u32>) -> Mesh {
+        let (vertices, vertices_uploaded) = ImmutableBuffer::from_iter(vertices.into_iter(),
+            BufferUsage::vertex_buffer(), queue.clone()).expect("Failed to create vertex buffer");
+        let (indices, indices_uploaded) = ImmutableBuffer::from_iter(indices.into_iter(),
+            BufferUsage::index_buffer(), queue).expect("Failed to create index buffer");
+        vertices_uploaded.join(indices_uploaded)
+            .then_signal_fence_and_flush().unwrap()
+            .wait(None).unwrap();
+        Mesh { vertices: memory::buffer(vertices), indices: memory::buffer(indices) }
+    }
+
+    /* How many triangles it has. */
+    pub fn triangles(&self) -> usize {
+        self.indices.len() / 3
+    }
+}
+
+/* A pipeline that draws lit meshes into a SIZE x SIZE target with a depth buffer, so whichever
+ * triangle is nearest the camera ends up in each pixel, whatever order they are drawn in. The
+ * triangle and its 2D cousins never needed one: nothing they draw overlaps. */
+pub struct MeshRenderer {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+    framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    image: Arc<AttachmentImage>,
+    uniforms: CpuBufferPool<Transform>,
+    samples: u32,
+}
+
+/* The depth attachment is cleared to the far plane at the start of the pass and thrown away at
+ * the end: only the color is read back. */
+fn render_pass(device: Arc<Device>) -> Arc<dyn RenderPassAbstract + Send + Sync> {
+    Arc::new(vulkano::single_pass_renderpass!(device,
+        attachments: {
+            color: {
+                load: Clear,
+                store: Store,
+                format: Format::R8G8B8A8Unorm,
+                samples: 1,
+            },
+            depth: {
+                load: Clear,
+                store: DontCare,
+                format: Format::D16Unorm,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {depth}
+        }
+    ).unwrap())
+}
+
+/* The same with `samples` samples per pixel, the way triangle.rs multisamples: the color and the
+ * depth are both multisampled, and only live for the pass, and the color is resolved into the
+ * image that is read back at the end of it. */
+fn multisampled_render_pass(device: Arc<Device>, samples: u32) -> Arc<dyn RenderPassAbstract + Send + Sync> {
+    Arc::new(vulkano::single_pass_renderpass!(device,
+        attachments: {
+            multisampled: {
+                load: Clear,
+                store: DontCare,
+                format: Format::R8G8B8A8Unorm,
+                samples: samples,
+            },
+            depth: {
+                load: Clear,
+                store: DontCare,
+                format: Format::D16Unorm,
+                samples: samples,
+            },
+            color: {
+                load: DontCare,
+                store: Store,
+                format: Format::R8G8B8A8Unorm,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [multisampled],
+            depth_stencil: {depth},
+            resolve: [color],
+        }
+    ).unwrap())
+}
+
+/* The sample counts a mesh can be drawn with, which need a depth buffer with as many samples as
+ * the color, in the mask `triangle::sample_count` takes. */
+pub fn supported_samples(physical: PhysicalDevice) -> u32 {
+    let limits = physical.limits();
+    limits.framebuffer_color_sample_counts() & limits.framebuffer_depth_sample_counts()
+}
+
+impl MeshRenderer {
+    pub fn new(device: Arc<Device>, queue: Arc<Queue>) -> MeshRenderer {
+        MeshRenderer::with_samples(device, queue, 1).unwrap()
+    }
+
+    /* A renderer drawing with `samples` samples per pixel, which has to be a count the device
+     * supports, as `supported_samples` gives them. */
+    pub fn with_samples(device: Arc<Device>, queue: Arc<Queue>, samples: u32) -> Result<MeshRenderer, String> {
+        let render_pass = if samples > 1 {
+            multisampled_render_pass(device.clone(), samples)
+        } else {
+            render_pass(device.clone())
+        };
+
+        let vs = vs::Shader::load(device.clone()).expect("Failed to create mesh vertex shader");
+        let fs = fs::Shader::load(device.clone()).expect("Failed to create mesh fragment shader");
+        let pipeline = Arc::new(GraphicsPipeline::start()
+            .vertex_input_single_buffer::<Vertex3D>()
+            .vertex_shader(vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .depth_stencil_simple_depth()
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone())
+            .unwrap());
+
+        let usage = ImageUsage { color_attachment: true, transfer_source: true, .. ImageUsage::none() };
+        let image = memory::image(AttachmentImage::with_usage(device.clone(), [SIZE, SIZE], Format::R8G8B8A8Unorm,
+            usage).unwrap());
+        let framebuffer: Arc<dyn FramebufferAbstract + Send + Sync> = if samples > 1 {
+            let unsupported = |e| format!("Can't draw the mesh with {}x MSAA on this device: {}", samples, e);
+            let multisampled = memory::image(AttachmentImage::transient_multisampled(device.clone(), [SIZE, SIZE],
+                samples, Format::R8G8B8A8Unorm).map_err(unsupported)?);
+            let depth = memory::image(AttachmentImage::transient_multisampled(device.clone(), [SIZE, SIZE], samples,
+                Format::D16Unorm).map_err(unsupported)?);
+            Arc::new(Framebuffer::start(render_pass)
+                .add(multisampled).unwrap()
+                .add(depth).unwrap()
+                .add(image.clone()).unwrap()
+                .build().unwrap())
+        } else {
+            let depth = memory::image(AttachmentImage::transient(device.clone(), [SIZE, SIZE], Format::D16Unorm)
+                .unwrap());
+            Arc::new(Framebuffer::start(render_pass)
+                .add(image.clone()).unwrap()
+                .add(depth).unwrap()
+                .build().unwrap())
+        };
+
+        /* A transform changes every frame, and the pool hands out a piece of a buffer it keeps
+         * for each one instead of a buffer being allocated per frame. Pieces go back to it once
+         * the frame that used them is dropped. */
+        let uniforms = CpuBufferPool::uniform_buffer(device.clone());
+        Ok(MeshRenderer { device, queue, pipeline, framebuffer, image, uniforms, samples })
+    }
+
+    /* Adds drawing `mesh` transformed by `transform` into the renderer's image to `builder`, for
+     * command buffers that do more, like copying the image somewhere of their own afterwards. */
+    pub fn record(&self, builder: AutoCommandBufferBuilder, mesh: &Mesh, transform: Transform)
+        -> AutoCommandBufferBuilder
+    {
+        let uniform = self.uniforms.next(transform).expect("Failed to allocate uniform buffer");
+        let set = Arc::new(PersistentDescriptorSet::start(self.pipeline.clone(), 0)
+            .add_buffer(uniform).unwrap()
+            .build().unwrap());
+
+        /* The resolved image isn't loaded, so it takes no clear value. */
+        let clear_values = if self.samples > 1 {
+            vec![triangle::CLEAR_COLOR.into(), 1f32.into(), ClearValue::None]
+        } else {
+            vec![triangle::CLEAR_COLOR.into(), 1f32.into()]
+        };
+        builder
+            .begin_render_pass(self.framebuffer.clone(), false, clear_values)
+            .unwrap()
+            .draw_indexed(self.pipeline.clone(), &triangle::dynamic_state(), vec![mesh.vertices.clone()],
+                mesh.indices.clone(), set, ()).unwrap()
+            .end_render_pass().unwrap()
+    }
+
+    /* The image `record` draws into. */
+    pub fn image(&self) -> Arc<AttachmentImage> {
+        self.image.clone()
+    }
+
+    /* Draws `mesh` transformed by `transform` and reads it back. */
+    pub fn draw(&self, mesh: &Mesh, transform: Transform) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let builder = AutoCommandBufferBuilder::primary_one_time_submit(self.device.clone(), self.queue.family())
+            .unwrap();
+        self.record(builder, mesh, transform)
+            .build().unwrap()
+            .execute(self.queue.clone()).unwrap()
+            .then_signal_fence_and_flush().unwrap()
+            .wait(None).unwrap();
+
+        readback::download_rgba8(self.device.clone(), self.queue.clone(), self.image.clone())
+    }
+}
+
+/* Renders the cube partway through a turn, with `samples` samples per pixel. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>, samples: u32) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String> {
+    let (vertices, indices) = cube();
+    let mesh = Mesh::new(queue.clone(), vertices, indices);
+    Ok(MeshRenderer::with_samples(device, queue, samples)?.draw(&mesh, spinning(0.6)))
+}
+
+/* Spins the cube once over `frames` frames, saving each as cube_NNNN.png. */
+pub fn animate(device: Arc<Device>, queue: Arc<Queue>, frames: u32, samples: u32) -> Result<(), String> {
+    let (vertices, indices) = cube();
+    let mesh = Mesh::new(queue.clone(), vertices, indices);
+    spin(device, queue, &mesh, "cube", frames, samples)
+}
+
+/* Spins `mesh` once over `frames` frames, saving each as <name>_NNNN.png. */
+pub fn spin(device: Arc<Device>, queue: Arc<Queue>, mesh: &Mesh, name: &str, frames: u32, samples: u32)
+    -> Result<(), String>
+{
+    let renderer = MeshRenderer::with_samples(device, queue, samples)?;
+    for index in 0..frames {
+        let angle = index as f32 / frames as f32 * PI * 2.0;
+        let path = format!("{}_{:04}.png", name, index);
+        renderer.draw(mesh, spinning(angle)).save(&path).unwrap();
+        println!("Wrote {}", path);
+    }
+    Ok(())
+}
+
+/* Checks the cube covers the middle of the image but not its corners, that turning it changes
+ * what is drawn, and that drawing its faces in the opposite order draws exactly the same
+ * picture, which it only does if the depth test keeps the nearest face in each pixel. Then
+ * draws it again with the most samples the device supports, if it supports more than one. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let (vertices, indices) = cube();
+    let mesh = Mesh::new(queue.clone(), vertices.clone(), indices.clone());
+    let renderer = MeshRenderer::new(device.clone(), queue.clone());
+
+    let image = renderer.draw(&mesh, spinning(0.6));
+    let center = image.get_pixel(SIZE / 2, SIZE / 2);
+    assert!(center[3] == 255, "The cube doesn't cover the middle of the image, which is {:?}", center);
+    for &(x, y) in &[(0, 0), (SIZE - 1, 0), (0, SIZE - 1), (SIZE - 1, SIZE - 1)] {
+        let corner = image.get_pixel(x, y);
+        assert!(corner[3] == 0, "The cube reaches the corner at ({}, {}), which is {:?}", x, y, corner);
+    }
+    assert!(renderer.draw(&mesh, spinning(1.2)).into_raw() != image.clone().into_raw(),
+        "Turning the cube didn't change it");
+
+    let reversed: Vec<u32> = indices.chunks(3).rev().flat_map(|triangle| triangle.to_vec()).collect();
+    let backwards = renderer.draw(&Mesh::new(queue.clone(), vertices, reversed), spinning(0.6));
+    assert!(backwards.into_raw() == image.clone().into_raw(), "Drawing the cube's faces back to front changed it");
+
+    /* Drawn aliased, every pixel is either the cube or the transparent background. Resolving
+     * several samples blends the two along its edges, and leaves the middle as it was. */
+    let edges = |image: &ImageBuffer<Rgba<u8>, Vec<u8>>| image.pixels().filter(|p| p[3] > 0 && p[3] < 255).count();
+    assert!(edges(&image) == 0, "The aliased cube has partly covered pixels");
+    let supported = supported_samples(device.physical_device());
+    if let Some(&samples) = triangle::SAMPLE_COUNTS.iter().rev().find(|&&n| n > 1 && supported & n != 0) {
+        let smooth = MeshRenderer::with_samples(device.clone(), queue, samples).unwrap().draw(&mesh, spinning(0.6));
+        assert!(edges(&smooth) > 0, "The cube with {}x MSAA has no partly covered pixels", samples);
+        assert!(smooth.get_pixel(SIZE / 2, SIZE / 2) == center, "{}x MSAA changed the middle of the cube", samples);
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/mesh.vert"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/mesh.frag"
+    }
+}
@@ -0,0 +1,21 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec3 color;
+
+/* Must match mesh.rs. The model matrix only ever rotates, so it turns normals too. */
+layout(set = 0, binding = 0) uniform Transform {
+    mat4 model;
+    mat4 view;
+    mat4 projection;
+} transform;
+
+layout(location = 0) out vec3 v_normal;
+layout(location = 1) out vec3 v_color;
+
+void main() {
+    v_normal = mat3(transform.model) * normal;
+    v_color = color;
+    gl_Position = transform.projection * transform.view * transform.model * vec4(position, 1.0);
+}
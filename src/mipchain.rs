@@ -0,0 +1,93 @@
+use image::{GenericImage, ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::Dimensions;
+use vulkano::sampler::Filter;
+
+use crate::blit;
+use crate::pattern;
+use crate::readback;
+use crate::storage;
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 128;
+
+/* The size of each level of a mip chain starting at width x height: every level is half the
+ * one before, rounded down but never below 1, down to 1 x 1. */
+pub fn level_sizes(width: u32, height: u32) -> Vec<(u32, u32)> {
+    let mut sizes = vec![(width, height)];
+    while sizes.last() != Some(&(1, 1)) {
+        let &(width, height) = sizes.last().unwrap();
+        sizes.push(((width / 2).max(1), (height / 2).max(1)));
+    }
+    sizes
+}
+
+/* Builds the mip chain of `base` by blitting each level down from the one before with linear
+ * filtering, and reads every level back. Mipmapped images keep their levels in one image, but
+ * vulkano 0.11 tracks an image as a whole, so a blit from one of its levels into the next
+ * counts as reading and writing the same resource and is refused. Here each level is an image
+ * of its own instead, which comes out the same. */
+pub fn chain(device: Arc<Device>, queue: Arc<Queue>, base: &Image) -> Vec<Image> {
+    let mut level = readback::upload_rgba8(device.clone(), queue.clone(), base);
+    let mut levels = vec![base.clone()];
+
+    for &(width, height) in &level_sizes(base.width(), base.height())[1..] {
+        let next = storage::image(&queue, Dimensions::Dim2d { width, height }, Format::R8G8B8A8Unorm);
+        blit::scaled_copy(device.clone(), queue.clone(), level, next.clone(), Filter::Linear).unwrap();
+        levels.push(readback::download_rgba8(device.clone(), queue.clone(), next.clone()));
+        level = next;
+    }
+
+    levels
+}
+
+/* Lays the levels out side by side, level 0 on the left, along the top of one transparent
+ * image as tall as the first. */
+pub fn side_by_side(levels: &[Image]) -> Image {
+    let width = levels.iter().map(|level| level.width()).sum();
+    let height = levels.iter().map(|level| level.height()).max().unwrap_or(0);
+    let mut image = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+
+    let mut x = 0;
+    for level in levels {
+        image.copy_from(level, x, 0);
+        x += level.width();
+    }
+    image
+}
+
+/* The mip chain of the uv pattern, as one image. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>) -> Image {
+    let base = pattern::uv(device.clone(), queue.clone(), WIDTH, HEIGHT);
+    side_by_side(&chain(device, queue, &base))
+}
+
+/* Checks that the chain halves all the way down to 1 x 1, that a level that has gone down to
+ * one pixel in one direction stays there, and that each level landed where it belongs in the
+ * side by side image. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    assert!(level_sizes(5, 1) == [(5, 1), (2, 1), (1, 1)], "A 5 x 1 chain came out {:?}", level_sizes(5, 1));
+
+    let base = pattern::uv(device.clone(), queue.clone(), WIDTH, HEIGHT);
+    let levels = chain(device, queue, &base);
+    let sizes: Vec<_> = levels.iter().map(|level| level.dimensions()).collect();
+    assert!(sizes.len() == 9, "A {} x {} chain has {} levels, expected 9", WIDTH, HEIGHT, sizes.len());
+    for (index, &size) in sizes.iter().enumerate() {
+        let expected = ((WIDTH >> index).max(1), (HEIGHT >> index).max(1));
+        assert!(size == expected, "Level {} is {:?}, expected {:?}", index, size, expected);
+    }
+
+    let image = side_by_side(&levels);
+    assert!(image.dimensions() == (sizes.iter().map(|size| size.0).sum(), HEIGHT),
+        "The side by side image is {:?}", image.dimensions());
+    let mut x = 0;
+    for (index, level) in levels.iter().enumerate() {
+        assert!(level.enumerate_pixels().all(|(lx, ly, pixel)| image.get_pixel(x + lx, ly) == pixel),
+            "Level {} isn't where it belongs", index);
+        x += level.width();
+    }
+}
@@ -0,0 +1,227 @@
+use image::{ImageBuffer, Rgba};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+
+use crate::mesh::{self, Mesh, MeshRenderer, Vertex3D};
+use crate::triangle::SIZE;
+
+/* What models are drawn in. The colors of an OBJ file are in the material library it names,
+ * which isn't read. */
+const COLOR: [f32; 3] = [0.8, 0.8, 0.8];
+
+/* A model's triangles, as the vertices and indices `Mesh::new` uploads. */
+pub struct Model {
+    pub vertices: Vec<Vertex3D>,
+    pub indices: Vec<u32>,
+}
+
+/* An OBJ index, counted from 1 or, if negative, back from the last one defined so far, as one
+ * of `count`. */
+fn resolve(index: &str, count: usize, what: &str) -> Result<usize, String> {
+    let index: i64 = index.parse().map_err(|_| format!("`{}` isn't a {} index", index, what))?;
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!("there is no {} {}, only {}", what, index, count));
+    }
+    Ok(resolved as usize)
+}
+
+/* Parses `count` numbers from `fields`, of which the ones past `required` are optional and
+ * default to 0. */
+fn numbers<'a, I: Iterator<Item = &'a str>>(fields: I, required: usize, count: usize) -> Result<Vec<f32>, String> {
+    let parsed: Vec<f32> = fields.take(count)
+        .map(|field| field.parse().map_err(|_| format!("`{}` isn't a number", field)))
+        .collect::<Result<_, _>>()?;
+    if parsed.len() < required {
+        return Err(format!("expected {} numbers, got {}", required, parsed.len()));
+    }
+    Ok((0..count).map(|i| parsed.get(i).cloned().unwrap_or(0.0)).collect())
+}
+
+fn subtract(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if length == 0.0 { [0.0, 1.0, 0.0] } else { [v[0] / length, v[1] / length, v[2] / length] }
+}
+
+/* The normal of the triangle a, b, c, wound counterclockwise. */
+fn face_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    let (u, v) = (subtract(b, a), subtract(c, a));
+    normalize([u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]])
+}
+
+/* Parses a Wavefront OBJ file's positions, normals, texture coordinates and faces, the part of
+ * the format nearly every exporter writes. Faces with more than three corners are split into a
+ * fan of triangles, which is right for the convex polygons exporters write. A corner that has
+ * no normal gets its face's, so a model without any is drawn flat shaded. Corners that are the
+ * same position, coordinates and normal share a vertex. Anything else, like objects, groups,
+ * smoothing groups and materials, is ignored. Errors name the line. */
+pub fn parse(source: &str, name: &str) -> Result<Model, String> {
+    let (mut positions, mut normals, mut uvs) = (Vec::new(), Vec::new(), Vec::new());
+    let mut model = Model { vertices: Vec::new(), indices: Vec::new() };
+    let mut shared: HashMap<(usize, Option<usize>, usize), u32> = HashMap::new();
+
+    for (number, line) in source.lines().enumerate() {
+        let error = |e: String| format!("{}:{}: {}", name, number + 1, e);
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("v") => {
+                let v = numbers(fields, 3, 3).map_err(error)?;
+                positions.push([v[0], v[1], v[2]]);
+            }
+            Some("vn") => {
+                let n = numbers(fields, 3, 3).map_err(error)?;
+                normals.push(normalize([n[0], n[1], n[2]]));
+            }
+            Some("vt") => {
+                let t = numbers(fields, 1, 2).map_err(error)?;
+                uvs.push([t[0], t[1]]);
+            }
+            Some("f") => {
+                /* Each corner is position, position/uv, position//normal or position/uv/normal. */
+                let mut corners = Vec::new();
+                for corner in fields {
+                    let mut parts = corner.split('/');
+                    let position = resolve(parts.next().unwrap(), positions.len(), "position").map_err(error)?;
+                    let uv = match parts.next() {
+                        Some("") | None => None,
+                        Some(index) => Some(resolve(index, uvs.len(), "texture coordinate").map_err(error)?),
+                    };
+                    let normal = match parts.next() {
+                        Some("") | None => None,
+                        Some(index) => Some(resolve(index, normals.len(), "normal").map_err(error)?),
+                    };
+                    corners.push((position, uv, normal));
+                }
+                if corners.len() < 3 {
+                    return Err(error(format!("a face needs at least 3 corners, not {}", corners.len())));
+                }
+
+                let flat = face_normal(positions[corners[0].0], positions[corners[1].0], positions[corners[2].0]);
+                let mut indices = Vec::with_capacity(corners.len());
+                for &(position, uv, normal) in &corners {
+                    let vertex = Vertex3D {
+                        position: positions[position],
+                        normal: normal.map_or(flat, |n| normals[n]),
+                        color: COLOR,
+                        uv: uv.map_or([0.0, 0.0], |t| uvs[t]),
+                    };
+                    let index = match normal {
+                        Some(normal) => {
+                            let next = model.vertices.len() as u32;
+                            let index = *shared.entry((position, uv, normal)).or_insert(next);
+                            if index == next {
+                                model.vertices.push(vertex);
+                            }
+                            index
+                        }
+                        None => {
+                            model.vertices.push(vertex);
+                            model.vertices.len() as u32 - 1
+                        }
+                    };
+                    indices.push(index);
+                }
+                for i in 1..indices.len() - 1 {
+                    model.indices.extend_from_slice(&[indices[0], indices[i], indices[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if model.indices.is_empty() {
+        return Err(format!("{} has no faces", name));
+    }
+    fit(&mut model.vertices);
+    Ok(model)
+}
+
+/* Moves and scales `vertices` so their bounds are centered on the origin and the longest side
+ * is 1, the size of the cube, whatever units the model was made in. */
+fn fit(vertices: &mut [Vertex3D]) {
+    let (mut low, mut high) = ([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]);
+    for vertex in vertices.iter() {
+        for axis in 0..3 {
+            low[axis] = low[axis].min(vertex.position[axis]);
+            high[axis] = high[axis].max(vertex.position[axis]);
+        }
+    }
+    let longest = (0..3).map(|axis| high[axis] - low[axis]).fold(0.0, f32::max);
+    let scale = if longest > 0.0 { 1.0 / longest } else { 1.0 };
+    for vertex in vertices.iter_mut() {
+        for axis in 0..3 {
+            vertex.position[axis] = (vertex.position[axis] - (low[axis] + high[axis]) / 2.0) * scale;
+        }
+    }
+}
+
+/* Loads the OBJ file at `path`. */
+pub fn load(path: &Path) -> Result<Model, String> {
+    let source = std::fs::read_to_string(path).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => format!("Could not load {}: there is no such file", path.display()),
+        _ => format!("Could not load {}: {}", path.display(), e),
+    })?;
+    parse(&source, &path.display().to_string())
+}
+
+/* Renders `model` partway through a turn, like the cube, with `samples` samples per pixel. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>, model: Model, samples: u32)
+    -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String>
+{
+    let mesh = Mesh::new(queue.clone(), model.vertices, model.indices);
+    Ok(MeshRenderer::with_samples(device, queue, samples)?.draw(&mesh, mesh::spinning(0.6)))
+}
+
+/* A square pyramid two high on a base two wide, with its base written as one quad, the sides in
+ * every corner format, and one side with negative indices. */
+const PYRAMID: &str = "
+# A pyramid
+o pyramid
+v -1 0 -1
+v 1 0 -1
+v 1 0 1
+v -1 0 1
+v 0 2 0
+vt 0 0
+vt 1 0
+vt 0.5 1
+vn 0 -1 0
+f 1//1 2//1 3//1 4//1
+f 4/1 3/2 5/3
+f 3 2 5
+f 2/1/1 1/2/1 5/3/1
+f -5 -2 -1
+";
+
+/* Checks the pyramid parses into six triangles, with the shared base corners shared, fitted
+ * into the unit cube with its normals facing out, that a broken file is an error naming the
+ * line, and that the pyramid renders over the middle of the image. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let model = parse(PYRAMID, "pyramid").unwrap();
+    assert_eq!(model.indices.len(), 18, "The pyramid should be 6 triangles");
+    assert_eq!(model.vertices.len(), 4 + 3 + 3 + 3 + 3, "The base's corners should be shared, no others");
+
+    let top = model.vertices.iter().map(|v| v.position[1]).fold(f32::NEG_INFINITY, f32::max);
+    let widest = model.vertices.iter().map(|v| v.position[0].abs()).fold(0.0, f32::max);
+    assert!((top - 0.5).abs() < 1e-5 && (widest - 0.5).abs() < 1e-5,
+        "The pyramid wasn't fitted into the unit cube: its top is at {} and its sides at {}", top, widest);
+    let side = &model.vertices[4];
+    assert!(side.normal[1] > 0.0 && side.normal[2] > 0.0, "The front face's normal is {:?}", side.normal);
+
+    for &(source, line) in &[("v 0 0 0\nf 1 2 3", 2), ("v 0 0\n", 1), ("v 0 0 0\nv 1 0 0\nf 1 2", 3)] {
+        match parse(source, "broken") {
+            Err(e) => assert!(e.starts_with(&format!("broken:{}:", line)), "Wrong line in `{}`", e),
+            Ok(_) => panic!("`{}` parsed", source),
+        }
+    }
+
+    let image = render(device, queue, model, 1).unwrap();
+    assert!(image.get_pixel(SIZE / 2, SIZE / 2)[3] == 255, "The pyramid doesn't cover the middle of the image");
+}
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+use vulkano::buffer::{CpuAccessibleBuffer, DeviceLocalBuffer};
+use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::device::{Device, Queue};
+use vulkano::pipeline::{ComputePipeline, ComputePipelineAbstract};
+
+use crate::batch;
+use crate::compute::ComputeTask;
+use crate::debug_dump::Intermediate;
+use crate::descriptors::{Descriptors, Resource};
+use crate::error::SandboxError;
+use crate::memory;
+use crate::scene::{Scene, SceneOutput};
+use crate::watchdog;
+
+/* Must match the local size in op.glsl. */
+const LOCAL_SIZE: u32 = 64;
+
+/* The element count the demo always used, plus sizes that don't fill a whole workgroup. */
+pub const DEFAULT_ELEMENTS: u32 = 65536;
+const SIZES: &[u32] = &[1, 63, 64, 65, 1000, DEFAULT_ELEMENTS];
+
+pub fn pipeline(device: Arc<Device>) -> Arc<dyn ComputePipelineAbstract + Send + Sync> {
+    let shader = cs::Shader::load(device.clone()).expect("Failed to create shader module");
+
+    Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())
+        .expect("failed to create compute pipeline"))
+}
+
+/* Uploads 0..elements to a device local buffer and sets up multiplying it there. The products
+ * are copied out to a buffer the CPU can read afterwards, in the same command buffer. */
+fn task(queue: Arc<Queue>, pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>, descriptors: &mut Descriptors,
+        elements: u32) -> Result<Multiply, SandboxError>
+{
+    assert!(elements > 0, "The multiply demo needs at least one element");
+
+    let data_buffer = memory::upload_to_device_local(queue.clone(), memory::storage_buffer(), 0..elements)?;
+    let readback = memory::readback(&queue, elements as usize)?;
+
+    let set = descriptors.set(&pipeline, 0, &[Resource::buffer(data_buffer.clone())]).unwrap();
+
+    /* Each workgroup handles LOCAL_SIZE elements, so round up to cover all of them. The shader
+     * skips the invocations past the end of the last, partially filled group. */
+    let groups = group_count(elements);
+    assert!(groups * LOCAL_SIZE >= elements);
+
+    let task = ComputeTask::new("multiply", pipeline, set, [groups, 1, 1], ());
+    Ok(Multiply { task, data_buffer, readback })
+}
+
+/* The multiply, and where its products end up. */
+struct Multiply {
+    task: ComputeTask<()>,
+    data_buffer: Arc<DeviceLocalBuffer<[u32]>>,
+    readback: Arc<CpuAccessibleBuffer<[u32]>>,
+}
+
+impl Multiply {
+    fn record(&self, builder: AutoCommandBufferBuilder) -> AutoCommandBufferBuilder {
+        self.task.record(builder)
+            .copy_buffer(self.data_buffer.clone(), self.readback.clone()).unwrap()
+    }
+}
+
+/* A recorded multiply, and the buffer its products will be in once it has run. */
+pub type Prepared = (AutoCommandBuffer, Arc<CpuAccessibleBuffer<[u32]>>);
+
+/* Uploads 0..elements and records multiplying it, without submitting anything. */
+pub fn prepare(queue: Arc<Queue>, pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>, elements: u32)
+    -> Result<Prepared, SandboxError>
+{
+    let mut descriptors = Descriptors::new(queue.device().clone(), 1);
+    let multiply = task(queue.clone(), pipeline, &mut descriptors, elements)?;
+    let builder = AutoCommandBufferBuilder::new(queue.device().clone(), queue.family()).unwrap();
+    Ok((multiply.record(builder).build().unwrap(), multiply.readback))
+}
+
+/* Multiplying 0..elements, as a scene. */
+pub fn scene(queue: Arc<Queue>, descriptors: &mut Descriptors, elements: u32)
+    -> Result<MultiplyScene, SandboxError>
+{
+    let multiply = task(queue.clone(), pipeline(queue.device().clone()), descriptors, elements)?;
+    Ok(MultiplyScene { multiply, elements })
+}
+
+pub struct MultiplyScene {
+    multiply: Multiply,
+    elements: u32,
+}
+
+impl Scene for MultiplyScene {
+    fn name(&self) -> &'static str {
+        "multiply"
+    }
+
+    fn record(&self, builder: AutoCommandBufferBuilder) -> AutoCommandBufferBuilder {
+        self.multiply.record(builder)
+    }
+
+    fn output(&self) -> SceneOutput {
+        SceneOutput::Values(self.multiply.readback.read().unwrap().to_vec())
+    }
+
+    fn work(&self) -> (u64, &'static str) {
+        (self.elements as u64, "elements")
+    }
+
+    /* The numbers in device local memory, where the shader multiplies them before they are
+     * copied back. */
+    fn intermediates(&self) -> Vec<(&'static str, Intermediate)> {
+        vec![("data", Intermediate::U32(self.multiply.data_buffer.clone()))]
+    }
+}
+
+/* What multiplying 0..elements should give, on the CPU. */
+pub fn cpu_multiply(elements: u32) -> Vec<u32> {
+    (0..elements).map(|n| n.wrapping_mul(12)).collect()
+}
+
+fn check(content: &[u32], elements: u32) {
+    assert_eq!(content.len(), elements as usize);
+    for (n, val) in content.iter().enumerate() {
+        assert_eq!(*val, (n as u32).wrapping_mul(12));
+    }
+}
+
+/* We will now perform an arbitrary operation using a compute shader. We will multiply each
+ * element of this buffer by 12, check the result and hand it back. */
+pub fn run(device: Arc<Device>, queue: Arc<Queue>, elements: u32) -> Vec<u32> {
+    let (command_buffer, readback) = prepare(queue.clone(), pipeline(device), elements)
+        .expect("Failed to create buffer");
+    watchdog::wait(command_buffer.execute(queue).unwrap(), "multiply");
+
+    let content = readback.read().unwrap();
+    check(&content, elements);
+    content.to_vec()
+}
+
+/* Runs the demo at a few sizes, including ones that aren't a multiple of the local size. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    for &elements in SIZES {
+        run(device.clone(), queue.clone(), elements);
+    }
+}
+
+/* The same sizes again, but each multiply gets its own buffer so they are independent and can
+ * all be submitted together and waited on once. */
+pub fn verify_batched(device: Arc<Device>, queue: Arc<Queue>) {
+    let pipeline = pipeline(device.clone());
+    let (command_buffers, buffers): (Vec<_>, Vec<_>) = SIZES.iter()
+        .map(|&elements| prepare(queue.clone(), pipeline.clone(), elements).expect("Failed to create buffer"))
+        .unzip();
+
+    batch::submit_all(device, queue, command_buffers);
+
+    for (buffer, &elements) in buffers.iter().zip(SIZES) {
+        check(&buffer.read().unwrap(), elements);
+    }
+}
+
+/* The workgroups it takes to cover `elements`. */
+pub fn group_count(elements: u32) -> u32 {
+    elements.div_ceil(LOCAL_SIZE)
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/op.glsl"
+    }
+}
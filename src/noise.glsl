@@ -0,0 +1,61 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+/* One height per texel, row by row. */
+layout(set = 0, binding = 0) buffer Heights {
+    float heights[];
+} buf;
+
+layout(push_constant) uniform PushConstants {
+    uint side;
+    uint octaves;
+    float scale;
+    uint seed;
+} pc;
+
+/* The same as `pcg` in random.rs, so the CPU can make the same noise. */
+uint pcg(uint v) {
+    uint state = v * 747796405u + 2891336453u;
+    uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+/* A random value between -1 and 1 for each corner of the lattice, a different lattice for each
+ * octave and seed. */
+float lattice(uvec2 corner, uint octave) {
+    uint hash = pcg(corner.x + pcg(corner.y + pcg(octave + pcg(pc.seed))));
+    return float(hash) / 4294967296.0 * 2.0 - 1.0;
+}
+
+/* Value noise: the values at the four lattice corners around `p`, blended with a smoothstep so
+ * the slope is continuous where one cell meets the next. */
+float value_noise(vec2 p, uint octave) {
+    uvec2 corner = uvec2(floor(p));
+    vec2 t = fract(p);
+    t = t * t * (3.0 - 2.0 * t);
+    float top = mix(lattice(corner, octave), lattice(corner + uvec2(1, 0), octave), t.x);
+    float bottom = mix(lattice(corner + uvec2(0, 1), octave), lattice(corner + uvec2(1, 1), octave), t.x);
+    return mix(top, bottom, t.y);
+}
+
+/* Fractal noise: octaves of value noise, each at twice the frequency and half the amplitude of
+ * the one before, so there are hills with bumps on them with pebbles on those. `scale` is how
+ * many lattice cells the first octave spans across the map. Dividing by the total amplitude
+ * keeps the height between -1 and 1 however many octaves there are. */
+void main() {
+    uvec2 texel = gl_GlobalInvocationID.xy;
+    if (any(greaterThanEqual(texel, uvec2(pc.side)))) {
+        return;
+    }
+
+    vec2 p = vec2(texel) / float(pc.side - 1) * pc.scale;
+    float height = 0.0, amplitude = 1.0, total = 0.0;
+    for (uint octave = 0; octave < pc.octaves; octave++) {
+        height += amplitude * value_noise(p, octave);
+        total += amplitude;
+        p *= 2.0;
+        amplitude *= 0.5;
+    }
+    buf.heights[texel.y * pc.side + texel.x] = height / total;
+}
@@ -0,0 +1,31 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D color_map;
+layout(set = 0, binding = 1) uniform sampler2D normal_map;
+
+layout(push_constant) uniform PushConstants {
+    vec4 light;
+} pc;
+
+void main() {
+    vec3 albedo = texture(color_map, v_uv).rgb;
+
+    /* A normal map stores each component of a unit vector remapped from -1..1 to 0..1, in the
+     * surface's tangent space: x to the right of the image, y towards its top and z out of the
+     * surface. */
+    vec3 tangent_normal = normalize(texture(normal_map, v_uv).rgb * 2.0 - 1.0);
+
+    /* The TBN matrix, whose columns are the tangent, bitangent and normal, takes tangent space
+     * to the space the light is given in: x right, y up and z towards the viewer. Our quad shows
+     * the image upright and facing us, so the two spaces line up and the matrix is the identity.
+     * On a mesh the columns would come from the vertex data instead. */
+    mat3 tbn = mat3(vec3(1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0), vec3(0.0, 0.0, 1.0));
+    vec3 normal = tbn * tangent_normal;
+
+    float diffuse = max(dot(normal, normalize(pc.light.xyz)), 0.0);
+    f_color = vec4(albedo * (0.1 + 0.9 * diffuse), 1.0);
+}
@@ -0,0 +1,146 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, Subpass};
+use vulkano::image::{AttachmentImage, Dimensions, ImageUsage, ImmutableImage};
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::vertex::{BufferlessDefinition, BufferlessVertices};
+use vulkano::sampler::Sampler;
+use vulkano::sync::GpuFuture;
+
+use crate::fullscreen;
+use crate::memory;
+use crate::readback;
+use crate::triangle::{self, SIZE};
+
+/* The light comes from the upper right, in front of the quad. */
+pub const DEFAULT_LIGHT: [f32; 3] = [1.0, 1.0, 1.0];
+
+/* Both textures are a grid of CELLS x CELLS squares, each with a hemispherical bump in the
+ * middle that only exists in the normal map. */
+const TEXTURE_SIZE: u32 = 256;
+const CELLS: u32 = 8;
+const BUMP_RADIUS: f32 = 12.0;
+
+/* A checkerboard to light. */
+fn color_texture() -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let cell = TEXTURE_SIZE / CELLS;
+    ImageBuffer::from_fn(TEXTURE_SIZE, TEXTURE_SIZE, |x, y| {
+        if (x / cell + y / cell).is_multiple_of(2) {
+            Rgba([200, 180, 150, 255])
+        } else {
+            Rgba([120, 140, 170, 255])
+        }
+    })
+}
+
+/* The normals of the bumps. On a hemisphere the normal points straight out from the center, so
+ * it is just the offset from the center scaled to unit length. Image rows run down while tangent
+ * space y runs up, hence the flip. Everywhere else the surface is flat and the normal is +z. */
+fn normal_texture() -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let cell = TEXTURE_SIZE / CELLS;
+    let encode = |n: f32| ((n * 0.5 + 0.5) * 255.0).round() as u8;
+    ImageBuffer::from_fn(TEXTURE_SIZE, TEXTURE_SIZE, |x, y| {
+        let dx = (x % cell) as f32 + 0.5 - cell as f32 / 2.0;
+        let dy = (y % cell) as f32 + 0.5 - cell as f32 / 2.0;
+        let d2 = dx * dx + dy * dy;
+        let r2 = BUMP_RADIUS * BUMP_RADIUS;
+        let normal = if d2 < r2 {
+            [dx / BUMP_RADIUS, -dy / BUMP_RADIUS, (r2 - d2).sqrt() / BUMP_RADIUS]
+        } else {
+            [0.0, 0.0, 1.0]
+        };
+        Rgba([encode(normal[0]), encode(normal[1]), encode(normal[2]), 255])
+    })
+}
+
+/* Puts `pixels` in an image that shaders can sample. */
+pub fn texture(queue: Arc<Queue>, pixels: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Arc<ImmutableImage<Format>> {
+    let dimensions = Dimensions::Dim2d { width: pixels.width(), height: pixels.height() };
+    let (image, upload) = ImmutableImage::from_iter(pixels.iter().cloned(), dimensions, Format::R8G8B8A8Unorm, queue)
+        .expect("Failed to create texture");
+    upload.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+    memory::image(image)
+}
+
+/* Lights a flat quad as if it were covered in bumps. The fragment shader reads the surface color
+ * from one texture and its normal from another, so the shading has detail the geometry doesn't.
+ * The quad is the bufferless fullscreen triangle, and `light` is the direction towards the light
+ * with x right, y up and z towards the viewer. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>, light: [f32; 3]) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let render_pass = triangle::render_pass(device.clone(), Format::R8G8B8A8Unorm);
+
+    let vs = fullscreen::vertex::Shader::load(device.clone()).expect("Failed to create fullscreen vertex shader");
+    let fs = fs::Shader::load(device.clone()).expect("Failed to create normal map shader");
+
+    let pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input(BufferlessDefinition)
+        .vertex_shader(vs.main_entry_point(), ())
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let sampler = Sampler::simple_repeat_linear_no_mipmap(device.clone());
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_sampled_image(texture(queue.clone(), &color_texture()), sampler.clone()).unwrap()
+        .add_sampled_image(texture(queue.clone(), &normal_texture()), sampler).unwrap()
+        .build().unwrap()
+    );
+
+    let usage = ImageUsage { color_attachment: true, transfer_source: true, .. ImageUsage::none() };
+    let image = memory::image(AttachmentImage::with_usage(device.clone(), [SIZE, SIZE], Format::R8G8B8A8Unorm,
+        usage).unwrap());
+    let framebuffer = Arc::new(Framebuffer::start(render_pass)
+        .add(image.clone()).unwrap()
+        .build().unwrap());
+
+    let push_constants = fs::ty::PushConstants { light: [light[0], light[1], light[2], 0.0] };
+
+    AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer, false, vec![triangle::CLEAR_COLOR.into()]).unwrap()
+        .draw(pipeline, &triangle::dynamic_state(), BufferlessVertices { vertices: 3, instances: 1 }, set,
+            push_constants)
+        .unwrap()
+        .end_render_pass().unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    readback::download_rgba8(device, queue, image)
+}
+
+/* Lights the quad from the left and from the right, and checks that each side of a bump is
+ * brighter when the light is on its side. A flat normal map would shade both the same. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let from_left = render(device.clone(), queue.clone(), [-1.0, 0.0, 1.0]);
+    let from_right = render(device, queue, [1.0, 0.0, 1.0]);
+
+    /* Halfway out from the center of a bump, in screen pixels. */
+    let scale = SIZE / TEXTURE_SIZE;
+    let cell = TEXTURE_SIZE / CELLS;
+    let center = (3 * cell + cell / 2) * scale;
+    let offset = (BUMP_RADIUS / 2.0) as u32 * scale;
+    let brightness = |image: &ImageBuffer<Rgba<u8>, Vec<u8>>, x: u32| {
+        let pixel = image.get_pixel(x, center);
+        pixel[0] as u32 + pixel[1] as u32 + pixel[2] as u32
+    };
+
+    let (left, right) = (center - offset, center + offset);
+    assert!(brightness(&from_left, left) > brightness(&from_right, left),
+        "The left side of a bump isn't brighter when lit from the left");
+    assert!(brightness(&from_right, right) > brightness(&from_left, right),
+        "The right side of a bump isn't brighter when lit from the right");
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/normalmap.glsl"
+    }
+}
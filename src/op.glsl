@@ -8,5 +8,8 @@ layout(set = 0, binding = 0) buffer Data {
 
 void main() {
     uint idx = gl_GlobalInvocationID.x;
+    if (idx >= buf.data.length()) {
+        return;
+    }
     buf.data[idx] *= 12;
 }
@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+layout(location = 1) in vec4 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 1, binding = 0) uniform sampler2D atlas;
+
+/* The uv is in atlas texels. A glyph covers its texels the way the atlas says in alpha; a
+ * panel, track or box has a uv below zero and covers everything it is drawn over. */
+void main() {
+    float coverage = texture(atlas, v_uv / vec2(textureSize(atlas, 0))).a;
+    f_color = vec4(v_color.rgb, v_color.a * (v_uv.x < 0.0 ? 1.0 : coverage));
+}
@@ -0,0 +1,392 @@
+use std::sync::Arc;
+use vulkano::buffer::CpuBufferPool;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::DescriptorSet;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::Queue;
+use vulkano::format::{ClearValue, Format};
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass};
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+use winit::dpi::LogicalPosition;
+use winit::{ElementState, MouseButton, WindowEvent};
+
+use crate::frame::{Frame, FrameSets};
+use crate::normalmap;
+use crate::text;
+use crate::triangle;
+use crate::window::Image;
+
+/* Font pixels are drawn SCALE x SCALE window pixels each. Every widget is a ROW tall and the
+ * panel's width, and the panel keeps PADDING pixels between its edge and what is on it. */
+const SCALE: f32 = 2.0;
+const ROW: f32 = 22.0;
+const WIDTH: f32 = 300.0;
+const PADDING: f32 = 8.0;
+
+const PANEL: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const TRACK: [f32; 4] = [0.25, 0.25, 0.3, 0.9];
+const FILL: [f32; 4] = [0.3, 0.45, 0.8, 0.9];
+/* text.frag's color. */
+const TEXT: [f32; 4] = [1.0, 0.8, 0.4, 1.0];
+
+/* A rectangle in window pixels, as its left, top, right and bottom edges. */
+type Rect = [f32; 4];
+
+fn contains(rect: Rect, point: [f32; 2]) -> bool {
+    point[0] >= rect[0] && point[0] < rect[2] && point[1] >= rect[1] && point[1] < rect[3]
+}
+
+/* A corner of a glyph or a rectangle: where it is in window pixels, where it is in the atlas in
+ * texels, below zero for a solid rectangle, and its color. */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UiVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+vulkano::impl_vertex!(UiVertex, position, uv, color);
+
+/* Two triangles covering `rect`, textured with `uv` of the atlas. */
+fn quad(vertices: &mut Vec<UiVertex>, rect: Rect, uv: Rect, color: [f32; 4]) {
+    let corner = |x: usize, y: usize| UiVertex { position: [rect[x], rect[y]], uv: [uv[x], uv[y]], color };
+    let (top_left, top_right) = (corner(0, 1), corner(2, 1));
+    let (bottom_left, bottom_right) = (corner(0, 3), corner(2, 3));
+    vertices.extend_from_slice(&[top_left, top_right, bottom_right, top_left, bottom_right, bottom_left]);
+}
+
+/* What the overlay remembers from one frame to the next: where the mouse is and what its left
+ * button is doing, which slider it is dragging, if any, and where the panel was last drawn.
+ * The window hands it every event, and it keeps what it needs of them until the next frame's
+ * widgets are laid out, which is when a click on one of them counts. */
+#[derive(Debug, Clone, Default)]
+pub struct Input {
+    cursor: [f32; 2],
+    down: bool,
+    pressed: bool,
+    active: Option<usize>,
+    panel: Option<Rect>,
+}
+
+impl Input {
+    /* Takes what the overlay needs from `event`. winit gives the cursor in logical pixels, and
+     * `hidpi` is what makes them the physical ones everything is drawn in. */
+    pub fn event(&mut self, event: &WindowEvent, hidpi: f64) {
+        match *event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let LogicalPosition { x, y } = position;
+                self.cursor = [(x * hidpi) as f32, (y * hidpi) as f32];
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+                self.down = state == ElementState::Pressed;
+                self.pressed |= self.down;
+            }
+            _ => (),
+        }
+    }
+
+    /* Whether the mouse belongs to the overlay rather than the scene under it: it is over the
+     * panel, or dragging one of its sliders off it. */
+    pub fn captured(&self) -> bool {
+        self.active.is_some() || self.panel.is_some_and(|panel| contains(panel, self.cursor))
+    }
+
+    /* Starts laying out this frame's widgets. */
+    pub fn frame(&mut self) -> Ui<'_> {
+        Ui { input: self, next_id: 0, y: PADDING * 2.0, right: PADDING * 2.0 + WIDTH, vertices: Vec::new() }
+    }
+}
+
+/* An immediate mode UI: nothing about a widget is kept besides what `Input` remembers. Each frame
+ * the widgets are laid out again, one below the other in a panel in the top left corner, each
+ * one handling the mouse as it is laid out and changing the value it was handed right away.
+ * A widget is known from one frame to the next by where it comes in the order, which holds as
+ * long as the same widgets are laid out every frame. */
+pub struct Ui<'a> {
+    input: &'a mut Input,
+    next_id: usize,
+    y: f32,
+    /* How far right anything has been drawn, so the panel can be made wide enough for text longer
+     * than a row. */
+    right: f32,
+    vertices: Vec<UiVertex>,
+}
+
+impl<'a> Ui<'a> {
+    /* The next widget's row. */
+    fn row(&mut self) -> (usize, Rect) {
+        let rect = [PADDING * 2.0, self.y, PADDING * 2.0 + WIDTH, self.y + ROW];
+        self.y += ROW + PADDING / 2.0;
+        self.next_id += 1;
+        (self.next_id - 1, rect)
+    }
+
+    /* Whether the left button went down over `rect` since the last frame. A click only counts
+     * for one widget. */
+    fn clicked(&mut self, rect: Rect) -> bool {
+        let clicked = self.input.pressed && self.input.active.is_none() && contains(rect, self.input.cursor);
+        if clicked {
+            self.input.pressed = false;
+        }
+        clicked
+    }
+
+    fn rect(&mut self, rect: Rect, color: [f32; 4]) {
+        quad(&mut self.vertices, rect, [-1.0; 4], color);
+    }
+
+    /* `text` on one line, its top left corner at `x`, `y`, textured from text.rs's atlas the way
+     * text.rs draws it. */
+    fn text(&mut self, x: f32, y: f32, text: &str) {
+        let (placed, width) = text::layout(text);
+        self.right = self.right.max(x + width as f32 * SCALE);
+        let height = text::GLYPH_HEIGHT as u32;
+        for (index, pen) in placed {
+            let width = text::glyph_width(index);
+            let (u, v) = text::cell(index);
+            let left = x + pen as f32 * SCALE;
+            quad(&mut self.vertices, [left, y, left + width as f32 * SCALE, y + height as f32 * SCALE],
+                [u as f32, v as f32, (u + width) as f32, (v + height) as f32], TEXT);
+        }
+    }
+
+    /* Where text goes in `rect`: `indent` pixels in, and halfway down. */
+    fn text_in(&mut self, rect: Rect, indent: f32, text: &str) {
+        let top = rect[1] + (ROW - text::GLYPH_HEIGHT as f32 * SCALE) / 2.0;
+        self.text(rect[0] + indent, top, text);
+    }
+
+    pub fn label(&mut self, text: &str) {
+        let (_, rect) = self.row();
+        self.text_in(rect, 0.0, text);
+    }
+
+    /* A bar filled in as far as `value` is from `min` to `max`, with `label` over it. Pressing
+     * the button on it and dragging sets `value` to wherever across the bar the mouse is, for as
+     * long as the button is held, wherever the mouse goes. Says whether `value` changed. */
+    pub fn slider(&mut self, label: &str, value: &mut f32, min: f32, max: f32) -> bool {
+        let (id, rect) = self.row();
+        if self.clicked(rect) {
+            self.input.active = Some(id);
+        }
+        let mut changed = false;
+        if self.input.active == Some(id) {
+            let along = ((self.input.cursor[0] - rect[0]) / (rect[2] - rect[0])).clamp(0.0, 1.0);
+            let dragged = min + along * (max - min);
+            changed = dragged != *value;
+            *value = dragged;
+        }
+
+        let filled = ((*value - min) / (max - min)).clamp(0.0, 1.0);
+        self.rect(rect, TRACK);
+        self.rect([rect[0], rect[1], rect[0] + filled * (rect[2] - rect[0]), rect[3]], FILL);
+        self.text_in(rect, PADDING / 2.0, label);
+        changed
+    }
+
+    /* A box, filled in when `value` is set, and `label` next to it. Clicking either flips it.
+     * Says whether it did. */
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let (_, rect) = self.row();
+        let clicked = self.clicked(rect);
+        if clicked {
+            *value = !*value;
+        }
+
+        let inset = PADDING / 2.0;
+        let square = [rect[0], rect[1], rect[0] + ROW, rect[3]];
+        self.rect(square, TRACK);
+        if *value {
+            self.rect([square[0] + inset, square[1] + inset, square[2] - inset, square[3] - inset], FILL);
+        }
+        self.text_in(rect, ROW + PADDING, label);
+        clicked
+    }
+
+    /* `label` on a button. Says whether it was clicked. */
+    pub fn button(&mut self, label: &str) -> bool {
+        let (_, rect) = self.row();
+        let clicked = self.clicked(rect);
+        self.rect(rect, if contains(rect, self.input.cursor) { FILL } else { TRACK });
+        self.text_in(rect, PADDING / 2.0, label);
+        clicked
+    }
+
+    /* The frame's widgets on their panel, for `Overlay::record`. A press that missed every
+     * widget is forgotten, and a slider being dragged lets go once the button has. */
+    pub fn finish(self) -> Vec<UiVertex> {
+        let panel = [PADDING, PADDING, self.right + PADDING, self.y + PADDING / 2.0];
+        self.input.panel = Some(panel);
+        self.input.pressed = false;
+        if !self.input.down {
+            self.input.active = None;
+        }
+
+        let mut vertices = Vec::with_capacity(self.vertices.len() + 6);
+        quad(&mut vertices, panel, [-1.0; 4], PANEL);
+        vertices.extend(self.vertices);
+        vertices
+    }
+}
+
+/* Draws a frame's widgets over whatever the scene left in the swapchain image. It is a render
+ * pass of its own after the scene's rather than a second subpass of it, since not every scene
+ * draws through a render pass: the mandelbrot is blitted. Loading the image keeps what is there,
+ * and alpha blending puts the panel and the text over it. The resolution the vertex shader needs
+ * comes in the frame uniform every scene is handed, from a pool of sets recycled the way
+ * `FrameSets` does for the offscreen loops, and the vertices from a pool of buffers that does the
+ * same for them. */
+pub struct Overlay {
+    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+    frames: FrameSets<Arc<dyn GraphicsPipelineAbstract + Send + Sync>>,
+    atlas: Arc<dyn DescriptorSet + Send + Sync>,
+    vertices: CpuBufferPool<UiVertex>,
+    framebuffers: Vec<Arc<dyn FramebufferAbstract + Send + Sync>>,
+    dynamic_state: DynamicState,
+}
+
+impl Overlay {
+    pub fn new(queue: Arc<Queue>, format: Format, images: &[Image]) -> Overlay {
+        let device = queue.device().clone();
+        let render_pass: Arc<dyn RenderPassAbstract + Send + Sync> = Arc::new(vulkano::single_pass_renderpass!(
+            device.clone(),
+            attachments: {
+                color: {
+                    load: Load,
+                    store: Store,
+                    format: format,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        ).unwrap());
+
+        let vs = vs::Shader::load(device.clone()).expect("Failed to create overlay vertex shader");
+        let fs = fs::Shader::load(device.clone()).expect("Failed to create overlay fragment shader");
+        let pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync> = Arc::new(GraphicsPipeline::start()
+            .vertex_input_single_buffer::<UiVertex>()
+            .vertex_shader(vs.main_entry_point(), ())
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .blend_alpha_blending()
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone())
+            .unwrap());
+
+        let sampler = Sampler::new(device.clone(), Filter::Nearest, Filter::Nearest, MipmapMode::Nearest,
+            SamplerAddressMode::ClampToEdge, SamplerAddressMode::ClampToEdge, SamplerAddressMode::ClampToEdge,
+            0.0, 1.0, 0.0, 0.0).unwrap();
+        let atlas = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 1)
+            .add_sampled_image(normalmap::texture(queue.clone(), &text::atlas()), sampler).unwrap()
+            .build().unwrap());
+
+        let mut overlay = Overlay {
+            render_pass,
+            pipeline: pipeline.clone(),
+            frames: FrameSets::new(device.clone(), pipeline),
+            atlas,
+            vertices: CpuBufferPool::vertex_buffer(device),
+            framebuffers: Vec::new(),
+            dynamic_state: DynamicState::none(),
+        };
+        overlay.resize(images);
+        overlay
+    }
+
+    pub fn resize(&mut self, images: &[Image]) {
+        let [width, height] = images[0].dimensions();
+        self.dynamic_state = triangle::sized_dynamic_state(width, height);
+        self.framebuffers = images.iter().map(|image| {
+            Arc::new(Framebuffer::start(self.render_pass.clone()).add(image.clone()).unwrap().build().unwrap())
+                as Arc<dyn FramebufferAbstract + Send + Sync>
+        }).collect();
+    }
+
+    /* Adds drawing `vertices` over image `index` to `builder`. */
+    pub fn record(&mut self, builder: AutoCommandBufferBuilder, index: usize, frame: Frame, vertices: Vec<UiVertex>)
+        -> AutoCommandBufferBuilder
+    {
+        if vertices.is_empty() {
+            return builder;
+        }
+        let frame = self.frames.next(frame);
+        let vertices = Arc::new(self.vertices.chunk(vertices).expect("Failed to allocate vertex buffer"));
+        builder
+            .begin_render_pass(self.framebuffers[index].clone(), false, vec![ClearValue::None]).unwrap()
+            .draw(self.pipeline.clone(), &self.dynamic_state, vec![vertices], (frame, self.atlas.clone()), ()).unwrap()
+            .end_render_pass().unwrap()
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/overlay.vert"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/overlay.frag"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn click(input: &mut Input, at: [f32; 2]) {
+        input.cursor = at;
+        input.down = true;
+        input.pressed = true;
+    }
+
+    #[test]
+    fn sliders_follow_the_mouse_until_it_lets_go() {
+        let mut input = Input::default();
+        let mut value = 0.0;
+        let middle = [PADDING * 2.0 + WIDTH / 2.0, PADDING * 2.0 + ROW / 2.0];
+        click(&mut input, middle);
+        let mut ui = input.frame();
+        assert!(ui.slider("", &mut value, 0.0, 10.0));
+        ui.finish();
+        assert!((value - 5.0).abs() < 1e-4 && input.captured());
+
+        /* Dragged off the end and below the panel, it stays the slider's. */
+        input.cursor = [PADDING * 4.0 + WIDTH, 500.0];
+        let mut ui = input.frame();
+        ui.slider("", &mut value, 0.0, 10.0);
+        ui.finish();
+        assert!(value == 10.0 && input.captured());
+
+        input.down = false;
+        let mut ui = input.frame();
+        assert!(!ui.slider("", &mut value, 0.0, 10.0));
+        ui.finish();
+        assert!(value == 10.0 && !input.captured());
+    }
+
+    #[test]
+    fn a_click_flips_only_the_checkbox_under_it() {
+        let mut input = Input::default();
+        let (mut first, mut second) = (false, false);
+        click(&mut input, [PADDING * 2.0 + 1.0, PADDING * 2.0 + ROW + PADDING + 1.0]);
+        let mut ui = input.frame();
+        assert!(!ui.checkbox("", &mut first));
+        assert!(ui.checkbox("", &mut second));
+        ui.finish();
+        assert!(!first && second);
+
+        /* Holding the button down isn't another click. */
+        let mut ui = input.frame();
+        ui.checkbox("", &mut first);
+        ui.checkbox("", &mut second);
+        ui.finish();
+        assert!(!first && second);
+    }
+}
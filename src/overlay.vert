@@ -0,0 +1,21 @@
+#version 450
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 uv;
+layout(location = 2) in vec4 color;
+
+layout(location = 0) out vec2 v_uv;
+layout(location = 1) out vec4 v_color;
+
+layout(set = 0, binding = 0) uniform Frame {
+    vec2 resolution;
+    float time;
+} frame;
+
+/* Positions are in pixels from the top left of the window, whatever size it is now, so the
+ * frame's resolution is what turns them into clip space. */
+void main() {
+    gl_Position = vec4(position / frame.resolution * 2.0 - 1.0, 0.0, 1.0);
+    v_uv = uv;
+    v_color = color;
+}
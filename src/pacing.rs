@@ -0,0 +1,266 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use vulkano::buffer::BufferUsage;
+use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder, CommandBuffer, CommandBufferExecFuture};
+use vulkano::command_buffer::pool::standard::StandardCommandPoolAlloc;
+use vulkano::command_buffer::submit::SubmitCommandBufferBuilder;
+use vulkano::command_buffer::sys::{Flags, Kind, UnsafeCommandBuffer, UnsafeCommandBufferBuilder};
+use vulkano::device::{Device, Queue};
+use vulkano::query::{QueryType, UnsafeQueryPool};
+use vulkano::sync::{Fence, FenceSignalFuture, GpuFuture, NowFuture, PipelineStages};
+
+use crate::batch;
+use crate::memory;
+
+/* How many frames a `FramePool` can keep in flight. One is the loop that waits for every frame
+ * before starting the next, there to compare with. */
+pub const MIN_FRAMES: usize = 1;
+pub const MAX_FRAMES: usize = 3;
+
+/* What `--in-flight` defaults to. */
+pub const DEFAULT_FRAMES: usize = 2;
+
+/* How many frames' timestamps one query pool has room for, a pair each. */
+const POOL_FRAMES: u32 = 256;
+
+type InFlight = FenceSignalFuture<CommandBufferExecFuture<NowFuture, AutoCommandBuffer>>;
+type Timestamps = (UnsafeCommandBuffer<StandardCommandPoolAlloc>, UnsafeCommandBuffer<StandardCommandPoolAlloc>);
+
+/* Everything a frame in flight holds on to until the GPU is done with it: the future of its
+ * commands, whose fence and command buffer go back to vulkano's pools once it is dropped, and
+ * the two command buffers writing the timestamps either side of it, the second of which signals
+ * a fence of the context's own that is reset and signalled again by every frame it holds. */
+struct FrameContext {
+    frame: Option<u32>,
+    future: Option<InFlight>,
+    timestamps: Option<Timestamps>,
+    fence: Fence,
+}
+
+/* How a run of frames went: how long it took, how much of that the CPU spent waiting for a
+ * frame to be done with before it could start another, and, where the queue can write
+ * timestamps, how long the GPU took over each frame. A CPU that waits most of the time is ahead
+ * of the GPU, and more frames in flight won't help; one that hardly waits had the GPU idle
+ * between frames before there were any in flight. */
+#[derive(Debug, Clone)]
+pub struct PacingStats {
+    pub frames: u32,
+    pub in_flight: usize,
+    pub elapsed: Duration,
+    pub cpu_wait: Duration,
+    pub gpu_ms: Option<Vec<f64>>,
+}
+
+impl PacingStats {
+    /* One line of the above in milliseconds a frame. */
+    pub fn summary(&self) -> String {
+        let per_frame = |d: Duration| d.as_secs_f64() * 1000.0 / self.frames.max(1) as f64;
+        let gpu = match self.gpu_ms {
+            Some(ref ms) => format!("{:.2} ms", ms.iter().sum::<f64>() / ms.len().max(1) as f64),
+            None => "an unknown time, the queue can't write timestamps,".to_string(),
+        };
+        format!("{} frame{} in flight: {:.2} ms a frame, of which the CPU waited {:.2} ms, and the GPU took {} a frame",
+            self.in_flight, if self.in_flight == 1 { "" } else { "s" }, per_frame(self.elapsed),
+            per_frame(self.cpu_wait), gpu)
+    }
+}
+
+/* Keeps up to `frames` frames in flight at once, so the CPU records the next frame while the
+ * GPU draws the last, instead of submitting each with `then_signal_fence_and_flush().wait()`
+ * and sitting idle until it is done. Each frame goes in the next of `frames` contexts round and
+ * round, and `begin` only waits when the context it needs still holds a frame the GPU hasn't
+ * finished: the oldest in flight, since they finish in the order they were submitted. Whatever
+ * was drawn into for that frame can then be read back and used again, so a loop needs as many
+ * of its targets and buffers as there are contexts, indexed by the slot `begin` hands back.
+ *
+ * Fences, semaphores and command buffers are recycled rather than made anew every frame:
+ * vulkano takes them back into its pools once the futures holding them are dropped, which
+ * `begin` does as soon as each frame is done, calling `cleanup_finished` on the others on the
+ * way so that ones already done let go of theirs without being waited for. */
+pub struct FramePool {
+    queue: Arc<Queue>,
+    contexts: Vec<FrameContext>,
+    next: u32,
+    timer: Option<(Vec<UnsafeQueryPool>, u32, f64)>,
+    start: Instant,
+    cpu_wait: Duration,
+}
+
+impl FramePool {
+    pub fn new(device: Arc<Device>, queue: Arc<Queue>, frames: usize) -> FramePool {
+        assert!((MIN_FRAMES..=MAX_FRAMES).contains(&frames), "Can't keep {} frames in flight, only {} to {}",
+            frames, MIN_FRAMES, MAX_FRAMES);
+        let contexts = (0..frames).map(|_| FrameContext {
+            frame: None,
+            future: None,
+            timestamps: None,
+            fence: Fence::alloc(device.clone()).unwrap(),
+        }).collect();
+
+        /* The results come back as 32 bit values, of which only the valid bits count. */
+        let timer = queue.family().timestamp_valid_bits().map(|bits| {
+            let mask = if bits >= 32 { u32::MAX } else { (1 << bits) - 1 };
+            (Vec::new(), mask, device.physical_device().limits().timestamp_period() as f64)
+        });
+        FramePool { queue, contexts, next: 0, timer, start: Instant::now(), cpu_wait: Duration::default() }
+    }
+
+    /* Waits until the context for the next frame is free and hands back its slot, along with the
+     * frame it held, now finished, if it held one. */
+    pub fn begin(&mut self) -> (usize, Option<u32>) {
+        for context in &mut self.contexts {
+            if let Some(ref mut future) = context.future {
+                future.cleanup_finished();
+            }
+        }
+        let slot = self.next as usize % self.contexts.len();
+        (slot, self.wait(slot))
+    }
+
+    /* Submits the commands of the frame `begin` made room for in `slot`, without waiting. */
+    pub fn submit(&mut self, slot: usize, command_buffer: AutoCommandBuffer) {
+        assert!(slot == self.next as usize % self.contexts.len() && self.contexts[slot].frame.is_none(),
+            "Frame {} was submitted to slot {} without beginning it", self.next, slot);
+        let frame = self.next;
+        self.next += 1;
+
+        let begin = self.timestamp(frame, 0);
+        let future = command_buffer.execute(self.queue.clone()).unwrap().then_signal_fence_and_flush().unwrap();
+        let end = self.timestamp(frame, 1);
+
+        let context = &mut self.contexts[slot];
+        context.frame = Some(frame);
+        context.future = Some(future);
+        if let (Some(begin), Some(end)) = (begin, end) {
+            unsafe {
+                let mut submit = SubmitCommandBufferBuilder::new();
+                submit.add_command_buffer(&end);
+                submit.set_fence_signal(&context.fence);
+                submit.submit(&self.queue).unwrap();
+            }
+            context.timestamps = Some((begin, end));
+        }
+    }
+
+    /* Waits for every frame still in flight, oldest first, handing each one's slot and frame to
+     * `done`, and says how the run went. */
+    pub fn finish<F: FnMut(usize, u32)>(mut self, mut done: F) -> PacingStats {
+        for i in 0..self.contexts.len() {
+            let slot = (self.next as usize + i) % self.contexts.len();
+            if let Some(frame) = self.wait(slot) {
+                done(slot, frame);
+            }
+        }
+        let elapsed = self.start.elapsed();
+
+        /* Every timestamp has been waited for, so their results are there to copy. */
+        let device = self.queue.device().clone();
+        let frames = self.next;
+        let gpu_ms = self.timer.take().map(|(pools, mask, period)| {
+            pools.iter().enumerate().flat_map(|(i, pool)| {
+                let count = (frames - i as u32 * POOL_FRAMES).min(POOL_FRAMES);
+                let results = memory::readback::<u32>(&self.queue, count as usize * 2)
+                    .expect("Failed to create buffer");
+                batch::submit_unsafe(device.clone(), &self.queue, |builder| unsafe {
+                    builder.copy_query_pool_results(pool.queries_range(0, count * 2).unwrap(), &*results, 4);
+                });
+                let content = results.read().unwrap();
+                content.chunks(2).map(|pair| (pair[1].wrapping_sub(pair[0]) & mask) as f64 * period / 1_000_000.0)
+                    .collect::<Vec<_>>()
+            }).collect()
+        });
+        PacingStats { frames, in_flight: self.contexts.len(), elapsed, cpu_wait: self.cpu_wait, gpu_ms }
+    }
+
+    /* Waits for the frame in `slot`, if there is one, and lets go of what it held. */
+    fn wait(&mut self, slot: usize) -> Option<u32> {
+        let start = Instant::now();
+        let context = &mut self.contexts[slot];
+        if let Some(future) = context.future.take() {
+            future.wait(None).unwrap();
+        }
+        if context.timestamps.take().is_some() {
+            context.fence.wait(None).unwrap();
+            context.fence.reset().unwrap();
+        }
+        self.cpu_wait += start.elapsed();
+        context.frame.take()
+    }
+
+    /* Records the command buffer writing timestamp `which`, 0 before and 1 after, of `frame`,
+     * submitting the one before straight away. The first frame of each pool resets it all. */
+    fn timestamp(&mut self, frame: u32, which: u32) -> Option<UnsafeCommandBuffer<StandardCommandPoolAlloc>> {
+        let device = self.queue.device().clone();
+        let (pools, _, _) = self.timer.as_mut()?;
+        let index = frame % POOL_FRAMES;
+        if index == 0 && which == 0 {
+            pools.push(UnsafeQueryPool::new(device.clone(), QueryType::Timestamp, POOL_FRAMES * 2).unwrap());
+        }
+        let pool = pools.last().unwrap();
+        let stages = PipelineStages { bottom_of_pipe: true, .. PipelineStages::none() };
+        let command_pool = Device::standard_command_pool(&device, self.queue.family());
+        let command_buffer = unsafe {
+            let mut builder = UnsafeCommandBufferBuilder::new(&command_pool, Kind::primary(), Flags::OneTimeSubmit)
+                .unwrap();
+            if index == 0 && which == 0 {
+                builder.reset_query_pool(pool.queries_range(0, POOL_FRAMES * 2).unwrap());
+            }
+            builder.write_timestamp(pool.query(index * 2 + which).unwrap(), stages);
+            builder.build().unwrap()
+        };
+        if which == 0 {
+            unsafe {
+                let mut submit = SubmitCommandBufferBuilder::new();
+                submit.add_command_buffer(&command_buffer);
+                submit.submit(&self.queue).unwrap();
+            }
+        }
+        Some(command_buffer)
+    }
+}
+
+/* Runs frames that each fill a buffer of their slot with their own number, with every number
+ * of frames in flight, and checks each frame is handed back once, in order, from the slot it
+ * was drawn in and with its own number still in that slot's buffer: a slot reused before its
+ * frame was done would hold the next one's. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let frames = 7;
+    for in_flight in MIN_FRAMES..=MAX_FRAMES {
+        let buffers: Vec<_> = (0..in_flight)
+            .map(|_| {
+                let buffer = memory::device_local::<u32>(&queue, BufferUsage::none(), 1024)
+                    .expect("Failed to create buffer");
+                (buffer, memory::readback(&queue, 1024).expect("Failed to create buffer"))
+            })
+            .collect();
+        let mut finished = Vec::new();
+        let mut check = |slot: usize, frame: u32| {
+            assert!(slot == frame as usize % in_flight, "Frame {} came back from slot {}", frame, slot);
+            let content = buffers[slot].1.read().unwrap();
+            assert!(content.iter().all(|&n| n == frame), "Slot {} doesn't hold frame {}", slot, frame);
+            finished.push(frame);
+        };
+
+        let mut pool = FramePool::new(device.clone(), queue.clone(), in_flight);
+        for frame in 0..frames {
+            let (slot, done) = pool.begin();
+            if let Some(done) = done {
+                check(slot, done);
+            }
+            let (ref buffer, ref readback) = buffers[slot];
+            let builder = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap()
+                .fill_buffer(buffer.clone(), frame).unwrap()
+                .copy_buffer(buffer.clone(), readback.clone()).unwrap();
+            pool.submit(slot, builder.build().unwrap());
+        }
+        let stats = pool.finish(&mut check);
+
+        assert!(finished.iter().cloned().eq(0..frames), "{} in flight finished {:?}", in_flight, finished);
+        assert!(stats.frames == frames && stats.in_flight == in_flight, "The stats count {} frames in {}",
+            stats.frames, stats.in_flight);
+        if let Some(ref gpu_ms) = stats.gpu_ms {
+            assert!(gpu_ms.len() == frames as usize && gpu_ms.iter().all(|ms| (0.0..1000.0).contains(ms)),
+                "The frames' GPU times are {:?}", gpu_ms);
+        }
+    }
+}
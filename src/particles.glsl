@@ -0,0 +1,36 @@
+#version 450
+
+layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
+
+/* Matches `Particle` in particles.rs. Under std430 it is packed, four floats per particle, which
+ * is also how the vertex input reads it. */
+struct Particle {
+    vec2 position;
+    vec2 velocity;
+};
+
+layout(set = 0, binding = 0) buffer Particles {
+    Particle particles[];
+} buf;
+
+layout(push_constant) uniform PushConstants {
+    uint count;
+    float dt;
+    float gravity;
+    float damping;
+} pc;
+
+/* One step of the simulation: every particle is pulled towards the center, harder the further
+ * out it is, and slowed a little so the swirl settles over time. */
+void main() {
+    uint idx = gl_GlobalInvocationID.x;
+    if (idx >= pc.count) {
+        return;
+    }
+
+    Particle p = buf.particles[idx];
+    vec2 acceleration = -pc.gravity * p.position;
+    p.velocity = (p.velocity + acceleration * pc.dt) * pc.damping;
+    p.position += p.velocity * pc.dt;
+    buf.particles[idx] = p;
+}
@@ -0,0 +1,268 @@
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, DeviceLocalBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::framebuffer::{Framebuffer, RenderPassAbstract, Subpass};
+use vulkano::image::{AttachmentImage, ImageUsage};
+use vulkano::pipeline::{ComputePipeline, GraphicsPipeline};
+use vulkano::pipeline::blend::{AttachmentBlend, BlendFactor, BlendOp};
+use vulkano::sync::GpuFuture;
+
+use crate::batch;
+use crate::memory;
+use crate::random;
+use crate::readback;
+use crate::triangle::{self, SIZE};
+
+/* Must match the local size in particles.glsl. */
+const LOCAL_SIZE: u32 = 64;
+
+/* What `--frames` defaults to with `--particles`. */
+pub const DEFAULT_FRAMES: u32 = 120;
+
+/* The simulation's constants, in clip space units and seconds. */
+const DT: f32 = 1.0 / 60.0;
+const GRAVITY: f32 = 4.0;
+const DAMPING: f32 = 0.998;
+
+/* The layout particles.glsl expects, and the vertex the point shader reads. */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Particle {
+    pub position: [f32; 2],
+    pub velocity: [f32; 2],
+}
+vulkano::impl_vertex!(Particle, position, velocity);
+
+/* `count` particles scattered over a disc, each moving around the center fast enough to nearly
+ * orbit it, so the gravity turns them into a swirl rather than a collapse. The same every run
+ * with the same `seed`. */
+pub fn initial(count: u32, seed: u32) -> Vec<Particle> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64 ^ u64::from(random::pcg(seed));
+    let mut random = move || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 40) as f32 / (1u64 << 24) as f32
+    };
+
+    (0..count).map(|_| {
+        let radius = 0.2 + 0.7 * random();
+        let angle = random() * std::f32::consts::PI * 2.0;
+        let (sin, cos) = angle.sin_cos();
+        let speed = radius * GRAVITY.sqrt() * (0.8 + 0.4 * random());
+        Particle { position: [radius * cos, radius * sin], velocity: [-speed * sin, speed * cos] }
+    }).collect()
+}
+
+/* The step particles.glsl takes, on the CPU. */
+fn step(particle: &mut Particle) {
+    for axis in 0..2 {
+        let acceleration = -GRAVITY * particle.position[axis];
+        particle.velocity[axis] = (particle.velocity[axis] + acceleration * DT) * DAMPING;
+        particle.position[axis] += particle.velocity[axis] * DT;
+    }
+}
+
+/* What the particles are drawn into. `Frames` clears an RGBA8 image for every frame, like a
+ * film camera. `Exposure` keeps one float image for the whole run and adds each frame on top,
+ * like a long exposure: pixels the particles keep passing through sum to well over one, which
+ * RGBA8 would clamp, so it is only worth keeping in a format like .hdr. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Film {
+    Frames,
+    Exposure,
+}
+
+/* A render pass that keeps what is already in the image instead of clearing it, so frames
+ * can be added on top of each other. */
+fn exposure_render_pass(device: Arc<Device>) -> Arc<dyn RenderPassAbstract + Send + Sync> {
+    Arc::new(vulkano::single_pass_renderpass!(device,
+        attachments: {
+            color: {
+                load: Load,
+                store: Store,
+                format: Format::R32G32B32A32Sfloat,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {}
+        }
+    ).unwrap())
+}
+
+/* Adds what the fragment shader writes to what is there, alpha included, so the alpha of each
+ * pixel counts the particles drawn into it. Blending 32 bit floats is optional in Vulkan, but
+ * every desktop GPU supports it. */
+fn additive() -> AttachmentBlend {
+    AttachmentBlend {
+        enabled: true,
+        color_op: BlendOp::Add,
+        color_source: BlendFactor::One,
+        color_destination: BlendFactor::One,
+        alpha_op: BlendOp::Add,
+        alpha_source: BlendFactor::One,
+        alpha_destination: BlendFactor::One,
+        .. AttachmentBlend::pass_through()
+    }
+}
+
+/* A buffer of particles that a compute shader moves and a graphics pipeline draws as points,
+ * without the data ever leaving the GPU in between. The buffer is bound to the compute pipeline
+ * as a storage buffer and to the graphics pipeline as a vertex buffer, and since both commands
+ * go in the same command buffer vulkano puts a barrier between the shader writing the particles
+ * and the vertex input reading them. */
+struct Simulation {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    particles: Arc<DeviceLocalBuffer<[Particle]>>,
+    count: u32,
+}
+
+impl Simulation {
+    fn new(device: Arc<Device>, queue: Arc<Queue>, count: u32, seed: u32) -> Simulation {
+        let usage = BufferUsage { vertex_buffer: true, .. memory::storage_buffer() };
+        let particles = memory::upload_to_device_local(queue.clone(), usage, initial(count, seed))
+            .expect("Failed to create buffer");
+        Simulation { device, queue, particles, count }
+    }
+
+    /* Steps the simulation `frames` times, drawing each step onto `film` and calling `frame`
+     * with the image after each one. */
+    fn run<F: FnMut(u32, Arc<AttachmentImage<Format>>)>(&self, frames: u32, film: Film, mut frame: F) {
+        let device = self.device.clone();
+
+        let shader = cs::Shader::load(device.clone()).expect("Failed to create particle shader");
+        let compute = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+        let set = Arc::new(PersistentDescriptorSet::start(compute.clone(), 0)
+            .add_buffer(self.particles.clone()).unwrap()
+            .build().unwrap());
+
+        let (format, render_pass, blend, clear) = match film {
+            Film::Frames => (Format::R8G8B8A8Unorm, triangle::render_pass(device.clone(), Format::R8G8B8A8Unorm),
+                AttachmentBlend::pass_through(), triangle::CLEAR_COLOR.into()),
+            Film::Exposure => (Format::R32G32B32A32Sfloat, exposure_render_pass(device.clone()), additive(),
+                ClearValue::None),
+        };
+        let vs = vs::Shader::load(device.clone()).expect("Failed to create point vertex shader");
+        let fs = fs::Shader::load(device.clone()).expect("Failed to create point fragment shader");
+        let graphics = Arc::new(GraphicsPipeline::start()
+            .vertex_input_single_buffer::<Particle>()
+            .vertex_shader(vs.main_entry_point(), ())
+            .point_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .blend_collective(blend)
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone())
+            .unwrap());
+
+        let usage = ImageUsage { color_attachment: true, transfer_source: true, transfer_destination: true,
+            .. ImageUsage::none() };
+        let image = memory::image(AttachmentImage::with_usage(device.clone(), [SIZE, SIZE], format, usage).unwrap());
+        if film == Film::Exposure {
+            batch::submit_and_wait(device.clone(), self.queue.clone(), |builder| builder
+                .clear_color_image(image.clone(), ClearValue::Float([0.0; 4])).unwrap());
+        }
+        let framebuffer = Arc::new(Framebuffer::start(render_pass)
+            .add(image.clone()).unwrap()
+            .build().unwrap());
+
+        let push_constants = cs::ty::PushConstants { count: self.count, dt: DT, gravity: GRAVITY, damping: DAMPING };
+        let groups = [self.count.div_ceil(LOCAL_SIZE), 1, 1];
+
+        for index in 0..frames {
+            AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), self.queue.family()).unwrap()
+                .dispatch(groups, compute.clone(), set.clone(), push_constants).unwrap()
+                .begin_render_pass(framebuffer.clone(), false, vec![clear]).unwrap()
+                .draw(graphics.clone(), &triangle::dynamic_state(), self.particles.clone(), (), ()).unwrap()
+                .end_render_pass().unwrap()
+                .build().unwrap()
+                .execute(self.queue.clone()).unwrap()
+                .then_signal_fence_and_flush().unwrap()
+                .wait(None).unwrap();
+
+            frame(index, image.clone());
+        }
+    }
+}
+
+/* Simulates `count` particles for `frames` frames and saves each as particles_NNNN.png. */
+pub fn animate(device: Arc<Device>, queue: Arc<Queue>, count: u32, frames: u32, seed: u32) {
+    let simulation = Simulation::new(device.clone(), queue.clone(), count, seed);
+    simulation.run(frames, Film::Frames, |index, image| {
+        let path = format!("particles_{:04}.png", index);
+        readback::download_rgba8(device.clone(), queue.clone(), image).save(&path).unwrap();
+        println!("Wrote {}", path);
+    });
+}
+
+/* Simulates `count` particles for `frames` frames as one long exposure, and returns the SIZE x
+ * SIZE float image of all of them added up as RGBA, row by row. */
+pub fn expose(device: Arc<Device>, queue: Arc<Queue>, count: u32, frames: u32, seed: u32) -> Vec<f32> {
+    let mut last = None;
+    let simulation = Simulation::new(device.clone(), queue.clone(), count, seed);
+    simulation.run(frames, Film::Exposure, |_, image| last = Some(image));
+    readback::download_rgba32f(device, queue, last.expect("Exposed no frames"))
+}
+
+/* Runs a few steps on the GPU and the same steps on the CPU, and checks that the particles end
+ * up in the same places and that the last frame drew some of them. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let (count, frames) = (1000, 30);
+    let simulation = Simulation::new(device.clone(), queue.clone(), count, random::DEFAULT_SEED);
+
+    let mut last = None;
+    simulation.run(frames, Film::Frames, |_, image| last = Some(image));
+
+    let mut expected = initial(count, random::DEFAULT_SEED);
+    for _ in 0..frames {
+        expected.iter_mut().for_each(step);
+    }
+
+    let gpu = memory::download_from_device_local(queue.clone(), simulation.particles.clone())
+        .expect("Failed to create buffer");
+    for (i, (gpu, cpu)) in gpu.iter().zip(&expected).enumerate() {
+        let off = (0..2).any(|axis| (gpu.position[axis] - cpu.position[axis]).abs() > 1e-3);
+        assert!(!off, "Particle {} is at {:?} on the GPU but {:?} on the CPU", i, gpu.position, cpu.position);
+    }
+
+    let last = readback::download_rgba8(device.clone(), queue.clone(), last.unwrap());
+    let lit = last.pixels().filter(|pixel| pixel[3] > 0).count();
+    assert!(lit > 0, "No particles were drawn");
+
+    /* In the long exposure each particle adds one to the alpha of the pixel it is drawn into
+     * every frame, so the alphas total how many were drawn on screen: whole numbers, at most one
+     * per particle per frame. The ones moving fastest swing out past the edge for a while, but
+     * most are always in view. Some pixels get hit more than once, which is the point of a
+     * float image. */
+    let exposure = expose(device, queue, count, frames, random::DEFAULT_SEED);
+    let alphas: Vec<f32> = exposure.chunks(4).map(|pixel| pixel[3]).collect();
+    let total = alphas.iter().sum::<f32>();
+    let drawn = (count * frames) as f32;
+    assert!(total.fract() == 0.0 && total <= drawn && total >= drawn / 2.0,
+        "The exposure drew {} particles, expected at most {}", total, drawn);
+    assert!(alphas.iter().cloned().fold(0.0, f32::max) > 1.0, "No pixel was drawn into more than once");
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/particles.glsl"
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/point.glsl"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/speed.glsl"
+    }
+}
@@ -0,0 +1,27 @@
+#version 450
+
+/* The positions descent.glsl wrote, bound as a storage buffer and fetched by index. */
+layout(set = 0, binding = 0) readonly buffer Paths {
+    vec2 paths[];
+} buf;
+
+layout(push_constant) uniform PushConstants {
+    uint count;
+    uint steps;
+} pc;
+
+layout(location = 0) out float progress;
+
+/* Must match descent.rs. */
+const float EXTENT = 5.0;
+
+/* One point per particle per step. The positions are stored step by step, so the points are
+ * drawn in that order too and the last step, where the particles came to rest, ends up on
+ * top of the paths that led there. */
+void main() {
+    vec2 p = buf.paths[gl_VertexIndex];
+    gl_Position = vec4(p.x / EXTENT, -p.y / EXTENT, 0.0, 1.0);
+    /* Anything bigger needs the large points feature, which the device isn't created with. */
+    gl_PointSize = 1.0;
+    progress = float(gl_VertexIndex / pc.count) / float(max(pc.steps, 1));
+}
@@ -0,0 +1,50 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::Dimensions;
+use vulkano::pipeline::ComputePipeline;
+use vulkano::sync::GpuFuture;
+
+use crate::memory;
+use crate::readback;
+use crate::storage;
+
+/* Must match the local size in uv.glsl. */
+const LOCAL_SIZE: u32 = 8;
+
+/* Renders the UV test pattern, whose pixels are exact and so make a good regression check. */
+pub fn uv(device: Arc<Device>, queue: Arc<Queue>, width: u32, height: u32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let shader = cs::Shader::load(device.clone()).expect("Could not load uv shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let image = storage::image(&queue, Dimensions::Dim2d { width, height }, Format::R8G8B8A8Unorm);
+
+    let pixels = (width * height) as usize * readback::RGBA8_BYTES;
+    let buf = memory::readback::<u8>(&queue, pixels).expect("Failed to create buffer");
+
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_image(image.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = [width.div_ceil(LOCAL_SIZE), height.div_ceil(LOCAL_SIZE), 1];
+    AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .dispatch(groups, pipeline, set, ()).unwrap()
+        .copy_image_to_buffer(image, buf.clone()).unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    let buffer_content = buf.read().unwrap();
+    readback::rgba8_image(width, height, width as usize * readback::RGBA8_BYTES, &buffer_content)
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/uv.glsl"
+    }
+}
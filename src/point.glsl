@@ -0,0 +1,13 @@
+#version 450
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 velocity;
+
+layout(location = 0) out float speed;
+
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+    /* Anything bigger needs the large points feature, which the device isn't created with. */
+    gl_PointSize = 1.0;
+    speed = length(velocity);
+}
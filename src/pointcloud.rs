@@ -0,0 +1,216 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::buffer::{DeviceLocalBuffer, TypedBufferAccess};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::DescriptorSet;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::descriptor::pipeline_layout::PipelineLayoutAbstract;
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass};
+use vulkano::image::{AttachmentImage, ImageUsage};
+use vulkano::pipeline::{ComputePipeline, GraphicsPipeline};
+use vulkano::pipeline::vertex::{BufferlessDefinition, BufferlessVertices};
+use vulkano::sync::GpuFuture;
+
+use crate::compute::ComputeTask;
+use crate::memory;
+use crate::readback;
+use crate::triangle::{self, SIZE};
+
+/* Must match the local size in surface.glsl. */
+const LOCAL_SIZE: u32 = 64;
+
+/* The grid is SIDE x SIDE points. */
+const SIDE: u32 = 128;
+
+/* What `--frames` defaults to with `--point-cloud`. */
+pub const DEFAULT_FRAMES: u32 = 120;
+
+/* Must match surface.glsl and cloud.glsl. */
+const AMPLITUDE: f32 = 0.35;
+const TILT: f32 = 0.5;
+const DISTANCE: f32 = 4.0;
+const FOCAL: f32 = 1.5;
+
+/* The point surface.glsl puts at `index`. */
+fn surface(index: u32, side: u32) -> [f32; 4] {
+    let grid = |i: u32| i as f32 / (side - 1) as f32 * 2.0 - 1.0;
+    let (x, z) = (grid(index % side), grid(index / side));
+    [x, AMPLITUDE * (4.0 * x).sin() * (4.0 * z).cos(), z, 1.0]
+}
+
+/* Where cloud.glsl puts `point` in the image with the camera at `angle`, in pixels. */
+fn project(point: [f32; 4], angle: f32) -> [f32; 2] {
+    let (s, c) = angle.sin_cos();
+    let (x, y, z) = (c * point[0] + s * point[2], point[1], -s * point[0] + c * point[2]);
+    let (s, c) = TILT.sin_cos();
+    let (y, z) = (c * y - s * z, s * y + c * z);
+
+    let w = DISTANCE - z;
+    let ndc = [FOCAL * x / w, -FOCAL * y / w];
+    [(ndc[0] + 1.0) / 2.0 * SIZE as f32, (ndc[1] + 1.0) / 2.0 * SIZE as f32]
+}
+
+/* Fills a buffer with side x side points of a surface with a compute shader. Only the GPU
+ * needs them, so they stay in device local memory. */
+fn generate(device: Arc<Device>, queue: Arc<Queue>, side: u32) -> Arc<DeviceLocalBuffer<[[f32; 4]]>> {
+    let shader = cs::Shader::load(device.clone()).expect("Could not load surface shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let points = memory::device_local(&queue, memory::storage_buffer(), (side * side) as usize)
+        .expect("Failed to create buffer");
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_buffer(points.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = [(side * side).div_ceil(LOCAL_SIZE), 1, 1];
+    ComputeTask::new("pointcloud", pipeline, set, groups, cs::ty::PushConstants { side }).submit(queue);
+    points
+}
+
+/* Kept as its concrete type, since a type erased graphics pipeline only takes vertex buffers
+ * and this one has none. */
+type Pipeline = GraphicsPipeline<BufferlessDefinition, Box<dyn PipelineLayoutAbstract + Send + Sync>,
+    Arc<dyn RenderPassAbstract + Send + Sync>>;
+
+/* Draws a buffer of points written by a compute shader. Unlike the particles, which are bound
+ * as a vertex buffer and described to the pipeline as vertices, the points here are bound as a
+ * storage buffer, and the pipeline has no vertex input at all: drawing `BufferlessVertices`
+ * runs the vertex shader once per point, and each invocation reads its own point by index. */
+struct PointCloud {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    pipeline: Arc<Pipeline>,
+    set: Arc<dyn DescriptorSet + Send + Sync>,
+    framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    image: Arc<AttachmentImage>,
+    count: u32,
+}
+
+impl PointCloud {
+    fn new(device: Arc<Device>, queue: Arc<Queue>, points: Arc<DeviceLocalBuffer<[[f32; 4]]>>) -> PointCloud {
+        let render_pass = triangle::render_pass(device.clone(), Format::R8G8B8A8Unorm);
+        let vs = vs::Shader::load(device.clone()).expect("Failed to create point cloud vertex shader");
+        let fs = fs::Shader::load(device.clone()).expect("Failed to create height shader");
+        let pipeline = Arc::new(GraphicsPipeline::start()
+            .vertex_input(BufferlessDefinition)
+            .vertex_shader(vs.main_entry_point(), ())
+            .point_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone())
+            .unwrap());
+
+        let count = points.len() as u32;
+        let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+            .add_buffer(points).unwrap()
+            .build().unwrap());
+
+        let usage = ImageUsage { color_attachment: true, transfer_source: true, .. ImageUsage::none() };
+        let image = memory::image(AttachmentImage::with_usage(device.clone(), [SIZE, SIZE], Format::R8G8B8A8Unorm,
+            usage).unwrap());
+        let framebuffer = Arc::new(Framebuffer::start(render_pass)
+            .add(image.clone()).unwrap()
+            .build().unwrap());
+
+        PointCloud { device, queue, pipeline, set, framebuffer, image, count }
+    }
+
+    /* Renders the points with the camera at `angle` radians around them. */
+    fn draw(&self, angle: f32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let vertices = BufferlessVertices { vertices: self.count as usize, instances: 1 };
+        AutoCommandBufferBuilder::primary_one_time_submit(self.device.clone(), self.queue.family()).unwrap()
+            .begin_render_pass(self.framebuffer.clone(), false, vec![triangle::CLEAR_COLOR.into()]).unwrap()
+            .draw(self.pipeline.clone(), &triangle::dynamic_state(), vertices, self.set.clone(),
+                vs::ty::PushConstants { angle }).unwrap()
+            .end_render_pass().unwrap()
+            .build().unwrap()
+            .execute(self.queue.clone()).unwrap()
+            .then_signal_fence_and_flush().unwrap()
+            .wait(None).unwrap();
+
+        readback::download_rgba8(self.device.clone(), self.queue.clone(), self.image.clone())
+    }
+}
+
+/* Renders the surface from a camera partway around it. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let points = generate(device.clone(), queue.clone(), SIDE);
+    PointCloud::new(device, queue, points).draw(0.6)
+}
+
+/* Circles the camera once around the surface over `frames` frames, saving each as
+ * pointcloud_NNNN.png. */
+pub fn animate(device: Arc<Device>, queue: Arc<Queue>, frames: u32) {
+    let points = generate(device.clone(), queue.clone(), SIDE);
+    let cloud = PointCloud::new(device, queue, points);
+    for index in 0..frames {
+        let angle = index as f32 / frames as f32 * std::f32::consts::PI * 2.0;
+        let path = format!("pointcloud_{:04}.png", index);
+        cloud.draw(angle).save(&path).unwrap();
+        println!("Wrote {}", path);
+    }
+}
+
+/* Checks the compute shader's points against the CPU's, then that points the CPU projects land
+ * on lit pixels of the right color: red for the highest, blue for the lowest. A point can round
+ * to a neighbouring pixel, so anywhere around it counts. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let side = 32;
+    let points = generate(device.clone(), queue.clone(), side);
+    let gpu = memory::download_from_device_local(queue.clone(), points.clone()).expect("Failed to create buffer");
+    for (index, point) in gpu.iter().enumerate() {
+        let expected = surface(index as u32, side);
+        assert!((0..3).all(|c| (point[c] - expected[c]).abs() < 1e-4),
+            "Point {} is {:?} on the GPU but {:?} on the CPU", index, point, expected);
+    }
+
+    let all: Vec<_> = (0..side * side).map(|index| surface(index, side)).collect();
+    let cloud = PointCloud::new(device, queue, points);
+    for &angle in &[0.0, 1.0, 2.5] {
+        let image = cloud.draw(angle);
+        let lit = image.pixels().filter(|pixel| pixel[3] > 0).count();
+        assert!(lit > 0, "No points were drawn at angle {}", angle);
+
+        let near = |point: [f32; 4]| {
+            let [x, y] = project(point, angle);
+            let mut found = Vec::new();
+            for py in (y as i32 - 1).max(0)..(y as i32 + 2).min(SIZE as i32) {
+                for px in (x as i32 - 1).max(0)..(x as i32 + 2).min(SIZE as i32) {
+                    found.push(*image.get_pixel(px as u32, py as u32));
+                }
+            }
+            found
+        };
+
+        let highest = *all.iter().max_by(|a, b| a[1].partial_cmp(&b[1]).unwrap()).unwrap();
+        let lowest = *all.iter().min_by(|a, b| a[1].partial_cmp(&b[1]).unwrap()).unwrap();
+        assert!(near(highest).iter().any(|pixel| pixel[3] > 0 && pixel[0] > pixel[2]),
+            "The highest point, {:?}, isn't drawn red at angle {}", highest, angle);
+        assert!(near(lowest).iter().any(|pixel| pixel[3] > 0 && pixel[2] > pixel[0]),
+            "The lowest point, {:?}, isn't drawn blue at angle {}", lowest, angle);
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/surface.glsl"
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/cloud.glsl"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/height.glsl"
+    }
+}
@@ -0,0 +1,66 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+/* Float images, so a pass can leave values outside 0..1, like the edge detector's, for the next
+ * one to deal with instead of clamping them on the way. */
+layout(set = 0, binding = 0, rgba32f) uniform readonly image2D src;
+layout(set = 0, binding = 1, rgba32f) uniform writeonly image2D dst;
+
+/* Which filter this pass runs, numbered as in postfx.rs, and for the blur which way it blurs. */
+layout(push_constant) uniform PushConstants {
+    ivec2 direction;
+    int kind;
+} pc;
+
+const int BLUR = 0;
+const int SOBEL = 1;
+const int TONEMAP = 2;
+const int INVERT = 3;
+
+/* Pixels past the edge are clamped to it. */
+vec4 at(ivec2 coords) {
+    return imageLoad(src, clamp(coords, ivec2(0), imageSize(src) - 1));
+}
+
+float luminance(vec3 color) {
+    return dot(color, vec3(0.2126, 0.7152, 0.0722));
+}
+
+/* Only the color is filtered; the alpha is the source pixel's. */
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coords, imageSize(dst)))) {
+        return;
+    }
+
+    vec4 source = at(coords);
+    vec3 color;
+    if (pc.kind == BLUR) {
+        /* Five binomial weights, close to a gaussian. Blurring across and then down with them is
+         * the same as one pass with their 5x5 outer product, in 10 loads instead of 25. */
+        float weights[5] = float[](1.0, 4.0, 6.0, 4.0, 1.0);
+        color = vec3(0.0);
+        for (int i = 0; i < 5; i++) {
+            color += weights[i] / 16.0 * at(coords + (i - 2) * pc.direction).rgb;
+        }
+    } else if (pc.kind == SOBEL) {
+        /* How fast the brightness changes across and down, each a difference of the columns or
+         * rows either side weighted 1, 2, 1, and how fast it changes in all. */
+        float l[9];
+        for (int i = 0; i < 9; i++) {
+            l[i] = luminance(at(coords + ivec2(i % 3 - 1, i / 3 - 1)).rgb);
+        }
+        float gx = (l[2] + 2.0 * l[5] + l[8]) - (l[0] + 2.0 * l[3] + l[6]);
+        float gy = (l[6] + 2.0 * l[7] + l[8]) - (l[0] + 2.0 * l[1] + l[2]);
+        color = vec3(length(vec2(gx, gy)));
+    } else if (pc.kind == TONEMAP) {
+        /* Reinhard: scales each pixel by 1 / (1 + its luminance), which leaves dark colors nearly
+         * alone and brings any brightness, however high, under 1 without changing the hue. */
+        color = source.rgb / (1.0 + luminance(source.rgb));
+    } else {
+        color = vec3(1.0) - source.rgb;
+    }
+
+    imageStore(dst, coords, vec4(color, source.a));
+}
@@ -0,0 +1,192 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor::DescriptorSet;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, StorageImage};
+use vulkano::pipeline::{ComputePipeline, ComputePipelineAbstract};
+
+use crate::batch;
+use crate::compute;
+use crate::memory;
+use crate::pattern;
+use crate::storage;
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/* What the passes read and write. */
+const FORMAT: Format = Format::R32G32B32A32Sfloat;
+
+/* The filters `--filters` can chain. */
+pub const FILTERS: [&str; 4] = ["blur", "sobel", "tonemap", "invert"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Blur,
+    Sobel,
+    Tonemap,
+    Invert,
+}
+
+impl Filter {
+    pub fn parse(name: &str) -> Result<Filter, String> {
+        match name {
+            "blur" => Ok(Filter::Blur),
+            "sobel" => Ok(Filter::Sobel),
+            "tonemap" => Ok(Filter::Tonemap),
+            "invert" => Ok(Filter::Invert),
+            _ => Err(format!("Unknown filter `{}`, expected one of {}", name, FILTERS.join(", "))),
+        }
+    }
+
+    /* The dispatches the filter takes, as the push constants for each. The blur is separable, so
+     * it is two, across and then down. */
+    fn passes(self) -> Vec<cs::ty::PushConstants> {
+        let pass = |kind, direction| cs::ty::PushConstants { direction, kind };
+        match self {
+            Filter::Blur => vec![pass(0, [1, 0]), pass(0, [0, 1])],
+            Filter::Sobel => vec![pass(1, [0, 0])],
+            Filter::Tonemap => vec![pass(2, [0, 0])],
+            Filter::Invert => vec![pass(3, [0, 0])],
+        }
+    }
+}
+
+/* Parses a comma separated list of filters, like blur,sobel, in the order they are applied. */
+pub fn parse_list(list: &str) -> Result<Vec<Filter>, String> {
+    if list.trim().is_empty() {
+        return Err(format!("Expected a comma separated list of filters out of {}", FILTERS.join(", ")));
+    }
+    list.split(',').map(|name| Filter::parse(name.trim())).collect()
+}
+
+/* The two images a chain of filters ping-pongs between, and the sets binding them either way
+ * round: the first pass reads A and writes B, the next reads B and writes A, and so on, each
+ * pass's output being the next one's input, so there are only two however long the chain is.
+ * `apply` runs one over an image once; a frame loop keeps one and runs it every frame. */
+pub struct Chain {
+    pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+    images: [Arc<StorageImage<Format>>; 2],
+    sets: [Arc<dyn DescriptorSet + Send + Sync>; 2],
+    width: u32,
+    height: u32,
+}
+
+impl Chain {
+    pub fn new(queue: &Queue, width: u32, height: u32) -> Chain {
+        let device = queue.device().clone();
+        let shader = cs::Shader::load(device.clone()).expect("Failed to create post-processing shader");
+        let pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync> =
+            Arc::new(ComputePipeline::new(device, &shader.main_entry_point(), &()).unwrap());
+
+        let image = || storage::image(queue, Dimensions::Dim2d { width, height }, FORMAT);
+        let images = [image(), image()];
+        let set = |src, dst| Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+            .add_image(src).unwrap()
+            .add_image(dst).unwrap()
+            .build().unwrap()) as Arc<dyn DescriptorSet + Send + Sync>;
+        let sets = [set(images[0].clone(), images[1].clone()), set(images[1].clone(), images[0].clone())];
+        Chain { pipeline, images, sets, width, height }
+    }
+
+    /* The image the first pass reads, which whatever is filtered has to be copied into first. */
+    pub fn input(&self) -> Arc<StorageImage<Format>> {
+        self.images[0].clone()
+    }
+
+    /* Adds a dispatch for every pass of `filters` to `builder`, and says which of the two images
+     * the last one wrote. It is the `AutoCommandBufferBuilder` that makes chaining them safe: it
+     * sees each dispatch read the image the one before wrote, and puts the pipeline barrier
+     * between them that barrier.rs writes out by hand, so no pass starts before the last one's
+     * writes are visible. */
+    pub fn record(&self, mut builder: AutoCommandBufferBuilder, filters: &[Filter])
+        -> (AutoCommandBufferBuilder, Arc<StorageImage<Format>>)
+    {
+        let passes: Vec<_> = filters.iter().flat_map(|filter| filter.passes()).collect();
+        let last = self.images[passes.len() % 2].clone();
+        let groups = compute::group_count(self.width, self.height);
+        for (index, push_constants) in passes.into_iter().enumerate() {
+            builder = builder.dispatch(groups, self.pipeline.clone(), self.sets[index % 2].clone(), push_constants)
+                .unwrap();
+        }
+        (builder, last)
+    }
+}
+
+/* Runs `filters` over `pixels` one after another on the GPU and reads back the result.
+ * Everything is one command buffer, the upload, every dispatch and the readback. */
+pub fn apply(device: Arc<Device>, queue: Arc<Queue>, pixels: &Image, filters: &[Filter]) -> Image {
+    let (width, height) = pixels.dimensions();
+    let chain = Chain::new(&queue, width, height);
+
+    let staging = memory::buffer(CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(),
+        pixels.iter().map(|&channel| channel as f32 / 255.0)).expect("Failed to create buffer"));
+    let result = memory::readback::<f32>(&queue, pixels.len()).expect("Failed to create buffer");
+
+    batch::submit_and_wait(device, queue, |builder| {
+        let builder = builder.copy_buffer_to_image(staging, chain.input()).unwrap();
+        let (builder, last) = chain.record(builder, filters);
+        builder.copy_image_to_buffer(last, result.clone()).unwrap()
+    });
+
+    let floats = result.read().unwrap();
+    let pixels = floats.iter().map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8).collect();
+    ImageBuffer::from_raw(width, height, pixels).unwrap()
+}
+
+/* Checks the list parsing, that no filters and an even number of inversions hand the image back
+ * untouched while an odd number inverts it, so both images of the ping-pong end up read from,
+ * and what each filter does to images whose answers are known: a flat color stays flat when
+ * blurred and has no edges, a dot blurs into the binomial weights, a step from black to white is
+ * an edge only where it steps, and white tonemaps to half. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    assert!(parse_list("blur, sobel") == Ok(vec![Filter::Blur, Filter::Sobel]), "blur, sobel didn't parse");
+    assert!(parse_list("blur,glow").is_err() && parse_list("").is_err(), "A bad filter list parsed");
+
+    let apply = |pixels: &Image, filters: &[Filter]| apply(device.clone(), queue.clone(), pixels, filters);
+
+    let source = pattern::uv(device.clone(), queue.clone(), 61, 37);
+    assert!(apply(&source, &[]).into_raw() == source.clone().into_raw(), "No filters changed the image");
+    assert!(apply(&source, &[Filter::Invert; 2]).into_raw() == source.clone().into_raw(),
+        "Inverting twice changed the image");
+    let inverted = apply(&source, &[Filter::Invert; 3]);
+    for (x, y, pixel) in inverted.enumerate_pixels() {
+        let original = source.get_pixel(x, y);
+        assert!((0..3).all(|c| pixel[c] == 255 - original[c]) && pixel[3] == original[3],
+            "Inverting three times made pixel ({}, {}) {:?} out of {:?}", x, y, pixel, original);
+    }
+
+    let flat = ImageBuffer::from_pixel(32, 32, Rgba([100, 150, 200, 255]));
+    assert!(apply(&flat, &[Filter::Blur]).into_raw() == flat.clone().into_raw(), "Blurring a flat image changed it");
+    assert!(apply(&flat, &[Filter::Sobel]).pixels().all(|p| p[0] == 0 && p[1] == 0 && p[2] == 0),
+        "A flat image has edges");
+
+    let mut dot = ImageBuffer::from_pixel(32, 32, Rgba([0, 0, 0, 255]));
+    dot.put_pixel(16, 16, Rgba([255, 255, 255, 255]));
+    let blurred = apply(&dot, &[Filter::Blur]);
+    for &(x, y, weight) in &[(16, 16, 36.0), (17, 16, 24.0), (18, 18, 1.0), (19, 16, 0.0)] {
+        let expected = weight * 255.0 / 256.0;
+        assert!((blurred.get_pixel(x, y)[0] as f32 - expected).abs() <= 1.0,
+            "The blurred dot is {} at ({}, {}), expected {}", blurred.get_pixel(x, y)[0], x, y, expected);
+    }
+
+    let step = ImageBuffer::from_fn(32, 32, |x, _| if x < 16 { Rgba([0, 0, 0, 255]) } else { Rgba([255; 4]) });
+    let edges = apply(&step, &[Filter::Sobel]);
+    assert!(edges.get_pixel(15, 8)[0] == 255 && edges.get_pixel(16, 8)[0] == 255, "The step isn't an edge");
+    assert!(edges.get_pixel(8, 8)[0] == 0 && edges.get_pixel(24, 8)[0] == 0, "There are edges away from the step");
+
+    let white = ImageBuffer::from_pixel(8, 8, Rgba([255; 4]));
+    let toned = apply(&white, &[Filter::Tonemap]);
+    assert!(toned.pixels().all(|p| p[0] == 128 && p[1] == 128 && p[2] == 128), "White tonemapped to {:?}",
+        toned.get_pixel(0, 0));
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/postfx.glsl"
+    }
+}
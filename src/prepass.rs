@@ -0,0 +1,234 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use std::time::Instant;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, RenderPassAbstract, Subpass};
+use vulkano::image::{AttachmentImage, ImageUsage};
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::depth_stencil::{Compare, DepthStencil};
+use vulkano::sync::GpuFuture;
+
+use crate::memory;
+use crate::readback;
+use crate::triangle::{self, SIZE};
+use crate::vertices;
+
+/* Overlapping squares stacked at different depths, the nearest one last. */
+const LAYERS: usize = 8;
+
+pub struct LayerVertex { position: [f32; 3], color: [f32; 3] }
+vulkano::impl_vertex!(LayerVertex, position, color);
+
+/* The squares are drawn back to front, the worst order for overdraw: every square passes the
+ * depth test against the ones before it, so each pixel is shaded once per square covering it
+ * even though only the nearest one is seen. */
+fn layer_buffer(device: Arc<Device>) -> Arc<CpuAccessibleBuffer<[LayerVertex]>> {
+    let mut vertices = Vec::with_capacity(LAYERS * 6);
+    for layer in 0..LAYERS {
+        let t = layer as f32 / LAYERS as f32;
+        let (x0, y0) = (-0.9 + t * 0.6, -0.9 + t * 0.5);
+        let (x1, y1) = (x0 + 1.2, y0 + 1.3);
+        let z = 0.9 - t * 0.8;
+        let color = [t, 1.0 - t, 0.5];
+
+        for &(x, y) in &[(x0, y0), (x1, y0), (x1, y1), (x0, y0), (x1, y1), (x0, y1)] {
+            vertices.push(LayerVertex { position: [x, y, z], color });
+        }
+    }
+
+    vertices::make_vertex_buffer(device, vertices)
+}
+
+/* Renders the layers once the usual way, with the depth test writing depth and every fragment
+ * shaded, and once with a depth prepass, then checks both images are the same. The prepass
+ * draws everything with a fragment shader that does nothing, so by the time the color pass
+ * runs the depth buffer already holds the nearest depth of every pixel. Testing for Equal with
+ * depth writes off then lets through exactly one fragment per pixel, and the expensive shader
+ * runs once per pixel instead of once per layer. The geometry is drawn twice, so this only
+ * pays off when shading costs more than processing vertices, as it does here. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let (single, single_time) = single_pass(device.clone(), queue.clone());
+    let (prepass, prepass_time) = with_prepass(device, queue);
+
+    println!("Depth prepass: {:.2} ms, single pass: {:.2} ms", prepass_time * 1000.0, single_time * 1000.0);
+    assert!(single.into_raw() == prepass.into_raw(), "The depth prepass changed the image");
+}
+
+fn color_image(device: Arc<Device>) -> Arc<AttachmentImage<Format>> {
+    let usage = ImageUsage { color_attachment: true, transfer_source: true, .. ImageUsage::none() };
+    memory::image(AttachmentImage::with_usage(device, [SIZE, SIZE], Format::R8G8B8A8Unorm, usage).unwrap())
+}
+
+fn single_pass(device: Arc<Device>, queue: Arc<Queue>) -> (ImageBuffer<Rgba<u8>, Vec<u8>>, f64) {
+    let render_pass: Arc<dyn RenderPassAbstract + Send + Sync> = Arc::new(vulkano::single_pass_renderpass!(device.clone(),
+        attachments: {
+            color: {
+                load: Clear,
+                store: Store,
+                format: Format::R8G8B8A8Unorm,
+                samples: 1,
+            },
+            depth: {
+                load: Clear,
+                store: DontCare,
+                format: Format::D16Unorm,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {depth}
+        }
+    ).unwrap());
+
+    let vs = layers::Shader::load(device.clone()).expect("Failed to create vertex shader");
+    let fs = shade::Shader::load(device.clone()).expect("Failed to create fragment shader");
+
+    let pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<LayerVertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .depth_stencil_simple_depth()
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let image = color_image(device.clone());
+    let depth = memory::image(AttachmentImage::transient(device.clone(), [SIZE, SIZE], Format::D16Unorm).unwrap());
+    let framebuffer = Arc::new(Framebuffer::start(render_pass)
+        .add(image.clone()).unwrap()
+        .add(depth).unwrap()
+        .build().unwrap());
+
+    let buf = readback_buffer(&queue);
+    let start = Instant::now();
+    AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer, false, vec![triangle::CLEAR_COLOR.into(), 1f32.into()]).unwrap()
+        .draw(pipeline, &triangle::dynamic_state(), layer_buffer(device.clone()), (), ()).unwrap()
+        .end_render_pass().unwrap()
+        .copy_image_to_buffer(image, buf.clone()).unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let buffer_content = buf.read().unwrap();
+    (readback::rgba8_image(SIZE, SIZE, SIZE as usize * readback::RGBA8_BYTES, &buffer_content), elapsed)
+}
+
+/* Both passes share one render pass: the first subpass only has the depth attachment, the
+ * second reads the depth it left behind while writing color. */
+fn with_prepass(device: Arc<Device>, queue: Arc<Queue>) -> (ImageBuffer<Rgba<u8>, Vec<u8>>, f64) {
+    let render_pass: Arc<dyn RenderPassAbstract + Send + Sync> = Arc::new(vulkano::ordered_passes_renderpass!(device.clone(),
+        attachments: {
+            color: {
+                load: Clear,
+                store: Store,
+                format: Format::R8G8B8A8Unorm,
+                samples: 1,
+            },
+            depth: {
+                load: Clear,
+                store: DontCare,
+                format: Format::D16Unorm,
+                samples: 1,
+            }
+        },
+        passes: [
+            {
+                color: [],
+                depth_stencil: {depth},
+                input: []
+            },
+            {
+                color: [color],
+                depth_stencil: {depth},
+                input: []
+            }
+        ]
+    ).unwrap());
+
+    let vs = layers::Shader::load(device.clone()).expect("Failed to create vertex shader");
+    let depth_fs = depth_only::Shader::load(device.clone()).expect("Failed to create fragment shader");
+    let fs = shade::Shader::load(device.clone()).expect("Failed to create fragment shader");
+
+    let depth_pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<LayerVertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(depth_fs.main_entry_point(), ())
+        .depth_stencil_simple_depth()
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let color_pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<LayerVertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .depth_stencil(DepthStencil {
+            depth_compare: Compare::Equal,
+            depth_write: false,
+            .. DepthStencil::simple_depth_test()
+        })
+        .render_pass(Subpass::from(render_pass.clone(), 1).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let image = color_image(device.clone());
+    let depth = memory::image(AttachmentImage::transient(device.clone(), [SIZE, SIZE], Format::D16Unorm).unwrap());
+    let framebuffer = Arc::new(Framebuffer::start(render_pass)
+        .add(image.clone()).unwrap()
+        .add(depth).unwrap()
+        .build().unwrap());
+
+    let vertex_buffer = layer_buffer(device.clone());
+    let buf = readback_buffer(&queue);
+    let start = Instant::now();
+    AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer, false, vec![triangle::CLEAR_COLOR.into(), 1f32.into()]).unwrap()
+        .draw(depth_pipeline, &triangle::dynamic_state(), vertex_buffer.clone(), (), ()).unwrap()
+        .next_subpass(false).unwrap()
+        .draw(color_pipeline, &triangle::dynamic_state(), vertex_buffer, (), ()).unwrap()
+        .end_render_pass().unwrap()
+        .copy_image_to_buffer(image, buf.clone()).unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let buffer_content = buf.read().unwrap();
+    (readback::rgba8_image(SIZE, SIZE, SIZE as usize * readback::RGBA8_BYTES, &buffer_content), elapsed)
+}
+
+fn readback_buffer(queue: &Queue) -> Arc<CpuAccessibleBuffer<[u8]>> {
+    memory::readback(queue, (SIZE * SIZE) as usize * readback::RGBA8_BYTES).expect("Failed to create buffer")
+}
+
+mod layers {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/layers.glsl"
+    }
+}
+
+mod depth_only {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/depth_only.glsl"
+    }
+}
+
+mod shade {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/shade.glsl"
+    }
+}
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::pipeline::ComputePipeline;
+
+use crate::memory;
+use crate::random;
+use crate::reduce;
+use crate::watchdog;
+
+/* Must match the local size in scan.glsl and histogram.glsl. */
+const LOCAL_SIZE: u32 = 256;
+
+/* The most bins `histogram` counts into, which is what fits in histogram.glsl's shared memory. */
+pub const MAX_BINS: u32 = 1024;
+
+/* The most workgroups a histogram dispatches. Past this each invocation counts more values
+ * rather than there being more workgroups to merge their counts. */
+const HISTOGRAM_GROUPS: u32 = 256;
+
+/* The passes of scan.glsl. */
+const SCAN: u32 = 0;
+const ADD: u32 = 1;
+
+fn groups(count: u32) -> u32 {
+    count.div_ceil(LOCAL_SIZE)
+}
+
+/* Replaces every value with the sum of it and every value before it, wrapping on overflow, on
+ * the GPU. A workgroup can only scan as many values as it has invocations, so the scan goes in
+ * levels: every workgroup scans its own values and writes its total to the next level, which is
+ * scanned the same way, and so on until a level fits in one workgroup. Then, from the top down,
+ * every workgroup of a level adds the scanned total of the ones before it to its values. That is
+ * 2 log256(n) dispatches, all in one command buffer. Each reads what the one before wrote, and
+ * the `AutoCommandBufferBuilder` puts a pipeline barrier between them for it, the way barrier.rs
+ * does by hand. Only the first level is read back. */
+pub fn prefix_sum(device: Arc<Device>, queue: Arc<Queue>, values: &[u32]) -> Vec<u32> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+
+    let shader = scan_cs::Shader::load(device.clone()).expect("Failed to create scan shader module");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())
+        .expect("Failed to create scan pipeline"));
+
+    /* Every level's length, down to the one workgroup's total. */
+    let mut counts = vec![values.len() as u32];
+    while *counts.last().unwrap() > 1 {
+        let next = groups(*counts.last().unwrap());
+        counts.push(next);
+    }
+    if counts.len() == 1 {
+        counts.push(1);
+    }
+
+    let mut levels = vec![memory::upload_to_device_local(queue.clone(), memory::storage_buffer(),
+        values.iter().cloned()).expect("Failed to create buffer")];
+    for &count in &counts[1..] {
+        levels.push(memory::device_local::<u32>(&queue, memory::storage_buffer(), count as usize)
+            .expect("Failed to create buffer"));
+    }
+    let sets: Vec<_> = levels.windows(2).map(|pair| Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_buffer(pair[0].clone()).unwrap()
+        .add_buffer(pair[1].clone()).unwrap()
+        .build().unwrap())).collect();
+    let readback = memory::readback::<u32>(&queue, values.len()).expect("Failed to create buffer");
+
+    let mut builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+    for (level, set) in sets.iter().enumerate() {
+        let count = counts[level];
+        builder = builder.dispatch([groups(count), 1, 1], pipeline.clone(), set.clone(),
+            scan_cs::ty::PushConstants { count, mode: SCAN }).unwrap();
+    }
+    /* The top level is a single workgroup, and has nothing before it to add. */
+    for (level, set) in sets.iter().enumerate().rev().skip(1) {
+        let count = counts[level];
+        builder = builder.dispatch([groups(count), 1, 1], pipeline.clone(), set.clone(),
+            scan_cs::ty::PushConstants { count, mode: ADD }).unwrap();
+    }
+    let command_buffer = builder.copy_buffer(levels[0].clone(), readback.clone()).unwrap().build().unwrap();
+    watchdog::wait(command_buffer.execute(queue.clone()).unwrap(), "primitives");
+
+    let content = readback.read().unwrap();
+    content.to_vec()
+}
+
+/* Adds up `values` on the GPU, wrapping on overflow. This is reduce.rs's reduction, here with
+ * the other primitives. */
+pub fn reduce_sum(device: Arc<Device>, queue: Arc<Queue>, values: &[u32]) -> u32 {
+    reduce::gpu_sum(device, queue, values)
+}
+
+/* Counts how many of `values` are each number below `bins`, on the GPU. Values of `bins` or
+ * more aren't counted. The counts are cleared with a fill before the dispatch that adds to
+ * them, in the same command buffer. */
+pub fn histogram(device: Arc<Device>, queue: Arc<Queue>, values: &[u32], bins: u32) -> Vec<u32> {
+    assert!(bins > 0 && bins <= MAX_BINS, "Can only count into 1 to {} bins, not {}", MAX_BINS, bins);
+    if values.is_empty() {
+        return vec![0; bins as usize];
+    }
+
+    let shader = histogram_cs::Shader::load(device.clone()).expect("Failed to create histogram shader module");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())
+        .expect("Failed to create histogram pipeline"));
+
+    let input = memory::upload_to_device_local(queue.clone(), memory::storage_buffer(), values.iter().cloned())
+        .expect("Failed to create buffer");
+    let counts = memory::device_local::<u32>(&queue, memory::storage_buffer(), bins as usize)
+        .expect("Failed to create buffer");
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_buffer(input).unwrap()
+        .add_buffer(counts.clone()).unwrap()
+        .build().unwrap());
+    let readback = memory::readback::<u32>(&queue, bins as usize).expect("Failed to create buffer");
+
+    let count = values.len() as u32;
+    let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+        .fill_buffer(counts.clone(), 0).unwrap()
+        .dispatch([groups(count).min(HISTOGRAM_GROUPS), 1, 1], pipeline.clone(), set,
+            histogram_cs::ty::PushConstants { count, bins }).unwrap()
+        .copy_buffer(counts, readback.clone()).unwrap()
+        .build().unwrap();
+    watchdog::wait(command_buffer.execute(queue.clone()).unwrap(), "primitives");
+
+    let content = readback.read().unwrap();
+    content.to_vec()
+}
+
+/* What `prefix_sum` should give, worked out on the CPU. */
+pub fn cpu_prefix_sum(values: &[u32]) -> Vec<u32> {
+    values.iter().scan(0u32, |sum, &value| {
+        *sum = sum.wrapping_add(value);
+        Some(*sum)
+    }).collect()
+}
+
+/* What `reduce_sum` should give. */
+pub fn cpu_reduce_sum(values: &[u32]) -> u32 {
+    values.iter().fold(0u32, |sum, &value| sum.wrapping_add(value))
+}
+
+/* What `histogram` should give. */
+pub fn cpu_histogram(values: &[u32], bins: u32) -> Vec<u32> {
+    let mut counts = vec![0; bins as usize];
+    for &value in values.iter().filter(|&&value| value < bins) {
+        counts[value as usize] += 1;
+    }
+    counts
+}
+
+/* Lengths to check: one workgroup, partly full and exactly full, one more than fits, three
+ * levels of scan, and enough for every histogram invocation to count several values. */
+const SIZES: &[u32] = &[1, 255, 256, 257, 65_537, 1 << 20];
+
+/* Checks every primitive against the CPU over each length, the prefix sum with values big
+ * enough to wrap, and the histogram with a few bin counts and some values past the last bin. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    assert!(prefix_sum(device.clone(), queue.clone(), &[]).is_empty(), "The prefix sum of nothing isn't empty");
+    assert!(histogram(device.clone(), queue.clone(), &[], 4) == vec![0; 4], "An empty histogram counted something");
+
+    for &n in SIZES {
+        let values: Vec<u32> = (0..n).map(|i| random::pcg(i ^ n)).collect();
+
+        let scanned = prefix_sum(device.clone(), queue.clone(), &values);
+        let expected = cpu_prefix_sum(&values);
+        if let Some(i) = (0..n as usize).find(|&i| scanned[i] != expected[i]) {
+            panic!("Prefix sum {} of {} values is {}, expected {}", i, n, scanned[i], expected[i]);
+        }
+
+        let sum = reduce_sum(device.clone(), queue.clone(), &values);
+        assert!(sum == cpu_reduce_sum(&values), "The sum of {} values is {}, expected {}", n, sum,
+            cpu_reduce_sum(&values));
+
+        for &bins in &[1, 16, MAX_BINS] {
+            let keys: Vec<u32> = values.iter().map(|&value| value % (bins + bins / 4 + 1)).collect();
+            let counts = histogram(device.clone(), queue.clone(), &keys, bins);
+            assert!(counts == cpu_histogram(&keys, bins), "The histogram of {} values into {} bins is wrong", n, bins);
+        }
+    }
+}
+
+mod scan_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/scan.glsl"
+    }
+}
+
+mod histogram_cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/histogram.glsl"
+    }
+}
@@ -0,0 +1,53 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D src;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D dst;
+
+/* Must hold MAX_COLORS in quantize.rs. */
+layout(set = 0, binding = 2) uniform Palette {
+    vec4 colors[64];
+} palette;
+
+/* Four counters per palette color: the sums of the red, green and blue bytes of the pixels
+ * nearest to it, and how many pixels that was. */
+layout(set = 0, binding = 3) buffer Sums {
+    uint sums[];
+} sums;
+
+layout(push_constant) uniform PushConstants {
+    uint colors;
+} pc;
+
+/* The assignment step of k-means: find the palette color nearest to this pixel, add the pixel
+ * to that color's sums and write the color out. The CPU turns the sums into a new palette. */
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coords, imageSize(src)))) {
+        return;
+    }
+
+    vec4 color = imageLoad(src, coords);
+
+    uint nearest = 0;
+    /* Further than any two colors can be apart. */
+    float best = 4.0;
+    for (uint i = 0; i < pc.colors; i++) {
+        vec3 d = color.rgb - palette.colors[i].rgb;
+        float distance = dot(d, d);
+        if (distance < best) {
+            best = distance;
+            nearest = i;
+        }
+    }
+
+    /* Atomics only work on integers, so sum whole bytes rather than floats. */
+    uvec3 bytes = uvec3(round(color.rgb * 255.0));
+    atomicAdd(sums.sums[nearest * 4 + 0], bytes.r);
+    atomicAdd(sums.sums[nearest * 4 + 1], bytes.g);
+    atomicAdd(sums.sums[nearest * 4 + 2], bytes.b);
+    atomicAdd(sums.sums[nearest * 4 + 3], 1);
+
+    imageStore(dst, coords, vec4(palette.colors[nearest].rgb, color.a));
+}
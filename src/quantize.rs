@@ -0,0 +1,131 @@
+use image::{ImageBuffer, Rgba};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::Dimensions;
+use vulkano::pipeline::ComputePipeline;
+use vulkano::sync::GpuFuture;
+
+use crate::compute;
+use crate::input::{self, Input};
+use crate::memory;
+use crate::pattern;
+use crate::readback;
+use crate::storage;
+
+/* Must match the palette size in quantize.glsl. */
+pub const MAX_COLORS: u32 = 64;
+
+/* What `--colors` defaults to. */
+pub const DEFAULT_COLORS: u32 = 16;
+
+/* How many times the palette is refined. k-means settles quickly, and each round costs a trip
+ * to the GPU and back. */
+const ITERATIONS: usize = 8;
+
+/* Reduces `pixels` to at most `colors` colors with k-means. The GPU does the expensive part,
+ * finding every pixel's nearest palette color and summing the pixels that chose each color,
+ * and the CPU moves every color to the average of its pixels. After a few rounds the palette
+ * fits the image, and the last round's output, every pixel replaced by its nearest color, is
+ * the result. */
+pub fn quantize(device: Arc<Device>, queue: Arc<Queue>, pixels: &ImageBuffer<Rgba<u8>, Vec<u8>>, colors: u32)
+    -> ImageBuffer<Rgba<u8>, Vec<u8>>
+{
+    assert!(colors > 0 && colors <= MAX_COLORS, "Can only quantize to between 1 and {} colors", MAX_COLORS);
+    let (width, height) = pixels.dimensions();
+
+    let shader = cs::Shader::load(device.clone()).expect("Could not load quantize shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let source = input::upload(device.clone(), queue.clone(), pixels);
+    let destination = storage::image(&queue, Dimensions::Dim2d { width, height }, Format::R8G8B8A8Unorm);
+
+    /* Start from colors picked evenly through the image, so they're all colors it contains. */
+    let raw: Vec<_> = pixels.pixels().collect();
+    let mut centroids: Vec<[f32; 4]> = (0..colors as usize)
+        .map(|i| {
+            let pixel = raw[i * raw.len() / colors as usize];
+            [pixel[0] as f32 / 255.0, pixel[1] as f32 / 255.0, pixel[2] as f32 / 255.0, 1.0]
+        })
+        .collect();
+
+    /* The palette and sums stay in device local memory. Each round the palette is copied in
+     * from a staging buffer, the sums are zeroed there, and copied out once the shader is done. */
+    let palette = memory::device_local::<[f32; 4]>(&queue, memory::storage_buffer(), MAX_COLORS as usize)
+        .expect("Failed to create buffer");
+    let sums = memory::device_local::<u32>(&queue, memory::storage_buffer(), (MAX_COLORS * 4) as usize)
+        .expect("Failed to create buffer");
+    let readback = memory::readback::<u32>(&queue, (MAX_COLORS * 4) as usize).expect("Failed to create buffer");
+
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_image(source).unwrap()
+        .add_image(destination.clone()).unwrap()
+        .add_buffer(palette.clone()).unwrap()
+        .add_buffer(sums.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = compute::group_count(width, height);
+    for _ in 0..=ITERATIONS {
+        let staging = memory::buffer(CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(),
+            (0..MAX_COLORS as usize).map(|i| centroids.get(i).cloned().unwrap_or([0.0; 4])))
+            .expect("Failed to create buffer"));
+
+        AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap()
+            .copy_buffer(staging, palette.clone()).unwrap()
+            .fill_buffer(sums.clone(), 0).unwrap()
+            .dispatch(groups, pipeline.clone(), set.clone(), cs::ty::PushConstants { colors }).unwrap()
+            .copy_buffer(sums.clone(), readback.clone()).unwrap()
+            .build().unwrap()
+            .execute(queue.clone()).unwrap()
+            .then_signal_fence_and_flush().unwrap()
+            .wait(None).unwrap();
+
+        /* A color no pixel chose keeps its place. */
+        let sums = readback.read().unwrap();
+        for (centroid, sum) in centroids.iter_mut().zip(sums.chunks(4)) {
+            if sum[3] > 0 {
+                let average = |total: u32| total as f32 / sum[3] as f32 / 255.0;
+                *centroid = [average(sum[0]), average(sum[1]), average(sum[2]), 1.0];
+            }
+        }
+    }
+
+    readback::download_rgba8(device, queue, destination)
+}
+
+/* Quantizes the image loaded for `--input` and saves the result next to it as
+ * <name>_quantized.png, returning where it went. */
+pub fn quantize_file(device: Arc<Device>, queue: Arc<Queue>, input: &Input, colors: u32) -> Result<PathBuf, String> {
+    input.save(&quantize(device, queue, &input.pixels, colors), "quantized")
+}
+
+/* Quantizes the UV pattern, which has thousands of colors, and checks that no more than the
+ * asked for number are left, and that more colors get closer to the original. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let source = pattern::uv(device.clone(), queue.clone(), 256, 256);
+
+    let error = |colors: u32| {
+        let quantized = quantize(device.clone(), queue.clone(), &source, colors);
+        let distinct: HashSet<_> = quantized.pixels().map(|pixel| pixel.data).collect();
+        assert!(distinct.len() <= colors as usize, "Quantizing to {} colors left {}", colors, distinct.len());
+
+        quantized.pixels().zip(source.pixels())
+            .map(|(a, b)| (0..3).map(|c| (a[c] as f64 - b[c] as f64).powi(2)).sum::<f64>())
+            .sum::<f64>()
+    };
+
+    let (coarse, fine) = (error(2), error(DEFAULT_COLORS));
+    assert!(fine < coarse, "{} colors are no closer to the original than 2 ({} vs {})", DEFAULT_COLORS, fine, coarse);
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/quantize.glsl"
+    }
+}
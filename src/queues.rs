@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, DeviceLocalBuffer};
+use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::descriptor::DescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::instance::{PhysicalDevice, QueueFamily};
+use vulkano::pipeline::ComputePipelineAbstract;
+use vulkano::sync::GpuFuture;
+
+use crate::error::SandboxError;
+use crate::memory;
+use crate::multiply;
+
+/* Picks the queue family to run on from what the demos about to run need. Devices usually have
+ * a family that does everything, but some only compute, and a run that only dispatches compute
+ * shaders shouldn't refuse to start on one just because it couldn't draw. The first family
+ * that can do everything needed wins, which on most devices is the same one either way. When
+ * none can, the error lists the families there are. */
+pub fn choose_family(physical: PhysicalDevice, graphics: bool, compute: bool) -> Result<QueueFamily, SandboxError> {
+    physical.queue_families()
+        .find(|&q| (!graphics || q.supports_graphics()) && (!compute || q.supports_compute()))
+        .ok_or_else(|| {
+            let needed = match (graphics, compute) {
+                (true, true) => "graphics and compute",
+                (true, false) => "graphics",
+                _ => "compute",
+            };
+            SandboxError::queue_family(physical, needed)
+        })
+}
+
+/* A queue family that computes but doesn't draw, other than `main`. GPUs have these so compute
+ * work can run alongside drawing, on hardware the drawing isn't using. */
+pub fn dedicated_compute_family<'a>(physical: PhysicalDevice<'a>, main: QueueFamily<'a>) -> Option<QueueFamily<'a>> {
+    physical.queue_families().find(|&q| q.id() != main.id() && q.supports_compute() && !q.supports_graphics())
+}
+
+/* How many pieces `--overlap` cuts its numbers into. */
+pub const CHUNKS: u32 = 8;
+
+/* One piece of the numbers: the staging buffer the CPU fills, the device local buffer it is
+ * copied to and multiplied in, and the buffer the product is copied back to. */
+struct Chunk {
+    staging: Arc<CpuAccessibleBuffer<[u32]>>,
+    buffer: Arc<DeviceLocalBuffer<[u32]>>,
+    result: Arc<CpuAccessibleBuffer<[u32]>>,
+    set: Arc<dyn DescriptorSet + Send + Sync>,
+    groups: u32,
+}
+
+impl Chunk {
+    /* The copy to the device, on `transfer`. */
+    fn upload(&self, transfer: &Queue) -> AutoCommandBuffer {
+        AutoCommandBufferBuilder::primary_one_time_submit(transfer.device().clone(), transfer.family()).unwrap()
+            .copy_buffer(self.staging.clone(), self.buffer.clone()).unwrap()
+            .build().unwrap()
+    }
+
+    /* The multiply and the copy back, on `compute`. */
+    fn work(&self, compute: &Queue, pipeline: &Arc<dyn ComputePipelineAbstract + Send + Sync>) -> AutoCommandBuffer {
+        AutoCommandBufferBuilder::primary_one_time_submit(compute.device().clone(), compute.family()).unwrap()
+            .dispatch([self.groups, 1, 1], pipeline.clone(), self.set.clone(), ()).unwrap()
+            .copy_buffer(self.buffer.clone(), self.result.clone()).unwrap()
+            .build().unwrap()
+    }
+}
+
+/* Multiplies 0..elements on the GPU in CHUNKS pieces, each uploaded to a device local buffer on
+ * `transfer` and multiplied and read back on `compute`, and hands back the products and how long
+ * it took. `transfer` and `compute` may be the same queue when the device has only one family.
+ *
+ * Overlapped, nothing waits on the CPU until the end. Each piece's upload signals a semaphore
+ * that its multiply waits on, which is what chaining the futures across queues does, so the GPU
+ * orders the two without the CPU in between, and meanwhile the transfer queue is free to go on
+ * to the next upload while the compute queue multiplies this one. Serialized, every submission
+ * is waited for before the next, the way the rest of the sandbox works, so copies and compute
+ * take turns. Each device local buffer is shared by both queues' families at once, which
+ * vulkano can synchronize on its own, where handing it over the way transfer.rs does would need
+ * barriers recorded by hand. */
+pub fn overlap(device: Arc<Device>, transfer: Arc<Queue>, compute: Arc<Queue>, elements: u32, overlapped: bool)
+    -> (Vec<u32>, Duration)
+{
+    let pipeline = multiply::pipeline(device.clone());
+    let mut families = vec![transfer.family()];
+    if compute.family().id() != transfer.family().id() {
+        families.push(compute.family());
+    }
+
+    let size = elements.div_ceil(CHUNKS).max(1);
+    let usage = BufferUsage { transfer_source: true, transfer_destination: true, storage_buffer: true,
+        .. BufferUsage::none() };
+    let chunks: Vec<Chunk> = (0..elements).step_by(size as usize).map(|start| {
+        let end = (start + size).min(elements);
+        let staging = memory::buffer(CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(),
+            start..end).expect("Failed to create buffer"));
+        let buffer = memory::buffer(DeviceLocalBuffer::array(device.clone(), (end - start) as usize, usage,
+            families.iter().cloned()).expect("Failed to create buffer"));
+        let result = memory::buffer(CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_destination(),
+            (start..end).map(|_| 0u32)).expect("Failed to create buffer"));
+        let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+            .add_buffer(buffer.clone()).unwrap()
+            .build().unwrap());
+        Chunk { staging, buffer, result, set, groups: multiply::group_count(end - start) }
+    }).collect();
+
+    let start = Instant::now();
+    if overlapped {
+        let futures: Vec<_> = chunks.iter().map(|chunk| {
+            chunk.upload(&transfer).execute(transfer.clone()).unwrap()
+                .then_signal_semaphore_and_flush().unwrap()
+                .then_execute(compute.clone(), chunk.work(&compute, &pipeline)).unwrap()
+                .then_signal_fence_and_flush().unwrap()
+        }).collect();
+        for future in futures {
+            future.wait(None).unwrap();
+        }
+    } else {
+        for chunk in &chunks {
+            chunk.upload(&transfer).execute(transfer.clone()).unwrap()
+                .then_signal_fence_and_flush().unwrap()
+                .wait(None).unwrap();
+            chunk.work(&compute, &pipeline).execute(compute.clone()).unwrap()
+                .then_signal_fence_and_flush().unwrap()
+                .wait(None).unwrap();
+        }
+    }
+    let elapsed = start.elapsed();
+
+    let products = chunks.iter().flat_map(|chunk| chunk.result.read().unwrap().to_vec()).collect();
+    (products, elapsed)
+}
+
+/* Runs `overlap` both ways and checks both multiplied every number, chunks that don't divide
+ * the numbers evenly included. */
+pub fn verify(device: Arc<Device>, transfer: Arc<Queue>, compute: Arc<Queue>) {
+    let elements = 1000;
+    for &overlapped in &[false, true] {
+        let (products, _) = overlap(device.clone(), transfer.clone(), compute.clone(), elements, overlapped);
+        let expected: Vec<u32> = (0..elements).map(|n| n * 12).collect();
+        assert!(products == expected, "Multiplying on queue families {} and {}{} went wrong",
+            transfer.family().id(), compute.family().id(), if overlapped { " overlapped" } else { "" });
+    }
+}
@@ -0,0 +1,12 @@
+/* What `--seed` defaults to. Every demo that picks anything at random starts from the seed, so
+ * two runs with the same one produce the same output on the same device. Another device may
+ * round the floating point that follows differently. */
+pub const DEFAULT_SEED: u32 = 0;
+
+/* A PCG hash, which turns consecutive numbers into ones that look random. The same as `pcg` in
+ * buddhabrot.glsl, so the CPU can pick the same points. */
+pub fn pcg(v: u32) -> u32 {
+    let state = v.wrapping_mul(747_796_405).wrapping_add(2_891_336_453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277_803_737);
+    (word >> 22) ^ word
+}
@@ -0,0 +1,97 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/* The demos `--raw-output` can save the output buffer of. */
+pub const DEMOS: &[&str] = &["multiply", "matmul", "sort", "mandelbrot"];
+
+/* A type a buffer can hold, with the name the .meta file gives it and the matching numpy
+ * dtype, so `np.fromfile(path, dtype=...)` reads the values straight back. */
+pub trait Element: Copy {
+    const NAME: &'static str;
+    const DTYPE: &'static str;
+    fn le_bytes(self) -> [u8; 4];
+}
+
+impl Element for u32 {
+    const NAME: &'static str = "u32";
+    const DTYPE: &'static str = "<u4";
+    fn le_bytes(self) -> [u8; 4] { self.to_le_bytes() }
+}
+
+impl Element for f32 {
+    const NAME: &'static str = "f32";
+    const DTYPE: &'static str = "<f4";
+    fn le_bytes(self) -> [u8; 4] { self.to_bits().to_le_bytes() }
+}
+
+/* Where the description of the values at `path` goes: next to them, with .meta added. */
+pub fn meta_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".meta");
+    PathBuf::from(name)
+}
+
+/* Writes `values` to `path` exactly as they sit in the buffer, little endian with nothing in
+ * between, and describes them in a small TOML file beside it:
+ *
+ *     type = "u32"
+ *     dtype = "<u4"
+ *     count = 262144
+ *     dimensions = [512, 512]
+ *
+ * `dimensions` is the shape, outermost first, so a row-major image is [height, width]. Returns
+ * where the description went. */
+pub fn write<T: Element>(path: &Path, values: &[T], dimensions: &[usize]) -> io::Result<PathBuf> {
+    assert!(dimensions.iter().product::<usize>() == values.len(),
+        "Dimensions {:?} don't hold {} values", dimensions, values.len());
+
+    let mut out = BufWriter::new(File::create(path)?);
+    for &value in values {
+        out.write_all(&value.le_bytes())?;
+    }
+    out.flush()?;
+
+    let meta = meta_path(path);
+    let dimensions: Vec<_> = dimensions.iter().map(|d| d.to_string()).collect();
+    let mut out = BufWriter::new(File::create(&meta)?);
+    writeln!(out, "type = \"{}\"", T::NAME)?;
+    writeln!(out, "dtype = \"{}\"", T::DTYPE)?;
+    writeln!(out, "count = {}", values.len())?;
+    writeln!(out, "dimensions = [{}]", dimensions.join(", "))?;
+    out.flush()?;
+    Ok(meta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn values_are_written_little_endian_with_their_description() {
+        let dir = std::env::temp_dir().join(format!("vulkano_tut_raw_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("values.bin");
+
+        let meta = write(&path, &[1u32, 0x0102_0304, 5, 6, 7, 8], &[2, 3]).unwrap();
+        assert_eq!(meta, dir.join("values.bin.meta"));
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(bytes.len(), 24);
+        assert_eq!(bytes[..8], [1, 0, 0, 0, 4, 3, 2, 1]);
+        assert_eq!(fs::read_to_string(&meta).unwrap(),
+            "type = \"u32\"\ndtype = \"<u4\"\ncount = 6\ndimensions = [2, 3]\n");
+
+        write(&path, &[1.0f32], &[1]).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), 1.0f32.to_le_bytes());
+        assert!(fs::read_to_string(&meta).unwrap().starts_with("type = \"f32\"\ndtype = \"<f4\"\n"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn dimensions_have_to_hold_every_value() {
+        write(&std::env::temp_dir().join("unused.bin"), &[1u32, 2, 3], &[2, 2]).unwrap();
+    }
+}
@@ -0,0 +1,256 @@
+use image::{ImageBuffer, Luma, Rgba};
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::buffer::cpu_access::ReadLock;
+use vulkano::device::{Device, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::{AttachmentImage, Dimensions, ImageAccess, ImageUsage, StorageImage};
+use vulkano::memory::Content;
+use vulkano::sync::GpuFuture;
+
+use crate::batch;
+use crate::memory;
+use crate::storage;
+
+pub const RGBA8_BYTES: usize = 4;
+
+/* Builds an image from RGBA8 pixels read back from the GPU. `ImageBuffer::from_raw` wants
+ * tightly packed rows, but a buffer copy may lay each row out `row_pitch` bytes apart with
+ * padding at the end. Reading such a buffer as if it were packed shifts every row a little
+ * further than the last and the image comes out sheared, so this copies out just the pixels of
+ * each row. Pass `width * RGBA8_BYTES` as the pitch for packed data. */
+pub fn rgba8_image(width: u32, height: u32, row_pitch: usize, data: &[u8]) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let row_bytes = width as usize * RGBA8_BYTES;
+    assert!(row_pitch >= row_bytes, "Row pitch {} is smaller than a row of {} pixels", row_pitch, width);
+    assert!(data.len() >= row_pitch * (height as usize - 1) + row_bytes,
+        "{} bytes is too small for a {}x{} image with a row pitch of {}", data.len(), width, height, row_pitch);
+
+    let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+    for row in data.chunks(row_pitch).take(height as usize) {
+        pixels.extend_from_slice(&row[..row_bytes]);
+    }
+
+    ImageBuffer::from_raw(width, height, pixels).unwrap()
+}
+
+/* Copies an RGBA8 image into a buffer, waits for the copy and turns it back into pixels. */
+pub fn download_rgba8<I>(device: Arc<Device>, queue: Arc<Queue>, image: Arc<I>) -> ImageBuffer<Rgba<u8>, Vec<u8>>
+    where I: ImageAccess + Send + Sync + 'static
+{
+    let [width, height] = image.dimensions().width_height();
+    let pixels = (width * height) as usize * RGBA8_BYTES;
+    let buf = memory::readback::<u8>(&queue, pixels).expect("Failed to create buffer");
+
+    batch::submit_and_wait(device.clone(), queue.clone(), |builder| builder
+        .copy_image_to_buffer(image, buf.clone()).unwrap());
+
+    let buffer_content = buf.read().unwrap();
+    rgba8_image(width, height, width as usize * RGBA8_BYTES, &buffer_content)
+}
+
+/* The other direction: puts `pixels` in a new storage image for compute shaders to work on. */
+pub fn upload_rgba8(device: Arc<Device>, queue: Arc<Queue>, pixels: &ImageBuffer<Rgba<u8>, Vec<u8>>)
+    -> Arc<StorageImage<Format>>
+{
+    let image = storage::image(&queue, Dimensions::Dim2d { width: pixels.width(), height: pixels.height() },
+        Format::R8G8B8A8Unorm);
+
+    let buf = memory::buffer(CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(),
+        pixels.iter().cloned()).expect("Failed to create buffer"));
+    batch::submit_and_wait(device.clone(), queue.clone(), |builder| builder
+        .copy_buffer_to_image(buf, image.clone()).unwrap());
+
+    image
+}
+
+/* Like `rgba8_image`, but for pixels of any `format` we know how to convert, so renders to float
+ * targets can be saved too. Values outside 0..1, which HDR targets are full of, are clamped, and
+ * single channel formats come out grey with an opaque alpha. */
+pub fn to_rgba8(format: Format, width: u32, height: u32, row_pitch: usize, data: &[u8])
+    -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String>
+{
+    let convert: fn(&[u8]) -> [u8; 4] = match format {
+        Format::R8G8B8A8Unorm => return Ok(rgba8_image(width, height, row_pitch, data)),
+        Format::B8G8R8A8Unorm => |texel| [texel[2], texel[1], texel[0], texel[3]],
+        Format::R16G16B16A16Sfloat => |texel| {
+            let channel = |i: usize| unorm(f16_to_f32(u16::from_ne_bytes([texel[i * 2], texel[i * 2 + 1]])));
+            [channel(0), channel(1), channel(2), channel(3)]
+        },
+        Format::R32Sfloat => |texel| {
+            let grey = unorm(f32::from_ne_bytes([texel[0], texel[1], texel[2], texel[3]]));
+            [grey, grey, grey, 255]
+        },
+        _ => return Err(format!("Can't convert {:?} pixels to RGBA8", format)),
+    };
+
+    let texel_bytes = format.size().unwrap();
+    let row_bytes = width as usize * texel_bytes;
+    assert!(row_pitch >= row_bytes, "Row pitch {} is smaller than a row of {} pixels", row_pitch, width);
+    assert!(data.len() >= row_pitch * (height as usize - 1) + row_bytes,
+        "{} bytes is too small for a {}x{} image with a row pitch of {}", data.len(), width, height, row_pitch);
+
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * RGBA8_BYTES);
+    for row in data.chunks(row_pitch).take(height as usize) {
+        for texel in row[..row_bytes].chunks(texel_bytes) {
+            pixels.extend_from_slice(&convert(texel));
+        }
+    }
+
+    Ok(ImageBuffer::from_raw(width, height, pixels).unwrap())
+}
+
+/* How `--channels` saves the demos whose output is a single value per pixel. RGBA repeats the
+ * value in red, green and blue like every other image, grey saves just the one channel. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channels {
+    Rgba,
+    Gray,
+}
+
+impl Channels {
+    pub fn parse(name: &str) -> Result<Channels, String> {
+        match name {
+            "rgba" => Ok(Channels::Rgba),
+            "gray" | "grey" => Ok(Channels::Gray),
+            _ => Err(format!("Unknown channels `{}`, expected rgba or gray", name)),
+        }
+    }
+}
+
+/* Like `to_rgba8`, but keeps the single channel of a scalar image as a grey level instead of
+ * spreading it over four. Formats with more than one channel are an error rather than quietly
+ * dropping all but the first. */
+pub fn to_luma8(format: Format, width: u32, height: u32, row_pitch: usize, data: &[u8])
+    -> Result<ImageBuffer<Luma<u8>, Vec<u8>>, String>
+{
+    let convert: fn(&[u8]) -> u8 = match format {
+        Format::R8Unorm => |texel| texel[0],
+        Format::R32Sfloat => |texel| unorm(f32::from_ne_bytes([texel[0], texel[1], texel[2], texel[3]])),
+        _ => return Err(format!("{:?} isn't a single channel format, so it can't be saved as grey", format)),
+    };
+
+    let texel_bytes = format.size().unwrap();
+    let row_bytes = width as usize * texel_bytes;
+    assert!(row_pitch >= row_bytes, "Row pitch {} is smaller than a row of {} pixels", row_pitch, width);
+    assert!(data.len() >= row_pitch * (height as usize - 1) + row_bytes,
+        "{} bytes is too small for a {}x{} image with a row pitch of {}", data.len(), width, height, row_pitch);
+
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for row in data.chunks(row_pitch).take(height as usize) {
+        pixels.extend(row[..row_bytes].chunks(texel_bytes).map(convert));
+    }
+
+    Ok(ImageBuffer::from_raw(width, height, pixels).unwrap())
+}
+
+/* Copies a single channel image into a buffer, waits for the copy and turns it into grey pixels. */
+pub fn download_luma8<I>(device: Arc<Device>, queue: Arc<Queue>, image: Arc<I>)
+    -> Result<ImageBuffer<Luma<u8>, Vec<u8>>, String>
+    where I: ImageAccess + Send + Sync + 'static
+{
+    let [width, height] = image.dimensions().width_height();
+    let format = image.format();
+    let texel_bytes = format.size().ok_or_else(|| format!("{:?} has no fixed texel size", format))?;
+    let buf = memory::readback::<u8>(&queue, (width * height) as usize * texel_bytes)?;
+
+    batch::submit_and_wait(device.clone(), queue.clone(), |builder| builder
+        .copy_image_to_buffer(image, buf.clone()).unwrap());
+
+    let buffer_content = buf.read().unwrap();
+    to_luma8(format, width, height, width as usize * texel_bytes, &buffer_content)
+}
+
+/* Reads an R32G32B32A32Sfloat image back as its floats, four per pixel, row by row, for
+ * renders whose values don't fit in 0..1. */
+pub fn download_rgba32f<I>(device: Arc<Device>, queue: Arc<Queue>, image: Arc<I>) -> Vec<f32>
+    where I: ImageAccess + Send + Sync + 'static
+{
+    assert!(image.format() == Format::R32G32B32A32Sfloat, "Can't read {:?} back as RGBA floats", image.format());
+    let [width, height] = image.dimensions().width_height();
+    let buf = memory::readback::<f32>(&queue, (width * height * 4) as usize).expect("Failed to create buffer");
+
+    batch::submit_and_wait(device, queue, |builder| builder
+        .copy_image_to_buffer(image, buf.clone()).unwrap());
+
+    let buffer_content = buf.read().unwrap();
+    buffer_content.to_vec()
+}
+
+fn unorm(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/* Rust has no 16-bit float type, so unpack the sign, exponent and mantissa by hand. */
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+/* Clears a small image of each supported format to the same color, reads it back and checks
+ * that `to_rgba8` turns every one of them into the pixel we expect, and that `to_luma8` takes
+ * only the single channel one. */
+pub fn verify_formats(device: Arc<Device>, queue: Arc<Queue>) {
+    let (width, height) = (4, 4);
+    let checks = [
+        (Format::R8G8B8A8Unorm, [64, 128, 255, 255]),
+        (Format::B8G8R8A8Unorm, [64, 128, 255, 255]),
+        (Format::R16G16B16A16Sfloat, [64, 128, 255, 255]),
+        (Format::R32Sfloat, [64, 64, 64, 255]),
+    ];
+
+    for &(format, expected) in &checks {
+        let usage = ImageUsage { transfer_source: true, transfer_destination: true, .. ImageUsage::none() };
+        let image = memory::image(AttachmentImage::with_usage(device.clone(), [width, height], format, usage).unwrap());
+        let texel_bytes = format.size().unwrap();
+        let buf = memory::readback::<u8>(&queue, (width * height) as usize * texel_bytes)
+            .expect("Failed to create buffer");
+
+        /* 2.0 is out of range for the unorm target and has to be clamped for the float ones. */
+        batch::submit_and_wait(device.clone(), queue.clone(), |builder| builder
+            .clear_color_image(image.clone(), ClearValue::Float([0.25, 0.5, 2.0, 1.0])).unwrap()
+            .copy_image_to_buffer(image.clone(), buf.clone()).unwrap());
+
+        let buffer_content = buf.read().unwrap();
+        let converted = to_rgba8(format, width, height, width as usize * texel_bytes, &buffer_content).unwrap();
+        for pixel in converted.pixels() {
+            for channel in 0..4 {
+                assert!((pixel[channel] as i32 - expected[channel]).abs() <= 1,
+                    "{:?} read back as {:?}, expected {:?}", format, pixel, expected);
+            }
+        }
+
+        /* Only the one single channel format can be read back as grey. */
+        match to_luma8(format, width, height, width as usize * texel_bytes, &buffer_content) {
+            Ok(grey) => {
+                assert!(format == Format::R32Sfloat, "{:?} has several channels but was read back as grey", format);
+                assert!(grey.pixels().all(|pixel| (pixel[0] as i32 - expected[0]).abs() <= 1),
+                    "{:?} read back as the wrong grey", format);
+            }
+            Err(_) => assert!(format != Format::R32Sfloat, "{:?} couldn't be read back as grey", format),
+        }
+    }
+}
+
+/* Waits for `future` to finish on the GPU and only then locks both buffers for reading. Taking
+ * the future by value means there is no way to get at the contents while the commands that
+ * write them may still be running, and the returned guards borrow the buffers so they can't
+ * outlive them. Holding both guards at once lets the caller compare the two directly. */
+pub fn read_after<'a, F, A, B>(future: F, a: &'a CpuAccessibleBuffer<A>, b: &'a CpuAccessibleBuffer<B>)
+    -> (ReadLock<'a, A>, ReadLock<'a, B>)
+    where F: GpuFuture,
+          A: ?Sized + Content + 'static,
+          B: ?Sized + Content + 'static
+{
+    future.then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    (a.read().unwrap(), b.read().unwrap())
+}
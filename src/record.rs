@@ -0,0 +1,253 @@
+use image::{ImageBuffer, Rgba};
+use std::f32::consts::PI;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder};
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::Dimensions;
+
+use crate::compute::{self, ComputeTask};
+use crate::mandelbrot::{self, Params, View};
+use crate::memory;
+use crate::mesh::{self, Mesh, MeshRenderer};
+use crate::pacing::{self, FramePool, PacingStats};
+use crate::readback::{self, RGBA8_BYTES};
+use crate::storage;
+use crate::triangle::SIZE;
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/* The animations `--record` knows. */
+pub const ANIMATIONS: [&str; 2] = ["cube", "mandelbrot"];
+
+/* What `--frames` defaults to with `--record`. */
+pub const DEFAULT_FRAMES: u32 = 120;
+
+/* The frame rate `--ffmpeg` encodes at. */
+pub const FPS: u32 = 30;
+
+/* Parses the name of an animation to record. */
+pub fn parse(name: &str) -> Result<&'static str, String> {
+    ANIMATIONS.iter().find(|&&animation| animation == name).cloned()
+        .ok_or_else(|| format!("Unknown animation `{}`, expected one of {}", name, ANIMATIONS.join(", ")))
+}
+
+/* What to record: the cube spinning once, or the mandelbrot zooming into the center of `view`,
+ * twice as close every second, at width x height. */
+#[derive(Debug, Clone, Copy)]
+pub enum Animation {
+    Cube,
+    Mandelbrot { view: View, width: u32, height: u32 },
+}
+
+impl Animation {
+    pub fn dimensions(&self) -> (u32, u32) {
+        match *self {
+            Animation::Cube => (SIZE, SIZE),
+            Animation::Mandelbrot { width, height, .. } => (width, height),
+        }
+    }
+
+    /* The mandelbrot view frame `index` is drawn at. */
+    fn zoomed(view: View, index: u32) -> View {
+        let zoom = view.magnification() * 2f32.powf(index as f32 / FPS as f32);
+        View { center: view.center(), zoom, region: None, .. view }
+    }
+
+    /* The cube's turn at frame `index` of `frames`. */
+    fn angle(index: u32, frames: u32) -> f32 {
+        index as f32 / frames as f32 * PI * 2.0
+    }
+
+    /* `slots` sets of whatever a frame is drawn into, each recording a frame and the copy of it
+     * into its own readback buffer. The pipelines and the cube's mesh are shared. */
+    fn slots(&self, device: Arc<Device>, queue: Arc<Queue>, frames: u32, slots: usize) -> Vec<Slot> {
+        let (width, height) = self.dimensions();
+        match *self {
+            Animation::Cube => {
+                let (vertices, indices) = mesh::cube();
+                let mesh = Arc::new(Mesh::new(queue.clone(), vertices, indices));
+                (0..slots).map(|_| {
+                    let renderer = MeshRenderer::new(device.clone(), queue.clone());
+                    let readback = memory::readback(&queue, (width * height) as usize * RGBA8_BYTES)
+                        .expect("Failed to create buffer");
+                    let (queue, mesh, target) = (queue.clone(), mesh.clone(), readback.clone());
+                    let draw = Box::new(move |index| {
+                        let builder = AutoCommandBufferBuilder::primary_one_time_submit(queue.device().clone(),
+                            queue.family()).unwrap();
+                        renderer.record(builder, &mesh, mesh::spinning(Animation::angle(index, frames)))
+                            .copy_image_to_buffer(renderer.image(), target.clone()).unwrap()
+                            .build().unwrap()
+                    });
+                    Slot { draw, readback }
+                }).collect()
+            }
+            Animation::Mandelbrot { view, .. } => {
+                let pipeline = mandelbrot::pipeline_with(device.clone(), Params::PushConstants);
+                (0..slots).map(|_| {
+                    let image = storage::image(&queue, Dimensions::Dim2d { width, height }, Format::R8G8B8A8Unorm);
+                    let set = mandelbrot::image_set(pipeline.clone(), image.clone()).unwrap();
+                    let readback = memory::readback(&queue, (width * height) as usize * RGBA8_BYTES)
+                        .expect("Failed to create buffer");
+                    let (queue, pipeline, target) = (queue.clone(), pipeline.clone(), readback.clone());
+                    let draw = Box::new(move |index| {
+                        let push_constants = Animation::zoomed(view, index).push_constants(width, height);
+                        let task = ComputeTask::new("record", pipeline.clone(), set.clone(),
+                            compute::group_count(width, height), push_constants);
+                        let builder = AutoCommandBufferBuilder::primary_one_time_submit(queue.device().clone(),
+                            queue.family()).unwrap();
+                        task.record(builder)
+                            .copy_image_to_buffer(image.clone(), target.clone()).unwrap()
+                            .build().unwrap()
+                    });
+                    Slot { draw, readback }
+                }).collect()
+            }
+        }
+    }
+}
+
+/* Records one frame into its slot's target and readback buffer. */
+struct Slot {
+    draw: Box<dyn Fn(u32) -> AutoCommandBuffer>,
+    readback: Arc<CpuAccessibleBuffer<[u8]>>,
+}
+
+/* Renders `frames` frames of `animation` and hands each to `consume` in order, on a thread of
+ * its own, stopping at the first error it returns, and says how the frames were paced. `in_flight`
+ * frames are in flight at a time, each in a target and readback buffer of its own: while the GPU
+ * draws one, the CPU copies the last one out of its buffer, and the thread encodes the one
+ * before. A slot is only drawn into again once the frame it last held has been copied out, which
+ * is what keeps the two from tearing. */
+pub fn record<F>(device: Arc<Device>, queue: Arc<Queue>, animation: Animation, frames: u32, in_flight: usize,
+                 mut consume: F) -> Result<PacingStats, String>
+    where F: FnMut(u32, Image) -> Result<(), String> + Send + 'static
+{
+    let (width, height) = animation.dimensions();
+    let slots = animation.slots(device.clone(), queue.clone(), frames, in_flight);
+
+    let (sender, receiver) = mpsc::sync_channel::<(u32, Image)>(in_flight);
+    let worker = thread::spawn(move || -> Result<(), String> {
+        for (index, image) in receiver {
+            consume(index, image)?;
+        }
+        Ok(())
+    });
+
+    /* Sends a finished frame off, unless the thread has already stopped. */
+    let send = |slot: usize, index: u32| {
+        let content = slots[slot].readback.read().unwrap();
+        let image = readback::rgba8_image(width, height, width as usize * RGBA8_BYTES, &content);
+        sender.send((index, image)).is_ok()
+    };
+
+    let mut pool = FramePool::new(device, queue, in_flight);
+    let mut sending = true;
+    for index in 0..frames {
+        let (slot, finished) = pool.begin();
+        if let Some(finished) = finished {
+            sending = send(slot, finished);
+            if !sending {
+                break;
+            }
+        }
+        pool.submit(slot, (slots[slot].draw)(index));
+    }
+    let stats = pool.finish(|slot, index| sending = sending && send(slot, index));
+
+    drop(sender);
+    worker.join().unwrap()?;
+    Ok(stats)
+}
+
+/* Records `animation` to frame_NNNN.png, or with `ffmpeg` pipes the frames to ffmpeg to encode
+ * into that file instead, and prints how fast it went and how the frames were paced. */
+pub fn save(device: Arc<Device>, queue: Arc<Queue>, animation: Animation, frames: u32, in_flight: usize,
+            ffmpeg: Option<&Path>) -> Result<(), String>
+{
+    let start = Instant::now();
+    let stats = match ffmpeg {
+        None => record(device, queue, animation, frames, in_flight, |index, image| {
+            let path = format!("frame_{:04}.png", index);
+            image.save(&path).map_err(|e| format!("Could not write {}: {}", path, e))?;
+            println!("Wrote {}", path);
+            Ok(())
+        })?,
+        Some(output) => {
+            let (width, height) = animation.dimensions();
+            let mut child = Command::new("ffmpeg")
+                .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgba"])
+                .args(["-s", &format!("{}x{}", width, height), "-r", &FPS.to_string(), "-i", "-"])
+                .args(["-pix_fmt", "yuv420p"])
+                .arg(output)
+                .stdin(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("Could not start ffmpeg: {}", e))?;
+            let mut stdin = child.stdin.take().unwrap();
+            let recorded = record(device, queue, animation, frames, in_flight, move |_, image| {
+                stdin.write_all(&image.into_raw()).map_err(|e| format!("Could not write to ffmpeg: {}", e))
+            });
+            let status = child.wait().map_err(|e| format!("ffmpeg didn't finish: {}", e))?;
+            let stats = recorded?;
+            if !status.success() {
+                return Err(format!("ffmpeg failed with {}", status));
+            }
+            println!("Wrote {}", output.display());
+            stats
+        }
+    };
+
+    let seconds = start.elapsed().as_secs_f64();
+    println!("Recorded {} frames in {:.2} s, {:.1} frames a second", frames, seconds, frames as f64 / seconds);
+    println!("{}", stats.summary());
+    Ok(())
+}
+
+/* Records a few frames of each animation, with two and three frames in flight, more than there
+ * are slots so every one gets reused, and checks they come out in order and exactly as drawing
+ * each frame on its own does. A frame read from the wrong slot, or from one drawn into again too
+ * early, would be another frame. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    assert!(parse("cube") == Ok("cube") && parse("triangle").is_err(), "The animation names didn't parse");
+
+    let frames = 5;
+    let view = View { center: [-0.75, 0.1], .. View::default() };
+    let renderer = MeshRenderer::new(device.clone(), queue.clone());
+    let (vertices, indices) = mesh::cube();
+    let mesh = Mesh::new(queue.clone(), vertices, indices);
+    let pipeline = mandelbrot::pipeline_with(device.clone(), Params::PushConstants);
+
+    let zoom = Animation::Mandelbrot { view, width: 72, height: 40 };
+    for &(animation, in_flight) in &[(Animation::Cube, 2), (Animation::Cube, 3), (zoom, 2), (zoom, 3)] {
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let sink = recorded.clone();
+        let stats = record(device.clone(), queue.clone(), animation, frames, in_flight, move |index, image| {
+            sink.lock().unwrap().push((index, image));
+            Ok(())
+        }).unwrap();
+        assert!(stats.frames == frames, "The {:?} stats count {} frames, not {}", animation, stats.frames, frames);
+
+        let recorded = recorded.lock().unwrap();
+        assert!(recorded.iter().map(|&(index, _)| index).eq(0..frames), "The {:?} frames came out of order", animation);
+        for (index, image) in recorded.iter() {
+            let expected = match animation {
+                Animation::Cube => renderer.draw(&mesh, mesh::spinning(Animation::angle(*index, frames))),
+                Animation::Mandelbrot { view, width, height } => mandelbrot::render(device.clone(), queue.clone(),
+                    pipeline.clone(), width, height, Animation::zoomed(view, *index)).unwrap(),
+            };
+            assert!(image.clone().into_raw() == expected.into_raw(), "Frame {} of {:?} isn't what it should be",
+                index, animation);
+        }
+    }
+
+    let stopped = record(device, queue, Animation::Cube, frames, pacing::DEFAULT_FRAMES, |index, _| {
+        if index == 1 { Err("stop".to_string()) } else { Ok(()) }
+    });
+    assert!(stopped.err() == Some("stop".to_string()), "Recording didn't stop at the first error");
+}
@@ -0,0 +1,43 @@
+#version 450
+
+layout(local_size_x = 256, local_size_y = 1, local_size_z = 1) in;
+
+/* Must match LOCAL_SIZE in reduce.rs. */
+#define LOCAL_SIZE 256
+
+layout(set = 0, binding = 0) readonly buffer Input {
+    uint values[];
+} src;
+
+layout(set = 0, binding = 1) writeonly buffer Output {
+    uint sums[];
+} dst;
+
+layout(push_constant) uniform PushConstants {
+    uint count;
+} pc;
+
+shared uint partial[LOCAL_SIZE];
+
+/* Sums LOCAL_SIZE values per workgroup: every invocation loads one, then half of them add in
+ * the other half's, then a quarter, and so on, with a barrier between steps so every add sees
+ * the previous step's results. Shared memory is what makes that fast, being on-chip and
+ * visible to the whole workgroup. The first invocation writes the group's sum. */
+void main() {
+    uint idx = gl_GlobalInvocationID.x;
+    uint local = gl_LocalInvocationID.x;
+
+    partial[local] = idx < pc.count ? src.values[idx] : 0;
+    barrier();
+
+    for (uint stride = LOCAL_SIZE / 2; stride > 0; stride /= 2) {
+        if (local < stride) {
+            partial[local] += partial[local + stride];
+        }
+        barrier();
+    }
+
+    if (local == 0) {
+        dst.sums[gl_WorkGroupID.x] = partial[0];
+    }
+}
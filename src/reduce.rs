@@ -0,0 +1,94 @@
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::pipeline::ComputePipeline;
+
+use crate::memory;
+use crate::watchdog;
+
+/* Must match the local size in reduce.glsl. */
+const LOCAL_SIZE: u32 = 256;
+
+/* Lengths to check, including ones that need several passes and ones that leave a workgroup
+ * partly empty. */
+const SIZES: &[u32] = &[1, 255, 256, 257, 100_000, 1 << 20];
+
+/* Adds up `values` on the GPU, wrapping on overflow. Each pass sums every workgroup's worth of
+ * values into one, in shared memory, so a pass shrinks the list LOCAL_SIZE times. The passes
+ * bounce between two buffers until one value is left, all in one command buffer.
+ *
+ * The same reduction is usually done with subgroup operations these days: `subgroupAdd` sums a
+ * value across the invocations of a subgroup, the 32 or 64 a GPU really runs in lockstep,
+ * without going through shared memory or barriers at all. Those need Vulkan 1.1, and vulkano
+ * 0.11 only ever creates Vulkan 1.0 instances, so this sticks to shared memory. */
+pub fn gpu_sum(device: Arc<Device>, queue: Arc<Queue>, values: &[u32]) -> u32 {
+    if values.is_empty() {
+        return 0;
+    }
+
+    let groups = |count: u32| count.div_ceil(LOCAL_SIZE);
+    let a = memory::upload_to_device_local(queue.clone(), memory::storage_buffer(), values.iter().cloned())
+        .expect("Failed to create buffer");
+    let b = memory::device_local::<u32>(&queue, memory::storage_buffer(), groups(values.len() as u32) as usize)
+        .expect("Failed to create buffer");
+    let readback = memory::readback::<u32>(&queue, 1).expect("Failed to create buffer");
+
+    let shader = cs::Shader::load(device.clone()).expect("Failed to create reduction shader module");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())
+        .expect("Failed to create reduction pipeline"));
+
+    let a_to_b = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_buffer(a.clone()).unwrap()
+        .add_buffer(b.clone()).unwrap()
+        .build().unwrap());
+    let b_to_a = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_buffer(b.clone()).unwrap()
+        .add_buffer(a.clone()).unwrap()
+        .build().unwrap());
+
+    let mut builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+    let mut count = values.len() as u32;
+    let mut forwards = true;
+    while count > 1 {
+        let set = if forwards { a_to_b.clone() } else { b_to_a.clone() };
+        builder = builder
+            .dispatch([groups(count), 1, 1], pipeline.clone(), set, cs::ty::PushConstants { count })
+            .unwrap();
+        count = groups(count);
+        forwards = !forwards;
+    }
+
+    /* An even number of passes leaves the result back in `a`. Only the one value is read back,
+     * the rest never leaves device local memory. */
+    let result = if forwards { a } else { b };
+    let command_buffer = builder.copy_buffer(result, readback.clone()).unwrap().build().unwrap();
+    watchdog::wait(command_buffer.execute(queue.clone()).unwrap(), "reduce");
+
+    let content = readback.read().unwrap();
+    content[0]
+}
+
+/* Sums a few lengths of pseudo-random data and checks each against the CPU. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    println!("Subgroup operations need Vulkan 1.1, which this vulkano can't create; using shared memory only");
+
+    for &n in SIZES {
+        let mut state = n as u64;
+        let values: Vec<u32> = (0..n).map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 32) as u32
+        }).collect();
+
+        let expected = values.iter().fold(0u32, |sum, &v| sum.wrapping_add(v));
+        let sum = gpu_sum(device.clone(), queue.clone(), &values);
+        assert!(sum == expected, "GPU sum of {} values is {}, expected {}", n, sum, expected);
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/reduce.glsl"
+    }
+}
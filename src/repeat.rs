@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+use std::os::raw::c_int;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use vulkano::command_buffer::{AutoCommandBuffer, CommandBuffer};
+use vulkano::device::Queue;
+
+use crate::watchdog;
+
+/* The demos `--repeat` can loop. */
+pub const DEMOS: [&str; 2] = ["mandelbrot", "multiply"];
+
+/* How many of the latest runs the rolling average is over. */
+const WINDOW: usize = 100;
+
+/* How often the running count is printed. */
+const REPORT_EVERY: Duration = Duration::from_secs(1);
+
+/* From <signal.h>. SIGINT is 2 everywhere, and `signal` is in the C library std already links
+ * against, so catching Ctrl-C doesn't need a crate. Handlers are passed as plain addresses, 0
+ * being the default handler. */
+const SIGINT: c_int = 2;
+const SIG_DFL: usize = 0;
+
+extern "C" {
+    fn signal(signum: c_int, handler: usize) -> usize;
+}
+
+/* Set when Ctrl-C is pressed. A signal handler can interrupt the program anywhere, even with a
+ * lock held, so all it may safely do is set a flag for the loop to find. It also puts the default
+ * handler back, so if the GPU has hung and the loop never looks again, a second Ctrl-C still
+ * quits. */
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn interrupt(_: c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    unsafe {
+        signal(SIGINT, SIG_DFL);
+    }
+}
+
+/* Checks `demo` is one `--repeat` knows. */
+pub fn parse(demo: &str) -> Result<&'static str, String> {
+    DEMOS.iter().find(|&&d| d == demo).cloned()
+        .ok_or_else(|| format!("Can't repeat `{}`, expected one of {}", demo, DEMOS.join(", ")))
+}
+
+/* Submits `command_buffer` to `queue` over and over until Ctrl-C, waiting for each run before the
+ * next, for checking that a GPU stays stable and its memory use stays flat over a long run. The
+ * command buffer is recorded once and everything it uses is allocated once, so any growth is
+ * the driver's. The run count and the average over the last WINDOW runs are printed every
+ * REPORT_EVERY, rewriting the same line, and once interrupted a summary of the whole run. */
+pub fn run(demo: &'static str, queue: Arc<Queue>, command_buffer: AutoCommandBuffer) {
+    let command_buffer = Arc::new(command_buffer);
+    unsafe {
+        signal(SIGINT, interrupt as extern "C" fn(c_int) as usize);
+    }
+    println!("Repeating {} until Ctrl-C", demo);
+
+    let start = Instant::now();
+    let mut last_report = start;
+    let mut latest = VecDeque::with_capacity(WINDOW);
+    let (mut runs, mut total, mut fastest, mut slowest) = (0u64, 0f64, f64::INFINITY, 0f64);
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        let run_start = Instant::now();
+        watchdog::wait(command_buffer.clone().execute(queue.clone()).unwrap(), demo);
+        let ms = run_start.elapsed().as_secs_f64() * 1000.0;
+
+        runs += 1;
+        total += ms;
+        fastest = fastest.min(ms);
+        slowest = slowest.max(ms);
+        if latest.len() == WINDOW {
+            latest.pop_front();
+        }
+        latest.push_back(ms);
+
+        if last_report.elapsed() >= REPORT_EVERY {
+            last_report = Instant::now();
+            let average = latest.iter().sum::<f64>() / latest.len() as f64;
+            print!("\r{} runs, {:.2} ms on average over the last {}", runs, average, latest.len());
+            std::io::Write::flush(&mut std::io::stdout()).unwrap();
+        }
+    }
+
+    println!();
+    if runs == 0 {
+        println!("Interrupted before the first run finished");
+        return;
+    }
+    println!("Ran {} {} times in {:.1} s: {:.2} ms on average, {:.2} ms at the fastest and {:.2} ms at the slowest",
+        demo, runs, start.elapsed().as_secs_f64(), total / runs as f64, fastest, slowest);
+}
@@ -0,0 +1,19 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+/* The resolved render, read texel by texel, so the sampler's filtering never comes into it. */
+layout(set = 0, binding = 0) uniform sampler2D src;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D dst;
+
+/* Inverts the color of each pixel, leaving alpha alone, like invert.glsl but reading through a
+ * sampler rather than a storage image. */
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coords, imageSize(dst)))) {
+        return;
+    }
+
+    vec4 color = texelFetch(src, coords, 0);
+    imageStore(dst, coords, vec4(vec3(1.0) - color.rgb, color.a));
+}
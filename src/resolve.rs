@@ -0,0 +1,114 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::framebuffer::{Framebuffer, Subpass};
+use vulkano::image::{AttachmentImage, Dimensions, ImageUsage};
+use vulkano::pipeline::{ComputePipeline, GraphicsPipeline};
+use vulkano::sampler::Sampler;
+use vulkano::sync::GpuFuture;
+
+use crate::memory;
+use crate::readback;
+use crate::storage;
+use crate::triangle::{self, SIZE};
+
+/* Must match the local size in resolve.glsl. */
+const LOCAL_SIZE: u32 = 8;
+
+/* The sample count asked for, or the most the device supports below it. */
+const SAMPLES: u32 = 4;
+
+/* The sample count the demo draws with on this device. */
+fn samples(device: &Device) -> u32 {
+    triangle::sample_count(device.physical_device().limits().framebuffer_color_sample_counts(), SAMPLES).unwrap()
+}
+
+/* Draws the triangle with multisampling and inverts it with a compute shader. A multisampled
+ * image holds several values per pixel, and a compute shader can't treat it as an ordinary
+ * image: storage images with samples need a device feature, and even then each sample has to
+ * be read and averaged by hand. So the render pass resolves it first, into a single sampled
+ * attachment, and it is that resolved image the compute shader reads. The attachment is bound
+ * through a sampler since vulkano keeps attachments in a layout storage images can't use, and
+ * vulkano moves it from the layout it was drawn in to the one for reading between the pass and
+ * the dispatch, all in one command buffer. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>, samples: u32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let format = Format::R8G8B8A8Unorm;
+    let render_pass = triangle::multisampled_render_pass(device.clone(), format, samples);
+
+    let vs = triangle::vertex::Shader::load(device.clone()).expect("Failed to create vertex shader");
+    let fs = triangle::frag::Shader::load(device.clone()).expect("Failed to create fragment shader");
+    let graphics = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<triangle::Vertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let multisampled = memory::image(AttachmentImage::transient_multisampled(device.clone(), [SIZE, SIZE], samples,
+        format).unwrap());
+    let usage = ImageUsage { color_attachment: true, sampled: true, .. ImageUsage::none() };
+    let resolved = memory::image(AttachmentImage::with_usage(device.clone(), [SIZE, SIZE], format, usage).unwrap());
+    let framebuffer = Arc::new(Framebuffer::start(render_pass)
+        .add(multisampled).unwrap()
+        .add(resolved.clone()).unwrap()
+        .build().unwrap());
+
+    let shader = cs::Shader::load(device.clone()).expect("Could not load resolve shader");
+    let compute = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+    let destination = storage::image(&queue, Dimensions::Dim2d { width: SIZE, height: SIZE }, format);
+    let set = Arc::new(PersistentDescriptorSet::start(compute.clone(), 0)
+        .add_sampled_image(resolved, Sampler::simple_repeat_linear_no_mipmap(device.clone())).unwrap()
+        .add_image(destination.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = [SIZE.div_ceil(LOCAL_SIZE), SIZE.div_ceil(LOCAL_SIZE), 1];
+    AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer, false, vec![triangle::CLEAR_COLOR.into(), ClearValue::None]).unwrap()
+        .draw(graphics, &triangle::dynamic_state(), triangle::vertex_buffer(device.clone()), (), ()).unwrap()
+        .end_render_pass().unwrap()
+        .dispatch(groups, compute, set, ()).unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    readback::download_rgba8(device, queue, destination)
+}
+
+/* Renders the demo with the device's sample count. */
+pub fn render_default(device: Arc<Device>, queue: Arc<Queue>) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let samples = samples(&device);
+    render(device, queue, samples)
+}
+
+/* Checks the post-processed image is exactly the triangle demo's own resolved render, inverted,
+ * and that the edges came out antialiased. Partly covered pixels are only partly opaque after a
+ * resolve, so if the shader had read one sample, or an unresolved image, they'd be missing. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let samples = samples(&device);
+    let inverted = render(device.clone(), queue.clone(), samples);
+    let resolved = triangle::render(device, queue, None, Format::R8G8B8A8Unorm, samples).unwrap();
+
+    for (x, y, pixel) in inverted.enumerate_pixels() {
+        let original = resolved.get_pixel(x, y);
+        let expected = [255 - original[0], 255 - original[1], 255 - original[2], original[3]];
+        assert!(pixel.data == expected, "Pixel {},{} is {:?} after inverting {:?}", x, y, pixel, original);
+    }
+
+    if samples > 1 {
+        let edges = inverted.pixels().filter(|pixel| pixel[3] > 0 && pixel[3] < 255).count();
+        assert!(edges > 0, "No pixel was partly covered with {}x MSAA", samples);
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/resolve.glsl"
+    }
+}
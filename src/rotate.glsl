@@ -0,0 +1,45 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D src;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D dst;
+
+/* The rotation's cosine and sine, and what to fill in where the rotated image doesn't reach. */
+layout(push_constant) uniform PushConstants {
+    vec4 background;
+    vec2 rotation;
+} pc;
+
+/* A texel of the source, or the background off its edges, so the image blends into the
+ * background there instead of smearing its border outwards. */
+vec4 texel(ivec2 coords) {
+    if (any(lessThan(coords, ivec2(0))) || any(greaterThanEqual(coords, imageSize(src)))) {
+        return pc.background;
+    }
+    return imageLoad(src, coords);
+}
+
+/* Rotating by pushing each source pixel to where it lands would leave gaps and pile some
+ * pixels on top of each other, so this works backwards: each destination pixel rotates its
+ * own center the opposite way about the middle of the image to find where it came from, and
+ * mixes the four source texels around that point by how close it is to each. The image is
+ * y down, so the rotation below turns it counterclockwise as it appears. */
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coords, imageSize(dst)))) {
+        return;
+    }
+
+    vec2 center = vec2(imageSize(src)) / 2.0;
+    vec2 d = vec2(coords) + 0.5 - center;
+    float c = pc.rotation.x;
+    float s = pc.rotation.y;
+    vec2 position = center + vec2(c * d.x - s * d.y, s * d.x + c * d.y) - 0.5;
+
+    ivec2 base = ivec2(floor(position));
+    vec2 t = position - vec2(base);
+    vec4 top = mix(texel(base), texel(base + ivec2(1, 0)), t.x);
+    vec4 bottom = mix(texel(base + ivec2(0, 1)), texel(base + ivec2(1, 1)), t.x);
+    imageStore(dst, coords, mix(top, bottom, t.y));
+}
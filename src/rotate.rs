@@ -0,0 +1,71 @@
+use image::{ImageBuffer, Rgba};
+use std::path::PathBuf;
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+use vulkano::pipeline::ComputePipeline;
+
+use crate::input::{self, Input};
+use crate::pattern;
+
+/* What `--degrees` defaults to. */
+pub const DEFAULT_DEGREES: f32 = 30.0;
+
+/* What the corners the rotated image no longer covers are filled with. */
+pub const BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.0];
+
+/* Rotates `pixels` counterclockwise by `degrees` about their center, keeping their size, so the
+ * corners of the original are cut off and new ones filled with BACKGROUND. */
+pub fn rotate_image(device: Arc<Device>, queue: Arc<Queue>, pixels: &ImageBuffer<Rgba<u8>, Vec<u8>>, degrees: f32)
+    -> ImageBuffer<Rgba<u8>, Vec<u8>>
+{
+    let shader = cs::Shader::load(device.clone()).expect("Could not load rotate shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let push_constants = cs::ty::PushConstants { background: BACKGROUND, rotation: [cos, sin] };
+    input::filter("rotate", device, queue, pixels, pipeline, push_constants)
+}
+
+/* Rotates the image loaded for `--input` and saves the result next to it as <name>_rotated.png,
+ * returning where it went. */
+pub fn rotate(device: Arc<Device>, queue: Arc<Queue>, input: &Input, degrees: f32) -> Result<PathBuf, String> {
+    input.save(&rotate_image(device, queue, &input.pixels, degrees), "rotated")
+}
+
+/* Checks the rotations that land exactly on pixels: none and a full turn leave the image as it
+ * was, and a quarter turn moves each pixel to the matching place along the other axis. An
+ * eighth of a turn of a square leaves its corners to the background. The sine and cosine
+ * aren't quite exact, so the bilinear mix can be a step or so off. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let size = 64;
+    let source = pattern::uv(device.clone(), queue.clone(), size, size);
+    let close = |a: &Rgba<u8>, b: &Rgba<u8>| (0..4).all(|c| (a[c] as i32 - b[c] as i32).abs() <= 2);
+
+    for &degrees in &[0.0, 360.0] {
+        let rotated = rotate_image(device.clone(), queue.clone(), &source, degrees);
+        for (x, y, pixel) in rotated.enumerate_pixels() {
+            assert!(close(pixel, source.get_pixel(x, y)),
+                "Rotating by {} degrees moved ({}, {}) from {:?} to {:?}", degrees, x, y, source.get_pixel(x, y), pixel);
+        }
+    }
+
+    let quarter = rotate_image(device.clone(), queue.clone(), &source, 90.0);
+    for (x, y, pixel) in quarter.enumerate_pixels() {
+        let expected = source.get_pixel(size - 1 - y, x);
+        assert!(close(pixel, expected), "A quarter turn put {:?} at ({}, {}), expected {:?}", pixel, x, y, expected);
+    }
+
+    let background = Rgba(BACKGROUND.map(|c| (c * 255.0) as u8));
+    let eighth = rotate_image(device, queue, &source, 45.0);
+    for &(x, y) in &[(0, 0), (size - 1, 0), (0, size - 1), (size - 1, size - 1)] {
+        assert!(*eighth.get_pixel(x, y) == background, "An eighth of a turn left {:?} in the corner at ({}, {})",
+            eighth.get_pixel(x, y), x, y);
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/rotate.glsl"
+    }
+}
@@ -0,0 +1,63 @@
+#version 450
+
+layout(local_size_x = 256, local_size_y = 1, local_size_z = 1) in;
+
+/* Must match LOCAL_SIZE in primitives.rs. */
+#define LOCAL_SIZE 256
+
+/* The two passes of the prefix sum. */
+#define SCAN 0
+#define ADD 1
+
+layout(set = 0, binding = 0) buffer Data {
+    uint values[];
+} data;
+
+/* One total per workgroup of `data`: written by SCAN, and read by ADD once it has been scanned
+ * in turn. */
+layout(set = 0, binding = 1) buffer Blocks {
+    uint sums[];
+} blocks;
+
+layout(push_constant) uniform PushConstants {
+    uint count;
+    uint mode;
+} pc;
+
+shared uint partial[LOCAL_SIZE];
+
+/* SCAN replaces every value with the sum of it and the ones before it in its workgroup, and
+ * writes the workgroup's total. Each step adds in the value `offset` places back, doubling the
+ * offset every time, so after log2(LOCAL_SIZE) steps every value has all of those before it.
+ * Reading and writing `partial` are separated by barriers, since one invocation's write is
+ * another's read of the same step. ADD then adds the scanned totals of every workgroup before
+ * to each value, which makes the workgroups' sums into one. */
+void main() {
+    uint idx = gl_GlobalInvocationID.x;
+    uint local = gl_LocalInvocationID.x;
+    uint group = gl_WorkGroupID.x;
+
+    if (pc.mode == ADD) {
+        if (group > 0 && idx < pc.count) {
+            data.values[idx] += blocks.sums[group - 1];
+        }
+        return;
+    }
+
+    partial[local] = idx < pc.count ? data.values[idx] : 0;
+    barrier();
+
+    for (uint offset = 1; offset < LOCAL_SIZE; offset *= 2) {
+        uint before = local >= offset ? partial[local - offset] : 0;
+        barrier();
+        partial[local] += before;
+        barrier();
+    }
+
+    if (idx < pc.count) {
+        data.values[idx] = partial[local];
+    }
+    if (local == LOCAL_SIZE - 1) {
+        blocks.sums[group] = partial[local];
+    }
+}
@@ -0,0 +1,159 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, DeviceLocalBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::device::{Device, Queue};
+use vulkano::instance::Instance;
+
+use crate::debug_dump::Intermediate;
+use crate::descriptors::Descriptors;
+use crate::dryrun::Plan;
+use crate::error::SandboxError;
+use crate::instancing;
+use crate::mandelbrot;
+use crate::memory;
+use crate::multiply;
+use crate::triangle;
+use crate::watchdog;
+
+/* What a scene hands back once it has run: a picture, or the numbers a compute shader left in a
+ * buffer. */
+pub enum SceneOutput {
+    Image(ImageBuffer<Rgba<u8>, Vec<u8>>),
+    Values(Vec<u32>),
+}
+
+/* One experiment. Everything it needs, its buffers, images, pipelines and the sets binding them,
+ * which come from the `Runner`'s `Descriptors`, is created along with it, so all that is left
+ * when it runs is to record its commands into the command buffer it is handed, and to read back
+ * what they made once the `Runner` has submitted it and waited. A new experiment only has to
+ * implement this and be added to `Runner::scene` to run with `--scene`, without touching how
+ * the device is picked or the commands got to the GPU. */
+pub trait Scene {
+    /* What `--scene` calls it, and `--deadline` too. */
+    fn name(&self) -> &'static str;
+
+    /* Adds the scene's commands to `builder`. */
+    fn record(&self, builder: AutoCommandBufferBuilder) -> AutoCommandBufferBuilder;
+
+    /* What the commands made, once they have run. */
+    fn output(&self) -> SceneOutput;
+
+    /* How much a run gets through and of what, like 262144 pixels, for `--bench` to report a
+     * throughput. */
+    fn work(&self) -> (u64, &'static str);
+
+    /* The images and buffers it makes along the way, by name, for `--dump-after` to write out
+     * with its output. */
+    fn intermediates(&self) -> Vec<(&'static str, Intermediate)> {
+        Vec::new()
+    }
+}
+
+/* The scenes `--scene` can run. */
+pub const SCENES: [&str; 5] = ["copy-buffer", "multiply", "mandelbrot", "triangle", "instancing"];
+
+/* Checks `name` is one `--scene` knows. */
+pub fn parse(name: &str) -> Result<&'static str, String> {
+    SCENES.iter().find(|&&s| s == name).cloned()
+        .ok_or_else(|| format!("Unknown scene `{}`, expected one of {}", name, SCENES.join(", ")))
+}
+
+/* Whether the scene `name` draws and whether it dispatches compute shaders, so a run of only
+ * that scene can ask for a queue family that does just that. The copy needs neither: every
+ * family that can do anything can transfer. */
+pub fn needs(name: &str) -> (bool, bool) {
+    match name {
+        "copy-buffer" => (false, false),
+        "multiply" | "mandelbrot" => (false, true),
+        _ => (true, false),
+    }
+}
+
+/* How many numbers `CopyBuffer` copies. */
+const ELEMENTS: u32 = 64;
+
+/* The first thing the tutorial does with a device: copies 0 to 63 from one buffer to another.
+ * Both are device local, so what was copied goes on to a buffer the CPU can read as well. */
+pub struct CopyBuffer {
+    source: Arc<DeviceLocalBuffer<[u32]>>,
+    dest: Arc<DeviceLocalBuffer<[u32]>>,
+    readback: Arc<CpuAccessibleBuffer<[u32]>>,
+}
+
+impl CopyBuffer {
+    pub fn new(queue: Arc<Queue>) -> Result<CopyBuffer, SandboxError> {
+        let dest = memory::device_local(&queue, BufferUsage::none(), ELEMENTS as usize)?;
+        let readback = memory::readback(&queue, ELEMENTS as usize)?;
+        let source = memory::upload_to_device_local(queue, BufferUsage::none(), 0..ELEMENTS)?;
+        Ok(CopyBuffer { source, dest, readback })
+    }
+}
+
+impl Scene for CopyBuffer {
+    fn name(&self) -> &'static str {
+        "copy-buffer"
+    }
+
+    fn record(&self, builder: AutoCommandBufferBuilder) -> AutoCommandBufferBuilder {
+        builder.copy_buffer(self.source.clone(), self.dest.clone()).unwrap()
+            .copy_buffer(self.dest.clone(), self.readback.clone()).unwrap()
+    }
+
+    fn output(&self) -> SceneOutput {
+        SceneOutput::Values(self.readback.read().unwrap().to_vec())
+    }
+
+    fn work(&self) -> (u64, &'static str) {
+        (ELEMENTS as u64, "elements")
+    }
+
+    fn intermediates(&self) -> Vec<(&'static str, Intermediate)> {
+        vec![("source", Intermediate::U32(self.source.clone()))]
+    }
+}
+
+/* Owns what every scene runs on, the instance, the device, the queue and the descriptor sets
+ * the scenes bind, and does the part of running one that is the same for all of them: a command
+ * buffer to record into, submitting it and waiting for it to finish. */
+pub struct Runner {
+    pub instance: Arc<Instance>,
+    pub device: Arc<Device>,
+    pub queue: Arc<Queue>,
+    pub descriptors: Descriptors,
+}
+
+impl Runner {
+    pub fn new(instance: Arc<Instance>, device: Arc<Device>, queue: Arc<Queue>) -> Runner {
+        let descriptors = Descriptors::new(device.clone(), 1);
+        Runner { instance, device, queue, descriptors }
+    }
+
+    /* Builds the scene called `name`, at the sizes and in the formats `plan` gives. */
+    pub fn scene(&mut self, name: &str, plan: &Plan) -> Result<Box<dyn Scene>, SandboxError> {
+        Ok(match name {
+            "copy-buffer" => Box::new(CopyBuffer::new(self.queue.clone())?),
+            "multiply" => Box::new(multiply::scene(self.queue.clone(), &mut self.descriptors, plan.elements)?),
+            "mandelbrot" => Box::new(mandelbrot::scene(self.queue.clone(), plan.mandelbrot.clone(),
+                &mut self.descriptors, plan.width, plan.height, plan.view, plan.tile)?),
+            "triangle" => Box::new(triangle::scene(self.queue.clone(), &mut self.descriptors, plan.spv_shader,
+                plan.format, plan.samples)?),
+            "instancing" => Box::new(instancing::scene(self.queue.clone(), plan.instances)?),
+            _ => {
+                let e = parse(name).err().unwrap_or_else(|| format!("No scene is registered as `{}`", name));
+                return Err(e.into());
+            }
+        })
+    }
+
+    /* Records `scene` into a command buffer of its own, runs it and hands back its output. */
+    pub fn run(&self, scene: &dyn Scene) -> Result<SceneOutput, SandboxError> {
+        let submit = |e: &dyn std::error::Error| SandboxError::Submit(format!("{}: {}", scene.name(), e));
+        let builder = AutoCommandBufferBuilder::primary_one_time_submit(self.device.clone(), self.queue.family())
+            .map_err(|e| submit(&e))?;
+        let command_buffer = scene.record(builder).build().map_err(|e| submit(&e))?;
+        let future = command_buffer.execute(self.queue.clone()).map_err(|e| submit(&e))?;
+        watchdog::wait(future, scene.name());
+        Ok(scene.output())
+    }
+}
@@ -0,0 +1,85 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer, DynamicState};
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, Subpass};
+use vulkano::image::{AttachmentImage, ImageUsage};
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::viewport::Scissor;
+use vulkano::sync::GpuFuture;
+
+use crate::memory;
+use crate::readback;
+use crate::triangle::{self, SIZE};
+
+/* The part of the target the triangle is allowed to touch, a square around its middle. */
+const SCISSOR: Scissor = Scissor { origin: [192, 192], dimensions: [128, 128] };
+
+/* Draws the triangle with the scissor test on, so everything it covers outside SCISSOR is
+ * thrown away before the fragment shader runs. The viewport still covers the whole target, so
+ * the triangle lands where it always does and is only cut, not squashed. Both are dynamic state
+ * here, which means they are given when drawing rather than baked into the pipeline. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let render_pass = triangle::render_pass(device.clone(), Format::R8G8B8A8Unorm);
+
+    let vs = triangle::vertex::Shader::load(device.clone()).expect("Failed to create vertex shader");
+    let fs = triangle::frag::Shader::load(device.clone()).expect("Failed to create fragment shader");
+
+    let pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<triangle::Vertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .viewports_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let usage = ImageUsage { color_attachment: true, transfer_source: true, .. ImageUsage::none() };
+    let image = memory::image(AttachmentImage::with_usage(device.clone(), [SIZE, SIZE], Format::R8G8B8A8Unorm,
+        usage).unwrap());
+    let framebuffer = Arc::new(Framebuffer::start(render_pass)
+        .add(image.clone()).unwrap()
+        .build().unwrap());
+
+    let dynamic_state = DynamicState { scissors: Some(vec![SCISSOR]), .. triangle::dynamic_state() };
+
+    AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer, false, vec![triangle::CLEAR_COLOR.into()]).unwrap()
+        .draw(pipeline, &dynamic_state, triangle::vertex_buffer(device.clone()), (), ())
+        .unwrap()
+        .end_render_pass().unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    readback::download_rgba8(device, queue, image)
+}
+
+/* Inside the scissor the clipped triangle has to match the whole one pixel for pixel, and
+ * outside it nothing may have been drawn. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>, clipped: &ImageBuffer<Rgba<u8>, Vec<u8>>) {
+    let whole = triangle::render(device, queue, None, Format::R8G8B8A8Unorm, 1).unwrap();
+    let clear = Rgba(triangle::CLEAR_COLOR.map(|c| (c * 255.0) as u8));
+
+    let inside = |x: u32, y: u32| {
+        let (x, y) = (x as i32, y as i32);
+        let [left, top] = SCISSOR.origin;
+        x >= left && y >= top && x < left + SCISSOR.dimensions[0] as i32 && y < top + SCISSOR.dimensions[1] as i32
+    };
+
+    let mut cut = 0;
+    for (x, y, pixel) in clipped.enumerate_pixels() {
+        if inside(x, y) {
+            assert!(pixel == whole.get_pixel(x, y), "Pixel ({}, {}) inside the scissor is {:?}, expected {:?}",
+                x, y, pixel, whole.get_pixel(x, y));
+        } else {
+            assert!(*pixel == clear, "Pixel ({}, {}) outside the scissor was drawn", x, y);
+            if *whole.get_pixel(x, y) != clear {
+                cut += 1;
+            }
+        }
+    }
+    assert!(cut > 0, "The scissor didn't cut anything off the triangle");
+}
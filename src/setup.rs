@@ -0,0 +1,106 @@
+use std::iter;
+use std::sync::Arc;
+use vulkano::device::{Device, DeviceExtensions, Queue, RawDeviceExtensions};
+use vulkano::instance::{InstanceExtensions, PhysicalDevice};
+
+use crate::device::DeviceSelector;
+use crate::error::SandboxError;
+use crate::instance;
+use crate::queues;
+use crate::scene::Runner;
+use crate::transfer;
+
+/* The queue families a run wants besides the main one, which has to do what the selector asks
+ * for, and the extensions it would like but can do without. */
+#[derive(Debug, Clone)]
+pub struct Families {
+    /* Uploads on a transfer-only family of their own, where there is one. */
+    pub transfer: bool,
+    /* Compute work on a compute-only family of its own, where there is one. */
+    pub compute: bool,
+    pub optional_extensions: DeviceExtensions,
+}
+
+impl Families {
+    /* Just the main family, and nothing optional. */
+    pub fn main_only() -> Families {
+        Families { transfer: false, compute: false, optional_extensions: DeviceExtensions::none() }
+    }
+}
+
+/* An opened device and its queues. The transfer and compute queues are the main one again
+ * where there was no family of their own to be had, or none was asked for. */
+pub struct Opened {
+    pub device: Arc<Device>,
+    pub queue: Arc<Queue>,
+    pub transfer_queue: Arc<Queue>,
+    pub compute_queue: Arc<Queue>,
+    /* Every extension the device was opened with, the selector's and the optional ones it had. */
+    pub extensions: DeviceExtensions,
+}
+
+/* Opens `physical`, as the selector picked it, with one queue from the first family that can do
+ * what the selector asks for and one from each family of its own `families` wants. Optional
+ * extensions the device doesn't have are left out with a warning rather than failing the whole
+ * device, which is what asking `Device::new` for them would do. */
+pub fn open(physical: PhysicalDevice, selector: &DeviceSelector, families: &Families) -> Result<Opened, SandboxError> {
+    let queue_family = queues::choose_family(physical, selector.graphics, selector.compute)?;
+
+    let supported = DeviceExtensions::supported_by_device(physical);
+    let missing = families.optional_extensions.difference(&supported);
+    if missing != DeviceExtensions::none() {
+        eprintln!("Warning: the device doesn't support {:?}, running without them.", missing);
+    }
+    let extensions = RawDeviceExtensions::from(&selector.extensions)
+        .union(&RawDeviceExtensions::from(&families.optional_extensions.intersection(&supported)));
+
+    let transfer_family = if families.transfer {
+        let family = transfer::dedicated_family(physical, queue_family);
+        match family {
+            Some(family) => eprintln!("Uploading on queue family {}", family.id()),
+            None => eprintln!("Warning: the device has no transfer-only queue family, uploading on the main queue."),
+        }
+        family
+    } else {
+        None
+    };
+    let compute_family = if families.compute {
+        let family = queues::dedicated_compute_family(physical, queue_family);
+        match family {
+            Some(family) => eprintln!("Computing on queue family {}", family.id()),
+            None => eprintln!("Warning: the device has no compute-only queue family, computing on the main queue."),
+        }
+        family
+    } else {
+        None
+    };
+
+    /* The device comes back with a list of queue objects we can submit operations to, in the
+     * order the families were asked for. */
+    let requested: Vec<_> = iter::once(queue_family).chain(transfer_family).chain(compute_family)
+        .map(|family| (family, 0.5))
+        .collect();
+    let enabled = DeviceExtensions::from(&extensions);
+    let (device, mut queues) = Device::new(physical, &selector.features, extensions, requested)?;
+    /* We asked for one queue, but don't take it on faith that we got it. */
+    let queue = queues.next()
+        .ok_or_else(|| SandboxError::NoDevice(format!("{} has no queue in queue family {}", physical.name(),
+            queue_family.id())))?;
+    let transfer_queue = transfer_family.and_then(|_| queues.next()).unwrap_or_else(|| queue.clone());
+    let compute_queue = compute_family.and_then(|_| queues.next()).unwrap_or_else(|| queue.clone());
+    Ok(Opened { device, queue, transfer_queue, compute_queue, extensions: enabled })
+}
+
+/* Gets a device running the way `main` does when no flags say otherwise: an instance without
+ * layers, the best device that can do `graphics` and `compute`, or the one the environment
+ * picks, and one queue from the first family that can do both. This is for the tests and other
+ * binaries, which have no options to parse but should still pick the same device a plain run
+ * would. Having no Vulkan driver or device is an error like any other, so a test can tell it
+ * apart and skip. */
+pub fn headless(graphics: bool, compute: bool) -> Result<Runner, SandboxError> {
+    let instance = instance::create(&[], InstanceExtensions::none(), None)?.instance;
+    let selector = DeviceSelector::new(graphics, compute).with_env()?;
+    let physical = selector.select(&instance)?;
+    let opened = open(physical, &selector, &Families::main_only())?;
+    Ok(Runner::new(instance.clone(), opened.device, opened.queue))
+}
@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) in vec3 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    /* Deliberately expensive, the way real lighting is, so that the cost of shading fragments
+     * that end up hidden shows. */
+    vec3 c = v_color;
+    for (int i = 0; i < 512; i++) {
+        c = fract(c * 1.618 + 0.1);
+    }
+    f_color = vec4(mix(v_color, c, 0.25), 1.0);
+}
@@ -0,0 +1,116 @@
+use shaderc::{CompileOptions, Compiler, ShaderKind};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/* The GLSL sources that `vulkano_shaders::shader!` compiles into the binary. The macro doesn't
+ * keep the SPIR-V it generates around for us to look at, so anything that wants the bytecode
+ * compiles the same source again with the same compiler. Keep this list in sync with the
+ * `shader!` invocations. */
+pub struct EmbeddedShader {
+    pub name: &'static str,
+    pub kind: ShaderKind,
+    pub source: &'static str,
+}
+
+pub const EMBEDDED: &[EmbeddedShader] = &[
+    EmbeddedShader { name: "op", kind: ShaderKind::Compute, source: include_str!("op.glsl") },
+    EmbeddedShader { name: "mandelbrot", kind: ShaderKind::Compute, source: include_str!("mandelbrot.glsl") },
+    EmbeddedShader { name: "mandelbrot_uniform", kind: ShaderKind::Compute, source: include_str!("mandelbrot_uniform.glsl") },
+    EmbeddedShader { name: "julia", kind: ShaderKind::Compute, source: include_str!("julia.glsl") },
+    EmbeddedShader { name: "escape", kind: ShaderKind::Compute, source: include_str!("escape.glsl") },
+    EmbeddedShader { name: "uv", kind: ShaderKind::Compute, source: include_str!("uv.glsl") },
+    EmbeddedShader { name: "invert", kind: ShaderKind::Compute, source: include_str!("invert.glsl") },
+    EmbeddedShader { name: "downsample", kind: ShaderKind::Compute, source: include_str!("downsample.glsl") },
+    EmbeddedShader { name: "convolve", kind: ShaderKind::Compute, source: include_str!("convolve.glsl") },
+    EmbeddedShader { name: "quantize", kind: ShaderKind::Compute, source: include_str!("quantize.glsl") },
+    EmbeddedShader { name: "matmul", kind: ShaderKind::Compute, source: include_str!("matmul.glsl") },
+    EmbeddedShader { name: "vertex", kind: ShaderKind::Vertex, source: include_str!("vertex.glsl") },
+    EmbeddedShader { name: "frag", kind: ShaderKind::Fragment, source: include_str!("frag.glsl") },
+    EmbeddedShader { name: "hue", kind: ShaderKind::Fragment, source: include_str!("hue.glsl") },
+    EmbeddedShader { name: "tint", kind: ShaderKind::Fragment, source: include_str!("tint.glsl") },
+    EmbeddedShader { name: "fullscreen", kind: ShaderKind::Vertex, source: include_str!("fullscreen.glsl") },
+    EmbeddedShader { name: "gradient", kind: ShaderKind::Fragment, source: include_str!("gradient.glsl") },
+    EmbeddedShader { name: "disc", kind: ShaderKind::Fragment, source: include_str!("disc.glsl") },
+    EmbeddedShader { name: "layers", kind: ShaderKind::Vertex, source: include_str!("layers.glsl") },
+    EmbeddedShader { name: "depth_only", kind: ShaderKind::Fragment, source: include_str!("depth_only.glsl") },
+    EmbeddedShader { name: "shade", kind: ShaderKind::Fragment, source: include_str!("shade.glsl") },
+    EmbeddedShader { name: "text_vert", kind: ShaderKind::Vertex, source: include_str!("text.vert") },
+    EmbeddedShader { name: "text_frag", kind: ShaderKind::Fragment, source: include_str!("text.frag") },
+    EmbeddedShader { name: "buddhabrot", kind: ShaderKind::Compute, source: include_str!("buddhabrot.glsl") },
+    EmbeddedShader { name: "layered", kind: ShaderKind::Compute, source: include_str!("layered.glsl") },
+    EmbeddedShader { name: "sky", kind: ShaderKind::Compute, source: include_str!("sky.glsl") },
+    EmbeddedShader { name: "sphere", kind: ShaderKind::Compute, source: include_str!("sphere.glsl") },
+    EmbeddedShader { name: "dither", kind: ShaderKind::Compute, source: include_str!("dither.glsl") },
+    EmbeddedShader { name: "rotate", kind: ShaderKind::Compute, source: include_str!("rotate.glsl") },
+    EmbeddedShader { name: "colorspace", kind: ShaderKind::Compute, source: include_str!("colorspace.glsl") },
+    EmbeddedShader { name: "fxaa", kind: ShaderKind::Compute, source: include_str!("fxaa.glsl") },
+    EmbeddedShader { name: "median", kind: ShaderKind::Compute, source: include_str!("median.glsl") },
+    EmbeddedShader { name: "noise", kind: ShaderKind::Compute, source: include_str!("noise.glsl") },
+    EmbeddedShader { name: "terrain_vert", kind: ShaderKind::Vertex, source: include_str!("terrain.vert") },
+    EmbeddedShader { name: "terrain_frag", kind: ShaderKind::Fragment, source: include_str!("terrain.frag") },
+    EmbeddedShader { name: "descent", kind: ShaderKind::Compute, source: include_str!("descent.glsl") },
+    EmbeddedShader { name: "himmelblau", kind: ShaderKind::Fragment, source: include_str!("himmelblau.glsl") },
+    EmbeddedShader { name: "path", kind: ShaderKind::Vertex, source: include_str!("path.glsl") },
+    EmbeddedShader { name: "trail", kind: ShaderKind::Fragment, source: include_str!("trail.glsl") },
+    EmbeddedShader { name: "surface", kind: ShaderKind::Compute, source: include_str!("surface.glsl") },
+    EmbeddedShader { name: "cloud", kind: ShaderKind::Vertex, source: include_str!("cloud.glsl") },
+    EmbeddedShader { name: "height", kind: ShaderKind::Fragment, source: include_str!("height.glsl") },
+    EmbeddedShader { name: "volume", kind: ShaderKind::Compute, source: include_str!("volume.glsl") },
+    EmbeddedShader { name: "resolve", kind: ShaderKind::Compute, source: include_str!("resolve.glsl") },
+    EmbeddedShader { name: "circle", kind: ShaderKind::Compute, source: include_str!("circle.glsl") },
+    EmbeddedShader { name: "reduce", kind: ShaderKind::Compute, source: include_str!("reduce.glsl") },
+    EmbeddedShader { name: "scan", kind: ShaderKind::Compute, source: include_str!("scan.glsl") },
+    EmbeddedShader { name: "histogram", kind: ShaderKind::Compute, source: include_str!("histogram.glsl") },
+    EmbeddedShader { name: "bitonic", kind: ShaderKind::Compute, source: include_str!("bitonic.glsl") },
+    EmbeddedShader { name: "count", kind: ShaderKind::Compute, source: include_str!("count.glsl") },
+    EmbeddedShader { name: "particles", kind: ShaderKind::Compute, source: include_str!("particles.glsl") },
+    EmbeddedShader { name: "point", kind: ShaderKind::Vertex, source: include_str!("point.glsl") },
+    EmbeddedShader { name: "speed", kind: ShaderKind::Fragment, source: include_str!("speed.glsl") },
+    EmbeddedShader { name: "normalmap", kind: ShaderKind::Fragment, source: include_str!("normalmap.glsl") },
+    EmbeddedShader { name: "mesh_vert", kind: ShaderKind::Vertex, source: include_str!("mesh.vert") },
+    EmbeddedShader { name: "mesh_frag", kind: ShaderKind::Fragment, source: include_str!("mesh.frag") },
+    EmbeddedShader { name: "texture_vert", kind: ShaderKind::Vertex, source: include_str!("texture.vert") },
+    EmbeddedShader { name: "texture_frag", kind: ShaderKind::Fragment, source: include_str!("texture.frag") },
+    EmbeddedShader { name: "postfx", kind: ShaderKind::Compute, source: include_str!("postfx.glsl") },
+    EmbeddedShader { name: "instancing_vert", kind: ShaderKind::Vertex, source: include_str!("instancing.vert") },
+    EmbeddedShader { name: "instancing_frag", kind: ShaderKind::Fragment, source: include_str!("instancing.frag") },
+    EmbeddedShader { name: "overlay_vert", kind: ShaderKind::Vertex, source: include_str!("overlay.vert") },
+    EmbeddedShader { name: "overlay_frag", kind: ShaderKind::Fragment, source: include_str!("overlay.frag") },
+];
+
+/* The embedded shader compiled from the file at `path`, if it is one of ours, so it can be
+ * compiled again as the stage it is. Files are named after the shader, with the stage as the
+ * extension for the ones that aren't .glsl, like text.vert for text_vert. */
+pub fn embedded(path: &Path) -> Option<&'static EmbeddedShader> {
+    let file_name = path.file_name()?.to_str()?;
+    let name = file_name.trim_end_matches(".glsl").replace('.', "_");
+    EMBEDDED.iter().find(|shader| shader.name == name)
+}
+
+/* Compiles GLSL to SPIR-V words using the same options the `shader!` macro uses, so the result
+ * is the same bytecode that ends up in the binary. */
+pub fn compile(name: &str, kind: ShaderKind, source: &str) -> Result<Vec<u32>, String> {
+    let mut compiler = Compiler::new().ok_or("Failed to create GLSL compiler")?;
+    let options = CompileOptions::new().ok_or("Failed to initialize compile options")?;
+
+    let artifact = compiler.compile_into_spirv(source, kind, name, "main", Some(&options))
+        .map_err(|e| format!("Failed to compile {}: {}", name, e))?;
+
+    Ok(artifact.as_binary().to_vec())
+}
+
+/* Writes `<name>.spv` into `dir` for every embedded shader, ready for `spirv-dis`. */
+pub fn dump_spirv(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut written = Vec::new();
+
+    for shader in EMBEDDED {
+        let words = compile(shader.name, shader.kind, shader.source)?;
+        let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes().to_vec()).collect();
+
+        let path = dir.join(format!("{}.spv", shader.name));
+        fs::write(&path, bytes).map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+use vulkano::device::Queue;
+
+/* Waits for the GPU to finish with everything submitted to `queues` when it is dropped, so
+ * nothing is still running when the device is destroyed. Destroying a device, or anything it
+ * made, while the GPU is still using it is an error the validation layers report, and on some
+ * drivers a crash. Every demo waits for its own work already, but a guard declared right after
+ * the device is dropped before it on every way out of main, early returns and panics included,
+ * so the next demo doesn't have to remember to.
+ *
+ * `Device::wait` would do the same for the whole device in one call, but it is unsafe: nothing
+ * may be submitted to any of its queues while it waits. `Queue::wait` holds the queue's lock
+ * while it waits instead, which makes it safe to call from anywhere. */
+pub struct Shutdown {
+    queues: Vec<Arc<Queue>>,
+}
+
+impl Shutdown {
+    pub fn new(queues: Vec<Arc<Queue>>) -> Shutdown {
+        Shutdown { queues }
+    }
+}
+
+impl Drop for Shutdown {
+    fn drop(&mut self) {
+        for queue in &self.queues {
+            if let Err(e) = queue.wait() {
+                eprintln!("Warning: failed to wait for queue family {} to go idle: {}", queue.family().id(), e);
+            }
+        }
+    }
+}
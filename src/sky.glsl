@@ -0,0 +1,45 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+/* A cubemap, written a face at a time: the z of the store is which face. */
+layout(set = 0, binding = 0, rgba8) uniform writeonly imageCube faces;
+
+const vec3 ZENITH = vec3(0.15, 0.35, 0.8);
+const vec3 HORIZON = vec3(0.8, 0.85, 0.9);
+const vec3 GROUND = vec3(0.25, 0.2, 0.15);
+const vec3 SUN = normalize(vec3(0.6, 0.5, -0.6));
+
+/* The direction from the center of the cube through texel (s, t) of `face`, both from -1 to 1.
+ * Faces go +x, -x, +y, -y, +z, -z, and this undoes the way a lookup picks the face and texel
+ * for a direction, so sampling in a direction gets back what was written for it. */
+vec3 direction(uint face, float s, float t) {
+    switch (face) {
+        case 0: return vec3(1.0, -t, -s);
+        case 1: return vec3(-1.0, -t, s);
+        case 2: return vec3(s, 1.0, t);
+        case 3: return vec3(s, -1.0, -t);
+        case 4: return vec3(s, -t, 1.0);
+        default: return vec3(-s, -t, -1.0);
+    }
+}
+
+/* A gradient from the horizon up to a deep blue, brown ground below it, and a small sun. */
+vec3 sky(vec3 d) {
+    vec3 color = d.y >= 0.0 ? mix(HORIZON, ZENITH, d.y) : mix(HORIZON, GROUND, -d.y);
+    return color + vec3(1.0, 0.9, 0.6) * pow(max(dot(d, SUN), 0.0), 256.0);
+}
+
+/* Every invocation shades one texel of one face, which the dispatch's z picks. */
+void main() {
+    int size = imageSize(faces).x;
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coords, ivec2(size)))) {
+        return;
+    }
+
+    uint face = gl_GlobalInvocationID.z;
+    vec2 st = (vec2(coords) + vec2(0.5)) / float(size) * 2.0 - 1.0;
+    vec3 d = normalize(direction(face, st.x, st.y));
+    imageStore(faces, ivec3(coords, face), vec4(sky(d), 1.0));
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::pipeline::ComputePipeline;
+
+use crate::memory;
+use crate::watchdog;
+
+/* Must match the local size in bitonic.glsl. */
+const LOCAL_SIZE: u32 = 64;
+
+/* Lengths to check, powers of two and not, including ones smaller than a workgroup. */
+const SIZES: &[usize] = &[1, 2, 5, 64, 1000, 4096, 100_003];
+
+/* Sorts `data` on the GPU with a bitonic sort. It only works on power of two lengths, so the
+ * buffer is padded with u32::MAX, which sorts to the end and is cut off again afterwards.
+ *
+ * The sort is log2(n) stages, each made of passes that compare every element against a partner
+ * a fixed distance away and swap them if they are out of order. Every pass depends on the one
+ * before, so each is its own dispatch with its stage and step in the push constants. They all
+ * go in one command buffer, and vulkano puts a barrier between dispatches that touch the same
+ * buffer, so this is a single submit and a single wait. */
+pub fn gpu_sort(device: Arc<Device>, queue: Arc<Queue>, data: &[u32]) -> Vec<u32> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+
+    let padded = data.len().next_power_of_two();
+    let mut values = data.to_vec();
+    values.resize(padded, u32::MAX);
+    let buffer = memory::upload_to_device_local(queue.clone(), memory::storage_buffer(), values)
+        .expect("Failed to create buffer");
+    let readback = memory::readback(&queue, padded).expect("Failed to create buffer");
+
+    let shader = cs::Shader::load(device.clone()).expect("Failed to create bitonic sort shader module");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &())
+        .expect("Failed to create bitonic sort pipeline"));
+
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_buffer(buffer.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = (padded as u32).div_ceil(LOCAL_SIZE);
+    let mut builder = AutoCommandBufferBuilder::new(device.clone(), queue.family()).unwrap();
+    let mut stage = 2;
+    while stage <= padded as u32 {
+        let mut step = stage / 2;
+        while step > 0 {
+            builder = builder
+                .dispatch([groups, 1, 1], pipeline.clone(), set.clone(), cs::ty::PushConstants { stage, step })
+                .unwrap();
+            step /= 2;
+        }
+        stage *= 2;
+    }
+
+    /* The sort happens in device local memory, and only the sorted values come back. */
+    let command_buffer = builder.copy_buffer(buffer, readback.clone()).unwrap().build().unwrap();
+    watchdog::wait(command_buffer.execute(queue.clone()).unwrap(), "sort");
+
+    let content = readback.read().unwrap();
+    content[..data.len()].to_vec()
+}
+
+/* `n` pseudo-random values, the same for the same `n` every run. A little LCG is plenty random
+ * for this. */
+pub fn pseudo_random(n: usize) -> Vec<u32> {
+    let mut state = n as u64;
+    (0..n).map(|_| {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 32) as u32
+    }).collect()
+}
+
+/* Sorts pseudo-random data of a few lengths and checks each against the standard library. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    for &n in SIZES {
+        let data = pseudo_random(n);
+
+        let mut expected = data.clone();
+        expected.sort();
+
+        assert!(gpu_sort(device.clone(), queue.clone(), &data) == expected, "GPU sort of {} elements is wrong", n);
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/bitonic.glsl"
+    }
+}
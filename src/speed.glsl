@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) in float speed;
+
+layout(location = 0) out vec4 f_color;
+
+/* Slow particles are blue and fast ones white. */
+void main() {
+    float t = clamp(speed, 0.0, 1.0);
+    f_color = vec4(mix(vec3(0.2, 0.4, 1.0), vec3(1.0), t), 1.0);
+}
@@ -0,0 +1,36 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform samplerCube environment;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D img;
+
+/* Must match RADIUS in cubemap.rs: the sphere's radius, with the image two units across. */
+const float RADIUS = 0.8;
+
+/* Ray traces a mirror ball in the middle of the image, seen from straight ahead down -z. Where
+ * a ray hits the ball it is reflected about the normal there and looks up the environment in
+ * the reflected direction. Rays that miss look up the environment behind the ball, spread out a
+ * little so the background isn't all one texel. */
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(img);
+    if (any(greaterThanEqual(coords, size))) {
+        return;
+    }
+
+    /* From -1 to 1 across, with y up. */
+    vec2 p = (vec2(coords) + vec2(0.5)) / vec2(size) * 2.0 - 1.0;
+    p.y = -p.y;
+
+    vec3 view = vec3(0.0, 0.0, -1.0);
+    vec3 d;
+    if (dot(p, p) < RADIUS * RADIUS) {
+        vec3 normal = vec3(p, sqrt(RADIUS * RADIUS - dot(p, p))) / RADIUS;
+        d = reflect(view, normal);
+    } else {
+        d = normalize(vec3(p * 0.5, -1.0));
+    }
+
+    imageStore(img, coords, vec4(texture(environment, d).rgb, 1.0));
+}
@@ -0,0 +1,207 @@
+use std::borrow::Cow;
+use std::ffi::CString;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use vulkano::descriptor::descriptor::{DescriptorBufferDesc, DescriptorDesc, DescriptorDescTy, DescriptorImageDesc,
+    DescriptorImageDescArray, DescriptorImageDescDimensions, ShaderStages};
+use vulkano::descriptor::pipeline_layout::{PipelineLayoutDesc, PipelineLayoutDescPcRange};
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::pipeline::shader::{ComputeEntryPoint, EmptyShaderInterfaceDef, GraphicsEntryPoint,
+    GraphicsShaderType, ShaderInterfaceDef, ShaderInterfaceDefEntry, ShaderModule};
+
+const MAGIC: u32 = 0x0723_0203;
+const OP_ENTRY_POINT: u32 = 15;
+const EXECUTION_MODEL_FRAGMENT: u32 = 4;
+const EXECUTION_MODEL_GL_COMPUTE: u32 = 5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stage {
+    Compute,
+    Fragment,
+}
+
+/* A shader loaded from a `.spv` file at runtime rather than compiled into the binary by
+ * `vulkano_shaders::shader!`. Since there is no macro to reflect over the module for us, the
+ * interface the shader is expected to have is spelled out by hand below: a compute shader must
+ * match mandelbrot.glsl (one rgba8 storage image at set 0, binding 0, 8x8 workgroups) and a
+ * fragment shader must match frag.glsl (no inputs, a vec4 color at location 0). A fragment
+ * shader may also declare the frame uniform from frame.rs, which is always bound for it. */
+pub struct SpvShader {
+    module: Arc<ShaderModule>,
+    entry_name: CString,
+    pub stage: Stage,
+}
+
+impl SpvShader {
+    pub fn load<P: AsRef<Path>>(device: Arc<Device>, path: P) -> Result<SpvShader, String> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        let words = words_from_bytes(&bytes)
+            .ok_or_else(|| format!("{} is not a SPIR-V module", path.display()))?;
+        SpvShader::from_words(device, &words, &path.display().to_string())
+    }
+
+    /* Like `load`, for SPIR-V that is already in memory, like a shader just compiled. `name` is
+     * only used in errors. */
+    pub fn from_words(device: Arc<Device>, words: &[u32], name: &str) -> Result<SpvShader, String> {
+        let (stage, entry_name) = find_entry_point(words)
+            .ok_or_else(|| format!("{} has no compute or fragment entry point", name))?;
+
+        /* Safety: vulkano can't check the module against the interface we promise below, so a
+         * mismatching shader is undefined behaviour. That is the price of skipping reflection. */
+        let module = unsafe { ShaderModule::from_words(device, words) }
+            .map_err(|e| format!("Could not create shader module: {}", e))?;
+
+        Ok(SpvShader { module, entry_name, stage })
+    }
+
+    pub fn compute_entry_point(&self) -> ComputeEntryPoint<'_, (), StorageImageLayout> {
+        assert_eq!(self.stage, Stage::Compute);
+        unsafe { self.module.compute_entry_point(&self.entry_name, StorageImageLayout) }
+    }
+
+    pub fn fragment_entry_point(&self)
+        -> GraphicsEntryPoint<'_, (), EmptyShaderInterfaceDef, ColorOutput, FrameLayout>
+    {
+        assert_eq!(self.stage, Stage::Fragment);
+        unsafe {
+            self.module.graphics_entry_point(&self.entry_name, EmptyShaderInterfaceDef, ColorOutput,
+                FrameLayout, GraphicsShaderType::Fragment)
+        }
+    }
+}
+
+/* SPIR-V is a stream of 32-bit words. The magic number tells us the byte order; modules written
+ * on a machine with the other endianness show up byte-swapped. */
+fn words_from_bytes(bytes: &[u8]) -> Option<Vec<u32>> {
+    if !bytes.len().is_multiple_of(4) || bytes.len() < 20 {
+        return None;
+    }
+
+    let words: Vec<u32> = bytes.chunks(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+
+    match words[0] {
+        MAGIC => Some(words),
+        m if m.swap_bytes() == MAGIC => Some(words.into_iter().map(u32::swap_bytes).collect()),
+        _ => None,
+    }
+}
+
+/* Walks the instructions after the five-word header looking for the first `OpEntryPoint` we
+ * know how to use, returning its stage and name. */
+fn find_entry_point(words: &[u32]) -> Option<(Stage, CString)> {
+    let mut i = 5;
+    while i < words.len() {
+        let word_count = (words[i] >> 16) as usize;
+        let opcode = words[i] & 0xffff;
+        if word_count == 0 || i + word_count > words.len() {
+            return None;
+        }
+
+        if opcode == OP_ENTRY_POINT && word_count >= 4 {
+            let stage = match words[i + 1] {
+                EXECUTION_MODEL_GL_COMPUTE => Some(Stage::Compute),
+                EXECUTION_MODEL_FRAGMENT => Some(Stage::Fragment),
+                _ => None,
+            };
+
+            if let Some(stage) = stage {
+                /* The name is a nul-terminated string packed four bytes to a word. */
+                let name: Vec<u8> = words[i + 3..i + word_count].iter()
+                    .flat_map(|w| w.to_le_bytes().to_vec())
+                    .take_while(|&b| b != 0)
+                    .collect();
+                return CString::new(name).ok().map(|name| (stage, name));
+            }
+        }
+
+        i += word_count;
+    }
+
+    None
+}
+
+/* The layout of mandelbrot.glsl: a single storage image at set 0, binding 0. */
+#[derive(Debug, Copy, Clone)]
+pub struct StorageImageLayout;
+
+unsafe impl PipelineLayoutDesc for StorageImageLayout {
+    fn num_sets(&self) -> usize { 1 }
+
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        if set == 0 { Some(1) } else { None }
+    }
+
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        if set != 0 || binding != 0 {
+            return None;
+        }
+
+        Some(DescriptorDesc {
+            ty: DescriptorDescTy::Image(DescriptorImageDesc {
+                sampled: false,
+                dimensions: DescriptorImageDescDimensions::TwoDimensional,
+                format: Some(Format::R8G8B8A8Unorm),
+                multisampled: false,
+                array_layers: DescriptorImageDescArray::NonArrayed,
+            }),
+            array_count: 1,
+            stages: ShaderStages { compute: true, .. ShaderStages::none() },
+            readonly: false,
+        })
+    }
+
+    fn num_push_constants_ranges(&self) -> usize { 0 }
+
+    fn push_constants_range(&self, _num: usize) -> Option<PipelineLayoutDescPcRange> { None }
+}
+
+/* The frame uniform at set 0, binding 0, for fragment shaders. A layout can have descriptors
+ * the shader never uses, so it is there whether or not the shader declares it. */
+#[derive(Debug, Copy, Clone)]
+pub struct FrameLayout;
+
+unsafe impl PipelineLayoutDesc for FrameLayout {
+    fn num_sets(&self) -> usize { 1 }
+
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        if set == 0 { Some(1) } else { None }
+    }
+
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        if set != 0 || binding != 0 {
+            return None;
+        }
+
+        Some(DescriptorDesc {
+            ty: DescriptorDescTy::Buffer(DescriptorBufferDesc { dynamic: Some(false), storage: false }),
+            array_count: 1,
+            stages: ShaderStages { fragment: true, .. ShaderStages::none() },
+            readonly: true,
+        })
+    }
+
+    fn num_push_constants_ranges(&self) -> usize { 0 }
+
+    fn push_constants_range(&self, _num: usize) -> Option<PipelineLayoutDescPcRange> { None }
+}
+
+/* The output of frag.glsl: one vec4 at location 0. */
+#[derive(Debug, Copy, Clone)]
+pub struct ColorOutput;
+
+unsafe impl ShaderInterfaceDef for ColorOutput {
+    type Iter = std::option::IntoIter<ShaderInterfaceDefEntry>;
+
+    fn elements(&self) -> Self::Iter {
+        Some(ShaderInterfaceDefEntry {
+            location: 0..1,
+            format: Format::R32G32B32A32Sfloat,
+            name: Some(Cow::Borrowed("f_color")),
+        }).into_iter()
+    }
+}
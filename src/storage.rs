@@ -0,0 +1,67 @@
+use std::ffi::c_void;
+use std::mem;
+use std::sync::Arc;
+use vulkano::VulkanObject;
+use vulkano::device::Queue;
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, StorageImage};
+use vulkano::instance::PhysicalDevice;
+use vulkano::instance::loader;
+
+use crate::memory;
+
+/* Formats worth suggesting in place of one that can't be a storage image. The first two are
+ * ones every Vulkan device has to support for storage. */
+const CANDIDATES: &[Format] = &[
+    Format::R8G8B8A8Unorm,
+    Format::R32Sfloat,
+    Format::R16G16B16A16Sfloat,
+    Format::R32G32B32A32Sfloat,
+    Format::R32Uint,
+    Format::R8Unorm,
+];
+
+/* Whether `format` can be a storage image, with the optimal tiling every image here uses.
+ * vulkano 0.11 makes the same query when it creates an image, but only reports that the
+ * usage isn't supported, and keeps the function that makes it to itself, so it is loaded here
+ * from the Vulkan library. */
+pub fn supported(physical: PhysicalDevice, format: Format) -> bool {
+    let loader = match loader::auto_loader() {
+        Ok(loader) => loader,
+        Err(_) => return false,
+    };
+    let instance = physical.instance().internal_object();
+    let pointers = vk_sys::InstancePointers::load(|name| {
+        loader.get_instance_proc_addr(instance, name.as_ptr()) as *const c_void
+    });
+
+    unsafe {
+        let mut properties: vk_sys::FormatProperties = mem::zeroed();
+        pointers.GetPhysicalDeviceFormatProperties(physical.internal_object(), format as u32, &mut properties);
+        properties.optimalTilingFeatures & vk_sys::FORMAT_FEATURE_STORAGE_IMAGE_BIT != 0
+    }
+}
+
+/* Checks `format` can be a storage image on `physical`, naming the formats that can if not. */
+pub fn check(physical: PhysicalDevice, format: Format) -> Result<(), String> {
+    if supported(physical, format) {
+        return Ok(());
+    }
+    let alternatives: Vec<_> = CANDIDATES.iter()
+        .filter(|&&candidate| candidate != format && supported(physical, candidate))
+        .map(|candidate| format!("{:?}", candidate))
+        .collect();
+    Err(format!("{} can't use {:?} for storage images, try one of {} instead", physical.name(), format,
+        alternatives.join(", ")))
+}
+
+/* Creates a storage image for `queue`'s family, the way every compute demo does, checking the
+ * format first so an unsupported one gets a message saying what to use instead of a bare
+ * `UnsupportedUsage`. */
+pub fn image(queue: &Queue, dimensions: Dimensions, format: Format) -> Arc<StorageImage<Format>> {
+    let device = queue.device();
+    if let Err(e) = check(device.physical_device(), format) {
+        panic!("{}", e);
+    }
+    memory::image(StorageImage::new(device.clone(), dimensions, format, Some(queue.family())).unwrap())
+}
@@ -0,0 +1,155 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, RenderPassAbstract, Subpass};
+use vulkano::image::{AttachmentImage, ImageUsage};
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::sync::GpuFuture;
+
+use crate::memory;
+use crate::readback;
+use crate::triangle::{self, Vertex, SIZE};
+use crate::vertices;
+
+/* How much of each channel the tint keeps. Must match tint.glsl. */
+const TINT: [f32; 3] = [1.0, 0.8, 0.6];
+
+/* Draws the triangle and tints it in one render pass with two subpasses. The first draws the
+ * triangle into `scene`, the second covers the screen with one big triangle whose fragment
+ * shader reads `scene` as an input attachment and writes the tinted color to `color`. Because
+ * the second subpass lists `scene` as an input, the render pass macro adds a dependency making
+ * the first subpass's color writes visible to it and moves `scene` into a layout it can be read
+ * in. Unlike a separate render pass sampling a texture, the intermediate image never has to
+ * leave the GPU's tile memory, which is why `scene` is transient and never stored. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let render_pass: Arc<dyn RenderPassAbstract + Send + Sync> = Arc::new(vulkano::ordered_passes_renderpass!(device.clone(),
+        attachments: {
+            scene: {
+                load: Clear,
+                store: DontCare,
+                format: Format::R8G8B8A8Unorm,
+                samples: 1,
+            },
+            color: {
+                load: DontCare,
+                store: Store,
+                format: Format::R8G8B8A8Unorm,
+                samples: 1,
+            }
+        },
+        passes: [
+            {
+                color: [scene],
+                depth_stencil: {},
+                input: []
+            },
+            {
+                color: [color],
+                depth_stencil: {},
+                input: [scene]
+            }
+        ]
+    ).unwrap());
+
+    let vs = vertex::Shader::load(device.clone()).expect("Failed to create vertex shader");
+    let fs = frag::Shader::load(device.clone()).expect("Failed to create fragment shader");
+    let tint_fs = tint::Shader::load(device.clone()).expect("Failed to create tint shader");
+
+    let scene_pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<Vertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let tint_pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<Vertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(tint_fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 1).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let scene = memory::image(AttachmentImage::transient_input_attachment(device.clone(), [SIZE, SIZE],
+        Format::R8G8B8A8Unorm).unwrap());
+    let usage = ImageUsage { color_attachment: true, transfer_source: true, .. ImageUsage::none() };
+    let image = memory::image(AttachmentImage::with_usage(device.clone(), [SIZE, SIZE], Format::R8G8B8A8Unorm,
+        usage).unwrap());
+
+    let framebuffer = Arc::new(Framebuffer::start(render_pass)
+        .add(scene.clone()).unwrap()
+        .add(image.clone()).unwrap()
+        .build().unwrap());
+
+    /* The input attachment is bound like any other image, at the binding tint.glsl declares. */
+    let set = Arc::new(PersistentDescriptorSet::start(tint_pipeline.clone(), 0)
+        .add_image(scene).unwrap()
+        .build().unwrap());
+
+    /* One triangle big enough to cover the whole viewport. */
+    let fullscreen = vertices::make_vertex_buffer(device.clone(), vec![
+        Vertex { position: [-1.0, -1.0] },
+        Vertex { position: [3.0, -1.0] },
+        Vertex { position: [-1.0, 3.0] },
+    ]);
+
+    let pixels = (SIZE * SIZE) as usize * readback::RGBA8_BYTES;
+    let buf = memory::readback::<u8>(&queue, pixels).expect("Failed to create buffer");
+
+    AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer, false, vec![triangle::CLEAR_COLOR.into(), vulkano::format::ClearValue::None])
+        .unwrap()
+        .draw(scene_pipeline, &triangle::dynamic_state(), triangle::vertex_buffer(device.clone()), (), ()).unwrap()
+        .next_subpass(false).unwrap()
+        .draw(tint_pipeline, &triangle::dynamic_state(), fullscreen, set, ()).unwrap()
+        .end_render_pass().unwrap()
+        .copy_image_to_buffer(image, buf.clone()).unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    let buffer_content = buf.read().unwrap();
+    readback::rgba8_image(SIZE, SIZE, SIZE as usize * readback::RGBA8_BYTES, &buffer_content)
+}
+
+/* The tinted image should be the plain triangle with every pixel scaled by TINT. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>, tinted: &ImageBuffer<Rgba<u8>, Vec<u8>>) {
+    let plain = triangle::render(device, queue, None, Format::R8G8B8A8Unorm, 1).unwrap();
+    for (x, y, pixel) in tinted.enumerate_pixels() {
+        let original = plain.get_pixel(x, y);
+        for channel in 0..3 {
+            let expected = original[channel] as f32 * TINT[channel];
+            assert!((pixel[channel] as f32 - expected).abs() <= 1.0,
+                "Tinted pixel ({}, {}) is {:?}, expected {:?} tinted", x, y, pixel, original);
+        }
+        assert_eq!(pixel[3], original[3]);
+    }
+}
+
+mod vertex {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/vertex.glsl"
+    }
+}
+
+mod frag {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/frag.glsl"
+    }
+}
+
+mod tint {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/tint.glsl"
+    }
+}
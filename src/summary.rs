@@ -0,0 +1,95 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::markers;
+
+/* Every image saved so far, for working out which demo wrote which. */
+static SAVED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/* Called for every image a demo writes. */
+pub fn record_output(path: &Path) {
+    SAVED.lock().unwrap().push(path.to_path_buf());
+}
+
+enum Status {
+    Passed,
+    Failed(String),
+    Skipped(&'static str),
+}
+
+struct Outcome {
+    name: &'static str,
+    status: Status,
+    duration: Duration,
+    outputs: Vec<PathBuf>,
+}
+
+/* Runs the demos of a full run one after another, carrying on past any that fail, and keeps
+ * track of how each went so it can all be printed in one table at the end. */
+#[derive(Default)]
+pub struct Summary {
+    outcomes: Vec<Outcome>,
+}
+
+impl Summary {
+    /* Runs `demo`, which fails if it returns an error or panics, like the demos' checks do when a
+     * result is wrong. Returns what the demo returned if it passed. With --profile-markers its
+     * GPU work is labeled with its name in captures. */
+    pub fn run<T, F>(&mut self, name: &'static str, demo: F) -> Option<T>
+        where F: FnOnce() -> Result<T, String>
+    {
+        let saved_before = SAVED.lock().unwrap().len();
+        let start = Instant::now();
+        markers::begin(name);
+
+        let result = match panic::catch_unwind(AssertUnwindSafe(demo)) {
+            Ok(result) => result,
+            Err(payload) => Err(payload.downcast_ref::<String>().cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "panicked".to_string())),
+        };
+
+        markers::end();
+        let duration = start.elapsed();
+        let outputs = SAVED.lock().unwrap()[saved_before..].to_vec();
+        let (status, value) = match result {
+            Ok(value) => (Status::Passed, Some(value)),
+            Err(e) => {
+                eprintln!("{} failed: {}", name, e);
+                (Status::Failed(e), None)
+            }
+        };
+        self.outcomes.push(Outcome { name, status, duration, outputs });
+        value
+    }
+
+    /* Records a demo that couldn't run, like one that needs the output of a failed demo. */
+    pub fn skip(&mut self, name: &'static str, reason: &'static str) {
+        self.outcomes.push(Outcome { name, status: Status::Skipped(reason), duration: Duration::default(), outputs: Vec::new() });
+    }
+
+    pub fn failed(&self) -> bool {
+        self.outcomes.iter().any(|outcome| matches!(outcome.status, Status::Failed(_)))
+    }
+
+    pub fn print(&self) {
+        println!();
+        println!("{:<14} {:<8} {:>10}  Output", "Demo", "Result", "Time");
+        for outcome in &self.outcomes {
+            let status = match outcome.status {
+                Status::Passed => "ok",
+                Status::Failed(_) => "FAILED",
+                Status::Skipped(_) => "skipped",
+            };
+            let paths: Vec<_> = outcome.outputs.iter().map(|path| path.display().to_string()).collect();
+            let detail = match outcome.status {
+                Status::Failed(ref e) => e.lines().next().unwrap_or("").to_string(),
+                Status::Skipped(reason) => reason.to_string(),
+                Status::Passed => paths.join(", "),
+            };
+            println!("{:<14} {:<8} {:>8.2}ms  {}", outcome.name, status, outcome.duration.as_secs_f64() * 1000.0, detail);
+        }
+    }
+}
@@ -0,0 +1,29 @@
+#version 450
+
+layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
+
+/* One point per element, as a vec4 so std430 packs them the way the vertex shader reads them.
+ * The w is unused. */
+layout(set = 0, binding = 0) buffer Points {
+    vec4 points[];
+} buf;
+
+layout(push_constant) uniform PushConstants {
+    uint side;
+} pc;
+
+/* Must match AMPLITUDE in pointcloud.rs. */
+const float AMPLITUDE = 0.35;
+
+/* Samples the surface y = sin(4x) cos(4z) on a side x side grid over -1..1 in x and z, one
+ * point per invocation. */
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    if (index >= pc.side * pc.side) {
+        return;
+    }
+
+    vec2 grid = vec2(index % pc.side, index / pc.side) / float(pc.side - 1) * 2.0 - 1.0;
+    float y = AMPLITUDE * sin(4.0 * grid.x) * cos(4.0 * grid.y);
+    buf.points[index] = vec4(grid.x, y, grid.y, 1.0);
+}
@@ -0,0 +1,14 @@
+#version 450
+
+layout(location = 0) in float height;
+layout(location = 1) in float shade;
+
+layout(location = 0) out vec4 f_color;
+
+/* Green valleys, brown slopes and white peaks, lit by the vertex shader's shade. */
+void main() {
+    float t = clamp(height, 0.0, 1.0);
+    vec3 color = mix(vec3(0.2, 0.5, 0.2), vec3(0.5, 0.4, 0.3), smoothstep(0.3, 0.6, t));
+    color = mix(color, vec3(0.95), smoothstep(0.7, 0.85, t));
+    f_color = vec4(color * shade, 1.0);
+}
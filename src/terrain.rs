@@ -0,0 +1,322 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, DeviceLocalBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::DescriptorSet;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass};
+use vulkano::image::{AttachmentImage, Dimensions, ImageUsage, StorageImage};
+use vulkano::pipeline::{ComputePipeline, GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+use vulkano::sync::GpuFuture;
+
+use crate::batch;
+use crate::compute::ComputeTask;
+use crate::memory;
+use crate::random::pcg;
+use crate::readback;
+use crate::storage;
+use crate::triangle::{self, SIZE};
+use crate::vertices;
+
+/* Must match the local size in noise.glsl. */
+const LOCAL_SIZE: u32 = 8;
+
+/* The heightmap is SIDE x SIDE texels, and the grid has a vertex on each. */
+const SIDE: u32 = 128;
+
+/* What `--frames` defaults to with `--terrain`. */
+pub const DEFAULT_FRAMES: u32 = 120;
+
+/* What `--octaves` and `--noise-scale` default to. */
+pub const DEFAULT_OCTAVES: u32 = 5;
+pub const DEFAULT_NOISE_SCALE: f32 = 3.0;
+
+/* Each octave's cells are half the size of the last one's, and well before this many they are
+ * smaller than a texel, so more would only cost time. */
+pub const MAX_OCTAVES: u32 = 12;
+
+/* How the grid is drawn: its edges as lines, or its cells as lit triangles. Drawing polygons
+ * as outlines needs a device feature, so the wireframe is a line list over the same vertices
+ * instead. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    Wireframe,
+    Shaded,
+}
+
+impl Style {
+    pub fn parse(name: &str) -> Result<Style, String> {
+        match name {
+            "wireframe" => Ok(Style::Wireframe),
+            "shaded" => Ok(Style::Shaded),
+            _ => Err(format!("Unknown terrain style `{}`, expected wireframe or shaded", name)),
+        }
+    }
+}
+
+/* What the heightmap is made of. */
+#[derive(Debug, Clone, Copy)]
+pub struct Noise {
+    pub octaves: u32,
+    pub scale: f32,
+    pub seed: u32,
+}
+
+/* Fills a buffer with a side x side heightmap of fractal noise with a compute shader. */
+pub fn heightmap(device: Arc<Device>, queue: Arc<Queue>, side: u32, noise: Noise) -> Arc<DeviceLocalBuffer<[f32]>> {
+    let shader = cs::Shader::load(device.clone()).expect("Could not load noise shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let heights = memory::device_local(&queue, memory::storage_buffer(), (side * side) as usize)
+        .expect("Failed to create buffer");
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_buffer(heights.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = [side.div_ceil(LOCAL_SIZE), side.div_ceil(LOCAL_SIZE), 1];
+    let push_constants = cs::ty::PushConstants { side, octaves: noise.octaves, scale: noise.scale, seed: noise.seed };
+    ComputeTask::new("terrain", pipeline, set, groups, push_constants).submit(queue);
+    heights
+}
+
+/* The height noise.glsl puts at texel (x, y). */
+fn cpu_height(x: u32, y: u32, side: u32, noise: Noise) -> f32 {
+    let lattice = |cx: u32, cy: u32, octave: u32| {
+        let hash = pcg(cx.wrapping_add(pcg(cy.wrapping_add(pcg(octave.wrapping_add(pcg(noise.seed)))))));
+        hash as f32 / 4_294_967_296.0 * 2.0 - 1.0
+    };
+    let mix = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let value_noise = |px: f32, py: f32, octave: u32| {
+        let (cx, cy) = (px.floor() as u32, py.floor() as u32);
+        let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+        let (tx, ty) = (smooth(px.fract()), smooth(py.fract()));
+        let top = mix(lattice(cx, cy, octave), lattice(cx + 1, cy, octave), tx);
+        let bottom = mix(lattice(cx, cy + 1, octave), lattice(cx + 1, cy + 1, octave), tx);
+        mix(top, bottom, ty)
+    };
+
+    let (mut px, mut py) = (x as f32 / (side - 1) as f32 * noise.scale, y as f32 / (side - 1) as f32 * noise.scale);
+    let (mut height, mut amplitude, mut total) = (0.0, 1.0, 0.0);
+    for octave in 0..noise.octaves {
+        height += amplitude * value_noise(px, py, octave);
+        total += amplitude;
+        px *= 2.0;
+        py *= 2.0;
+        amplitude *= 0.5;
+    }
+    height / total
+}
+
+/* Copies the heights into a one channel float image, so the vertex shader can look them up
+ * by texel. */
+fn texture(device: Arc<Device>, queue: Arc<Queue>, heights: Arc<DeviceLocalBuffer<[f32]>>, side: u32)
+    -> Arc<StorageImage<Format>>
+{
+    let image = storage::image(&queue, Dimensions::Dim2d { width: side, height: side }, Format::R32Sfloat);
+    batch::submit_and_wait(device, queue, |builder| builder
+        .copy_buffer_to_image(heights, image.clone()).unwrap());
+    image
+}
+
+/* A vertex of the flat grid, which the vertex shader lifts to the height at its texel. */
+pub struct GridVertex { position: [f32; 2] }
+vulkano::impl_vertex!(GridVertex, position);
+
+/* The grid's side x side vertices, row by row, and the indices that join them up: two
+ * triangles per cell when shaded, and each edge once when drawn as a wireframe. */
+fn grid(side: u32, style: Style) -> (Vec<GridVertex>, Vec<u32>) {
+    let vertices = (0..side * side)
+        .map(|i| GridVertex { position: [(i % side) as f32 / (side - 1) as f32, (i / side) as f32 / (side - 1) as f32] })
+        .collect();
+
+    let mut indices = Vec::new();
+    let at = |x: u32, y: u32| y * side + x;
+    for y in 0..side {
+        for x in 0..side {
+            match style {
+                Style::Shaded if x + 1 < side && y + 1 < side => {
+                    indices.extend_from_slice(&[at(x, y), at(x + 1, y), at(x, y + 1)]);
+                    indices.extend_from_slice(&[at(x + 1, y), at(x + 1, y + 1), at(x, y + 1)]);
+                }
+                Style::Shaded => {}
+                Style::Wireframe => {
+                    if x + 1 < side {
+                        indices.extend_from_slice(&[at(x, y), at(x + 1, y)]);
+                    }
+                    if y + 1 < side {
+                        indices.extend_from_slice(&[at(x, y), at(x, y + 1)]);
+                    }
+                }
+            }
+        }
+    }
+    (vertices, indices)
+}
+
+/* The heightmap, the grid and a pipeline that draws one displaced by the other, with a depth
+ * buffer so nearer hills hide the ones behind them. */
+struct Terrain {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+    set: Arc<dyn DescriptorSet + Send + Sync>,
+    vertices: Arc<CpuAccessibleBuffer<[GridVertex]>>,
+    indices: Arc<CpuAccessibleBuffer<[u32]>>,
+    framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    image: Arc<AttachmentImage>,
+}
+
+impl Terrain {
+    fn new(device: Arc<Device>, queue: Arc<Queue>, noise: Noise, style: Style) -> Terrain {
+        let render_pass: Arc<dyn RenderPassAbstract + Send + Sync> = Arc::new(vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 1,
+                },
+                depth: {
+                    load: Clear,
+                    store: DontCare,
+                    format: Format::D16Unorm,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {depth}
+            }
+        ).unwrap());
+
+        let vs = vs::Shader::load(device.clone()).expect("Failed to create terrain vertex shader");
+        let fs = fs::Shader::load(device.clone()).expect("Failed to create terrain fragment shader");
+        let start = GraphicsPipeline::start()
+            .vertex_input_single_buffer::<GridVertex>()
+            .vertex_shader(vs.main_entry_point(), ())
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .depth_stencil_simple_depth()
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap());
+        let pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync> = match style {
+            Style::Wireframe => Arc::new(start.line_list().build(device.clone()).unwrap()),
+            Style::Shaded => Arc::new(start.triangle_list().build(device.clone()).unwrap()),
+        };
+
+        /* Linear filtering isn't guaranteed for float formats, and texelFetch doesn't filter
+         * anyway. */
+        let sampler = Sampler::new(device.clone(), Filter::Nearest, Filter::Nearest, MipmapMode::Nearest,
+            SamplerAddressMode::ClampToEdge, SamplerAddressMode::ClampToEdge, SamplerAddressMode::ClampToEdge,
+            0.0, 1.0, 0.0, 0.0).unwrap();
+        let heights = texture(device.clone(), queue.clone(), heightmap(device.clone(), queue.clone(), SIDE, noise), SIDE);
+        let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+            .add_sampled_image(heights, sampler).unwrap()
+            .build().unwrap());
+
+        let (grid_vertices, grid_indices) = grid(SIDE, style);
+        let vertices = vertices::make_vertex_buffer(device.clone(), grid_vertices);
+        let indices = memory::buffer(CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::index_buffer(),
+            grid_indices.into_iter()).expect("Failed to create index buffer"));
+
+        let usage = ImageUsage { color_attachment: true, transfer_source: true, .. ImageUsage::none() };
+        let image = memory::image(AttachmentImage::with_usage(device.clone(), [SIZE, SIZE], Format::R8G8B8A8Unorm,
+            usage).unwrap());
+        let depth = memory::image(AttachmentImage::transient(device.clone(), [SIZE, SIZE], Format::D16Unorm).unwrap());
+        let framebuffer = Arc::new(Framebuffer::start(render_pass)
+            .add(image.clone()).unwrap()
+            .add(depth).unwrap()
+            .build().unwrap());
+
+        Terrain { device, queue, pipeline, set, vertices, indices, framebuffer, image }
+    }
+
+    /* Renders the terrain with the camera at `angle` radians around it. */
+    fn draw(&self, angle: f32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        AutoCommandBufferBuilder::primary_one_time_submit(self.device.clone(), self.queue.family()).unwrap()
+            .begin_render_pass(self.framebuffer.clone(), false, vec![triangle::CLEAR_COLOR.into(), 1f32.into()]).unwrap()
+            .draw_indexed(self.pipeline.clone(), &triangle::dynamic_state(), vec![self.vertices.clone()],
+                self.indices.clone(), self.set.clone(), vs::ty::PushConstants { angle }).unwrap()
+            .end_render_pass().unwrap()
+            .build().unwrap()
+            .execute(self.queue.clone()).unwrap()
+            .then_signal_fence_and_flush().unwrap()
+            .wait(None).unwrap();
+
+        readback::download_rgba8(self.device.clone(), self.queue.clone(), self.image.clone())
+    }
+}
+
+/* Renders the terrain from a camera partway around it. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>, noise: Noise, style: Style) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    Terrain::new(device, queue, noise, style).draw(0.6)
+}
+
+/* Circles the camera once around the terrain over `frames` frames, saving each as
+ * terrain_NNNN.png. */
+pub fn animate(device: Arc<Device>, queue: Arc<Queue>, noise: Noise, style: Style, frames: u32) {
+    let terrain = Terrain::new(device, queue, noise, style);
+    for index in 0..frames {
+        let angle = index as f32 / frames as f32 * std::f32::consts::PI * 2.0;
+        let path = format!("terrain_{:04}.png", index);
+        terrain.draw(angle).save(&path).unwrap();
+        println!("Wrote {}", path);
+    }
+}
+
+/* Checks the compute shader's heights against the CPU's and that they stay between -1 and 1,
+ * that more octaves only add detail on top of the same hills, and that both styles draw
+ * something that turns with the camera, the shaded one covering more than the lines do. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let side = 33;
+    let noise = Noise { octaves: DEFAULT_OCTAVES, scale: DEFAULT_NOISE_SCALE, seed: 1 };
+    let heights = heightmap(device.clone(), queue.clone(), side, noise);
+    let gpu = memory::download_from_device_local(queue.clone(), heights).expect("Failed to create buffer");
+    for (index, &height) in gpu.iter().enumerate() {
+        let (x, y) = (index as u32 % side, index as u32 / side);
+        let expected = cpu_height(x, y, side, noise);
+        assert!((height - expected).abs() < 1e-4, "The height at ({}, {}) is {} on the GPU but {} on the CPU",
+            x, y, height, expected);
+        assert!(height.abs() <= 1.0, "The height at ({}, {}) is {}, outside -1..1", x, y, height);
+    }
+
+    let one = heightmap(device.clone(), queue.clone(), side, Noise { octaves: 1, .. noise });
+    let difference = gpu.iter().zip(memory::download_from_device_local(queue.clone(), one)
+        .expect("Failed to create buffer").iter())
+        .map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+    assert!(difference > 0.0 && difference < 1.0, "{} octaves differ from one by up to {}", noise.octaves, difference);
+
+    let mut covered = Vec::new();
+    for &style in &[Style::Wireframe, Style::Shaded] {
+        let terrain = Terrain::new(device.clone(), queue.clone(), noise, style);
+        let (front, side_on) = (terrain.draw(0.0), terrain.draw(1.0));
+        let lit = front.pixels().filter(|pixel| pixel[3] > 0).count();
+        assert!(lit > 0, "Nothing was drawn in the {:?} style", style);
+        assert!(front.into_raw() != side_on.into_raw(), "Turning the camera didn't change the {:?} terrain", style);
+        covered.push(lit);
+    }
+    assert!(covered[1] > covered[0], "The shaded terrain covers {} pixels, the wireframe {}", covered[1], covered[0]);
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/noise.glsl"
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/terrain.vert"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/terrain.frag"
+    }
+}
@@ -0,0 +1,54 @@
+#version 450
+
+/* Where on the map the vertex is, from 0 to 1 in both directions. */
+layout(location = 0) in vec2 position;
+
+layout(set = 0, binding = 0) uniform sampler2D heightmap;
+
+layout(push_constant) uniform PushConstants {
+    float angle;
+} pc;
+
+layout(location = 0) out float height;
+layout(location = 1) out float shade;
+
+/* Must match pointcloud.rs and terrain.rs. */
+const float AMPLITUDE = 0.35;
+const float TILT = 0.5;
+const float DISTANCE = 4.0;
+const float FOCAL = 1.5;
+
+/* Coming from up and to the side. */
+const vec3 LIGHT = normalize(vec3(-0.5, 1.0, 0.3));
+
+float height_at(ivec2 texel) {
+    ivec2 size = textureSize(heightmap, 0);
+    return texelFetch(heightmap, clamp(texel, ivec2(0), size - 1), 0).r * AMPLITUDE;
+}
+
+/* Displaces a flat grid over -1..1 in x and z by the heightmap, one texel per vertex, and
+ * looks at it with the point cloud's circling camera. The normal comes from the slopes to the
+ * neighbouring texels, for the shading. */
+void main() {
+    ivec2 size = textureSize(heightmap, 0);
+    ivec2 texel = ivec2(round(position * vec2(size - 1)));
+    vec3 p = vec3(position.x * 2.0 - 1.0, height_at(texel), position.y * 2.0 - 1.0);
+
+    float spacing = 2.0 / float(size.x - 1);
+    float dx = height_at(texel + ivec2(1, 0)) - height_at(texel - ivec2(1, 0));
+    float dz = height_at(texel + ivec2(0, 1)) - height_at(texel - ivec2(0, 1));
+    vec3 normal = normalize(vec3(-dx, 2.0 * spacing, -dz));
+    shade = 0.25 + 0.75 * max(dot(normal, LIGHT), 0.0);
+    height = p.y / AMPLITUDE * 0.5 + 0.5;
+
+    float c = cos(pc.angle), s = sin(pc.angle);
+    p = vec3(c * p.x + s * p.z, p.y, -s * p.x + c * p.z);
+    c = cos(TILT);
+    s = sin(TILT);
+    p = vec3(p.x, c * p.y - s * p.z, s * p.y + c * p.z);
+
+    /* Clip space y points down, so up is flipped to stay up on screen. Depth goes from 0 at
+     * the camera to 1 twice as far away as the middle of the map. */
+    float w = DISTANCE - p.z;
+    gl_Position = vec4(FOCAL * p.x, -FOCAL * p.y, w * w / (2.0 * DISTANCE), w);
+}
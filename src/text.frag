@@ -0,0 +1,13 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D atlas;
+
+/* The atlas only says how much of each texel a glyph covers, in alpha. The color is the same
+ * for all the text, and blending puts it over the background. */
+void main() {
+    f_color = vec4(1.0, 0.8, 0.4, texture(atlas, v_uv).a);
+}
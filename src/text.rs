@@ -0,0 +1,280 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer, DynamicState};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, Subpass};
+use vulkano::image::{AttachmentImage, ImageUsage};
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::viewport::Viewport;
+use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+use vulkano::sync::GpuFuture;
+
+use crate::memory;
+use crate::normalmap;
+use crate::readback;
+use crate::triangle;
+use crate::vertices;
+
+/* What `--text` defaults to. */
+pub const DEFAULT_TEXT: &str = "Hello, Vulkan!";
+
+/* Every glyph is GLYPH_HEIGHT font pixels tall and drawn SCALE x SCALE screen pixels per font
+ * pixel, with MARGIN screen pixels of background around the line. */
+pub const GLYPH_HEIGHT: usize = 7;
+const SCALE: u32 = 4;
+const MARGIN: u32 = 16;
+
+/* The atlas is a grid of COLUMNS cells across, each CELL x CELL texels, with a glyph in the top
+ * left corner of each. */
+const CELL: u32 = 8;
+const COLUMNS: u32 = 8;
+
+/* What the target is cleared to, and the text color text.frag uses, as bytes. */
+const BACKGROUND: [f32; 4] = [0.1, 0.1, 0.1, 1.0];
+const TEXT_COLOR: [u8; 3] = [255, 204, 102];
+
+/* A glyph's bitmap, one string per row from the top with `#` for a filled pixel, and how far to
+ * move along after drawing it. Every row is the glyph's width. */
+struct Glyph {
+    ch: char,
+    advance: u32,
+    rows: [&'static str; GLYPH_HEIGHT],
+}
+
+/* The font: capitals, digits and a little punctuation. Lowercase letters are drawn as capitals
+ * and anything else as a question mark. */
+const FONT: &[Glyph] = &[
+    Glyph { ch: 'A', advance: 6, rows: [".###.", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"] },
+    Glyph { ch: 'B', advance: 6, rows: ["####.", "#...#", "#...#", "####.", "#...#", "#...#", "####."] },
+    Glyph { ch: 'C', advance: 6, rows: [".###.", "#...#", "#....", "#....", "#....", "#...#", ".###."] },
+    Glyph { ch: 'D', advance: 6, rows: ["####.", "#...#", "#...#", "#...#", "#...#", "#...#", "####."] },
+    Glyph { ch: 'E', advance: 6, rows: ["#####", "#....", "#....", "####.", "#....", "#....", "#####"] },
+    Glyph { ch: 'F', advance: 6, rows: ["#####", "#....", "#....", "####.", "#....", "#....", "#...."] },
+    Glyph { ch: 'G', advance: 6, rows: [".###.", "#...#", "#....", "#.###", "#...#", "#...#", ".####"] },
+    Glyph { ch: 'H', advance: 6, rows: ["#...#", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"] },
+    Glyph { ch: 'I', advance: 4, rows: ["###", ".#.", ".#.", ".#.", ".#.", ".#.", "###"] },
+    Glyph { ch: 'J', advance: 6, rows: ["..###", "...#.", "...#.", "...#.", "...#.", "#..#.", ".##.."] },
+    Glyph { ch: 'K', advance: 6, rows: ["#...#", "#..#.", "#.#..", "##...", "#.#..", "#..#.", "#...#"] },
+    Glyph { ch: 'L', advance: 6, rows: ["#....", "#....", "#....", "#....", "#....", "#....", "#####"] },
+    Glyph { ch: 'M', advance: 6, rows: ["#...#", "##.##", "#.#.#", "#.#.#", "#...#", "#...#", "#...#"] },
+    Glyph { ch: 'N', advance: 6, rows: ["#...#", "#...#", "##..#", "#.#.#", "#..##", "#...#", "#...#"] },
+    Glyph { ch: 'O', advance: 6, rows: [".###.", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."] },
+    Glyph { ch: 'P', advance: 6, rows: ["####.", "#...#", "#...#", "####.", "#....", "#....", "#...."] },
+    Glyph { ch: 'Q', advance: 6, rows: [".###.", "#...#", "#...#", "#...#", "#.#.#", "#..#.", ".##.#"] },
+    Glyph { ch: 'R', advance: 6, rows: ["####.", "#...#", "#...#", "####.", "#.#..", "#..#.", "#...#"] },
+    Glyph { ch: 'S', advance: 6, rows: [".####", "#....", "#....", ".###.", "....#", "....#", "####."] },
+    Glyph { ch: 'T', advance: 6, rows: ["#####", "..#..", "..#..", "..#..", "..#..", "..#..", "..#.."] },
+    Glyph { ch: 'U', advance: 6, rows: ["#...#", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."] },
+    Glyph { ch: 'V', advance: 6, rows: ["#...#", "#...#", "#...#", "#...#", "#...#", ".#.#.", "..#.."] },
+    Glyph { ch: 'W', advance: 6, rows: ["#...#", "#...#", "#...#", "#.#.#", "#.#.#", "#.#.#", ".#.#."] },
+    Glyph { ch: 'X', advance: 6, rows: ["#...#", "#...#", ".#.#.", "..#..", ".#.#.", "#...#", "#...#"] },
+    Glyph { ch: 'Y', advance: 6, rows: ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#..", "..#.."] },
+    Glyph { ch: 'Z', advance: 6, rows: ["#####", "....#", "...#.", "..#..", ".#...", "#....", "#####"] },
+    Glyph { ch: '0', advance: 6, rows: [".###.", "#...#", "#..##", "#.#.#", "##..#", "#...#", ".###."] },
+    Glyph { ch: '1', advance: 4, rows: [".#.", "##.", ".#.", ".#.", ".#.", ".#.", "###"] },
+    Glyph { ch: '2', advance: 6, rows: [".###.", "#...#", "....#", "...#.", "..#..", ".#...", "#####"] },
+    Glyph { ch: '3', advance: 6, rows: ["#####", "...#.", "..#..", "...#.", "....#", "#...#", ".###."] },
+    Glyph { ch: '4', advance: 6, rows: ["...#.", "..##.", ".#.#.", "#..#.", "#####", "...#.", "...#."] },
+    Glyph { ch: '5', advance: 6, rows: ["#####", "#....", "####.", "....#", "....#", "#...#", ".###."] },
+    Glyph { ch: '6', advance: 6, rows: ["..##.", ".#...", "#....", "####.", "#...#", "#...#", ".###."] },
+    Glyph { ch: '7', advance: 6, rows: ["#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#..."] },
+    Glyph { ch: '8', advance: 6, rows: [".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###."] },
+    Glyph { ch: '9', advance: 6, rows: [".###.", "#...#", "#...#", ".####", "....#", "...#.", ".##.."] },
+    Glyph { ch: ' ', advance: 4, rows: ["...", "...", "...", "...", "...", "...", "..."] },
+    Glyph { ch: '.', advance: 2, rows: [".", ".", ".", ".", ".", ".", "#"] },
+    Glyph { ch: ',', advance: 3, rows: ["..", "..", "..", "..", "..", ".#", "#."] },
+    Glyph { ch: '!', advance: 2, rows: ["#", "#", "#", "#", "#", ".", "#"] },
+    Glyph { ch: '?', advance: 6, rows: [".###.", "#...#", "....#", "...#.", "..#..", ".....", "..#.."] },
+    Glyph { ch: '-', advance: 4, rows: ["...", "...", "...", "###", "...", "...", "..."] },
+    Glyph { ch: ':', advance: 2, rows: [".", "#", ".", ".", ".", "#", "."] },
+    Glyph { ch: '\'', advance: 2, rows: ["#", "#", ".", ".", ".", ".", "."] },
+    Glyph { ch: '(', advance: 4, rows: ["..#", ".#.", "#..", "#..", "#..", ".#.", "..#"] },
+    Glyph { ch: ')', advance: 4, rows: ["#..", ".#.", "..#", "..#", "..#", ".#.", "#.."] },
+    Glyph { ch: '+', advance: 6, rows: [".....", "..#..", "..#..", "#####", "..#..", "..#..", "....."] },
+    Glyph { ch: '/', advance: 6, rows: ["....#", "....#", "...#.", "..#..", ".#...", "#....", "#...."] },
+];
+
+impl Glyph {
+    fn width(&self) -> u32 {
+        self.rows[0].len() as u32
+    }
+
+    fn filled(&self, x: u32, y: u32) -> bool {
+        self.rows[y as usize].as_bytes()[x as usize] == b'#'
+    }
+}
+
+/* The index in FONT of the glyph `ch` is drawn with. */
+fn glyph_index(ch: char) -> usize {
+    let ch = ch.to_ascii_uppercase();
+    FONT.iter().position(|glyph| glyph.ch == ch)
+        .unwrap_or_else(|| FONT.iter().position(|glyph| glyph.ch == '?').unwrap())
+}
+
+/* How many font pixels wide glyph `index` of FONT is, as `layout` gives it. */
+pub fn glyph_width(index: usize) -> u32 {
+    FONT[index].width()
+}
+
+/* Draws every glyph of FONT into its cell, white, with alpha saying where the glyph is. */
+pub fn atlas() -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let rows = (FONT.len() as u32).div_ceil(COLUMNS);
+    let mut atlas = ImageBuffer::from_pixel(COLUMNS * CELL, rows * CELL, Rgba([255, 255, 255, 0]));
+    for (index, glyph) in FONT.iter().enumerate() {
+        let (cell_x, cell_y) = cell(index);
+        for y in 0..GLYPH_HEIGHT as u32 {
+            for x in 0..glyph.width() {
+                if glyph.filled(x, y) {
+                    atlas.put_pixel(cell_x + x, cell_y + y, Rgba([255, 255, 255, 255]));
+                }
+            }
+        }
+    }
+    atlas
+}
+
+/* The top left texel of glyph `index`'s cell in the atlas. */
+pub fn cell(index: usize) -> (u32, u32) {
+    ((index as u32 % COLUMNS) * CELL, (index as u32 / COLUMNS) * CELL)
+}
+
+/* Where each glyph of `text` goes: its index in FONT and its left edge in font pixels. Also
+ * returns the width of the whole line, without the gap after the last glyph. */
+pub fn layout(text: &str) -> (Vec<(usize, u32)>, u32) {
+    let mut pen = 0;
+    let mut width = 0;
+    let placed = text.chars().map(|ch| {
+        let index = glyph_index(ch);
+        let placed = (index, pen);
+        width = pen + FONT[index].width();
+        pen += FONT[index].advance;
+        placed
+    }).collect();
+    (placed, width)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TextVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+}
+vulkano::impl_vertex!(TextVertex, position, uv);
+
+/* Renders `text` on one line, into an image just big enough to hold it. Each glyph is a quad,
+ * two triangles, textured with its cell of the atlas: one texture holding every glyph, so the
+ * whole string is a single draw with a single descriptor set however many different glyphs it
+ * uses. The sampler is nearest-neighbor, so the font pixels stay sharp when scaled up. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>, text: &str) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String> {
+    let (placed, line_width) = layout(text);
+    let width = (2 * MARGIN + line_width * SCALE).max(1);
+    let height = 2 * MARGIN + GLYPH_HEIGHT as u32 * SCALE;
+    let max = device.physical_device().limits().max_image_dimension_2d();
+    if width > max {
+        return Err(format!("`{}` would be {} pixels wide, but the device can only render {}", text, width, max));
+    }
+
+    let atlas_pixels = atlas();
+    let (atlas_width, atlas_height) = (atlas_pixels.width() as f32, atlas_pixels.height() as f32);
+
+    /* Screen pixels to clip space, where the top left is (-1, -1), and atlas texels to texture
+     * coordinates, where the whole atlas is 0 to 1. */
+    let clip = |x: u32, y: u32| [x as f32 / width as f32 * 2.0 - 1.0, y as f32 / height as f32 * 2.0 - 1.0];
+    let texture = |x: u32, y: u32| [x as f32 / atlas_width, y as f32 / atlas_height];
+
+    let vertices = placed.iter().flat_map(|&(index, pen)| {
+        let glyph = &FONT[index];
+        let (x0, y0) = (MARGIN + pen * SCALE, MARGIN);
+        let (x1, y1) = (x0 + glyph.width() * SCALE, y0 + GLYPH_HEIGHT as u32 * SCALE);
+        let (u0, v0) = cell(index);
+        let (u1, v1) = (u0 + glyph.width(), v0 + GLYPH_HEIGHT as u32);
+
+        let corner = |x, y, u, v| TextVertex { position: clip(x, y), uv: texture(u, v) };
+        let (top_left, top_right) = (corner(x0, y0, u0, v0), corner(x1, y0, u1, v0));
+        let (bottom_left, bottom_right) = (corner(x0, y1, u0, v1), corner(x1, y1, u1, v1));
+        vec![top_left, top_right, bottom_right, top_left, bottom_right, bottom_left]
+    });
+    let vertex_buffer = vertices::make_vertex_buffer(device.clone(), vertices);
+
+    let render_pass = triangle::render_pass(device.clone(), Format::R8G8B8A8Unorm);
+    let vs = vs::Shader::load(device.clone()).expect("Failed to create text vertex shader");
+    let fs = fs::Shader::load(device.clone()).expect("Failed to create text fragment shader");
+    let pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<TextVertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .blend_alpha_blending()
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let sampler = Sampler::new(device.clone(), Filter::Nearest, Filter::Nearest, MipmapMode::Nearest,
+        SamplerAddressMode::ClampToEdge, SamplerAddressMode::ClampToEdge, SamplerAddressMode::ClampToEdge,
+        0.0, 1.0, 0.0, 0.0).unwrap();
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_sampled_image(normalmap::texture(queue.clone(), &atlas_pixels), sampler).unwrap()
+        .build().unwrap());
+
+    let usage = ImageUsage { color_attachment: true, transfer_source: true, .. ImageUsage::none() };
+    let image = memory::image(AttachmentImage::with_usage(device.clone(), [width, height], Format::R8G8B8A8Unorm,
+        usage).unwrap());
+    let framebuffer = Arc::new(Framebuffer::start(render_pass)
+        .add(image.clone()).unwrap()
+        .build().unwrap());
+
+    let dynamic_state = DynamicState {
+        viewports: Some(vec![Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [width as f32, height as f32],
+            depth_range: 0.0 .. 1.0,
+        }]),
+        .. DynamicState::none()
+    };
+
+    AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer, false, vec![BACKGROUND.into()]).unwrap()
+        .draw(pipeline, &dynamic_state, vertex_buffer, set, ()).unwrap()
+        .end_render_pass().unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    Ok(readback::download_rgba8(device, queue, image))
+}
+
+/* Renders a string using every kind of glyph width and checks the middle of every font pixel
+ * on screen against the font: text colored where the glyph is filled, background elsewhere. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let text = "Hi, 1 W!";
+    let image = render(device, queue, text).unwrap();
+    let (placed, _) = layout(text);
+
+    for &(index, pen) in &placed {
+        let glyph = &FONT[index];
+        for y in 0..GLYPH_HEIGHT as u32 {
+            for x in 0..glyph.width() {
+                let pixel = image.get_pixel(MARGIN + (pen + x) * SCALE + SCALE / 2, MARGIN + y * SCALE + SCALE / 2);
+                let is_text = pixel[0] == TEXT_COLOR[0] && pixel[1] == TEXT_COLOR[1] && pixel[2] == TEXT_COLOR[2];
+                assert!(is_text == glyph.filled(x, y), "Pixel ({}, {}) of `{}` in `{}` is {:?}",
+                    x, y, glyph.ch, text, pixel);
+            }
+        }
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/text.vert"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/text.frag"
+    }
+}
@@ -0,0 +1,14 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D tex;
+
+/* The sampler picks the mip levels whose texels are about the size of this pixel and blends
+ * between them, so however small the quad is drawn it shows the texture's average there rather
+ * than whichever texel happened to land under the pixel. */
+void main() {
+    f_color = texture(tex, v_uv);
+}
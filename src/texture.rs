@@ -0,0 +1,213 @@
+use image::{ImageBuffer, Rgba};
+use std::path::Path;
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, Subpass};
+use vulkano::image::{AttachmentImage, Dimensions, ImageLayout, ImageUsage, ImmutableImage};
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+use vulkano::sync::GpuFuture;
+
+use crate::batch;
+use crate::input;
+use crate::memory;
+use crate::mipchain;
+use crate::readback::{self, RGBA8_BYTES};
+use crate::storage;
+use crate::triangle::{self, SIZE};
+use crate::vertices;
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/* How much of the image the quad covers across, unless `render` is asked for another size. */
+pub const DEFAULT_SCALE: f32 = 0.8;
+
+/* Loads the PNG or JPEG at `path` as 8 bit RGBA, with the same errors as `--input`. */
+pub fn load(path: &Path) -> Result<Image, String> {
+    input::load(path).map(|input| input.pixels)
+}
+
+/* Puts `pixels` in a device-local image with a full mip chain, ready to be sampled. The pixels
+ * go into a staging buffer the CPU can write, are copied from there into the first of a chain
+ * of scratch images, and each level is blitted down from the one before with linear filtering,
+ * all on the GPU in one command buffer. The levels can't be blitted within the texture itself,
+ * since vulkano 0.11 tracks an image as a whole and refuses a blit that reads and writes the
+ * same one, so like `mipchain::chain` each level is built in an image of its own and then
+ * copied into its level of the texture. The texture starts out undefined, vulkano moves it to
+ * the transfer destination layout for the copies and to the shader read-only layout once the
+ * command buffer is done, which is the layout it is created to be sampled in. */
+pub fn upload(device: Arc<Device>, queue: Arc<Queue>, pixels: &Image) -> Arc<ImmutableImage<Format>> {
+    let sizes = mipchain::level_sizes(pixels.width(), pixels.height());
+    let dimensions = Dimensions::Dim2d { width: pixels.width(), height: pixels.height() };
+    let usage = ImageUsage { sampled: true, transfer_source: true, transfer_destination: true, .. ImageUsage::none() };
+    let (image, initialization) = ImmutableImage::uninitialized(device.clone(), dimensions, Format::R8G8B8A8Unorm,
+        sizes.len() as u32, usage, ImageLayout::ShaderReadOnlyOptimal, Some(queue.family()))
+        .expect("Failed to create texture");
+    let initialization = Arc::new(initialization);
+
+    let staging = memory::buffer(CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(),
+        pixels.iter().cloned()).expect("Failed to create buffer"));
+    let levels: Vec<_> = sizes.iter()
+        .map(|&(width, height)| storage::image(&queue, Dimensions::Dim2d { width, height }, Format::R8G8B8A8Unorm))
+        .collect();
+    let corner = |(width, height): (u32, u32)| [width as i32, height as i32, 1];
+
+    batch::submit_and_wait(device, queue, |builder| {
+        let mut builder = builder.copy_buffer_to_image(staging, levels[0].clone()).unwrap();
+        for level in 1..levels.len() {
+            builder = builder.blit_image(levels[level - 1].clone(), [0, 0, 0], corner(sizes[level - 1]), 0, 0,
+                levels[level].clone(), [0, 0, 0], corner(sizes[level]), 0, 0, 1, Filter::Linear).unwrap();
+        }
+        for (level, &(width, height)) in sizes.iter().enumerate() {
+            builder = builder.copy_image(levels[level].clone(), [0, 0, 0], 0, 0,
+                initialization.clone(), [0, 0, 0], 0, level as u32, [width, height, 1], 1).unwrap();
+        }
+        builder
+    });
+
+    memory::image(image)
+}
+
+/* Reads mip level `level` of `texture` back. */
+fn download_level(device: Arc<Device>, queue: Arc<Queue>, texture: Arc<ImmutableImage<Format>>, level: u32) -> Image {
+    let [width, height] = texture.dimensions().width_height();
+    let (width, height) = ((width >> level).max(1), (height >> level).max(1));
+    let buf = memory::readback::<u8>(&queue, (width * height) as usize * RGBA8_BYTES).expect("Failed to create buffer");
+
+    batch::submit_and_wait(device, queue, |builder| builder
+        .copy_image_to_buffer_dimensions(texture, buf.clone(), [0, 0, 0], [width, height, 1], 0, 1, level).unwrap());
+
+    let buffer_content = buf.read().unwrap();
+    readback::rgba8_image(width, height, width as usize * RGBA8_BYTES, &buffer_content)
+}
+
+/* Samples with trilinear filtering, blending the four nearest texels of the two nearest mip
+ * levels, across every level the texture has. */
+pub fn sampler(device: Arc<Device>, texture: &ImmutableImage<Format>) -> Arc<Sampler> {
+    Sampler::new(device, Filter::Linear, Filter::Linear, MipmapMode::Linear,
+        SamplerAddressMode::Repeat, SamplerAddressMode::Repeat, SamplerAddressMode::Repeat,
+        0.0, 1.0, 0.0, texture.mipmap_levels() as f32).unwrap()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TexturedVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+}
+vulkano::impl_vertex!(TexturedVertex, position, uv);
+
+/* Draws `texture` on a square quad in the middle of the image, `scale` of its width across,
+ * upright with the texture's first row at the top, through a descriptor set holding the texture
+ * and its sampler. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>, texture: Arc<ImmutableImage<Format>>, scale: f32) -> Image {
+    let corner = |x: f32, y: f32| TexturedVertex {
+        position: [x * scale, y * scale],
+        uv: [x * 0.5 + 0.5, y * 0.5 + 0.5],
+    };
+    let (top_left, top_right) = (corner(-1.0, -1.0), corner(1.0, -1.0));
+    let (bottom_left, bottom_right) = (corner(-1.0, 1.0), corner(1.0, 1.0));
+    let vertex_buffer = vertices::make_vertex_buffer(device.clone(),
+        vec![top_left, top_right, bottom_right, top_left, bottom_right, bottom_left]);
+
+    let render_pass = triangle::render_pass(device.clone(), Format::R8G8B8A8Unorm);
+    let vs = vs::Shader::load(device.clone()).expect("Failed to create texture vertex shader");
+    let fs = fs::Shader::load(device.clone()).expect("Failed to create texture fragment shader");
+    let pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<TexturedVertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let sampler = sampler(device.clone(), &texture);
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_sampled_image(texture, sampler).unwrap()
+        .build().unwrap());
+
+    let usage = ImageUsage { color_attachment: true, transfer_source: true, .. ImageUsage::none() };
+    let image = memory::image(AttachmentImage::with_usage(device.clone(), [SIZE, SIZE], Format::R8G8B8A8Unorm,
+        usage).unwrap());
+    let framebuffer = Arc::new(Framebuffer::start(render_pass)
+        .add(image.clone()).unwrap()
+        .build().unwrap());
+
+    AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap()
+        .begin_render_pass(framebuffer, false, vec![triangle::CLEAR_COLOR.into()]).unwrap()
+        .draw(pipeline, &triangle::dynamic_state(), vertex_buffer, set, ()).unwrap()
+        .end_render_pass().unwrap()
+        .build().unwrap()
+        .execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    readback::download_rgba8(device, queue, image)
+}
+
+const TEXTURE_SIZE: u32 = 256;
+
+/* Red, green, blue and white quarters, so a texture drawn flipped or rotated is caught. */
+fn quadrants() -> Image {
+    let half = TEXTURE_SIZE / 2;
+    ImageBuffer::from_fn(TEXTURE_SIZE, TEXTURE_SIZE, |x, y| match (x < half, y < half) {
+        (true, true) => Rgba([255, 0, 0, 255]),
+        (false, true) => Rgba([0, 255, 0, 255]),
+        (true, false) => Rgba([0, 0, 255, 255]),
+        (false, false) => Rgba([255, 255, 255, 255]),
+    })
+}
+
+/* Black and white texels alternating, which averages to grey at every level but the first. */
+fn fine_checker() -> Image {
+    ImageBuffer::from_fn(TEXTURE_SIZE, TEXTURE_SIZE, |x, y| {
+        if (x + y) % 2 == 0 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) }
+    })
+}
+
+/* Checks that the quarters texture has every mip level down to one texel, the average of the
+ * four colors, and is drawn the right way up, and that the checker drawn 20 pixels wide, where
+ * each pixel covers about 13 texels, is the grey of its lower levels rather than the black or
+ * white of whichever texel a pixel lands on. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let texture = upload(device.clone(), queue.clone(), &quadrants());
+    assert!(texture.mipmap_levels() == 9, "A {0} x {0} texture has {1} levels, expected 9", TEXTURE_SIZE,
+        texture.mipmap_levels());
+    let last = download_level(device.clone(), queue.clone(), texture.clone(), 8);
+    let average = last.get_pixel(0, 0);
+    assert!((0..3).all(|channel| (average[channel] as i32 - 128).abs() <= 8),
+        "The last mip level is {:?}, not the average of the quarters", average);
+
+    let image = render(device.clone(), queue.clone(), texture, 1.0);
+    let (near, far) = (SIZE / 4, SIZE * 3 / 4);
+    for &(x, y, expected) in &[(near, near, [255, 0, 0]), (far, near, [0, 255, 0]), (near, far, [0, 0, 255]),
+                                (far, far, [255, 255, 255])] {
+        let pixel = image.get_pixel(x, y);
+        assert!(pixel[0] == expected[0] && pixel[1] == expected[1] && pixel[2] == expected[2],
+            "The texture is drawn {:?} at {}, {}, expected {:?}", pixel, x, y, expected);
+    }
+
+    let checker = upload(device.clone(), queue.clone(), &fine_checker());
+    let image = render(device, queue, checker, 20.0 / SIZE as f32);
+    let pixel = image.get_pixel(SIZE / 2, SIZE / 2);
+    assert!((0..3).all(|channel| (pixel[channel] as i32 - 128).abs() <= 16),
+        "The minified checker is {:?}, not grey, so it wasn't sampled from its mip levels", pixel);
+}
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/texture.vert"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/texture.frag"
+    }
+}
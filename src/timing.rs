@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::device::{Device, Queue};
+use vulkano::query::{QueryType, UnsafeQueryPool};
+use vulkano::sync::PipelineStages;
+
+use crate::batch;
+use crate::memory;
+
+/* How long one run of a benchmark took. `iteration` is whatever the benchmark varies between
+ * runs, like the mandelbrot's iteration count. The GPU time is missing on queues that can't
+ * write timestamps. */
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    pub iteration: u32,
+    pub host_ms: f64,
+    pub gpu_ms: Option<f64>,
+}
+
+/* Measures GPU time with a pair of timestamps, one written before some work and one after. The
+ * host timing around a submit also counts recording, submitting and waking up again, which can
+ * swamp a short dispatch. `AutoCommandBufferBuilder` can't write timestamps, so each one goes
+ * in a command buffer of its own on the same queue as the work. A timestamp is written once all
+ * the work submitted before it is done, so the difference still includes the moment it takes
+ * to submit the work, but none of the CPU's side of things. */
+pub struct GpuTimer {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    pool: UnsafeQueryPool,
+    results: Arc<CpuAccessibleBuffer<[u32]>>,
+    mask: u32,
+    period: f64,
+}
+
+impl GpuTimer {
+    /* Returns None if `queue` doesn't support timestamps. */
+    pub fn new(device: Arc<Device>, queue: Arc<Queue>) -> Option<GpuTimer> {
+        let bits = queue.family().timestamp_valid_bits()?;
+        let pool = UnsafeQueryPool::new(device.clone(), QueryType::Timestamp, 2).unwrap();
+        let results = memory::readback(&queue, 2).expect("Failed to create buffer");
+
+        /* The results come back as 32 bit values, of which only the valid bits count. */
+        let mask = if bits >= 32 { u32::MAX } else { (1 << bits) - 1 };
+        let period = device.physical_device().limits().timestamp_period() as f64;
+        Some(GpuTimer { device, queue, pool, results, mask, period })
+    }
+
+    /* Runs `work`, which has to submit its commands to the timer's queue and wait for them, and
+     * returns what it returned along with how many milliseconds the GPU took. */
+    pub fn time<T, F: FnOnce() -> T>(&self, work: F) -> (T, f64) {
+        let stages = PipelineStages { bottom_of_pipe: true, .. PipelineStages::none() };
+
+        batch::submit_unsafe(self.device.clone(), &self.queue, |builder| unsafe {
+            builder.reset_query_pool(self.pool.queries_range(0, 2).unwrap());
+            builder.write_timestamp(self.pool.query(0).unwrap(), stages);
+        });
+        let value = work();
+        batch::submit_unsafe(self.device.clone(), &self.queue, |builder| unsafe {
+            builder.write_timestamp(self.pool.query(1).unwrap(), stages);
+        });
+
+        /* Both timestamps have been waited for, so their results are there to copy. */
+        batch::submit_unsafe(self.device.clone(), &self.queue, |builder| unsafe {
+            builder.copy_query_pool_results(self.pool.queries_range(0, 2).unwrap(), &*self.results, 4);
+        });
+
+        let content = self.results.read().unwrap();
+        let ticks = content[1].wrapping_sub(content[0]) & self.mask;
+        (value, ticks as f64 * self.period / 1_000_000.0)
+    }
+}
+
+/* Writes `timings` of `demo` to `path` as CSV, one row per run under a
+ * `demo,iteration,host_ms,gpu_ms` header. A missing GPU time is left empty. */
+pub fn write_csv(path: &Path, demo: &str, timings: &[Timing]) -> io::Result<()> {
+    write_csv_all(path, &[(demo, timings)])
+}
+
+/* Like `write_csv`, for the timings of more than one demo in the same file. */
+pub fn write_csv_all(path: &Path, demos: &[(&str, &[Timing])]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "demo,iteration,host_ms,gpu_ms")?;
+    for &(demo, timings) in demos {
+        for timing in timings {
+            let gpu_ms = timing.gpu_ms.map(|ms| format!("{:.4}", ms)).unwrap_or_default();
+            writeln!(out, "{},{},{:.4},{}", demo, timing.iteration, timing.host_ms, gpu_ms)?;
+        }
+    }
+    out.flush()
+}
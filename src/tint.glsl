@@ -0,0 +1,12 @@
+#version 450
+
+/* The color the first subpass wrote at this very pixel. An input attachment can only be read
+ * at the fragment's own position, which is all a fullscreen tint needs. */
+layout(input_attachment_index = 0, set = 0, binding = 0) uniform subpassInput scene;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    vec4 color = subpassLoad(scene);
+    f_color = vec4(color.rgb * vec3(1.0, 0.8, 0.6), color.a);
+}
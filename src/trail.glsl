@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) in float progress;
+
+layout(location = 0) out vec4 f_color;
+
+/* Red where the particles set out, fading to white where they end up. */
+void main() {
+    float t = clamp(progress, 0.0, 1.0);
+    f_color = vec4(mix(vec3(0.8, 0.1, 0.1), vec3(1.0), t), 1.0);
+}
@@ -0,0 +1,216 @@
+use image::{ImageBuffer, Rgba};
+use std::iter;
+use std::mem;
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, DeviceLocalBuffer};
+use vulkano::command_buffer::submit::SubmitCommandBufferBuilder;
+use vulkano::command_buffer::sys::{Flags, Kind, UnsafeCommandBuffer, UnsafeCommandBufferBuilder,
+    UnsafeCommandBufferBuilderBufferImageCopy, UnsafeCommandBufferBuilderImageAspect,
+    UnsafeCommandBufferBuilderPipelineBarrier};
+use vulkano::command_buffer::pool::standard::{StandardCommandPoolAlloc, StandardCommandPoolBuilder};
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, ImageLayout, StorageImage};
+use vulkano::instance::{PhysicalDevice, QueueFamily};
+use vulkano::sync::{AccessFlagBits, Fence, PipelineStages, Semaphore};
+
+use crate::batch;
+use crate::memory;
+use crate::pattern;
+use crate::readback;
+use crate::storage;
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/* A queue family that only does transfers, other than `main`. GPUs with one usually have it to
+ * drive a copy engine of its own, which moves data over the bus while the rest of the GPU gets
+ * on with drawing and computing. */
+pub fn dedicated_family<'a>(physical: PhysicalDevice<'a>, main: QueueFamily<'a>) -> Option<QueueFamily<'a>> {
+    physical.queue_families().find(|&q| {
+        q.id() != main.id() && q.supports_transfers() && !q.supports_graphics() && !q.supports_compute()
+    })
+}
+
+/* The stages the barriers below wait for or hold up. */
+fn top() -> PipelineStages {
+    PipelineStages { top_of_pipe: true, .. PipelineStages::none() }
+}
+
+fn bottom() -> PipelineStages {
+    PipelineStages { bottom_of_pipe: true, .. PipelineStages::none() }
+}
+
+fn copies() -> PipelineStages {
+    PipelineStages { transfer: true, .. PipelineStages::none() }
+}
+
+/* Whatever `queue` does with an upload first: copy it somewhere or read it in a compute shader. */
+fn first_use() -> PipelineStages {
+    PipelineStages { transfer: true, compute_shader: true, .. PipelineStages::none() }
+}
+
+/* Records the upload on `transfer` and the first use on `queue`, submits them and waits. The
+ * upload's command buffer signals a semaphore when it is done, and the other one waits on it
+ * before it starts, so the GPU orders them without the CPU waiting in between. Only the second
+ * one signals a fence: it can't finish before the first, so once it has both are done and can
+ * be dropped. */
+fn submit_pair(device: Arc<Device>, transfer: &Queue, queue: &Queue,
+               upload: UnsafeCommandBuffer<StandardCommandPoolAlloc>,
+               acquire: UnsafeCommandBuffer<StandardCommandPoolAlloc>)
+{
+    let semaphore = Semaphore::alloc(device.clone()).unwrap();
+    let fence = Fence::alloc(device).unwrap();
+    unsafe {
+        let mut submit = SubmitCommandBufferBuilder::new();
+        submit.add_command_buffer(&upload);
+        submit.add_signal_semaphore(&semaphore);
+        submit.submit(transfer).unwrap();
+
+        let mut submit = SubmitCommandBufferBuilder::new();
+        submit.add_wait_semaphore(&semaphore, first_use());
+        submit.add_command_buffer(&acquire);
+        submit.set_fence_signal(&fence);
+        submit.submit(queue).unwrap();
+    }
+    fence.wait(None).unwrap();
+}
+
+/* A new unsafe command buffer for `queue`'s family, recorded by `record`. */
+fn record<F>(device: &Arc<Device>, queue: &Queue, record: F) -> UnsafeCommandBuffer<StandardCommandPoolAlloc>
+    where F: FnOnce(&mut UnsafeCommandBufferBuilder<StandardCommandPoolBuilder>)
+{
+    let pool = Device::standard_command_pool(device, queue.family());
+    unsafe {
+        let mut builder = UnsafeCommandBufferBuilder::new(&pool, Kind::primary(), Flags::OneTimeSubmit).unwrap();
+        record(&mut builder);
+        builder.build().unwrap()
+    }
+}
+
+/* Puts `data` in a device local buffer by copying it there on `transfer`, for compute shaders on
+ * `queue` to read. The buffer is exclusive to one queue family at a time, the fast way to share,
+ * so if `transfer` is from another family than `queue` it has to be handed over: the transfer
+ * family releases it with a barrier naming both families, then `queue`'s family acquires it
+ * with a barrier naming the same two. Until the acquire, `queue` must not touch it. If both
+ * queues are from the same family there is nothing to hand over and the barriers are plain
+ * ones. */
+pub fn upload_buffer(device: Arc<Device>, queue: Arc<Queue>, transfer: Arc<Queue>, data: &[u32])
+    -> Arc<DeviceLocalBuffer<[u32]>>
+{
+    let size = mem::size_of_val(data);
+    let staging = memory::buffer(CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(),
+        data.iter().cloned()).expect("Failed to create buffer"));
+    let usage = BufferUsage { transfer_source: true, transfer_destination: true, storage_buffer: true, .. BufferUsage::none() };
+    let buffer = memory::buffer(DeviceLocalBuffer::array(device.clone(), data.len(), usage, Some(transfer.family()))
+        .expect("Failed to create buffer"));
+
+    let handover = handover(&transfer, &queue);
+    let write = AccessFlagBits { transfer_write: true, .. AccessFlagBits::none() };
+    let read = AccessFlagBits { transfer_read: true, shader_read: true, .. AccessFlagBits::none() };
+
+    let upload = record(&device, &transfer, |builder| unsafe {
+        builder.copy_buffer(&*staging, &*buffer, iter::once((0, 0, size)));
+        /* The release. The destination is left empty: on this queue nothing comes after. */
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        barrier.add_buffer_memory_barrier(&*buffer, copies(), write,
+            bottom(), AccessFlagBits::none(), false, handover, 0, size);
+        builder.pipeline_barrier(&barrier);
+    });
+    let acquire = record(&device, &queue, |builder| unsafe {
+        /* The acquire, the same barrier again with no source accesses instead: the write it
+         * waits for happened on the other queue, which the semaphore already waited for. Its
+         * source stages are the ones the semaphore holds up, which chains the two, so nothing
+         * here starts before the release is done. */
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        barrier.add_buffer_memory_barrier(&*buffer, first_use(), AccessFlagBits::none(),
+            first_use(), read, false, handover, 0, size);
+        builder.pipeline_barrier(&barrier);
+    });
+
+    submit_pair(device, &transfer, &queue, upload, acquire);
+    buffer
+}
+
+/* The same for a texture: puts `pixels` in a storage image by copying them on `transfer` and
+ * handing it over to `queue`'s family. An image barrier can move the image to another layout at
+ * the same time, and the release and the acquire have to agree on that too. It ends up in the
+ * General layout the other compute demos expect storage images to be in. */
+pub fn upload_rgba8(device: Arc<Device>, queue: Arc<Queue>, transfer: Arc<Queue>, pixels: &Image)
+    -> Arc<StorageImage<Format>>
+{
+    let (width, height) = pixels.dimensions();
+    if let Err(e) = storage::check(device.physical_device(), Format::R8G8B8A8Unorm) {
+        panic!("{}", e);
+    }
+    let image = memory::image(StorageImage::new(device.clone(), Dimensions::Dim2d { width, height },
+        Format::R8G8B8A8Unorm,
+        Some(transfer.family())).unwrap());
+    let staging = memory::buffer(CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(),
+        pixels.iter().cloned()).expect("Failed to create buffer"));
+
+    let handover = handover(&transfer, &queue);
+    let write = AccessFlagBits { transfer_write: true, .. AccessFlagBits::none() };
+    let read = AccessFlagBits { transfer_read: true, shader_read: true, shader_write: true, .. AccessFlagBits::none() };
+
+    let upload = record(&device, &transfer, |builder| unsafe {
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        barrier.add_image_memory_barrier(&*image, 0..1, 0..1, top(), AccessFlagBits::none(),
+            copies(), write, false, None, ImageLayout::Undefined, ImageLayout::TransferDstOptimal);
+        builder.pipeline_barrier(&barrier);
+
+        builder.copy_buffer_to_image(&*staging, &*image, ImageLayout::TransferDstOptimal, iter::once(
+            UnsafeCommandBufferBuilderBufferImageCopy {
+                buffer_offset: 0,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_aspect: UnsafeCommandBufferBuilderImageAspect { color: true, depth: false, stencil: false },
+                image_mip_level: 0,
+                image_base_array_layer: 0,
+                image_layer_count: 1,
+                image_offset: [0, 0, 0],
+                image_extent: [width, height, 1],
+            }));
+
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        barrier.add_image_memory_barrier(&*image, 0..1, 0..1, copies(), write,
+            bottom(), AccessFlagBits::none(), false, handover,
+            ImageLayout::TransferDstOptimal, ImageLayout::General);
+        builder.pipeline_barrier(&barrier);
+    });
+    let acquire = record(&device, &queue, |builder| unsafe {
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        barrier.add_image_memory_barrier(&*image, 0..1, 0..1, first_use(), AccessFlagBits::none(),
+            first_use(), read, false, handover, ImageLayout::TransferDstOptimal, ImageLayout::General);
+        builder.pipeline_barrier(&barrier);
+    });
+
+    submit_pair(device, &transfer, &queue, upload, acquire);
+    image
+}
+
+/* The families a barrier hands a resource over between, if there are two. */
+fn handover(transfer: &Queue, queue: &Queue) -> Option<(u32, u32)> {
+    if transfer.family().id() == queue.family().id() {
+        None
+    } else {
+        Some((transfer.family().id(), queue.family().id()))
+    }
+}
+
+/* Uploads a buffer and a texture on `transfer` and reads them back on `queue`, which only works
+ * if they were handed over. `transfer` may be `queue` itself, which checks the same uploads
+ * without a handover. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>, transfer: Arc<Queue>) {
+    let data: Vec<u32> = (0..4096).map(|i| i * 3 + 1).collect();
+    let buffer = upload_buffer(device.clone(), queue.clone(), transfer.clone(), &data);
+    let readback = memory::readback::<u32>(&queue, data.len()).expect("Failed to create buffer");
+    batch::submit_and_wait(device.clone(), queue.clone(), |builder| builder
+        .copy_buffer(buffer, readback.clone()).unwrap());
+    assert!(*readback.read().unwrap() == data[..], "The buffer uploaded on queue family {} came back different",
+        transfer.family().id());
+
+    let pixels = pattern::uv(device.clone(), queue.clone(), 64, 48);
+    let image = upload_rgba8(device.clone(), queue.clone(), transfer.clone(), &pixels);
+    assert!(*readback::download_rgba8(device, queue, image) == *pixels,
+        "The texture uploaded on queue family {} came back different", transfer.family().id());
+}
@@ -0,0 +1,200 @@
+use std::iter;
+use std::mem;
+use std::sync::Arc;
+use vulkano::buffer::BufferAccess;
+use vulkano::command_buffer::submit::SubmitCommandBufferBuilder;
+use vulkano::command_buffer::sys::{Flags, Kind, UnsafeCommandBufferBuilder, UnsafeCommandBufferBuilderBufferImageCopy,
+    UnsafeCommandBufferBuilderColorImageClear, UnsafeCommandBufferBuilderImageAspect,
+    UnsafeCommandBufferBuilderPipelineBarrier};
+use vulkano::descriptor::descriptor::ShaderStages;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::descriptor::DescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::{Dimensions, ImageAccess, ImageLayout};
+use vulkano::sync::{AccessFlagBits, Fence, PipelineStages};
+
+use crate::mandelbrot::{self, View};
+use crate::memory;
+use crate::readback;
+use crate::storage;
+
+const SIZE: u32 = 256;
+const LOCAL_SIZE: u32 = 8;
+
+/* What the image is cleared to before the mandelbrot is drawn over it. */
+const CLEAR: [u8; 4] = [255, 0, 128, 255];
+
+/* One way of using an image: the layout it has to be in for it, and the pipeline stages and
+ * memory accesses that touch it. A transition goes from one use to the next, and needs both
+ * ends: the stages and accesses before it are what has to finish and be flushed, the ones after
+ * it are what has to wait and see the result. */
+#[derive(Clone, Copy)]
+pub struct Use {
+    pub layout: ImageLayout,
+    pub stages: PipelineStages,
+    pub access: AccessFlagBits,
+}
+
+impl Use {
+    /* A new image, or one whose contents we don't care about. Nothing came before, so nothing
+     * has to be waited for, and leaving Undefined lets the driver throw the contents away. An
+     * image can only ever be transitioned out of Undefined, never into it. */
+    pub fn undefined() -> Use {
+        Use {
+            layout: ImageLayout::Undefined,
+            stages: PipelineStages { top_of_pipe: true, .. PipelineStages::none() },
+            access: AccessFlagBits::none(),
+        }
+    }
+
+    /* Written by a transfer command, like a clear, a copy or a blit into the image. */
+    pub fn transfer_destination() -> Use {
+        Use {
+            layout: ImageLayout::TransferDstOptimal,
+            stages: PipelineStages { transfer: true, .. PipelineStages::none() },
+            access: AccessFlagBits { transfer_write: true, .. AccessFlagBits::none() },
+        }
+    }
+
+    /* Read by a transfer command, like a copy out to a buffer. */
+    pub fn transfer_source() -> Use {
+        Use {
+            layout: ImageLayout::TransferSrcOptimal,
+            stages: PipelineStages { transfer: true, .. PipelineStages::none() },
+            access: AccessFlagBits { transfer_read: true, .. AccessFlagBits::none() },
+        }
+    }
+
+    /* Read and written as a storage image by a compute shader. General is the only layout
+     * storage images can be used in; it allows anything, but is optimal for nothing. */
+    pub fn compute_storage() -> Use {
+        Use {
+            layout: ImageLayout::General,
+            stages: PipelineStages { compute_shader: true, .. PipelineStages::none() },
+            access: AccessFlagBits { shader_read: true, shader_write: true, .. AccessFlagBits::none() },
+        }
+    }
+}
+
+/* Records a barrier that moves `image` from one use to the next. This is what vulkano's
+ * automatic tracking inserts for us in an `AutoCommandBufferBuilder`. It's unsafe because
+ * nothing checks that `from` is how the image was really last used. */
+unsafe fn transition<P, I>(builder: &mut UnsafeCommandBufferBuilder<P>, image: &I, from: Use, to: Use)
+    where I: ?Sized + ImageAccess
+{
+    let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+    barrier.add_image_memory_barrier(image, 0..1, 0..1, from.stages, from.access, to.stages, to.access,
+        false, None, from.layout, to.layout);
+    builder.pipeline_barrier(&barrier);
+}
+
+/* Copies all of `image`, which must be in TransferSrcOptimal, into `buffer`. */
+unsafe fn copy_out<P, I, B>(builder: &mut UnsafeCommandBufferBuilder<P>, image: &I, buffer: &B)
+    where I: ?Sized + ImageAccess, B: ?Sized + BufferAccess
+{
+    builder.copy_image_to_buffer(image, ImageLayout::TransferSrcOptimal, buffer, iter::once(
+        UnsafeCommandBufferBuilderBufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_aspect: UnsafeCommandBufferBuilderImageAspect { color: true, depth: false, stencil: false },
+            image_mip_level: 0,
+            image_base_array_layer: 0,
+            image_layer_count: 1,
+            image_offset: [0, 0, 0],
+            image_extent: [SIZE, SIZE, 1],
+        }));
+}
+
+/* Walks one image through every layout its uses need, in a single command buffer recorded
+ * without any automatic tracking: cleared as a transfer destination, copied out as a transfer
+ * source, drawn into by the mandelbrot shader as a storage image, and copied out again. Returns
+ * what the two copies read. */
+fn walk(device: Arc<Device>, queue: Arc<Queue>) -> (Vec<u8>, Vec<u8>) {
+    let image = storage::image(&queue, Dimensions::Dim2d { width: SIZE, height: SIZE }, Format::R8G8B8A8Unorm);
+    let bytes = (SIZE * SIZE) as usize * readback::RGBA8_BYTES;
+    let buffer = || memory::readback::<u8>(&queue, bytes).expect("Failed to create buffer");
+    let (cleared, drawn) = (buffer(), buffer());
+
+    let pipeline = mandelbrot::pipeline_with(device.clone(), mandelbrot::Params::PushConstants);
+    let set = PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_image(image.clone()).unwrap()
+        .build().unwrap();
+
+    let pool = Device::standard_command_pool(&device, queue.family());
+    let command_buffer = unsafe {
+        let mut builder = UnsafeCommandBufferBuilder::new(&pool, Kind::primary(), Flags::OneTimeSubmit).unwrap();
+
+        /* Undefined -> TransferDstOptimal: the image is new, and clears want it laid out for
+         * transfer writes. */
+        transition(&mut builder, &image, Use::undefined(), Use::transfer_destination());
+        let color = CLEAR.iter().map(|&c| c as f32 / 255.0).collect::<Vec<_>>();
+        builder.clear_color_image(&image, ImageLayout::TransferDstOptimal,
+            ClearValue::Float([color[0], color[1], color[2], color[3]]),
+            iter::once(UnsafeCommandBufferBuilderColorImageClear {
+                base_mip_level: 0, level_count: 1, base_array_layer: 0, layer_count: 1,
+            }));
+
+        /* TransferDstOptimal -> TransferSrcOptimal: the clear has to land before the copy reads
+         * the image, laid out for transfer reads. */
+        transition(&mut builder, &image, Use::transfer_destination(), Use::transfer_source());
+        copy_out(&mut builder, &image, &cleared);
+
+        /* TransferSrcOptimal -> General: the shader overwrites what the copy reads, so the copy
+         * has to be done first. That's a write-after-read hazard, the one kind that needs no
+         * flushing, only waiting, but the layout change needs the barrier regardless. */
+        transition(&mut builder, &image, Use::transfer_source(), Use::compute_storage());
+        let push_constants = View::default().push_constants(SIZE, SIZE);
+        builder.bind_pipeline_compute(&pipeline);
+        builder.bind_descriptor_sets(false, &pipeline, 0, iter::once(set.inner()), iter::empty());
+        builder.push_constants(&pipeline, ShaderStages { compute: true, .. ShaderStages::none() }, 0,
+            mem::size_of::<mandelbrot::PushConstants>() as u32, &push_constants);
+        builder.dispatch([SIZE / LOCAL_SIZE, SIZE / LOCAL_SIZE, 1]);
+
+        /* General -> TransferSrcOptimal: the shader's writes have to be finished and flushed
+         * before the second copy reads them. */
+        transition(&mut builder, &image, Use::compute_storage(), Use::transfer_source());
+        copy_out(&mut builder, &image, &drawn);
+
+        /* Buffers have no layouts, but the CPU still has to see what the copies wrote. */
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        for buffer in &[&cleared, &drawn] {
+            barrier.add_buffer_memory_barrier(*buffer,
+                PipelineStages { transfer: true, .. PipelineStages::none() },
+                AccessFlagBits { transfer_write: true, .. AccessFlagBits::none() },
+                PipelineStages { host: true, .. PipelineStages::none() },
+                AccessFlagBits { host_read: true, .. AccessFlagBits::none() },
+                false, None, 0, bytes);
+        }
+        builder.pipeline_barrier(&barrier);
+
+        builder.build().unwrap()
+    };
+
+    let fence = Fence::alloc(device.clone()).unwrap();
+    unsafe {
+        let mut submit = SubmitCommandBufferBuilder::new();
+        submit.add_command_buffer(&command_buffer);
+        submit.set_fence_signal(&fence);
+        submit.submit(&queue).unwrap();
+    }
+    fence.wait(None).unwrap();
+
+    let cleared = cleared.read().unwrap().to_vec();
+    let drawn = drawn.read().unwrap().to_vec();
+    (cleared, drawn)
+}
+
+/* Checks the first copy saw the clear and the second saw the same mandelbrot as a render that
+ * leaves the layouts to vulkano. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let (cleared, drawn) = walk(device.clone(), queue.clone());
+
+    let wrong = cleared.chunks(readback::RGBA8_BYTES).filter(|pixel| *pixel != &CLEAR[..]).count();
+    assert!(wrong == 0, "{} pixels of the cleared image aren't the clear color", wrong);
+
+    let expected = mandelbrot::render(device.clone(), queue, mandelbrot::pipeline(device), SIZE, SIZE, View::default())
+        .unwrap();
+    assert!(drawn == expected.into_raw(), "The mandelbrot drawn after explicit transitions differs from the usual render");
+}
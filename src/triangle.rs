@@ -0,0 +1,398 @@
+use image::{ImageBuffer, Rgba};
+use std::sync::Arc;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder, CommandBuffer, DynamicState};
+use vulkano::descriptor::DescriptorSet;
+use vulkano::descriptor::descriptor_set::DescriptorSetsCollection;
+use vulkano::device::{Device, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass};
+use vulkano::image::{AttachmentImage, ImageUsage};
+use vulkano::pipeline::{viewport::Viewport, GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::sync::GpuFuture;
+
+use crate::debug_dump::Intermediate;
+use crate::descriptors::Descriptors;
+use crate::frame::{self, Frame, FrameSets};
+use crate::memory;
+use crate::readback;
+use crate::scene::{Scene, SceneOutput};
+use crate::spirv::{SpvShader, Stage};
+use crate::vertices;
+
+pub const SIZE: u32 = 512;
+
+/* What the target is cleared to before drawing. */
+pub const CLEAR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.0];
+
+pub struct Vertex { pub position: [f32; 2] }
+vulkano::impl_vertex!(Vertex, position);
+
+pub fn vertex_buffer(device: Arc<Device>) -> Arc<CpuAccessibleBuffer<[Vertex]>> {
+    let v1 = Vertex { position: [-0.5, -0.5] };
+    let v2 = Vertex { position: [0.0, 0.5]   };
+    let v3 = Vertex { position: [0.5, -0.25] };
+
+    vertices::make_vertex_buffer(device, vec![v1, v2, v3])
+}
+
+/* The color attachment formats `--format` can pick, all of which `readback::to_rgba8` can save. */
+pub const FORMATS: &[(&str, Format)] = &[
+    ("rgba8", Format::R8G8B8A8Unorm),
+    ("rgba16f", Format::R16G16B16A16Sfloat),
+    ("bgra8", Format::B8G8R8A8Unorm),
+];
+
+pub fn render_pass(device: Arc<Device>, format: Format) -> Arc<dyn RenderPassAbstract + Send + Sync> {
+    Arc::new(vulkano::single_pass_renderpass!(device,
+        attachments: {
+            color: {
+                load: Clear,
+                store: Store,
+                format: format,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {}
+        }
+    ).unwrap())
+}
+
+/* With multisampling the triangle is drawn into an image with `samples` samples per pixel, which
+ * is resolved, averaging the samples of each pixel, into a normal image at the end of the pass.
+ * Only the resolved image is ever read, so the multisampled one needn't be stored. */
+pub fn multisampled_render_pass(device: Arc<Device>, format: Format, samples: u32)
+    -> Arc<dyn RenderPassAbstract + Send + Sync>
+{
+    Arc::new(vulkano::single_pass_renderpass!(device,
+        attachments: {
+            multisampled: {
+                load: Clear,
+                store: DontCare,
+                format: format,
+                samples: samples,
+            },
+            color: {
+                load: DontCare,
+                store: Store,
+                format: format,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [multisampled],
+            depth_stencil: {},
+            resolve: [color],
+        }
+    ).unwrap())
+}
+
+/* The sample counts `--msaa` accepts. */
+pub const SAMPLE_COUNTS: &[u32] = &[1, 2, 4, 8];
+
+/* Picks the sample count to use from `requested` and the device's supported counts, a mask
+ * with bit n set if n samples are supported, like `framebuffer_color_sample_counts`. Devices
+ * needn't support every count, so when the requested one is missing the highest supported count
+ * below it is used instead, with a warning. */
+pub fn sample_count(supported: u32, requested: u32) -> Result<u32, String> {
+    if supported & requested != 0 {
+        return Ok(requested);
+    }
+    let fallback = SAMPLE_COUNTS.iter().rev().cloned().find(|&n| n < requested && supported & n != 0)
+        .ok_or_else(|| format!("The device supports no sample count up to {}", requested))?;
+    eprintln!("Warning: the device doesn't support {}x MSAA, using {}x instead.", requested, fallback);
+    Ok(fallback)
+}
+
+/* An offscreen image to draw into and a buffer to copy it back to the CPU through. */
+struct Target {
+    image: Arc<AttachmentImage<Format>>,
+    buf: Arc<CpuAccessibleBuffer<[u8]>>,
+    framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    format: Format,
+    samples: u32,
+}
+
+impl Target {
+    /* Not every device can render to and copy out of every format, and creating the image is
+     * where Vulkan tells us, so that's where an unsupported `--format` gets reported. */
+    fn new(queue: &Queue, render_pass: Arc<dyn RenderPassAbstract + Send + Sync>, format: Format, samples: u32)
+        -> Result<Target, String>
+    {
+        let device = queue.device().clone();
+        let usage = ImageUsage { color_attachment: true, transfer_source: true, .. ImageUsage::none() };
+        let image = memory::image(AttachmentImage::with_usage(device.clone(), [SIZE, SIZE], format, usage)
+            .map_err(|e| format!("Can't render to {:?} on this device: {}", format, e))?);
+
+        let bytes = (SIZE * SIZE) as usize * format.size().unwrap();
+        let buf = memory::readback::<u8>(queue, bytes)?;
+
+        let framebuffer: Arc<dyn FramebufferAbstract + Send + Sync> = if samples > 1 {
+            let multisampled = memory::image(AttachmentImage::transient_multisampled(device.clone(), [SIZE, SIZE],
+                samples, format)
+                .map_err(|e| format!("Can't render to {:?} with {}x MSAA on this device: {}", format, samples, e))?);
+            Arc::new(Framebuffer::start(render_pass)
+                .add(multisampled).unwrap()
+                .add(image.clone()).unwrap()
+                .build().unwrap())
+        } else {
+            Arc::new(Framebuffer::start(render_pass)
+                .add(image.clone()).unwrap()
+                .build().unwrap())
+        };
+
+        Ok(Target { image, buf, framebuffer, format, samples })
+    }
+
+    /* The render pass for drawing into a `format` target with `samples` samples per pixel. */
+    fn render_pass(device: Arc<Device>, format: Format, samples: u32) -> Arc<dyn RenderPassAbstract + Send + Sync> {
+        if samples > 1 {
+            multisampled_render_pass(device, format, samples)
+        } else {
+            render_pass(device, format)
+        }
+    }
+
+    /* What was drawn, once the commands copying it back have run. */
+    fn pixels(&self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let buffer_content = self.buf.read().unwrap();
+        readback::to_rgba8(self.format, SIZE, SIZE, SIZE as usize * self.format.size().unwrap(), &buffer_content)
+            .unwrap()
+    }
+
+    /* The resolved image isn't loaded, so it takes no clear value. */
+    fn clear_values(&self) -> Vec<ClearValue> {
+        if self.samples > 1 {
+            vec![CLEAR_COLOR.into(), ClearValue::None]
+        } else {
+            vec![CLEAR_COLOR.into()]
+        }
+    }
+}
+
+pub fn dynamic_state() -> DynamicState {
+    sized_dynamic_state(SIZE, SIZE)
+}
+
+/* A viewport covering a width x height target. */
+pub fn sized_dynamic_state(width: u32, height: u32) -> DynamicState {
+    DynamicState {
+        viewports: Some(vec![Viewport {
+            origin: [0.0,0.0],
+            dimensions: [width as f32, height as f32],
+            depth_range: 0.0 .. 1.0,
+        }]),
+        .. DynamicState::none()
+    }
+}
+
+/* Clears the target, draws the triangle with `pipeline` and reads the result back. */
+fn draw<S, Pc>(device: Arc<Device>, queue: Arc<Queue>, target: &Target,
+               pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+               vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>, sets: S, push_constants: Pc)
+               -> ImageBuffer<Rgba<u8>, Vec<u8>>
+    where S: DescriptorSetsCollection, Pc: Send + Sync + 'static
+{
+    let command_buffer = record(device, queue.clone(), target, pipeline, vertex_buffer, sets, push_constants);
+    command_buffer.execute(queue.clone()).unwrap()
+        .then_signal_fence_and_flush().unwrap()
+        .wait(None).unwrap();
+
+    target.pixels()
+}
+
+/* Records what `draw` submits. */
+fn record<S, Pc>(device: Arc<Device>, queue: Arc<Queue>, target: &Target,
+                 pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+                 vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>, sets: S, push_constants: Pc) -> AutoCommandBuffer
+    where S: DescriptorSetsCollection, Pc: Send + Sync + 'static
+{
+    let builder = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap();
+    record_into(builder, target, pipeline, vertex_buffer, sets, push_constants)
+        .build()
+        .unwrap()
+}
+
+/* Adds clearing the target, drawing the triangle and copying it back to `builder`. */
+fn record_into<S, Pc>(builder: AutoCommandBufferBuilder, target: &Target,
+                      pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+                      vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>, sets: S, push_constants: Pc)
+                      -> AutoCommandBufferBuilder
+    where S: DescriptorSetsCollection, Pc: Send + Sync + 'static
+{
+    builder
+        .begin_render_pass(target.framebuffer.clone(), false, target.clear_values())
+        .unwrap()
+        .draw(pipeline, &dynamic_state(), vec![vertex_buffer], sets, push_constants)
+        .unwrap()
+        .end_render_pass()
+        .unwrap()
+        .copy_image_to_buffer(target.image.clone(), target.buf.clone())
+        .unwrap()
+}
+
+/* Renders a triangle into a `format` target with `samples` samples per pixel! A fragment
+ * shader loaded with --shader-spv replaces frag.glsl. */
+pub fn render(device: Arc<Device>, queue: Arc<Queue>, spv_shader: Option<&SpvShader>, format: Format, samples: u32)
+    -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String>
+{
+    let mut descriptors = Descriptors::new(device.clone(), 1);
+    let (target, pipeline, sets) = setup(&queue, &mut descriptors, spv_shader, format, samples)?;
+    Ok(draw(device.clone(), queue, &target, pipeline, vertex_buffer(device), sets, ()))
+}
+
+/* Records what `render` would submit, without submitting it. */
+pub fn prepare(device: Arc<Device>, queue: Arc<Queue>, spv_shader: Option<&SpvShader>, format: Format, samples: u32)
+    -> Result<AutoCommandBuffer, String>
+{
+    let mut descriptors = Descriptors::new(device.clone(), 1);
+    let scene = scene(queue.clone(), &mut descriptors, spv_shader, format, samples)?;
+    let builder = AutoCommandBufferBuilder::primary_one_time_submit(device, queue.family()).unwrap();
+    Ok(scene.record(builder).build().unwrap())
+}
+
+/* What `render` draws, as a scene. */
+pub fn scene(queue: Arc<Queue>, descriptors: &mut Descriptors, spv_shader: Option<&SpvShader>, format: Format,
+             samples: u32) -> Result<TriangleScene, String>
+{
+    let (target, pipeline, sets) = setup(&queue, descriptors, spv_shader, format, samples)?;
+    Ok(TriangleScene { target, pipeline, vertex_buffer: vertex_buffer(queue.device().clone()), sets })
+}
+
+/* The triangle drawn once into an offscreen target and copied back. */
+pub struct TriangleScene {
+    target: Target,
+    pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    sets: Vec<Arc<dyn DescriptorSet + Send + Sync>>,
+}
+
+impl Scene for TriangleScene {
+    fn name(&self) -> &'static str {
+        "triangle"
+    }
+
+    fn record(&self, builder: AutoCommandBufferBuilder) -> AutoCommandBufferBuilder {
+        record_into(builder, &self.target, self.pipeline.clone(), self.vertex_buffer.clone(), self.sets.clone(), ())
+    }
+
+    fn output(&self) -> SceneOutput {
+        SceneOutput::Image(self.target.pixels())
+    }
+
+    fn work(&self) -> (u64, &'static str) {
+        (SIZE as u64 * SIZE as u64, "pixels")
+    }
+
+    /* The color attachment, in the target format rather than converted like the output. With
+     * MSAA that is the resolved image, the multisampled one is never stored. */
+    fn intermediates(&self) -> Vec<(&'static str, Intermediate)> {
+        vec![("target", Intermediate::Image(self.target.image.clone()))]
+    }
+}
+
+type Setup = (Target, Arc<dyn GraphicsPipelineAbstract + Send + Sync>, Vec<Arc<dyn DescriptorSet + Send + Sync>>);
+
+/* The target and pipeline `render` draws with, and the descriptor sets to bind: the frame
+ * uniform for a loaded shader, which might read it, and nothing for frag.glsl, which doesn't. */
+fn setup(queue: &Queue, descriptors: &mut Descriptors, spv_shader: Option<&SpvShader>, format: Format, samples: u32)
+    -> Result<Setup, String>
+{
+    let device = queue.device().clone();
+    let render_pass = Target::render_pass(device.clone(), format, samples);
+    let target = Target::new(queue, render_pass.clone(), format, samples)?;
+
+    let vs = vertex::Shader::load(device.clone()).expect("Failed to create vertex shader");
+
+    match spv_shader {
+        Some(spv) if spv.stage == Stage::Fragment => {
+            let pipeline = Arc::new(GraphicsPipeline::start()
+                .vertex_input_single_buffer::<Vertex>()
+                .vertex_shader(vs.main_entry_point(), ())
+                .viewports_dynamic_scissors_irrelevant(1)
+                .fragment_shader(spv.fragment_entry_point(), ())
+                .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+                .build(device.clone())
+                .unwrap());
+            let frame = descriptors.uniform(Frame::new(SIZE, SIZE, 0.0));
+            let set = descriptors.set(&pipeline, frame::SET, &[frame])?;
+            Ok((target, pipeline, vec![set]))
+        }
+        _ => {
+            let fs = frag::Shader::load(device.clone()).expect("Failed to create fragment shader");
+            let pipeline = Arc::new(GraphicsPipeline::start()
+                .vertex_input_single_buffer::<Vertex>()
+                .vertex_shader(vs.main_entry_point(), ())
+                .viewports_dynamic_scissors_irrelevant(1)
+                .fragment_shader(fs.main_entry_point(), ())
+                .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+                .build(device.clone())
+                .unwrap());
+            Ok((target, pipeline, Vec::new()))
+        }
+    }
+}
+
+/* Renders `frames` frames of the triangle cycling once around the color wheel, saving each as
+ * triangle_NNNN.png. The only thing that changes from frame to frame is the time in the frame
+ * uniform, which goes from 0 to 1 over the animation, so the uniform and its descriptor set come
+ * from a pool rather than being allocated anew each frame. With `verbose` the number of sets the
+ * pool allocated is printed next to the one per frame it saved. */
+pub fn animate(device: Arc<Device>, queue: Arc<Queue>, frames: u32, format: Format, samples: u32, verbose: bool)
+    -> Result<(), String>
+{
+    let render_pass = Target::render_pass(device.clone(), format, samples);
+    let target = Target::new(&queue, render_pass.clone(), format, samples)?;
+    let vertex_buffer = vertex_buffer(device.clone());
+
+    let vs = vertex::Shader::load(device.clone()).expect("Failed to create vertex shader");
+    let fs = hue::Shader::load(device.clone()).expect("Failed to create hue shader");
+
+    let pipeline = Arc::new(GraphicsPipeline::start()
+        .vertex_input_single_buffer::<Vertex>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap());
+
+    let mut sets = FrameSets::new(device.clone(), pipeline.clone());
+    for frame in 0..frames {
+        let set = sets.next(Frame::new(SIZE, SIZE, frame as f32 / frames as f32));
+        let image = draw(device.clone(), queue.clone(), &target, pipeline.clone(), vertex_buffer.clone(), set, ());
+
+        let path = format!("triangle_{:04}.png", frame);
+        image.save(&path).unwrap();
+        println!("Wrote {}", path);
+    }
+
+    if verbose {
+        println!("Descriptor sets allocated over {} frames: {} from the pool, {} without it",
+            frames, sets.allocated(), frames);
+    }
+    Ok(())
+}
+
+pub mod vertex {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/vertex.glsl"
+    }
+}
+
+pub mod frag {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/frag.glsl"
+    }
+}
+
+mod hue {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/hue.glsl"
+    }
+}
@@ -0,0 +1,18 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform writeonly image2D img;
+
+/* Red counts up along x and green along y, wrapping every 256 pixels. Every value is exactly
+ * k/255, which converts back to the byte k on any device, so the output is the same everywhere
+ * down to the last bit. */
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coords, imageSize(img)))) {
+        return;
+    }
+
+    vec2 uv = vec2(coords % 256) / 255.0;
+    imageStore(img, coords, vec4(uv, 0.0, 1.0));
+}
@@ -0,0 +1,15 @@
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::device::Device;
+
+use crate::memory;
+
+/* Puts `vertices` in a buffer that can only be used for vertex input, which is all the drawing
+ * demos need. Any iterator will do, not just ones that know their length up front. */
+pub fn make_vertex_buffer<V, I>(device: Arc<Device>, vertices: I) -> Arc<CpuAccessibleBuffer<[V]>>
+    where V: Send + Sync + 'static, I: IntoIterator<Item = V>
+{
+    let vertices: Vec<V> = vertices.into_iter().collect();
+    memory::buffer(CpuAccessibleBuffer::from_iter(device, BufferUsage::vertex_buffer(), vertices.into_iter())
+        .expect("Failed to create vertex buffer"))
+}
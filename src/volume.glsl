@@ -0,0 +1,22 @@
+#version 450
+
+layout(local_size_x = 4, local_size_y = 4, local_size_z = 4) in;
+
+layout(set = 0, binding = 0, r32f) uniform writeonly image3D volume;
+
+/* Must match RADIUS in volume.rs: the sphere's radius, with the volume two units across. */
+const float RADIUS = 0.6;
+
+/* Fills a 3D image with the signed distance from each voxel's center to a sphere in the middle
+ * of it: negative inside, zero on the surface and positive outside. The dispatch is three
+ * dimensional too, so each invocation's ID is the voxel it writes, x, y and z. */
+void main() {
+    ivec3 coords = ivec3(gl_GlobalInvocationID);
+    ivec3 size = imageSize(volume);
+    if (any(greaterThanEqual(coords, size))) {
+        return;
+    }
+
+    vec3 p = (vec3(coords) + vec3(0.5)) / vec3(size) * 2.0 - 1.0;
+    imageStore(volume, coords, vec4(length(p) - RADIUS));
+}
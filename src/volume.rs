@@ -0,0 +1,109 @@
+use image::{ImageBuffer, Luma};
+use std::sync::Arc;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, ImageAccess, StorageImage};
+use vulkano::pipeline::ComputePipeline;
+
+use crate::batch;
+use crate::compute::ComputeTask;
+use crate::memory;
+use crate::storage;
+
+/* Must match the local size in volume.glsl. */
+const LOCAL_SIZE: u32 = 4;
+
+/* The volume is SIDE x SIDE x SIDE voxels. */
+pub const SIDE: u32 = 64;
+
+/* Must match RADIUS in volume.glsl. */
+const RADIUS: f32 = 0.6;
+
+/* What `--slice` defaults to: the one through the middle, where the sphere is widest. */
+pub const DEFAULT_SLICE: u32 = SIDE / 2;
+
+/* A side x side x side R32Sfloat 3D image of the signed distance to a sphere. A 3D image is not
+ * an array of 2D ones: it is one image with a depth, which samplers filter across in z just as
+ * they do in x and y, and which a shader addresses with an ivec3 into an image3D. */
+pub fn volume(device: Arc<Device>, queue: Arc<Queue>, side: u32) -> Arc<StorageImage<Format>> {
+    let shader = cs::Shader::load(device.clone()).expect("Could not load volume shader");
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &()).unwrap());
+
+    let dimensions = Dimensions::Dim3d { width: side, height: side, depth: side };
+    let image = storage::image(&queue, dimensions, Format::R32Sfloat);
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_image(image.clone()).unwrap()
+        .build().unwrap());
+
+    let groups = side.div_ceil(LOCAL_SIZE);
+    ComputeTask::new("volume", pipeline, set, [groups, groups, groups], ()).submit(queue);
+    image
+}
+
+/* Copies the 2D slice at depth `z` out of a 3D R32Sfloat image, row by row. The copy takes a
+ * 3D offset and extent, so the slice is a box one voxel deep starting at z. The layer range is
+ * still there, for array images, and a 3D image only has the one. */
+pub fn download_slice(device: Arc<Device>, queue: Arc<Queue>, image: Arc<StorageImage<Format>>, z: u32) -> Vec<f32> {
+    let [width, height] = image.dimensions().width_height();
+    let buf = memory::readback::<f32>(&queue, (width * height) as usize).expect("Failed to create buffer");
+
+    batch::submit_and_wait(device, queue, |builder| builder
+        .copy_image_to_buffer_dimensions(image, buf.clone(), [0, 0, z], [width, height, 1], 0, 1, 0).unwrap());
+
+    let buffer_content = buf.read().unwrap();
+    buffer_content.to_vec()
+}
+
+/* The distance volume.glsl puts in voxel (x, y, z). */
+fn distance(x: u32, y: u32, z: u32, side: u32) -> f32 {
+    let coord = |i: u32| (i as f32 + 0.5) / side as f32 * 2.0 - 1.0;
+    let (px, py, pz) = (coord(x), coord(y), coord(z));
+    (px * px + py * py + pz * pz).sqrt() - RADIUS
+}
+
+/* Fills the volume and saves the slice at depth `z` as grey: white deep inside the sphere,
+ * mid grey on its surface and darker the further out. */
+pub fn slice(device: Arc<Device>, queue: Arc<Queue>, z: u32) -> Result<ImageBuffer<Luma<u8>, Vec<u8>>, String> {
+    if z >= SIDE {
+        return Err(format!("Slice {} is out of range, the volume only has slices 0 to {}", z, SIDE - 1));
+    }
+
+    let image = volume(device.clone(), queue.clone(), SIDE);
+    let distances = download_slice(device, queue, image, z);
+    let pixels = distances.iter().map(|&d| ((0.5 - d).clamp(0.0, 1.0) * 255.0).round() as u8).collect();
+    Ok(ImageBuffer::from_raw(SIDE, SIDE, pixels).unwrap())
+}
+
+/* Checks a few slices against the CPU's distances, and that each cuts the sphere in a circle of
+ * the radius it should: the slice at height h through a sphere of radius r is a disc of radius
+ * sqrt(r^2 - h^2), so the number of voxels inside it is about pi times that squared. A slice
+ * past the end has to be refused. */
+pub fn verify(device: Arc<Device>, queue: Arc<Queue>) {
+    let side = 32;
+    let image = volume(device.clone(), queue.clone(), side);
+    for &z in &[0, side / 4, side / 2, side - 1] {
+        let distances = download_slice(device.clone(), queue.clone(), image.clone(), z);
+        for (index, &d) in distances.iter().enumerate() {
+            let (x, y) = (index as u32 % side, index as u32 / side);
+            let expected = distance(x, y, z, side);
+            assert!((d - expected).abs() < 1e-4, "Voxel ({}, {}, {}) is {} on the GPU but {} on the CPU",
+                x, y, z, d, expected);
+        }
+
+        let h = (z as f32 + 0.5) / side as f32 * 2.0 - 1.0;
+        let disc = (RADIUS * RADIUS - h * h).max(0.0) * std::f32::consts::PI * (side as f32 / 2.0).powi(2);
+        let inside = distances.iter().filter(|&&d| d < 0.0).count() as f32;
+        assert!((inside - disc).abs() <= disc * 0.1 + side as f32,
+            "Slice {} has {} voxels inside the sphere, expected about {}", z, inside, disc);
+    }
+
+    assert!(slice(device, queue, SIDE).is_err(), "Slice {} wasn't refused", SIDE);
+}
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/volume.glsl"
+    }
+}
@@ -0,0 +1,168 @@
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+use shaderc::ShaderKind;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::pipeline::ComputePipeline;
+
+use crate::mandelbrot;
+use crate::shaders;
+use crate::spirv::{SpvShader, Stage};
+use crate::triangle;
+
+/* Watches the GLSL shaders at `paths` and, every time one is saved, compiles it again and
+ * renders it to <name>.png: a fragment shader as the triangle's, a compute shader in place of the
+ * mandelbrot's at width x height and `view`, with the interfaces --shader-spv expects. Shaders
+ * are taken to be fragment shaders unless they say otherwise with `#pragma
+ * shader_stage(compute)`, or are one of the built-in shaders in src, which are compiled as the
+ * stage they are built as: watching src/mandelbrot.glsl shows an edit to it without rebuilding
+ * the sandbox. A shader that fails to compile or render is reported and the last good image is
+ * left alone, so a typo doesn't lose anything. Every shader is rendered once to start with, and
+ * after that only when `Changes` says it was saved; nothing is done in between. Runs until
+ * interrupted. */
+pub fn watch(device: Arc<Device>, queue: Arc<Queue>, paths: &[PathBuf], format: Format, width: u32, height: u32,
+             view: mandelbrot::View) -> !
+{
+    let changes = Changes::new(paths).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let render = |path: &Path| {
+        let (name, output) = (path.display().to_string(), path.with_extension("png"));
+        match reload(device.clone(), queue.clone(), path, &name, format, (width, height), view) {
+            Ok(image) => match image.save(&output) {
+                Ok(()) => println!("Rendered {} to {}", name, output.display()),
+                Err(e) => eprintln!("Could not save {}: {}", output.display(), e),
+            },
+            Err(e) => eprintln!("{}\nKeeping the last good render.", e),
+        }
+    };
+
+    for path in paths {
+        println!("Watching {}, rendering to {}", path.display(), path.with_extension("png").display());
+        render(path);
+    }
+    loop {
+        for path in changes.wait() {
+            render(&path);
+        }
+    }
+}
+
+/* Compiles the shader at `path` and renders it once. */
+fn reload(device: Arc<Device>, queue: Arc<Queue>, path: &Path, name: &str, format: Format, (width, height): (u32, u32),
+          view: mandelbrot::View) -> Result<image::ImageBuffer<image::Rgba<u8>, Vec<u8>>, String>
+{
+    let shader = compile(device.clone(), path, name)?;
+    match shader.stage {
+        Stage::Fragment => triangle::render(device, queue, Some(&shader), format, 1),
+        Stage::Compute => {
+            let pipeline = ComputePipeline::new(device.clone(), &shader.compute_entry_point(), &())
+                .map_err(|e| format!("Could not create a pipeline for {}: {}", name, e))?;
+            mandelbrot::render(device, queue, Arc::new(pipeline), width, height, view)
+        }
+    }
+}
+
+/* Compiles the GLSL shader at `path` with shaderc as the stage `watch` takes it to be, into a
+ * module on `device`. `name` is only used in errors. */
+pub fn compile(device: Arc<Device>, path: &Path, name: &str) -> Result<SpvShader, String> {
+    let kind = match shaders::embedded(path).map(|shader| shader.kind) {
+        Some(kind @ ShaderKind::Fragment) | Some(kind @ ShaderKind::Compute) => kind,
+        Some(_) => return Err(format!("{} is a built-in vertex shader, only fragment and compute shaders can be \
+            watched", name)),
+        None => ShaderKind::DefaultFragment,
+    };
+    let source = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", name, e))?;
+    let words = shaders::compile(name, kind, &source)?;
+    SpvShader::from_words(device, &words, name)
+}
+
+/* How long a file has to go without changing before it counts as saved. An editor can write a
+ * file in several goes, and this way it is compiled once, when it is done. */
+const SETTLE: Duration = Duration::from_millis(100);
+
+/* Tells which of some files have been saved since it was last asked, from the file system's own
+ * notifications rather than by looking at the files. What is watched is the directories the
+ * files are in: many editors save by writing a new file and renaming it over the old one, and a
+ * watch on the old file would go with it. That also means a file can be watched before it is
+ * there. The notifications name files by their absolute paths, so each file is kept as that and
+ * as it was given, which is what it is handed back as. */
+pub struct Changes {
+    /* Notifications stop once the watcher is dropped. */
+    _watcher: RecommendedWatcher,
+    events: Receiver<DebouncedEvent>,
+    paths: Vec<(PathBuf, PathBuf)>,
+}
+
+/* `path` made absolute, the way the notifications name it. Only its directory has to exist. */
+fn absolute(path: &Path) -> Result<PathBuf, String> {
+    let directory = match path.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => directory,
+        _ => Path::new("."),
+    };
+    let name = path.file_name().ok_or_else(|| format!("{} is not a file to watch", path.display()))?;
+    fs::canonicalize(directory).map(|directory| directory.join(name))
+        .map_err(|e| format!("Could not watch {}: {}", path.display(), e))
+}
+
+impl Changes {
+    pub fn new(paths: &[PathBuf]) -> Result<Changes, String> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher: RecommendedWatcher = Watcher::new(sender, SETTLE)
+            .map_err(|e| format!("Could not watch for changes to the shaders: {}", e))?;
+        let paths = paths.iter()
+            .map(|path| Ok((absolute(path)?, path.clone())))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut directories: Vec<&Path> = paths.iter().filter_map(|(path, _)| path.parent()).collect();
+        directories.sort();
+        directories.dedup();
+        for directory in directories {
+            watcher.watch(directory, RecursiveMode::NonRecursive)
+                .map_err(|e| format!("Could not watch {}: {}", directory.display(), e))?;
+        }
+        Ok(Changes { _watcher: watcher, events, paths })
+    }
+
+    /* The files saved since the last call, each once however many times it was. */
+    pub fn saved(&self) -> Vec<PathBuf> {
+        self.files(self.events.try_iter())
+    }
+
+    /* Waits until one of the files is saved, and says which, along with any others saved by
+     * then. */
+    pub fn wait(&self) -> Vec<PathBuf> {
+        loop {
+            let first = self.events.recv().expect("The file watcher stopped");
+            let saved = self.files(iter::once(first).chain(self.events.try_iter()));
+            if !saved.is_empty() {
+                return saved;
+            }
+        }
+    }
+
+    /* The watched files `events` saved, as they were given. Everything else in the directories,
+     * and whatever else happened to the files, is left out. */
+    fn files<I: Iterator<Item = DebouncedEvent>>(&self, events: I) -> Vec<PathBuf> {
+        let mut saved = Vec::new();
+        for event in events {
+            let path = match event {
+                DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Rename(_, path) => path,
+                _ => continue,
+            };
+            for (absolute, given) in &self.paths {
+                if *absolute == path && !saved.contains(given) {
+                    saved.push(given.clone());
+                }
+            }
+        }
+        saved
+    }
+}
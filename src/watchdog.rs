@@ -0,0 +1,48 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use vulkano::sync::{FlushError, GpuFuture};
+
+/* How long each demo's GPU work may take before we warn about it. Set once from the command
+ * line; a demo without its own deadline gets `default`, and no deadline means wait silently. */
+#[derive(Debug, Default, Clone)]
+pub struct Deadlines {
+    pub default: Option<Duration>,
+    pub per_demo: Vec<(String, Duration)>,
+}
+
+impl Deadlines {
+    fn get(&self, demo: &str) -> Option<Duration> {
+        self.per_demo.iter().find(|(name, _)| name == demo).map(|&(_, deadline)| deadline).or(self.default)
+    }
+}
+
+static DEADLINES: OnceLock<Deadlines> = OnceLock::new();
+
+pub fn set_deadlines(deadlines: Deadlines) {
+    DEADLINES.set(deadlines).expect("Deadlines were already set");
+}
+
+/* Flushes `future` and waits for it like `then_signal_fence_and_flush().wait(None)`, but
+ * reports when `demo` runs past its deadline instead of hanging without a word. A shader stuck
+ * in a very long loop can't be stopped from here: the GPU keeps using the resources we gave it
+ * until it is done with them, so freeing them early is not an option, and dropping the future
+ * just waits anyway. What we can do is say what is taking so long, and for how long. */
+pub fn wait<F: GpuFuture>(future: F, demo: &str) {
+    let fence = future.then_signal_fence_and_flush().unwrap();
+
+    let deadline = match DEADLINES.get().and_then(|deadlines| deadlines.get(demo)) {
+        Some(deadline) => deadline,
+        None => return fence.wait(None).unwrap(),
+    };
+
+    let start = Instant::now();
+    match fence.wait(Some(deadline)) {
+        Ok(()) => return,
+        Err(FlushError::Timeout) => eprintln!("Warning: {} is past its deadline of {:.1} s and still running",
+            demo, deadline.as_secs_f64()),
+        Err(e) => panic!("Waiting for {} failed: {}", demo, e),
+    }
+
+    fence.wait(None).unwrap();
+    eprintln!("Warning: {} took {:.1} s", demo, start.elapsed().as_secs_f64());
+}
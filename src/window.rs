@@ -0,0 +1,740 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::DescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass};
+use vulkano::image::{Dimensions, ImageUsage, StorageImage, SwapchainImage};
+use vulkano::instance::{Instance, InstanceExtensions, PhysicalDevice};
+use vulkano::pipeline::{ComputePipeline, ComputePipelineAbstract, GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::sampler::Filter;
+use vulkano::swapchain::{self, AcquireError, CompositeAlpha, PresentMode, Surface, Swapchain, SwapchainCreationError};
+use vulkano::sync::{self, FlushError, GpuFuture};
+use vulkano_win::VkSurfaceBuild;
+use winit::dpi::{LogicalPosition, LogicalSize};
+use winit::{ElementState, Event, EventsLoop, KeyboardInput, MonitorId, MouseButton, MouseScrollDelta, VirtualKeyCode,
+    WindowBuilder, WindowEvent};
+
+use crate::compute::{self, ComputeTask};
+use crate::error::SandboxError;
+use crate::explore::{self, Explorer, FrameLoop};
+use crate::frame::{Frame, FrameSets};
+use crate::mandelbrot::{self, Params, View};
+use crate::memory;
+use crate::mesh::{self, Mesh, MeshRenderer};
+use crate::overlay::{Input, Overlay, Ui};
+use crate::postfx::{self, Chain};
+use crate::spirv::{SpvShader, Stage};
+use crate::storage;
+use crate::triangle::{self, Vertex, SIZE};
+use crate::watch::{self, Changes};
+
+/* One of the images the window presents, which a frame is drawn into. */
+pub type Image = Arc<SwapchainImage<winit::Window>>;
+
+/* The scenes `--window` can show. */
+pub const SCENES: [&str; 4] = ["triangle", "mandelbrot", "cube", "explore"];
+
+/* Checks `name` is one `--window` knows. */
+pub fn parse(name: &str) -> Result<&'static str, String> {
+    SCENES.iter().find(|&&s| s == name).cloned()
+        .ok_or_else(|| format!("Unknown window scene `{}`, expected one of {}", name, SCENES.join(", ")))
+}
+
+/* Whether the window scene `name` draws and whether it dispatches compute shaders, the same way
+ * `scene::needs` says it for the offscreen ones. Every one of them needs a graphics queue, since
+ * even copying a compute shader's image onto the window's is a blit, which only those can do. */
+pub fn needs(name: &str) -> (bool, bool) {
+    (true, name == "mandelbrot" || name == "explore")
+}
+
+/* The instance extensions presenting to a window takes on this platform. */
+pub fn extensions() -> InstanceExtensions {
+    vulkano_win::required_extensions()
+}
+
+/* Which monitor `--fullscreen` covers: the primary one, or the one `--monitor` numbers, in the
+ * order the window system lists them. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Monitor {
+    Primary,
+    Index(usize),
+}
+
+/* The name and size of `monitor`, for saying which one a window went fullscreen on. */
+fn describe(monitor: &MonitorId) -> String {
+    let (width, height): (u32, u32) = monitor.get_dimensions().into();
+    format!("{} ({}x{})", monitor.get_name().unwrap_or_else(|| "an unnamed monitor".to_string()), width, height)
+}
+
+/* Finds `monitor` among the ones `events_loop` knows about, listing them if it isn't there. */
+fn find(events_loop: &EventsLoop, monitor: Monitor) -> Result<MonitorId, SandboxError> {
+    match monitor {
+        Monitor::Primary => Ok(events_loop.get_primary_monitor()),
+        Monitor::Index(index) => {
+            let monitors: Vec<_> = events_loop.get_available_monitors().collect();
+            monitors.get(index).cloned().ok_or_else(|| {
+                let listed: Vec<_> = monitors.iter().enumerate()
+                    .map(|(i, monitor)| format!("{}: {}", i, describe(monitor)))
+                    .collect();
+                SandboxError::Window(format!("There is no monitor {}, the monitors are {}", index, listed.join(", ")))
+            })
+        }
+    }
+}
+
+/* A window, and the events loop handing over what happens to it. It is opened before the device
+ * is, since whether a queue family can present to it depends on its surface. */
+pub struct Window {
+    events_loop: EventsLoop,
+    surface: Arc<Surface<winit::Window>>,
+    fullscreen: bool,
+}
+
+impl Window {
+    /* Opens a width x height window on an instance created with `extensions`, or with
+     * `fullscreen` one covering the whole of that monitor without a border. The monitor keeps the
+     * video mode it is in, so the window is as big as the monitor already is; winit doesn't change
+     * modes, and going back to the desktop doesn't have to change them back. */
+    pub fn open(instance: Arc<Instance>, title: &str, width: u32, height: u32, fullscreen: Option<Monitor>)
+        -> Result<Window, SandboxError>
+    {
+        let events_loop = EventsLoop::new();
+        let monitor = match fullscreen {
+            Some(monitor) => Some(find(&events_loop, monitor)?),
+            None => None,
+        };
+        if let Some(ref monitor) = monitor {
+            println!("Fullscreen on {}, Escape goes back to a window", describe(monitor));
+        }
+        let surface = WindowBuilder::new()
+            .with_title(title)
+            .with_dimensions(LogicalSize::new(f64::from(width), f64::from(height)))
+            .with_fullscreen(monitor)
+            .build_vk_surface(&events_loop, instance)
+            .map_err(|e| SandboxError::Window(format!("Could not open a window: {}", e)))?;
+        Ok(Window { events_loop, surface, fullscreen: fullscreen.is_some() })
+    }
+
+    /* How big the window's drawable area is in pixels, or nothing once it has gone. */
+    fn dimensions(&self) -> Option<[u32; 2]> {
+        let window = self.surface.window();
+        window.get_inner_size().map(|size| {
+            let (width, height): (u32, u32) = size.to_physical(window.get_hidpi_factor()).into();
+            [width, height]
+        })
+    }
+
+    /* Whether the surface has become a different size from `swapchain`, which then presents
+     * stretched or cropped, or not at all. Vulkan says so as the image is acquired, as
+     * VK_SUBOPTIMAL_KHR, but vulkano 0.11 doesn't pass that on, so the sizes are compared instead. */
+    fn suboptimal(&self, swapchain: &Swapchain<winit::Window>, physical: PhysicalDevice) -> bool {
+        let extent = self.surface.capabilities(physical).ok().and_then(|capabilities| capabilities.current_extent)
+            .or_else(|| self.dimensions());
+        extent.is_some_and(|extent| extent != swapchain.dimensions())
+    }
+
+    /* A swapchain for the window, made the way its surface says it can be on `device`: with
+     * `images` images or as close as it allows, in the first format it lists, at the size it is,
+     * and able to be drawn into and blitted onto. Fifo waits for the display rather than tearing,
+     * and is the one present mode every surface has. */
+    fn swapchain(&self, device: Arc<Device>, queue: &Arc<Queue>, images: Option<u32>)
+        -> Result<(Arc<Swapchain<winit::Window>>, Vec<Image>), SandboxError>
+    {
+        let capabilities = self.surface.capabilities(device.physical_device())
+            .map_err(|e| SandboxError::Window(format!("Could not ask the window's surface what it supports: {}", e)))?;
+        let dimensions = capabilities.current_extent.or_else(|| self.dimensions())
+            .ok_or_else(|| SandboxError::Window("The window closed before it could be drawn to".to_string()))?;
+        let (format, _) = *capabilities.supported_formats.first()
+            .ok_or_else(|| SandboxError::Window("The window's surface supports no formats".to_string()))?;
+        let alpha = if capabilities.supported_composite_alpha.opaque {
+            CompositeAlpha::Opaque
+        } else {
+            capabilities.supported_composite_alpha.iter().next().expect("The surface supports no composite alpha")
+        };
+        let usage = ImageUsage { color_attachment: true, transfer_destination: true, .. ImageUsage::none() };
+        let (min, max) = (capabilities.min_image_count, capabilities.max_image_count);
+        let count = image_count(images, min, max);
+        if images.is_some_and(|images| images != count) {
+            eprintln!("Warning: the window takes {} to {} swapchain images, using {}.", min,
+                max.map_or("any number of".to_string(), |max| max.to_string()), count);
+        }
+
+        Swapchain::new(device, self.surface.clone(), count, format, dimensions, 1, usage, queue,
+            capabilities.current_transform, alpha, PresentMode::Fifo, true, None)
+            .map_err(|e| SandboxError::Window(format!("Could not create the swapchain: {}", e)))
+    }
+}
+
+/* How many swapchain images to ask for: `requested`, or one more than the fewest the surface takes
+ * so that one can be drawn into while another waits to be shown, and either way no fewer and no
+ * more than it allows. A surface without a `max` takes as many as there is memory for. */
+pub fn image_count(requested: Option<u32>, min: u32, max: Option<u32>) -> u32 {
+    let count = requested.unwrap_or(min + 1).max(min);
+    max.map_or(count, |max| count.min(max))
+}
+
+/* What a window scene draws every frame, onto one of the swapchain's images, which it is handed
+ * again every time the swapchain is made anew. */
+pub trait Layer {
+    /* Makes whatever depends on the images, like framebuffers or an image the size of the window. */
+    fn resize(&mut self, images: &[Image]);
+
+    /* Adds drawing the frame onto image `index` to `builder`. `frame` is the same one the overlay
+     * is drawn with: the image's size, and the seconds since the window opened. */
+    fn draw(&mut self, builder: AutoCommandBufferBuilder, index: usize, frame: Frame) -> AutoCommandBufferBuilder;
+
+    /* Lays out the scene's own controls on the overlay, which change it before the frame is drawn. */
+    fn ui(&mut self, _ui: &mut Ui) {}
+
+    /* Hands over something that happened to the window, with `hidpi` to turn its positions into
+     * pixels. With `captured` the mouse is the overlay's just now, as far as starting anything. */
+    fn event(&mut self, _event: &WindowEvent, _hidpi: f64, _captured: bool) {}
+
+    /* Swaps `shader` in for the scene's own, as a pipeline of its own. On an error
+     * nothing changes, and the scene goes on drawing with the pipeline it had. */
+    fn reload(&mut self, _shader: &SpvShader) -> Result<(), String> {
+        Err("The scene has no shader to swap".to_string())
+    }
+}
+
+/* The triangle, drawn with triangle.rs's render pass and shaders straight into the window. A
+ * fragment shader swapped in for frag.glsl is handed the frame uniform, the way --shader-spv's
+ * is, so it can animate by the time. */
+struct Triangle {
+    device: Arc<Device>,
+    render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
+    pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+    frames: Option<FrameSets<Arc<dyn GraphicsPipelineAbstract + Send + Sync>>>,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    framebuffers: Vec<Arc<dyn FramebufferAbstract + Send + Sync>>,
+    dynamic_state: DynamicState,
+}
+
+impl Triangle {
+    fn new(device: Arc<Device>, format: Format, images: &[Image]) -> Triangle {
+        let render_pass = triangle::render_pass(device.clone(), format);
+        let vs = triangle::vertex::Shader::load(device.clone()).expect("Failed to create vertex shader");
+        let fs = triangle::frag::Shader::load(device.clone()).expect("Failed to create fragment shader");
+        let pipeline = Arc::new(GraphicsPipeline::start()
+            .vertex_input_single_buffer::<Vertex>()
+            .vertex_shader(vs.main_entry_point(), ())
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs.main_entry_point(), ())
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone())
+            .unwrap());
+
+        let mut layer = Triangle {
+            device: device.clone(),
+            render_pass,
+            pipeline,
+            frames: None,
+            vertex_buffer: triangle::vertex_buffer(device),
+            framebuffers: Vec::new(),
+            dynamic_state: DynamicState::none(),
+        };
+        layer.resize(images);
+        layer
+    }
+}
+
+impl Layer for Triangle {
+    fn resize(&mut self, images: &[Image]) {
+        let [width, height] = images[0].dimensions();
+        self.dynamic_state = triangle::sized_dynamic_state(width, height);
+        self.framebuffers = images.iter().map(|image| {
+            Arc::new(Framebuffer::start(self.render_pass.clone()).add(image.clone()).unwrap().build().unwrap())
+                as Arc<dyn FramebufferAbstract + Send + Sync>
+        }).collect();
+    }
+
+    fn draw(&mut self, builder: AutoCommandBufferBuilder, index: usize, frame: Frame) -> AutoCommandBufferBuilder {
+        let builder = builder
+            .begin_render_pass(self.framebuffers[index].clone(), false, vec![triangle::CLEAR_COLOR.into()]).unwrap();
+        let vertices = self.vertex_buffer.clone();
+        let builder = match self.frames {
+            Some(ref mut frames) => builder.draw(self.pipeline.clone(), &self.dynamic_state, vec![vertices],
+                frames.next(frame), ()),
+            None => builder.draw(self.pipeline.clone(), &self.dynamic_state, vec![vertices], (), ()),
+        };
+        builder.unwrap().end_render_pass().unwrap()
+    }
+
+    fn reload(&mut self, shader: &SpvShader) -> Result<(), String> {
+        if shader.stage != Stage::Fragment {
+            return Err("The triangle takes a fragment shader".to_string());
+        }
+        let vs = triangle::vertex::Shader::load(self.device.clone()).expect("Failed to create vertex shader");
+        let pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync> = Arc::new(GraphicsPipeline::start()
+            .vertex_input_single_buffer::<Vertex>()
+            .vertex_shader(vs.main_entry_point(), ())
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(shader.fragment_entry_point(), ())
+            .render_pass(Subpass::from(self.render_pass.clone(), 0).unwrap())
+            .build(self.device.clone())
+            .map_err(|e| format!("Could not create a pipeline for the shader: {}", e))?);
+        self.frames = Some(FrameSets::new(self.device.clone(), pipeline.clone()));
+        self.pipeline = pipeline;
+        Ok(())
+    }
+}
+
+/* The mandelbrot at `view`, dispatched into a storage image the size of the window every frame
+ * and blitted onto the window's image, which converts it to whatever format that is. With any of
+ * the filters on, it goes through them on the way: blitted into the chain's float image, run
+ * through them there, and blitted from whichever image they finished in. */
+struct Mandelbrot {
+    queue: Arc<Queue>,
+    pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>,
+    view: View,
+    filters: Vec<(postfx::Filter, bool)>,
+    images: Vec<Image>,
+    image: Arc<StorageImage<Format>>,
+    set: Arc<dyn DescriptorSet + Send + Sync>,
+    chain: Chain,
+}
+
+impl Mandelbrot {
+    /* The view is made a center and a zoom, whatever it started as, since that is what the
+     * overlay's slider moves. */
+    fn new(queue: Arc<Queue>, view: View, images: &[Image]) -> Mandelbrot {
+        let pipeline = mandelbrot::pipeline_with(queue.device().clone(), Params::PushConstants);
+        let (image, set, chain) = Mandelbrot::target(&queue, pipeline.clone(), images);
+        let view = View { center: view.center(), zoom: view.magnification(), region: None, .. view };
+        let filters = postfx::FILTERS.iter().map(|name| (postfx::Filter::parse(name).unwrap(), false)).collect();
+        Mandelbrot { queue, pipeline, view, filters, images: images.to_vec(), image, set, chain }
+    }
+
+    /* The storage image the shader writes, as big as `images`, the set binding it, and a filter
+     * chain the same size. */
+    fn target(queue: &Queue, pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync>, images: &[Image])
+        -> (Arc<StorageImage<Format>>, Arc<dyn DescriptorSet + Send + Sync>, Chain)
+    {
+        let [width, height] = images[0].dimensions();
+        let image = storage::image(queue, Dimensions::Dim2d { width, height }, Format::R8G8B8A8Unorm);
+        let set = mandelbrot::image_set(pipeline, image.clone()).expect("The mandelbrot shader binds one image");
+        (image, set, Chain::new(queue, width, height))
+    }
+}
+
+impl Layer for Mandelbrot {
+    fn resize(&mut self, images: &[Image]) {
+        let (image, set, chain) = Mandelbrot::target(&self.queue, self.pipeline.clone(), images);
+        self.images = images.to_vec();
+        self.image = image;
+        self.set = set;
+        self.chain = chain;
+    }
+
+    fn draw(&mut self, builder: AutoCommandBufferBuilder, index: usize, _frame: Frame) -> AutoCommandBufferBuilder {
+        let [width, height] = self.images[index].dimensions();
+        let corner = [width as i32, height as i32, 1];
+        let builder = ComputeTask::new("mandelbrot", self.pipeline.clone(), self.set.clone(),
+            compute::group_count(width, height), self.view.push_constants(width, height)).record(builder);
+
+        let filters: Vec<_> = self.filters.iter().filter(|&&(_, on)| on).map(|&(filter, _)| filter).collect();
+        let (builder, shown) = if filters.is_empty() {
+            (builder, self.image.clone())
+        } else {
+            let builder = builder
+                .blit_image(self.image.clone(), [0, 0, 0], corner, 0, 0, self.chain.input(), [0, 0, 0], corner, 0, 0,
+                    1, Filter::Nearest)
+                .unwrap();
+            self.chain.record(builder, &filters)
+        };
+        builder
+            .blit_image(shown, [0, 0, 0], corner, 0, 0, self.images[index].clone(), [0, 0, 0], corner, 0, 0, 1,
+                Filter::Nearest)
+            .unwrap()
+    }
+
+    /* The sliders go by powers of two, so a drag covers a few iterations as easily as thousands,
+     * and a zoom into the edge as easily as the whole set. */
+    fn ui(&mut self, ui: &mut Ui) {
+        let mut iterations = (self.view.max_iter as f32).log2();
+        if ui.slider(&format!("Iterations: {}", self.view.max_iter), &mut iterations, 3.0, 14.0) {
+            self.view.max_iter = 2f32.powf(iterations).round() as u32;
+        }
+        let mut zoom = self.view.zoom.log2();
+        if ui.slider(&format!("Zoom: {:.1}", self.view.zoom), &mut zoom, -2.0, 16.0) {
+            self.view.zoom = 2f32.powf(zoom);
+        }
+        for (filter, on) in &mut self.filters {
+            ui.checkbox(&format!("{:?}", filter), on);
+        }
+    }
+
+    /* The shader gets the same push constants mandelbrot.glsl does. */
+    fn reload(&mut self, shader: &SpvShader) -> Result<(), String> {
+        if shader.stage != Stage::Compute {
+            return Err("The mandelbrot takes a compute shader".to_string());
+        }
+        let pipeline: Arc<dyn ComputePipelineAbstract + Send + Sync> = Arc::new(
+            ComputePipeline::new(self.queue.device().clone(), &shader.compute_entry_point(), &())
+                .map_err(|e| format!("Could not create a pipeline for the shader: {}", e))?);
+        self.set = mandelbrot::image_set(pipeline.clone(), self.image.clone())?;
+        self.pipeline = pipeline;
+        Ok(())
+    }
+}
+
+/* The cube, spinning at `speed` radians a second, drawn by a mesh renderer into its own SIZE x
+ * SIZE image and blitted into the middle of the window's, as big as fits. The renderer is made
+ * again whenever the overlay changes how many samples it takes, and a count the device can't do
+ * is said and skipped, keeping the one before. */
+struct Cube {
+    queue: Arc<Queue>,
+    renderer: MeshRenderer,
+    mesh: Mesh,
+    images: Vec<Image>,
+    /* The sample counts the device can draw the cube with, and which of them the renderer has. */
+    counts: Vec<u32>,
+    samples: usize,
+    speed: f32,
+    angle: f32,
+    time: f32,
+}
+
+impl Cube {
+    fn new(queue: Arc<Queue>, images: &[Image]) -> Cube {
+        let (vertices, indices) = mesh::cube();
+        let supported = mesh::supported_samples(queue.device().physical_device());
+        Cube {
+            renderer: MeshRenderer::new(queue.device().clone(), queue.clone()),
+            mesh: Mesh::new(queue.clone(), vertices, indices),
+            queue,
+            images: images.to_vec(),
+            counts: (0..7).map(|bit| 1 << bit).filter(|count| supported & count != 0).collect(),
+            samples: 0,
+            speed: 1.0,
+            angle: 0.0,
+            time: 0.0,
+        }
+    }
+}
+
+impl Layer for Cube {
+    fn resize(&mut self, images: &[Image]) {
+        self.images = images.to_vec();
+    }
+
+    fn draw(&mut self, builder: AutoCommandBufferBuilder, index: usize, frame: Frame) -> AutoCommandBufferBuilder {
+        self.angle += self.speed * (frame.time - self.time);
+        self.time = frame.time;
+
+        let target = self.images[index].clone();
+        let [width, height] = target.dimensions();
+        let side = width.min(height);
+        let (x, y) = ((width - side) / 2, (height - side) / 2);
+        self.renderer.record(builder, &self.mesh, mesh::spinning(self.angle))
+            .clear_color_image(target.clone(), triangle::CLEAR_COLOR.into()).unwrap()
+            .blit_image(self.renderer.image(), [0, 0, 0], [SIZE as i32, SIZE as i32, 1], 0, 0, target,
+                [x as i32, y as i32, 0], [(x + side) as i32, (y + side) as i32, 1], 0, 0, 1, Filter::Linear)
+            .unwrap()
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        ui.slider(&format!("Rotation: {:.2} radians/s", self.speed), &mut self.speed, 0.0, 4.0);
+        if ui.button(&format!("MSAA: {}x, click to change", self.counts[self.samples])) {
+            let next = (self.samples + 1) % self.counts.len();
+            match MeshRenderer::with_samples(self.queue.device().clone(), self.queue.clone(), self.counts[next]) {
+                Ok(renderer) => {
+                    self.renderer = renderer;
+                    self.samples = next;
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    self.counts.remove(next);
+                }
+            }
+        }
+    }
+}
+
+/* How many pixels a touchpad scrolls for one notch of a wheel. */
+const PIXELS_PER_NOTCH: f32 = 40.0;
+
+/* The explorer, taking its events from the window rather than the command line: dragging with
+ * the left button pans, the wheel zooms towards the cursor, and the keys are the ones
+ * `explore::Event::Key` knows. It is dispatched every frame with the push constants of wherever
+ * it has got to, mandelbrot or julia set, into an image the size of the window, which is blitted
+ * onto the window's the way the mandelbrot scene's is. */
+struct Explore {
+    queue: Arc<Queue>,
+    explorer: Explorer,
+    frames: FrameLoop,
+    images: Vec<Image>,
+    cursor: [f32; 2],
+    dragging: bool,
+}
+
+impl Explore {
+    fn new(queue: Arc<Queue>, view: View, images: &[Image]) -> Explore {
+        let [width, height] = images[0].dimensions();
+        Explore {
+            explorer: Explorer::new(width, height, view),
+            frames: FrameLoop::new(queue.device().clone(), queue.clone(), width, height),
+            queue,
+            images: images.to_vec(),
+            cursor: [0.0, 0.0],
+            dragging: false,
+        }
+    }
+}
+
+impl Layer for Explore {
+    fn resize(&mut self, images: &[Image]) {
+        let [width, height] = images[0].dimensions();
+        self.explorer.width = width;
+        self.explorer.height = height;
+        self.frames = FrameLoop::new(self.queue.device().clone(), self.queue.clone(), width, height);
+        self.images = images.to_vec();
+    }
+
+    fn draw(&mut self, builder: AutoCommandBufferBuilder, index: usize, _frame: Frame) -> AutoCommandBufferBuilder {
+        let [width, height] = self.images[index].dimensions();
+        let corner = [width as i32, height as i32, 1];
+        self.frames.record(builder, &self.explorer)
+            .blit_image(self.frames.image(), [0, 0, 0], corner, 0, 0, self.images[index].clone(), [0, 0, 0], corner,
+                0, 0, 1, Filter::Nearest)
+            .unwrap()
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        ui.label(&self.explorer.describe());
+        ui.label("Drag, scroll, + - p j r");
+    }
+
+    /* A drag that started on the scene keeps going over the overlay. */
+    fn event(&mut self, event: &WindowEvent, hidpi: f64, captured: bool) {
+        match *event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let LogicalPosition { x, y } = position;
+                let cursor = [(x * hidpi) as f32, (y * hidpi) as f32];
+                if self.dragging {
+                    let moved = [cursor[0] - self.cursor[0], cursor[1] - self.cursor[1]];
+                    self.explorer.apply(explore::Event::Drag(moved));
+                }
+                self.cursor = cursor;
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+                self.dragging = state == ElementState::Pressed && !captured;
+            }
+            WindowEvent::MouseWheel { delta, .. } if !captured => {
+                let notches = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => (position.y * hidpi) as f32 / PIXELS_PER_NOTCH,
+                };
+                self.explorer.apply(explore::Event::Scroll(notches, Some(self.cursor)));
+            }
+            WindowEvent::ReceivedCharacter(key) => self.explorer.apply(explore::Event::Key(key)),
+            _ => (),
+        }
+    }
+}
+
+/* Makes `swapchain` again at the window's size, and everything `layer` and `overlay` made for its
+ * images. Halfway through a resize, or minimized, the window can be a size the surface doesn't
+ * allow, and then there is nothing to make until the next frame, when it will be another. */
+fn recreate_swapchain(window: &Window, swapchain: &Arc<Swapchain<winit::Window>>, layer: &mut dyn Layer,
+    overlay: &mut Overlay) -> Result<Option<Arc<Swapchain<winit::Window>>>, SandboxError>
+{
+    let dimensions = match window.dimensions() {
+        Some(dimensions) => dimensions,
+        None => return Ok(None),
+    };
+    match swapchain.recreate_with_dimension(dimensions) {
+        Ok((recreated, images)) => {
+            layer.resize(&images);
+            overlay.resize(&images);
+            Ok(Some(recreated))
+        }
+        Err(SwapchainCreationError::UnsupportedDimensions) => Ok(None),
+        Err(e) => Err(SandboxError::Window(format!("Could not recreate the swapchain: {}", e))),
+    }
+}
+
+/* What `--window` was asked to show and how, besides the scene. */
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /* Where the mandelbrot is drawn. */
+    pub view: View,
+    /* How many images the swapchain should have, if not one more than the fewest it can. */
+    pub swapchain_images: Option<u32>,
+    /* GLSL shaders to swap in for the scene's, again every time one is saved. */
+    pub watch: Vec<PathBuf>,
+}
+
+/* Compiles the shader at `path` and swaps it into `layer`, saying how that went. */
+fn reload(device: Arc<Device>, layer: &mut dyn Layer, path: &Path) {
+    let name = path.display().to_string();
+    match watch::compile(device, path, &name).and_then(|shader| layer.reload(&shader)) {
+        Ok(()) => println!("Reloaded {}", name),
+        Err(e) => eprintln!("{}: {}\nKeeping the last good pipeline.", name, e),
+    }
+}
+
+/* Shows the window scene `name` in `window` until it is closed, drawing on `queue`, which has to
+ * be able to present to it, as `settings` say. A frame is only waited for once
+ * the next has been submitted, so the CPU records one while the GPU draws the other. Resizing
+ * the window leaves the swapchain the wrong size, which the window says as it happens and
+ * acquiring or presenting says as `OutOfDate`, and either way it is made again at the new size
+ * before anything more is drawn, along with everything the scene made for its images. Leaving
+ * fullscreen with Escape is a resize like any other. A swapchain that has only become
+ * suboptimal still presents its image, and is made again afterwards.
+ *
+ * Over the scene is the overlay, which F1 hides and shows again: how long frames are taking,
+ * averaged over the last few dozen so it can be read, what the GPU is and has allocated, and
+ * whatever controls the scene has. Every frame gets one `Frame`, which the scene and the overlay
+ * are both drawn with.
+ *
+ * With shaders to watch, each is compiled and swapped in for the scene's own as the window opens,
+ * and again whenever it is saved, between one frame and the next, the way `--watch` renders them
+ * to a file: a fragment shader in place of the triangle's and a compute shader in place of the
+ * mandelbrot's. One that doesn't compile, or doesn't fit, is reported and the scene goes on with
+ * the pipeline it had, so a typo costs nothing but the error. */
+pub fn run(mut window: Window, device: Arc<Device>, queue: Arc<Queue>, name: &str, settings: Settings)
+    -> Result<(), SandboxError>
+{
+    if !window.surface.is_supported(queue.family()).unwrap_or(false) {
+        return Err(SandboxError::Unsupported(format!("{} can't present to the window from queue family {}",
+            device.physical_device().name(), queue.family().id())));
+    }
+    let (mut swapchain, images) = window.swapchain(device.clone(), &queue, settings.swapchain_images)?;
+    let mut layer: Box<dyn Layer> = match name {
+        "triangle" => Box::new(Triangle::new(device.clone(), swapchain.format(), &images)),
+        "cube" => Box::new(Cube::new(queue.clone(), &images)),
+        "explore" => Box::new(Explore::new(queue.clone(), settings.view, &images)),
+        _ => Box::new(Mandelbrot::new(queue.clone(), settings.view, &images)),
+    };
+    let mut overlay = Overlay::new(queue.clone(), swapchain.format(), &images);
+    let changes = if settings.watch.is_empty() {
+        None
+    } else {
+        Some(Changes::new(&settings.watch).map_err(SandboxError::Window)?)
+    };
+    for path in &settings.watch {
+        println!("Watching {}", path.display());
+        reload(device.clone(), &mut *layer, path);
+    }
+    let [width, height] = swapchain.dimensions();
+    println!("Showing the {} at {}x{} in {:?} on {} swapchain images, close the window to stop, F1 hides the \
+        overlay", name, width, height, swapchain.format(), swapchain.num_images());
+
+    let mut input = Input::default();
+    let mut shown = true;
+    let started = Instant::now();
+    let mut last = started;
+    let mut frame_time = 0.0;
+
+    let mut previous: Box<dyn GpuFuture> = Box::new(sync::now(device.clone()));
+    let mut recreate = false;
+    'frames: loop {
+        let hidpi = window.surface.window().get_hidpi_factor();
+        let mut keys = Vec::new();
+        let mut closed = false;
+        window.events_loop.poll_events(|event| if let Event::WindowEvent { event, .. } = event {
+            input.event(&event, hidpi);
+            layer.event(&event, hidpi, shown && input.captured());
+            match event {
+                WindowEvent::CloseRequested => closed = true,
+                WindowEvent::Resized(_) => recreate = true,
+                WindowEvent::KeyboardInput { input: KeyboardInput {
+                    state: ElementState::Pressed, virtual_keycode: Some(key), ..
+                }, .. } => keys.push(key),
+                _ => (),
+            }
+        });
+        if closed {
+            return Ok(());
+        }
+        if keys.contains(&VirtualKeyCode::Escape) && window.fullscreen {
+            window.surface.window().set_fullscreen(None);
+            window.fullscreen = false;
+            recreate = true;
+        }
+        if keys.contains(&VirtualKeyCode::F1) {
+            shown = !shown;
+        }
+        for path in changes.iter().flat_map(Changes::saved) {
+            reload(device.clone(), &mut *layer, &path);
+        }
+        previous.cleanup_finished();
+
+        /* Acquiring can find the swapchain out of date before the window has said it was resized,
+         * which means the same: it is made again, and the image acquired from the new one, still
+         * in this frame. */
+        let (index, acquired) = loop {
+            if recreate {
+                match recreate_swapchain(&window, &swapchain, &mut *layer, &mut overlay)? {
+                    Some(recreated) => {
+                        swapchain = recreated;
+                        recreate = false;
+                    }
+                    None => continue 'frames,
+                }
+            }
+            match swapchain::acquire_next_image(swapchain.clone(), None) {
+                Ok(acquired) => break acquired,
+                Err(AcquireError::OutOfDate) => recreate = true,
+                Err(e) => return Err(SandboxError::Window(format!("Could not acquire a swapchain image: {}", e))),
+            }
+        };
+        /* The image still presents if the swapchain is only suboptimal, so the frame is drawn, and
+         * the swapchain is made again before the next one. */
+        if window.suboptimal(&swapchain, device.physical_device()) {
+            recreate = true;
+        }
+
+        let now = Instant::now();
+        frame_time += ((now - last).as_secs_f32() * 1000.0 - frame_time) * 0.05;
+        last = now;
+        let [width, height] = swapchain.dimensions();
+        let frame = Frame::new(width, height, (now - started).as_secs_f32());
+
+        let vertices = if shown {
+            let mut ui = input.frame();
+            ui.label(&format!("{:.2} ms a frame, {:.0} fps", frame_time, 1000.0 / frame_time.max(0.001)));
+            ui.label(&device.physical_device().name());
+            ui.label(&format!("{} allocated, {} images", memory::size(memory::in_use()), swapchain.num_images()));
+            layer.ui(&mut ui);
+            ui.finish()
+        } else {
+            Vec::new()
+        };
+
+        let builder = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap();
+        let builder = layer.draw(builder, index, frame);
+        let command_buffer = overlay.record(builder, index, frame, vertices).build().unwrap();
+        let future = previous.join(acquired)
+            .then_execute(queue.clone(), command_buffer).unwrap()
+            .then_swapchain_present(queue.clone(), swapchain.clone(), index)
+            .then_signal_fence_and_flush();
+        previous = match future {
+            Ok(future) => Box::new(future),
+            Err(FlushError::OutOfDate) => {
+                recreate = true;
+                Box::new(sync::now(device.clone()))
+            }
+            Err(e) => return Err(SandboxError::Submit(e.to_string())),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_counts_stay_in_the_surfaces_range() {
+        assert_eq!(image_count(None, 2, Some(8)), 3);
+        assert_eq!(image_count(None, 3, Some(3)), 3);
+        assert_eq!(image_count(Some(1), 2, Some(8)), 2);
+        assert_eq!(image_count(Some(16), 2, Some(8)), 8);
+        assert_eq!(image_count(Some(16), 2, None), 16);
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use vulkano::device::{Device, DeviceExtensions, Features};
+use vulkano::instance::PhysicalDevice;
+
+use vulkano_tut::{multiply, queues, setup};
+
+/* A run that only computes asks for a family that computes, which on a device with a family
+ * that can't draw has to be allowed to be that one. */
+#[test]
+fn compute_runs_are_not_refused_a_compute_only_family() {
+    let instance = match setup::headless(false, true) {
+        Ok(runner) => runner.instance,
+        Err(e) => return eprintln!("Skipping: {}", e),
+    };
+
+    for physical in PhysicalDevice::enumerate(&instance) {
+        let computes = physical.queue_families().any(|q| q.supports_compute());
+        let draws = physical.queue_families().any(|q| q.supports_graphics());
+        assert_eq!(queues::choose_family(physical, false, true).is_ok(), computes);
+        assert_eq!(queues::choose_family(physical, true, false).is_ok(), draws);
+        if let Ok(family) = queues::choose_family(physical, false, true) {
+            assert!(family.supports_compute());
+        }
+    }
+}
+
+/* Runs the multiply demo's own check on a family that supports compute but not graphics,
+ * where a device has one. Plenty of desktop GPUs have such a family next to their main one. */
+#[test]
+fn multiply_runs_on_a_compute_only_family() {
+    let instance = match setup::headless(false, true) {
+        Ok(runner) => runner.instance,
+        Err(e) => return eprintln!("Skipping: {}", e),
+    };
+    let family = PhysicalDevice::enumerate(&instance)
+        .flat_map(|physical| physical.queue_families())
+        .find(|q| q.supports_compute() && !q.supports_graphics());
+    let family = match family {
+        Some(family) => family,
+        None => return eprintln!("Skipping: no compute-only queue family"),
+    };
+
+    /* setup::headless picks the family it prefers, which is rarely this one, so the device for
+     * it is made here. */
+    let (device, mut queues) = Device::new(family.physical_device(), &Features::none(),
+        &DeviceExtensions::none(), [(family, 0.5)].iter().cloned()).unwrap();
+    let queue: Arc<_> = queues.next().unwrap();
+    multiply::verify(device, queue);
+}
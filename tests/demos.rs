@@ -0,0 +1,137 @@
+use image::{ImageBuffer, Rgba};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+
+use vulkano_tut::{
+    barrier, blit, buddhabrot, circle, clear, colorspace, convolve, cubemap, debug_dump, descent, descriptors,
+    dither, downsample, explore, fullscreen, fxaa, gamma, instancing, layered, mandelbrot, matmul, median, mesh,
+    mipchain, model, multiply, normalmap, pacing, particles, pattern, pointcloud, postfx, prepass, primitives,
+    quantize, queues, random, readback, record, reduce, resolve, rotate, scissor, setup, sort, subpass, terrain,
+    text, texture, transfer, transition, triangle, volume,
+};
+
+type Image = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/* A demo, run headlessly: what it returns is how big its output came out. */
+type Demo = fn(Arc<Device>, Arc<Queue>) -> usize;
+
+/* A small image to feed the demos that filter one. */
+fn source(device: Arc<Device>, queue: Arc<Queue>) -> Image {
+    pattern::uv(device, queue, 32, 32)
+}
+
+/* Every demo, small where it can be made small. The ones with nothing to return but their own
+ * check count the check as their output. */
+const DEMOS: &[(&str, Demo)] = &[
+    ("multiply", |device, queue| multiply::run(device, queue, 1000).len()),
+    ("descriptors", |device, queue| { descriptors::verify(device, queue); 1 }),
+    ("matmul", |device, queue| {
+        let (a, b) = matmul::example_inputs(16);
+        matmul::gpu_matmul(device, queue, &a, &b, 16).len()
+    }),
+    ("reduce", |device, queue| reduce::gpu_sum(device, queue, &[1; 1000]) as usize),
+    ("primitives", |device, queue| { primitives::verify(device, queue); 1 }),
+    ("sort", |device, queue| sort::gpu_sort(device, queue, &sort::pseudo_random(256)).len()),
+    ("mandelbrot", |device, queue| {
+        let pipeline = mandelbrot::pipeline(device.clone());
+        mandelbrot::render(device, queue, pipeline, 64, 48, mandelbrot::View::default()).unwrap().len()
+    }),
+    ("escape counts", |device, queue| {
+        mandelbrot::escape_counts(device, queue, 64, 48, mandelbrot::View::default()).len()
+    }),
+    ("buddhabrot", |device, queue| {
+        buddhabrot::histogram(device, queue, 1 << 12, random::DEFAULT_SEED, 32, 32, 50).len()
+    }),
+    ("uv", |device, queue| source(device, queue).len()),
+    ("blit", |device, queue| blit::half_size(device.clone(), queue.clone(), &source(device, queue)).len()),
+    ("mipchain", |device, queue| mipchain::render(device, queue).len()),
+    ("downsample", |device, queue| downsample::half(device.clone(), queue.clone(), &source(device, queue)).len()),
+    ("convolve", |device, queue| {
+        convolve::convolve_image(device.clone(), queue.clone(), &source(device, queue), convolve::DEFAULT_KERNEL)
+            .unwrap().len()
+    }),
+    ("quantize", |device, queue| quantize::quantize(device.clone(), queue.clone(), &source(device, queue), 4).len()),
+    ("dither", |device, queue| dither::dither(device.clone(), queue.clone(), &source(device, queue), 2).len()),
+    ("colorspace", |device, queue| {
+        let source = source(device.clone(), queue.clone());
+        colorspace::convert(device, queue, &source, colorspace::ColorSpace::Rgb, colorspace::ColorSpace::Hsv).len()
+    }),
+    ("median", |device, queue| median::median(device.clone(), queue.clone(), &source(device, queue), 3).len()),
+    ("rotate", |device, queue| rotate::rotate_image(device.clone(), queue.clone(), &source(device, queue), 30.0).len()),
+    ("particles", |device, queue| { particles::verify(device, queue); 1 }),
+    ("pointcloud", |device, queue| pointcloud::render(device, queue).len()),
+    ("terrain", |device, queue| {
+        let noise = terrain::Noise { octaves: terrain::DEFAULT_OCTAVES, scale: terrain::DEFAULT_NOISE_SCALE, seed: 0 };
+        terrain::render(device, queue, noise, terrain::Style::Shaded).len()
+    }),
+    ("mesh", |device, queue| mesh::render(device, queue, 1).unwrap().len()),
+    ("model", |device, queue| { model::verify(device, queue); 1 }),
+    ("texture", |device, queue| { texture::verify(device, queue); 1 }),
+    ("postfx", |device, queue| { postfx::verify(device, queue); 1 }),
+    ("explore", |device, queue| { explore::verify(device, queue); 1 }),
+    ("pacing", |device, queue| { pacing::verify(device, queue); 1 }),
+    ("record", |device, queue| { record::verify(device, queue); 1 }),
+    ("descent", |device, queue| {
+        let descent = descent::Descent { steps: descent::DEFAULT_STEPS, step_size: descent::DEFAULT_STEP_SIZE };
+        descent::render(device, queue, descent).len()
+    }),
+    ("readback", |device, queue| { readback::verify_formats(device, queue); 1 }),
+    ("dump", |device, queue| { debug_dump::verify(device, queue); 1 }),
+    ("circle", |device, queue| circle::draw_circle(device, queue, circle::CENTER, circle::RADIUS).len()),
+    ("clear", |device, queue| clear::cleared(device, queue, 16, 16, [255, 0, 0, 255]).len()),
+    ("layered", |device, queue| layered::render_layer(device, queue, 0).unwrap().len()),
+    ("fxaa", |device, queue| { fxaa::verify(device, queue); 1 }),
+    ("volume", |device, queue| volume::slice(device, queue, volume::DEFAULT_SLICE).unwrap().len()),
+    ("cubemap", |device, queue| {
+        let sky = cubemap::sky(device.clone(), queue.clone(), 16);
+        layered::download_layers(device, queue, sky).iter().map(|face| face.len()).sum()
+    }),
+    ("barrier", |device, queue| { barrier::verify(device, queue); 1 }),
+    ("transfer", |device, queue| { transfer::verify(device, queue.clone(), queue); 1 }),
+    ("queues", |device, queue| { queues::verify(device, queue.clone(), queue); 1 }),
+    ("transition", |device, queue| { transition::verify(device, queue); 1 }),
+    ("triangle", |device, queue| triangle::render(device, queue, None, Format::R8G8B8A8Unorm, 1).unwrap().len()),
+    ("resolve", |device, queue| resolve::render_default(device, queue).len()),
+    ("scissor", |device, queue| scissor::render(device, queue).len()),
+    ("instancing", |device, queue| { instancing::verify(device, queue); 1 }),
+    ("fullscreen", |device, queue| fullscreen::render(device, queue).len()),
+    ("disc", |device, queue| fullscreen::render_disc(device, queue, 48, 32).len()),
+    ("gamma", |device, queue| {
+        let (linear, srgb) = gamma::render(device, queue);
+        linear.len().min(srgb.len())
+    }),
+    ("subpass", |device, queue| subpass::render(device, queue).len()),
+    ("prepass", |device, queue| { prepass::verify(device, queue); 1 }),
+    ("normalmap", |device, queue| normalmap::render(device, queue, normalmap::DEFAULT_LIGHT).len()),
+    ("text", |device, queue| text::render(device, queue, "Hi").unwrap().len()),
+];
+
+fn device() -> Option<(Arc<Device>, Arc<Queue>)> {
+    let runner = setup::headless(true, true).map_err(|e| eprintln!("Skipping: {}", e)).ok()?;
+    Some((runner.device, runner.queue))
+}
+
+/* Runs every demo end to end, the way the default run does but without saving anything, so
+ * changing a helper they share can't quietly break one of them. A demo that panics doesn't stop
+ * the others: they all run, and the test fails naming each one that panicked or came back
+ * empty. */
+#[test]
+fn every_demo_runs_headlessly() {
+    let (device, queue) = match device() {
+        Some(device) => device,
+        None => return,
+    };
+
+    let mut failed = Vec::new();
+    for &(name, demo) in DEMOS {
+        let (device, queue) = (device.clone(), queue.clone());
+        match panic::catch_unwind(AssertUnwindSafe(|| demo(device, queue))) {
+            Ok(0) => failed.push(format!("{} produced no output", name)),
+            Ok(_) => (),
+            Err(_) => failed.push(format!("{} panicked", name)),
+        }
+    }
+    assert!(failed.is_empty(), "{}", failed.join(", "));
+}
@@ -0,0 +1,107 @@
+use vulkano::format::Format;
+
+use vulkano_tut::dryrun::Plan;
+use vulkano_tut::golden::{self, Tolerance};
+use vulkano_tut::scene::{Runner, SceneOutput, SCENES};
+use vulkano_tut::{compare, fullscreen, mandelbrot, mesh, multiply, pattern, setup};
+
+/* Rasterizers may place an edge pixel or round a blend a step differently from one GPU to the
+ * next, so drawn images get a little leeway where the UV pattern, computed exactly, gets none. */
+const DRAWN: Tolerance = Tolerance { threshold: 2, outliers: 0.002 };
+
+fn runner() -> Option<Runner> {
+    setup::headless(true, true).map_err(|e| eprintln!("Skipping: {}", e)).ok()
+}
+
+/* Small sizes, so the suite runs quickly even on a software renderer. */
+fn plan(runner: &Runner) -> Plan<'static> {
+    Plan {
+        elements: 1000,
+        width: 96,
+        height: 64,
+        view: mandelbrot::View::default(),
+        mandelbrot: mandelbrot::pipeline(runner.device.clone()),
+        spv_shader: None,
+        format: Format::R8G8B8A8Unorm,
+        samples: 1,
+        tile: 96,
+        instances: 100,
+    }
+}
+
+/* Every scene, run the way `--scene` runs it, against a CPU reference where there is one and
+ * its golden image otherwise. */
+#[test]
+fn every_scene_matches_its_reference() {
+    let mut runner = match runner() {
+        Some(runner) => runner,
+        None => return,
+    };
+    let plan = plan(&runner);
+
+    let mut failed = Vec::new();
+    for &name in &SCENES {
+        let scene = runner.scene(name, &plan).unwrap();
+        let result = match (name, runner.run(&*scene).unwrap()) {
+            ("copy-buffer", SceneOutput::Values(values)) => {
+                if values.iter().cloned().eq(0..64) { Ok(()) } else { Err("didn't copy 0 to 63".to_string()) }
+            }
+            ("multiply", SceneOutput::Values(values)) => {
+                if values == multiply::cpu_multiply(plan.elements) {
+                    Ok(())
+                } else {
+                    Err("doesn't match multiplying on the CPU".to_string())
+                }
+            }
+            /* The escape counts on the edge of the set may be a step off the CPU's, which at most
+             * changes their color by one step in 255. */
+            ("mandelbrot", SceneOutput::Image(image)) => {
+                let expected = mandelbrot::cpu_render(plan.width, plan.height, plan.view);
+                Tolerance { threshold: 2, outliers: 0.01 }.check(&image, &expected)
+            }
+            (_, SceneOutput::Image(image)) => golden::check(name, &image, DRAWN),
+            (_, SceneOutput::Values(_)) => Err("has no reference to check its values against".to_string()),
+        };
+        if let Err(e) = result {
+            failed.push(format!("{}: {}", name, e));
+        }
+    }
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
+
+/* The rendering demos that draw the same picture every time, against their golden images. */
+#[test]
+fn renders_match_their_golden_images() {
+    let runner = match runner() {
+        Some(runner) => runner,
+        None => return,
+    };
+    let (device, queue) = (runner.device.clone(), runner.queue.clone());
+
+    let renders = [
+        ("uv", pattern::uv(device.clone(), queue.clone(), 64, 64), Tolerance::exact()),
+        ("disc", fullscreen::render_disc(device.clone(), queue.clone(), 64, 48), DRAWN),
+        ("fullscreen", fullscreen::render(device.clone(), queue.clone()), DRAWN),
+        ("mesh", mesh::render(device, queue, 1).unwrap(), DRAWN),
+    ];
+    let failed: Vec<_> = renders.iter()
+        .filter_map(|(name, image, tolerance)| golden::check(name, image, *tolerance).err())
+        .collect();
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
+
+/* The tolerance itself: an image matches itself exactly, and one changed by more than the
+ * threshold in more pixels than it allows doesn't. */
+#[test]
+fn tolerance_allows_only_what_it_says() {
+    let image = image::ImageBuffer::from_fn(10, 10, |x, y| image::Rgba([x as u8 * 20, y as u8 * 20, 0, 255]));
+    let mut changed = image.clone();
+    changed.get_pixel_mut(3, 4).data[0] += 5;
+
+    assert!(Tolerance::exact().check(&image, &image).is_ok());
+    assert!(Tolerance::exact().check(&image, &changed).is_err());
+    assert!(Tolerance { threshold: 5, outliers: 0.0 }.check(&image, &changed).is_ok());
+    assert!(Tolerance { threshold: 2, outliers: 0.01 }.check(&image, &changed).is_ok());
+    assert!(Tolerance { threshold: 2, outliers: 0.005 }.check(&image, &changed).is_err());
+    assert!(compare::compare(&image, &changed, 0).unwrap().differing == 1);
+}
@@ -0,0 +1,38 @@
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+
+use vulkano_tut::{hash, mandelbrot, pattern, setup};
+
+/* The 256x256 UV pattern, whose every pixel is exact. */
+const UV_HASH: u64 = 0x0414_d4c9_6162_f325;
+
+fn device() -> Option<(Arc<Device>, Arc<Queue>)> {
+    let runner = setup::headless(false, true).map_err(|e| eprintln!("Skipping: {}", e)).ok()?;
+    Some((runner.device, runner.queue))
+}
+
+#[test]
+fn uv_pattern_matches_its_hash() {
+    let (device, queue) = match device() {
+        Some(device) => device,
+        None => return,
+    };
+
+    let image = pattern::uv(device, queue, 256, 256);
+    assert_eq!(hash::pixels(&image), UV_HASH);
+}
+
+/* The mandelbrot's floating point may round differently from one GPU to the next, so there is
+ * no single hash to expect, but on any one device the same parameters must give the same
+ * pixels every time. */
+#[test]
+fn mandelbrot_is_deterministic() {
+    let (device, queue) = match device() {
+        Some(device) => device,
+        None => return,
+    };
+
+    let render = || mandelbrot::render(device.clone(), queue.clone(), mandelbrot::pipeline(device.clone()),
+        512, 512, mandelbrot::View::default()).unwrap();
+    assert_eq!(hash::pixels(&render()), hash::pixels(&render()));
+}
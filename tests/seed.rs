@@ -0,0 +1,24 @@
+use std::sync::Arc;
+use vulkano::device::{Device, Queue};
+
+use vulkano_tut::{buddhabrot, setup};
+
+fn device() -> Option<(Arc<Device>, Arc<Queue>)> {
+    let runner = setup::headless(false, true).map_err(|e| eprintln!("Skipping: {}", e)).ok()?;
+    Some((runner.device, runner.queue))
+}
+
+/* The Buddhabrot's hits are counted with atomics, in whatever order the GPU gets to them, but
+ * the counts themselves only depend on which points were picked. So the same seed has to give
+ * the very same histogram twice, and another seed a different one. */
+#[test]
+fn buddhabrot_is_reproducible_from_its_seed() {
+    let (device, queue) = match device() {
+        Some(device) => device,
+        None => return,
+    };
+
+    let histogram = |seed| buddhabrot::histogram(device.clone(), queue.clone(), 1 << 14, seed, 64, 64, 100);
+    assert_eq!(histogram(42), histogram(42));
+    assert_ne!(histogram(42), histogram(43));
+}
@@ -0,0 +1,20 @@
+use vulkano_tut::{setup, triangle};
+
+/* The triangle's vertices are (-0.5, -0.5), (0, 0.5) and (0.5, -0.25), so its centroid is at
+ * (0, -1/12) in normalized device coordinates: pixel (256, 234) of the 512x512 target. A
+ * corner pixel is nowhere near it. If the viewport or the coordinate conventions break, one of
+ * the two will end up the wrong color. */
+#[test]
+fn triangle_covers_its_centroid_but_not_the_corner() {
+    let runner = match setup::headless(true, false) {
+        Ok(runner) => runner,
+        Err(e) => return eprintln!("Skipping: {}", e),
+    };
+
+    let image = triangle::render(runner.device, runner.queue, None, vulkano::format::Format::R8G8B8A8Unorm, 1).unwrap();
+
+    let clear = image::Rgba(triangle::CLEAR_COLOR.map(|c| (c * 255.0) as u8));
+    let centroid = (triangle::SIZE / 2, (triangle::SIZE as f32 * (1.0 - 1.0 / 12.0) / 2.0) as u32);
+    assert_ne!(*image.get_pixel(centroid.0, centroid.1), clear, "The triangle's centroid was not drawn");
+    assert_eq!(*image.get_pixel(0, 0), clear, "The corner should only have been cleared");
+}